@@ -0,0 +1,153 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! A minimal accessibility tree, which lets screen readers see custom drawn content.
+//!
+//! Only macOS exposes the tree to screen readers so far; the other platforms ignore it.
+
+use crate::kurbo::Rect;
+
+/// Identifies a node in an [`AccessibilityTree`].
+///
+/// Ids are chosen by the application, and must be unique within a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AccessibilityNodeId(pub u64);
+
+/// What kind of element an [`AccessibilityNode`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Role {
+    /// Something that can be clicked to perform an action.
+    Button,
+    /// Text that can't be edited.
+    StaticText,
+    /// A list, whose children are usually [`ListItem`](Role::ListItem)s.
+    List,
+    /// An item in a [`List`](Role::List).
+    ListItem,
+    /// A container for other nodes.
+    Group,
+}
+
+/// A single element in an [`AccessibilityTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilityNode {
+    /// The id of the node.
+    pub id: AccessibilityNodeId,
+    /// What kind of element this is.
+    pub role: Role,
+    /// The name of the element, which is what screen readers say first.
+    pub label: Option<String>,
+    /// The current value of the element, for example the text of a [`Role::StaticText`].
+    pub value: Option<String>,
+    /// The bounds of the element, in display points relative to the window's content area.
+    pub bounds: Rect,
+    /// The nodes contained in this one.
+    pub children: Vec<AccessibilityNode>,
+}
+
+/// The accessibility elements of a window.
+///
+/// See [`WindowHandle::set_accessibility_tree`](crate::WindowHandle::set_accessibility_tree).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessibilityTree {
+    /// The top level nodes of the window.
+    pub nodes: Vec<AccessibilityNode>,
+    /// The node that has keyboard focus, if any.
+    pub focus: Option<AccessibilityNodeId>,
+}
+
+impl AccessibilityNode {
+    /// Creates a node without a label, value or children.
+    pub fn new(id: AccessibilityNodeId, role: Role, bounds: Rect) -> Self {
+        AccessibilityNode {
+            id,
+            role,
+            label: None,
+            value: None,
+            bounds,
+            children: Vec::new(),
+        }
+    }
+
+    /// Builder-style method to set the label.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Builder-style method to set the value.
+    pub fn value(mut self, value: impl Into<String>) -> Self {
+        self.value = Some(value.into());
+        self
+    }
+
+    /// Builder-style method to add a child.
+    pub fn child(mut self, child: AccessibilityNode) -> Self {
+        self.children.push(child);
+        self
+    }
+}
+
+impl AccessibilityTree {
+    /// Creates a tree with the given top level nodes and no focus.
+    pub fn new(nodes: Vec<AccessibilityNode>) -> Self {
+        AccessibilityTree { nodes, focus: None }
+    }
+
+    /// Builder-style method to set the focused node.
+    pub fn focus(mut self, focus: AccessibilityNodeId) -> Self {
+        self.focus = Some(focus);
+        self
+    }
+
+    /// Returns the node with the given id, searching the whole tree.
+    pub fn find(&self, id: AccessibilityNodeId) -> Option<&AccessibilityNode> {
+        fn find_in(
+            nodes: &[AccessibilityNode],
+            id: AccessibilityNodeId,
+        ) -> Option<&AccessibilityNode> {
+            nodes.iter().find_map(|node| {
+                if node.id == id {
+                    Some(node)
+                } else {
+                    find_in(&node.children, id)
+                }
+            })
+        }
+        find_in(&self.nodes, id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn find_nested_node() {
+        let item = |id| {
+            AccessibilityNode::new(AccessibilityNodeId(id), Role::ListItem, Rect::ZERO)
+                .label(format!("item {id}"))
+        };
+        let list = AccessibilityNode::new(AccessibilityNodeId(1), Role::List, Rect::ZERO)
+            .child(item(2))
+            .child(item(3));
+        let tree = AccessibilityTree::new(vec![list]).focus(AccessibilityNodeId(3));
+
+        assert_eq!(tree.find(AccessibilityNodeId(1)).unwrap().role, Role::List);
+        let focused = tree.find(tree.focus.unwrap()).unwrap();
+        assert_eq!(focused.label.as_deref(), Some("item 3"));
+        assert!(tree.find(AccessibilityNodeId(4)).is_none());
+    }
+}
@@ -15,12 +15,20 @@
 //! The top-level application type.
 
 use std::cell::RefCell;
+use std::ops::ControlFlow;
+use std::path::Path;
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
 use crate::backend::application as backend;
 use crate::clipboard::Clipboard;
 use crate::error::Error;
+use crate::keyboard::Modifiers;
+use crate::kurbo::{Point, Rect, Size};
+use crate::menu::Menu;
+use crate::mouse::MouseButtons;
+use crate::piet::Color;
 use crate::util;
 
 /// A top-level handler that is not associated with any window.
@@ -40,6 +48,318 @@ pub trait AppHandler {
     /// Called when a menu item is selected.
     #[allow(unused_variables)]
     fn command(&mut self, id: u32) {}
+
+    /// Called for each item of the application menu just before it is shown, when no window
+    /// handles the menu.
+    ///
+    /// This works like [`WinHandler::menu_item_enabled`], and is only called on macOS.
+    ///
+    /// [`WinHandler::menu_item_enabled`]: crate::WinHandler::menu_item_enabled
+    #[allow(unused_variables)]
+    fn menu_item_enabled(&mut self, id: u32) -> Option<bool> {
+        None
+    }
+
+    /// Called when the system is about to go to sleep.
+    ///
+    /// The system waits for this method to return before sleeping (within
+    /// platform-imposed limits of a few seconds), so this is the place to
+    /// flush any unsaved state.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn system_will_sleep(&mut self) {}
+
+    /// Called when the system has woken up from sleep.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn system_did_wake(&mut self) {}
+
+    /// Called when the system switches between AC and battery power.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    #[allow(unused_variables)]
+    fn power_source_changed(&mut self, source: PowerSource) {}
+
+    /// Called when the user's session is locked, whether by the user or by an idle timeout.
+    ///
+    /// Applications showing sensitive content may want to hide it here.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn session_locked(&mut self) {}
+
+    /// Called when the user's session is unlocked.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn session_unlocked(&mut self) {}
+
+    /// Called when the user switches keyboard layout or input source.
+    ///
+    /// The new layout can be queried with [`Application::keyboard_layout_name`].
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn keyboard_layout_changed(&mut self) {}
+
+    /// Called when the user turns the system's high contrast or increased contrast
+    /// setting on or off.
+    ///
+    /// See [`Application::prefers_high_contrast`].
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    #[allow(unused_variables)]
+    fn high_contrast_changed(&mut self, enabled: bool) {}
+
+    /// Called when the user changes the system's language or region settings.
+    ///
+    /// The new locale can be queried with [`Application::get_locale`].
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK. With GTK this is called
+    /// when the GNOME region setting changes, but [`Application::get_locale`] keeps returning
+    /// the locale of the environment that the application was started in.
+    fn locale_changed(&mut self) {}
+
+    /// Called when the system asks the application to open a file.
+    ///
+    /// On macOS this happens when files are dropped on the Dock icon, or when the user
+    /// picks one from the system's recent documents, see
+    /// [`Application::note_recent_document`].
+    ///
+    /// On Windows and GTK this is called for each file passed on the command line when
+    /// the application starts, which is how the file manager opens a document with the
+    /// application associated with its type. Files sent to an already running instance
+    /// are only delivered there if it was created with
+    /// [`Application::new_single_instance`]; otherwise every launch starts a new instance.
+    ///
+    /// This is not implemented on the other platforms.
+    #[allow(unused_variables)]
+    fn open_file(&mut self, path: &Path) {}
+
+    /// Called when the system asks the application to open a URL with one of its
+    /// custom schemes, such as a `myapp://` link clicked in a browser.
+    ///
+    /// On macOS the schemes are declared with `CFBundleURLTypes` in the bundle's
+    /// `Info.plist`, and the URLs are delivered to the running application.
+    ///
+    /// On Windows and GTK the scheme is registered with the system to launch the
+    /// application with the URL on the command line, like the files of [`open_file`].
+    /// As with files, a URL only reaches an already running instance if it was created
+    /// with [`Application::new_single_instance`].
+    ///
+    /// This is not implemented on the other platforms.
+    ///
+    /// [`open_file`]: AppHandler::open_file
+    #[allow(unused_variables)]
+    fn open_url(&mut self, url: &str) {}
+
+    /// Called when the application becomes the active, frontmost application.
+    ///
+    /// See [`Application::is_active`].
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn did_become_active(&mut self) {}
+
+    /// Called when another application becomes active.
+    ///
+    /// Applications may want to pause expensive work, such as animations, until they
+    /// are active again.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn did_resign_active(&mut self) {}
+
+    /// Called when the user logs out, or the system restarts or shuts down, before
+    /// the application is terminated.
+    ///
+    /// Returning [`EndSessionResponse::Block`] asks the system to wait, showing the reason
+    /// to the user, for example while there are unsaved documents. The answer is given later
+    /// with [`Application::reply_to_end_session`].
+    ///
+    /// On macOS this is only called for the quit sequence of logging out, restarting or
+    /// shutting down; quitting from the Dock or the application menu isn't affected.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    fn query_end_session(&mut self) -> EndSessionResponse {
+        EndSessionResponse::Allow
+    }
+
+    /// Called when the session is ending and the application is about to be terminated.
+    ///
+    /// There is little time left, so only the most important state should be saved here.
+    ///
+    /// This is currently only implemented on macOS and Windows.
+    fn end_session(&mut self) {}
+}
+
+/// The answer to [`AppHandler::query_end_session`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EndSessionResponse {
+    /// Let the session end.
+    Allow,
+    /// Ask the system to wait, with a reason that it can show to the user.
+    Block(String),
+}
+
+/// The source of power for the system, as reported to [`AppHandler::power_source_changed`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PowerSource {
+    /// The system is plugged in.
+    AC,
+    /// The system is running on battery.
+    Battery,
+}
+
+/// A sound the system plays to get the user's attention, see
+/// [`Application::play_system_sound`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SystemSound {
+    /// The sound for a warning, which is usually the same as [`Application::beep`].
+    Alert,
+    /// The sound for an error, such as invalid input.
+    Error,
+    /// The sound for an informational message.
+    Info,
+}
+
+/// Whether the user has given the application a permission, see
+/// [`Application::check_accessibility_permission`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PermissionState {
+    /// The permission is granted.
+    Granted,
+    /// The permission isn't granted yet, or the user denied it.
+    Denied,
+    /// The platform doesn't have this permission, so nothing is restricted by it.
+    NotRequired,
+}
+
+/// The version of the operating system, see [`Application::os_version`].
+///
+/// Versions compare by `major`, then `minor`, then `patch`, so features that need a newer
+/// system can be checked with `>=`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OsVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl OsVersion {
+    /// Creates a new `OsVersion`.
+    pub const fn new(major: u32, minor: u32, patch: u32) -> OsVersion {
+        OsVersion {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Parses the leading `major.minor.patch` of a version string, such as a Linux kernel
+    /// release like `6.5.0-14-generic`. Missing components are zero.
+    #[allow(dead_code)]
+    pub(crate) fn parse(version: &str) -> Option<OsVersion> {
+        let numbers = version
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()?;
+        let mut parts = numbers.split('.').map(|part| part.parse::<u32>().ok());
+        let major = parts.next()??;
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(OsVersion::new(major, minor, patch))
+    }
+}
+
+impl std::fmt::Display for OsVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// The direction in which the user interface is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayoutDirection {
+    /// Content flows from left to right, as in English.
+    LeftToRight,
+    /// Content flows from right to left, as in Arabic or Hebrew.
+    RightToLeft,
+}
+
+impl LayoutDirection {
+    /// Guesses the layout direction from a locale string, for platforms that
+    /// don't report it directly.
+    #[allow(dead_code)]
+    pub(crate) fn from_locale(locale: &str) -> LayoutDirection {
+        const RTL_LANGUAGES: &[&str] = &[
+            "ar", "arc", "ckb", "dv", "fa", "he", "iw", "ks", "ps", "sd", "ug", "ur", "yi",
+        ];
+        let language = locale.split(|c| c == '-' || c == '_').next().unwrap_or("");
+        if RTL_LANGUAGES.contains(&language.to_ascii_lowercase().as_str()) {
+            LayoutDirection::RightToLeft
+        } else {
+            LayoutDirection::LeftToRight
+        }
+    }
+}
+
+/// Identifies a window of another application, see [`WindowInfo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ExternalWindowId(u64);
+
+impl ExternalWindowId {
+    /// Create an id from the platform's window identifier.
+    ///
+    /// This is the `CGWindowID` on macOS, the `HWND` on Windows and the window id on X11.
+    pub const fn from_raw(id: u64) -> ExternalWindowId {
+        ExternalWindowId(id)
+    }
+
+    /// Get the platform's window identifier, see [`from_raw`](ExternalWindowId::from_raw).
+    pub const fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// A window of another application, as returned by [`Application::window_list`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct WindowInfo {
+    /// The platform's identifier for the window.
+    pub id: ExternalWindowId,
+    /// The title of the window, which may be empty.
+    pub title: String,
+    /// The name of the application that owns the window.
+    pub owner_name: String,
+    /// The frame of the window, in the same coordinates as [`Monitor::virtual_rect`].
+    ///
+    /// [`Monitor::virtual_rect`]: crate::Monitor::virtual_rect
+    pub bounds: Rect,
+}
+
+/// Observes the mouse anywhere on the screen, see [`Application::add_global_mouse_monitor`].
+///
+/// The monitor is removed when this is dropped.
+pub struct GlobalMouseMonitor {
+    _backend: backend::GlobalMouseMonitor,
+}
+
+/// How the run loop of an [`Application`] ended, as returned by [`Application::try_run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct RunOutcome {
+    /// The exit code that the platform's run loop ended with.
+    ///
+    /// This is `0` unless something outside of druid-shell ended the loop with another
+    /// code, which can only happen on Windows and GTK.
+    pub exit_code: i32,
+    /// Whether the loop ended because [`Application::quit`] was called, rather than
+    /// because the platform ended it, for example when the last GTK window was closed.
+    pub quit_requested: bool,
+}
+
+impl RunOutcome {
+    pub(crate) fn new(exit_code: i32, quit_requested: bool) -> RunOutcome {
+        RunOutcome {
+            exit_code,
+            quit_requested,
+        }
+    }
 }
 
 /// The top level application object.
@@ -71,15 +391,53 @@ impl Application {
     ///
     /// Errors if an `Application` has already been created.
     ///
+    /// On macOS, returns [`Error::NotMainThread`] if this isn't called on the
+    /// first thread of the process.
+    ///
     /// This may change in the future. See [druid#771] for discussion.
     ///
     /// [druid#771]: https://github.com/linebender/druid/issues/771
     pub fn new() -> Result<Application, Error> {
+        Application::new_with(|| backend::Application::new().map_err(Error::from))
+    }
+
+    /// Create a new `Application`, unless another instance with the same `app_id` is
+    /// already running.
+    ///
+    /// If there is one, the files on the command line are forwarded to it, where they
+    /// arrive through [`AppHandler::open_file`], and [`Error::AlreadyRunning`] is returned.
+    /// The caller should then exit without showing any windows.
+    ///
+    /// On macOS `app_id` has to be the bundle identifier of the application.
+    /// On GTK it has to be a valid D-Bus name, like `com.example.editor`.
+    ///
+    /// Single instance enforcement is not implemented on the other platforms, where
+    /// this behaves like [`Application::new`].
+    ///
+    /// # Errors
+    ///
+    /// Besides [`Error::AlreadyRunning`], this fails for the same reasons as
+    /// [`Application::new`].
+    pub fn new_single_instance(app_id: &str) -> Result<Application, Error> {
+        Application::new_with(|| backend::Application::new_single_instance(app_id))
+    }
+
+    fn new_with(
+        new_backend: impl FnOnce() -> Result<backend::Application, Error>,
+    ) -> Result<Application, Error> {
         APPLICATION_CREATED
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
             .map_err(|_| Error::ApplicationAlreadyExists)?;
         util::claim_main_thread();
-        let backend_app = backend::Application::new()?;
+        let backend_app = match new_backend() {
+            Ok(backend_app) => backend_app,
+            Err(e) => {
+                // Let the caller try again, for example from another thread.
+                util::release_main_thread();
+                APPLICATION_CREATED.store(false, Ordering::Release);
+                return Err(e);
+            }
+        };
         let state = Rc::new(RefCell::new(State { running: false }));
         let app = Application { backend_app, state };
         GLOBAL_APP.with(|global_app| {
@@ -130,12 +488,31 @@ impl Application {
     /// The provided `handler` will be used to inform of events.
     ///
     /// This will consume the `Application` and block the current thread
-    /// until the `Application` has finished executing.
+    /// until the `Application` has finished executing. Errors are logged; use
+    /// [`try_run`] to find out how the runloop ended.
     ///
     /// # Panics
     ///
     /// Panics if the `Application` is already running.
+    ///
+    /// [`try_run`]: #method.try_run
     pub fn run(self, handler: Option<Box<dyn AppHandler>>) {
+        if let Err(e) = self.try_run(handler) {
+            tracing::error!("Application run loop failed: {}", e);
+        }
+    }
+
+    /// Start the `Application` runloop, and return how it ended.
+    ///
+    /// This is the same as [`run`], except that it returns an error if the platform's
+    /// runloop failed, and otherwise describes why it stopped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the `Application` is already running.
+    ///
+    /// [`run`]: #method.run
+    pub fn try_run(self, handler: Option<Box<dyn AppHandler>>) -> Result<RunOutcome, Error> {
         // Make sure this application hasn't run() yet.
         if let Ok(mut state) = self.state.try_borrow_mut() {
             if state.running {
@@ -147,7 +524,7 @@ impl Application {
         }
 
         // Run the platform application
-        self.backend_app.run(handler);
+        let outcome = self.backend_app.run(handler);
 
         // This application is no longer active, so clear the global reference
         GLOBAL_APP.with(|global_app| {
@@ -159,6 +536,32 @@ impl Application {
         APPLICATION_CREATED
             .compare_exchange(true, false, Ordering::AcqRel, Ordering::Acquire)
             .expect("Application marked as not created while still running.");
+
+        outcome.map_err(Into::into)
+    }
+
+    /// Handles the events that are waiting, for embedding in a loop that isn't ours.
+    ///
+    /// With a `timeout` of `None` this waits until there is at least one event, with
+    /// `Some(Duration::ZERO)` it doesn't wait at all, and otherwise it waits at most `timeout`.
+    /// All the events that are pending then are handled before it returns.
+    ///
+    /// Returns [`ControlFlow::Break`] once [`quit`] has been called, after which the caller
+    /// should stop pumping. Windows get their events as usual, but there is no [`AppHandler`]
+    /// while pumping, since that is only given to [`run`].
+    ///
+    /// This maps to `nextEventMatchingMask:untilDate:inMode:dequeue:` on macOS, to
+    /// `PeekMessage` on Windows and to `g_main_context_iteration` on GTK.
+    /// Wayland and web don't support it, and always return right away.
+    ///
+    /// [`quit`]: Application::quit
+    /// [`run`]: Application::run
+    pub fn pump_events(&self, timeout: Option<Duration>) -> ControlFlow<()> {
+        if self.backend_app.pump_events(timeout) {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
     }
 
     /// Quit the `Application`.
@@ -170,11 +573,325 @@ impl Application {
         self.backend_app.quit()
     }
 
+    /// Exits the process with `code` right away, without closing the windows first.
+    ///
+    /// This is for getting out of a state that [`quit`] might not get out of, for example
+    /// when a window's close handling is stuck. [`run`] doesn't return, and nothing is dropped:
+    /// no [`WinHandler`] is told about the window being destroyed, and [`Drop`] implementations
+    /// don't run, so anything that needs saving should be saved before calling this.
+    ///
+    /// [`quit`]: Application::quit
+    /// [`run`]: Application::run
+    /// [`WinHandler`]: crate::WinHandler
+    pub fn terminate_now(&self, code: i32) -> ! {
+        std::process::exit(code)
+    }
+
+    /// Answers an end of session that [`AppHandler::query_end_session`] blocked.
+    ///
+    /// On macOS, `allow` lets the logout, restart or shutdown go on, or cancels it.
+    /// Windows and GTK can't resume an end of session once it has been blocked, so this only
+    /// withdraws the reason that was shown; the user has to start it again.
+    ///
+    /// This does nothing when there is no blocked end of session.
+    pub fn reply_to_end_session(&self, allow: bool) {
+        self.backend_app.reply_to_end_session(allow)
+    }
+
+    /// Sets the menu of the application.
+    ///
+    /// On macOS this is the menu bar, which replaces the whole menu bar, including the
+    /// application menu. While no window is open, its items are sent to [`AppHandler::command`].
+    ///
+    /// On GTK every window built without a menu of its own shows this menu, and gets the new
+    /// one when it is set again. Its items are sent to the [`WinHandler::command`] of the window.
+    ///
+    /// Windows has no application menu, since menus belong to windows there, so this only
+    /// logs a warning; use [`WindowHandle::set_menu`] instead. The other backends don't
+    /// support menus at all yet.
+    ///
+    /// [`WinHandler::command`]: crate::WinHandler::command
+    /// [`WindowHandle::set_menu`]: crate::WindowHandle::set_menu
+    pub fn set_menu(&self, menu: Menu) {
+        self.backend_app.set_menu(menu.into_inner())
+    }
+
     /// Returns a handle to the system clipboard.
     pub fn clipboard(&self) -> Clipboard {
         self.backend_app.clipboard().into()
     }
 
+    /// Opens `uri` with the user's default application for it.
+    ///
+    /// This works for web links, as well as for `file://` URIs and any other scheme
+    /// that the system has a handler registered for.
+    pub fn open_uri(&self, uri: &str) -> Result<(), Error> {
+        self.backend_app.open_uri(uri).map_err(Into::into)
+    }
+
+    /// Shows `path` in the system file manager.
+    ///
+    /// Where the platform supports it, the file manager opens the containing directory
+    /// with `path` selected. Otherwise the containing directory is opened.
+    pub fn reveal_in_file_manager(&self, path: &Path) -> Result<(), Error> {
+        self.backend_app
+            .reveal_in_file_manager(path)
+            .map_err(Into::into)
+    }
+
+    /// Adds `path` to the system's list of recently used documents.
+    ///
+    /// On macOS this list is shown in the Dock menu and in a File > Open Recent menu that
+    /// AppKit manages; documents picked from it are passed to [`AppHandler::open_file`].
+    /// On Windows it is shown in the taskbar jump list, and on GTK in the recent files of
+    /// file choosers.
+    pub fn note_recent_document(&self, path: &Path) {
+        self.backend_app.note_recent_document(path)
+    }
+
+    /// Removes this application's documents from the system's list of recently used
+    /// documents.
+    ///
+    /// Windows doesn't track which application added a document, so there this clears the
+    /// whole list.
+    pub fn clear_recent_documents(&self) {
+        self.backend_app.clear_recent_documents()
+    }
+
+    /// Returns the user-facing name of the current keyboard layout, such as "French".
+    ///
+    /// Returns `None` if the platform doesn't expose one.
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        self.backend_app.keyboard_layout_name()
+    }
+
+    /// Returns the direction the system lays out its user interface in.
+    ///
+    /// Right-to-left layouts should mirror their horizontal arrangement.
+    pub fn layout_direction(&self) -> LayoutDirection {
+        self.backend_app.layout_direction()
+    }
+
+    /// Returns `true` if the user asked for more contrast in the system's accessibility settings.
+    ///
+    /// This is "Increase contrast" on macOS, a high contrast theme on Windows, and the
+    /// HighContrast theme on GTK. It is always `false` on other platforms.
+    pub fn prefers_high_contrast(&self) -> bool {
+        self.backend_app.prefers_high_contrast()
+    }
+
+    /// Returns the background color the system uses for selected text.
+    ///
+    /// This follows the current theme, including dark mode where the platform supports it.
+    /// Returns `None` if the platform doesn't expose one.
+    pub fn selection_color(&self) -> Option<Color> {
+        self.backend_app.selection_color()
+    }
+
+    /// Returns the color the system uses for the text insertion cursor.
+    ///
+    /// Returns `None` if the platform doesn't expose one.
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        self.backend_app.text_cursor_color()
+    }
+
+    /// Returns `true` if this is the active application, the one that receives keyboard input.
+    ///
+    /// On platforms without a notion of an active application this is `true` when one
+    /// of the application's windows has focus. It is always `false` on X11, Wayland and
+    /// the web.
+    pub fn is_active(&self) -> bool {
+        self.backend_app.is_active()
+    }
+
+    /// Brings the application to the front and makes it active.
+    ///
+    /// If `ignore_other_apps` is `false`, the application is only activated when no other
+    /// application is active, which avoids taking focus away from what the user is doing.
+    ///
+    /// Most platforms restrict when an application may take focus from another one, so
+    /// this may only flash the application in the taskbar instead. With GTK, whether
+    /// another application is active is left to the window manager, which may mark the
+    /// window as urgent instead of activating it.
+    pub fn activate(&self, ignore_other_apps: bool) {
+        self.backend_app.activate(ignore_other_apps)
+    }
+
+    /// Plays the system's alert sound.
+    ///
+    /// This is the sound the user picked in their sound settings, and the one that
+    /// native applications play when, for example, a key press has no effect.
+    pub fn beep(&self) {
+        self.backend_app.beep()
+    }
+
+    /// Plays one of the system's sounds.
+    ///
+    /// Platforms that only have a single alert sound, such as GTK and X11, play that
+    /// for every [`SystemSound`].
+    pub fn play_system_sound(&self, sound: SystemSound) {
+        self.backend_app.play_system_sound(sound)
+    }
+
+    /// Returns how long it has been since the user last used any input device,
+    /// in any application.
+    ///
+    /// This can be polled to lock the application after a period of inactivity.
+    /// Returns `None` if the platform doesn't expose it. On GTK this needs a desktop
+    /// that implements the GNOME or freedesktop idle time D-Bus interfaces.
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        self.backend_app.system_idle_time()
+    }
+
+    /// Returns how many buttons the mouse has.
+    ///
+    /// This can be used to decide whether to offer bindings for the
+    /// [`X1`](crate::MouseButton::X1) and [`X2`](crate::MouseButton::X2) buttons.
+    /// Returns `None` if the platform doesn't expose it, which is the case on macOS and GTK.
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        self.backend_app.mouse_button_count()
+    }
+
+    /// Returns the largest image, in pixels, that [`WindowHandle::make_cursor`] can make
+    /// a cursor from.
+    ///
+    /// On Windows this is the size of the system's cursors, usually 32×32, and
+    /// [`WindowHandle::make_cursor`] fails for larger images. With GTK it is the largest
+    /// size that the display supports, which is enforced the same way. The other platforms
+    /// have no limit, and return an infinite size.
+    ///
+    /// [`WindowHandle::make_cursor`]: crate::WindowHandle::make_cursor
+    pub fn max_cursor_size(&self) -> Size {
+        self.backend_app.max_cursor_size()
+    }
+
+    /// Returns the mouse buttons that are held down right now.
+    ///
+    /// Unlike the buttons of a [`MouseEvent`](crate::MouseEvent), this is read when it's
+    /// called, so it can be used to notice that a button was released while the application
+    /// didn't get the event, for example because a menu was open.
+    ///
+    /// This is currently unsupported on Wayland and web, where it returns no buttons.
+    pub fn mouse_buttons(&self) -> MouseButtons {
+        self.backend_app.mouse_buttons()
+    }
+
+    /// Returns the keyboard modifiers that are held down right now.
+    ///
+    /// This is currently unsupported on Wayland and web, where it returns no modifiers.
+    pub fn modifiers(&self) -> Modifiers {
+        self.backend_app.modifiers()
+    }
+
+    /// Returns the visible windows of other applications, from front to back.
+    ///
+    /// This is meant for letting the user pick a window, for example to record it.
+    /// On macOS the titles are only included with screen recording permission, see
+    /// [`request_screen_capture_access`](Application::request_screen_capture_access).
+    ///
+    /// This is implemented on macOS, Windows and X11. It always returns an empty list with
+    /// GTK, which only knows about the application's own windows, and on Wayland and web.
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        self.backend_app.window_list()
+    }
+
+    /// Returns whether the application is allowed to capture the contents of the screen,
+    /// including the titles of other applications' windows.
+    ///
+    /// This is only restricted on macOS, the other platforms always return `true`.
+    pub fn has_screen_capture_access(&self) -> bool {
+        self.backend_app.has_screen_capture_access()
+    }
+
+    /// Asks the user for permission to capture the contents of the screen, and returns
+    /// whether it is granted.
+    ///
+    /// On macOS the system only prompts the first time this is called, and the application
+    /// has to be restarted before a permission granted in the system settings takes effect.
+    /// The other platforms always return `true`.
+    pub fn request_screen_capture_access(&self) -> bool {
+        self.backend_app.request_screen_capture_access()
+    }
+
+    /// Returns whether the application is trusted with accessibility, which macOS requires
+    /// for observing or sending keyboard events of other applications.
+    ///
+    /// If `prompt` is `true` and the permission isn't granted, macOS shows a prompt that
+    /// leads the user to the system settings. The prompt doesn't wait for the user, so this
+    /// still returns [`PermissionState::Denied`]; check again later, for example when the
+    /// application becomes active. The other platforms return
+    /// [`PermissionState::NotRequired`].
+    pub fn check_accessibility_permission(&self, prompt: bool) -> PermissionState {
+        self.backend_app.check_accessibility_permission(prompt)
+    }
+
+    /// Returns the name of the backend that druid-shell was built with: `"mac"`,
+    /// `"windows"`, `"gtk"`, `"x11"`, `"wayland"` or `"web"`.
+    pub fn backend_name(&self) -> &'static str {
+        self.backend_app.backend_name()
+    }
+
+    /// Returns the version of the operating system, or `None` if it can't be found out.
+    ///
+    /// On macOS this is the macOS version, such as `14.2.1`. On Windows it is the real
+    /// version, even without a compatibility manifest, with the build number as the patch
+    /// version: Windows 11 is `10.0.22000` and later. On Linux it is the version of the
+    /// kernel. On the BSDs and the web it is always `None`.
+    pub fn os_version(&self) -> Option<OsVersion> {
+        self.backend_app.os_version()
+    }
+
+    /// Calls `callback` with the position of the mouse whenever it moves or a button is
+    /// pressed, even when it is over other applications.
+    ///
+    /// The position is in virtual screen coordinates, so it can be passed to
+    /// [`Screen::pixel_color`] to build an eyedropper. The callback is called on the main
+    /// thread until the returned monitor is dropped.
+    ///
+    /// On GTK this grabs the pointer, so other applications don't receive mouse events while
+    /// the monitor exists.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the platform refuses the monitor, for example because another application
+    /// has grabbed the pointer on GTK. This is not implemented on X11, Wayland and web.
+    ///
+    /// Observing the mouse doesn't need the accessibility permission on macOS, see
+    /// [`check_accessibility_permission`](Application::check_accessibility_permission).
+    ///
+    /// [`Screen::pixel_color`]: crate::Screen::pixel_color
+    pub fn add_global_mouse_monitor(
+        &self,
+        callback: impl FnMut(Point) + 'static,
+    ) -> Result<GlobalMouseMonitor, Error> {
+        let monitor = self
+            .backend_app
+            .add_global_mouse_monitor(Box::new(callback))?;
+        Ok(GlobalMouseMonitor { _backend: monitor })
+    }
+
+    /// Runs `f` on the main thread and returns its result, blocking the calling thread
+    /// until it is done.
+    ///
+    /// This lets other threads use things that only work on the main thread, like the
+    /// clipboard. `f` runs once the main thread gets back to its run loop, so the main thread
+    /// must not be waiting for the calling thread. When called on the main thread, or before
+    /// an [`Application`] is created, `f` runs right away.
+    ///
+    /// # Errors
+    ///
+    /// Fails without running `f` if the main thread can't run it: on Windows when the
+    /// message loop isn't running, and on X11 and Wayland, where this isn't implemented yet.
+    pub fn run_on_main<T: Send + 'static>(
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, Error> {
+        if util::is_main_thread_or_main_unclaimed() {
+            Ok(f())
+        } else {
+            backend::Application::run_on_main(f)
+        }
+    }
+
     /// Returns the current locale string.
     ///
     /// This should a [Unicode language identifier].
@@ -183,4 +900,90 @@ impl Application {
     pub fn get_locale() -> String {
         backend::Application::get_locale()
     }
+
+    /// Returns the current locale as a [BCP-47 language tag], including the user's
+    /// calendar and numbering system preferences as Unicode extensions, such as
+    /// `ja-JP-u-ca-japanese`.
+    ///
+    /// Use this for formatting dates and numbers. Only macOS reports extensions, so on the
+    /// other platforms this is the same as [`get_locale`].
+    ///
+    /// [BCP-47 language tag]: https://www.rfc-editor.org/info/bcp47
+    /// [`get_locale`]: Application::get_locale
+    pub fn get_locale_tag() -> String {
+        backend::Application::get_locale_tag()
+    }
+}
+
+/// Returns whether a command line argument is a URL rather than a path, which is the
+/// case when it starts with a scheme.
+///
+/// Schemes of a single letter are taken to be Windows drive letters.
+#[allow(dead_code)]
+pub(crate) fn is_url(arg: &str) -> bool {
+    match arg.split_once(':') {
+        Some((scheme, _)) => {
+            scheme.len() > 1
+                && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn os_version_parse() {
+        assert_eq!(
+            OsVersion::parse("6.5.0-14-generic"),
+            Some(OsVersion::new(6, 5, 0))
+        );
+        assert_eq!(
+            OsVersion::parse("13.2-RELEASE"),
+            Some(OsVersion::new(13, 2, 0))
+        );
+        assert_eq!(OsVersion::parse("10"), Some(OsVersion::new(10, 0, 0)));
+        assert_eq!(OsVersion::parse("unknown"), None);
+        assert!(OsVersion::new(10, 0, 22000) > OsVersion::new(10, 0, 19045));
+    }
+
+    #[test]
+    fn url_arguments() {
+        assert!(is_url("myapp://open?id=1"));
+        assert!(is_url("mailto:someone@example.com"));
+        assert!(is_url("x-my.app+v2:thing"));
+        assert!(!is_url("C:\\Users\\me\\file.txt"));
+        assert!(!is_url("relative/path.txt"));
+        assert!(!is_url("/tmp/a:b"));
+        assert!(!is_url("1app://x"));
+    }
+
+    #[test]
+    fn layout_direction_from_locale() {
+        assert_eq!(
+            LayoutDirection::from_locale("en-US"),
+            LayoutDirection::LeftToRight
+        );
+        assert_eq!(
+            LayoutDirection::from_locale("ar-EG"),
+            LayoutDirection::RightToLeft
+        );
+        assert_eq!(
+            LayoutDirection::from_locale("he_IL"),
+            LayoutDirection::RightToLeft
+        );
+        assert_eq!(
+            LayoutDirection::from_locale("fa"),
+            LayoutDirection::RightToLeft
+        );
+        assert_eq!(
+            LayoutDirection::from_locale(""),
+            LayoutDirection::LeftToRight
+        );
+    }
 }
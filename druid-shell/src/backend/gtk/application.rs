@@ -14,44 +14,145 @@
 
 //! GTK implementation of features at the application scope.
 
-use gtk::gio::prelude::ApplicationExtManual;
-use gtk::gio::{ApplicationFlags, Cancellable};
-use gtk::Application as GtkApplication;
+use std::cell::{Cell, RefCell};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc;
+use std::time::Duration;
 
-use gtk::prelude::{ApplicationExt, GtkApplicationExt};
+use anyhow::anyhow;
+use gdkx11::X11Display;
+use gtk::gdk::{self, EventMask, GrabStatus, SeatCapabilities};
+use gtk::gio::prelude::{ApplicationExtManual, FileExt, SettingsExt};
+use gtk::gio::{self, AppInfo, AppLaunchContext, ApplicationFlags, Cancellable, File};
+use gtk::glib::prelude::Cast;
+use gtk::{Application as GtkApplication, ApplicationInhibitFlags, Inhibit, Invisible};
 
-use crate::application::AppHandler;
+use gtk::prelude::{
+    ApplicationExt, GtkApplicationExt, GtkWindowExt, ObjectExt, RecentManagerExt, StyleContextExt,
+    WidgetExt, WidgetExtManual,
+};
+use gtk::traits::SettingsExt;
+
+use crate::application::{
+    AppHandler, EndSessionResponse, LayoutDirection, OsVersion, PermissionState, RunOutcome,
+    SystemSound, WindowInfo,
+};
+use crate::keyboard::Modifiers;
+use crate::kurbo::{Point, Size};
+use crate::mouse::MouseButtons;
+use crate::piet::Color;
+use crate::Error as ShellError;
 
 use super::clipboard::Clipboard;
 use super::error::Error;
+use super::menu::Menu;
+use super::power::PowerMonitor;
+use super::session::{self, SessionMonitor};
+use super::window::{get_modifiers, get_mouse_buttons_from_modifiers, WindowHandle};
+
+/// Returns the time of the last user input to the application, as GDK event timestamps.
+fn last_user_time(display: &gdk::Display) -> u32 {
+    match display.downcast_ref::<X11Display>() {
+        Some(display) => display.user_time(),
+        // Other displays only know about the event that is being handled, if there is one.
+        None => gtk::current_event_time(),
+    }
+}
+
+/// The [`AppHandler`], shared between the D-Bus signal handlers.
+pub(crate) type SharedHandler = Rc<RefCell<Option<Box<dyn AppHandler>>>>;
 
 #[derive(Clone)]
 pub(crate) struct Application {
     gtk_app: GtkApplication,
+    quitting: Rc<Cell<bool>>,
+    /// The menu given to [`Application::set_menu`].
+    menu: Rc<RefCell<Option<Menu>>>,
+    /// The windows without a menu of their own, which show `menu` instead.
+    menu_windows: Rc<RefCell<Vec<WindowHandle>>>,
+    /// The cookie of the inhibitor that blocks the end of the session, see
+    /// [`AppHandler::query_end_session`].
+    end_session_inhibitor: Rc<Cell<Option<u32>>>,
+}
+
+/// An invisible window that has grabbed the pointer, so that it gets all pointer events.
+pub(crate) struct GlobalMouseMonitor {
+    seat: gdk::Seat,
+    invisible: Invisible,
+}
+
+impl Drop for GlobalMouseMonitor {
+    fn drop(&mut self) {
+        self.seat.ungrab();
+        unsafe { self.invisible.destroy() };
+    }
 }
 
 impl Application {
     pub fn new() -> Result<Application, Error> {
-        // TODO: we should give control over the application ID to the user
-        let gtk_app = GtkApplication::new(
-            Some("com.github.linebender.druid"),
+        Application::with_id(
+            "com.github.linebender.druid",
             // TODO we set this to avoid connecting to an existing running instance
             // of "com.github.linebender.druid" after which we would never receive
             // the "Activate application" below. See pull request druid#384
             // Which shows another way once we have in place a mechanism for
             // communication with remote instances.
-            ApplicationFlags::NON_UNIQUE,
-        );
+            //
+            // HANDLES_OPEN makes files on the command line arrive through the "open" signal.
+            ApplicationFlags::NON_UNIQUE | ApplicationFlags::HANDLES_OPEN,
+        )
+    }
+
+    pub fn new_single_instance(app_id: &str) -> Result<Application, ShellError> {
+        if !gio::Application::id_is_valid(app_id) {
+            return Err(anyhow!("invalid application id: {}", app_id).into());
+        }
+        // Without NON_UNIQUE, registering finds out whether the id is already owned
+        // on the session bus.
+        let app = Application::with_id(app_id, ApplicationFlags::HANDLES_OPEN)?;
+        if !app.gtk_app.is_remote() {
+            return Ok(app);
+        }
+        // The primary instance receives these through the "open" signal.
+        let files: Vec<File> = std::env::args_os()
+            .skip(1)
+            .map(File::for_commandline_arg)
+            .collect();
+        if files.is_empty() {
+            app.gtk_app.activate();
+        } else {
+            app.gtk_app.open(&files, "");
+        }
+        // The calls are asynchronous, so make sure they are sent before we exit.
+        if let Some(connection) = app.gtk_app.dbus_connection() {
+            if let Err(err) = connection.flush_sync(None as Option<&Cancellable>) {
+                tracing::warn!("failed to forward the launch arguments: {}", err);
+            }
+        }
+        Err(ShellError::AlreadyRunning)
+    }
+
+    fn with_id(app_id: &str, flags: ApplicationFlags) -> Result<Application, Error> {
+        let gtk_app = GtkApplication::new(Some(app_id), flags);
 
         gtk_app.connect_activate(|_app| {
             tracing::info!("gtk: Activated application");
         });
+        // Without this the session manager doesn't send us `query-end`.
+        gtk_app.set_register_session(true);
 
         if let Err(err) = gtk_app.register(None as Option<&Cancellable>) {
             return Err(Error::Error(err));
         }
 
-        Ok(Application { gtk_app })
+        Ok(Application {
+            gtk_app,
+            quitting: Rc::new(Cell::new(false)),
+            menu: Rc::new(RefCell::new(None)),
+            menu_windows: Rc::new(RefCell::new(Vec::new())),
+            end_session_inhibitor: Rc::new(Cell::new(None)),
+        })
     }
 
     #[inline]
@@ -59,20 +160,165 @@ impl Application {
         &self.gtk_app
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {
-        self.gtk_app.run();
+    pub fn run(self, handler: Option<Box<dyn AppHandler>>) -> Result<RunOutcome, anyhow::Error> {
+        let handler = Rc::new(RefCell::new(handler));
+        let _power_monitor = PowerMonitor::new(handler.clone());
+        let _session_monitor = SessionMonitor::new(handler.clone());
+        let keymap = gtk::gdk::Display::default().and_then(|d| gtk::gdk::Keymap::for_display(&d));
+        if let Some(keymap) = &keymap {
+            let handler = handler.clone();
+            keymap.connect_keys_changed(move |_| {
+                with_handler(&handler, |h| h.keyboard_layout_changed());
+            });
+        }
+        {
+            let handler = handler.clone();
+            let active = Rc::new(Cell::new(is_app_active(&self.gtk_app)));
+            self.gtk_app.connect_window_added(move |app, window| {
+                let app = app.downgrade();
+                let handler = handler.clone();
+                let active = active.clone();
+                window.connect_is_active_notify(move |_| {
+                    let app = app.clone();
+                    let handler = handler.clone();
+                    let active = active.clone();
+                    // Focus moving between our windows briefly leaves none of them active,
+                    // so wait until it has settled.
+                    gtk::glib::idle_add_local_once(move || {
+                        let now_active = match app.upgrade() {
+                            Some(app) => is_app_active(&app),
+                            None => return,
+                        };
+                        if active.replace(now_active) != now_active {
+                            with_handler(&handler, |h| {
+                                if now_active {
+                                    h.did_become_active()
+                                } else {
+                                    h.did_resign_active()
+                                }
+                            });
+                        }
+                    });
+                });
+            });
+        }
+        {
+            let handler = handler.clone();
+            self.gtk_app.connect_open(move |_app, files, _hint| {
+                for file in files {
+                    // URLs with a scheme that GIO doesn't know have no local path.
+                    match file.path() {
+                        Some(path) => with_handler(&handler, |h| h.open_file(&path)),
+                        None => with_handler(&handler, |h| h.open_url(&file.uri())),
+                    }
+                }
+            });
+        }
+        {
+            let handler = handler.clone();
+            let inhibitor = self.end_session_inhibitor.clone();
+            // `connect_query_end` needs GTK 3.24.8, so connect to the signal by name.
+            self.gtk_app.connect_local("query-end", false, move |args| {
+                let app = args[0].get::<GtkApplication>().ok()?;
+                let mut response = EndSessionResponse::Allow;
+                with_handler(&handler, |h| response = h.query_end_session());
+                if let EndSessionResponse::Block(reason) = response {
+                    if inhibitor.get().is_none() {
+                        let window = app.active_window();
+                        let cookie = app.inhibit(
+                            window.as_ref(),
+                            ApplicationInhibitFlags::LOGOUT,
+                            Some(&reason),
+                        );
+                        inhibitor.set((cookie != 0).then_some(cookie));
+                    }
+                }
+                None
+            });
+        }
+        if let Some(screen) = gtk::gdk::Screen::default() {
+            screen.connect_monitors_changed(|_| crate::screen::monitors_changed());
+            screen.connect_size_changed(|_| crate::screen::monitors_changed());
+        }
+        // GNOME keeps the region, which the formats follow, in its own settings.
+        let _locale_settings = locale_settings().map(|settings| {
+            let handler = handler.clone();
+            settings.connect_changed(Some("region"), move |_, _| {
+                with_handler(&handler, |h| h.locale_changed());
+            });
+            settings
+        });
+        if let Some(settings) = gtk::Settings::default() {
+            let high_contrast = Cell::new(is_high_contrast_theme(&settings));
+            settings.connect_gtk_theme_name_notify(move |settings| {
+                let now_high_contrast = is_high_contrast_theme(settings);
+                if high_contrast.replace(now_high_contrast) != now_high_contrast {
+                    with_handler(&handler, |h| h.high_contrast_changed(now_high_contrast));
+                }
+            });
+        }
+        let exit_code = self.gtk_app.run();
+        Ok(RunOutcome::new(exit_code, self.quitting.get()))
     }
 
-    pub fn quit(&self) {
-        match self.gtk_app.active_window() {
+    pub fn pump_events(&self, timeout: Option<Duration>) -> bool {
+        let context = gtk::glib::MainContext::default();
+        match timeout {
             None => {
-                // no application is running, main is not running
+                context.iteration(true);
             }
-            Some(_) => {
-                // we still have an active window, close the run loop
-                self.gtk_app.quit();
+            Some(timeout) if timeout.is_zero() => {}
+            Some(timeout) => {
+                // A blocking iteration only wakes up for a source, so add one for the timeout.
+                let fired = Rc::new(Cell::new(false));
+                let source = {
+                    let fired = fired.clone();
+                    gtk::glib::timeout_add_local_once(timeout, move || fired.set(true))
+                };
+                context.iteration(true);
+                if !fired.get() {
+                    source.remove();
+                }
             }
         }
+        while context.pending() {
+            context.iteration(false);
+        }
+        !self.quitting.get()
+    }
+
+    pub fn quit(&self) {
+        // This is also how `pump_events` learns to stop, even when no window is active.
+        // Quitting a `GtkApplication` that isn't running does nothing.
+        self.quitting.set(true);
+        self.gtk_app.quit();
+    }
+
+    pub fn reply_to_end_session(&self, _allow: bool) {
+        // The session manager has already given up on ending the session, so this only
+        // stops blocking the next one.
+        if let Some(cookie) = self.end_session_inhibitor.take() {
+            self.gtk_app.uninhibit(cookie);
+        }
+    }
+
+    pub fn set_menu(&self, menu: Menu) {
+        // GTK only has application menu bars for menu models, which our menus aren't, so
+        // every window without a menu of its own gets a copy.
+        let mut windows = self.menu_windows.borrow_mut();
+        windows.retain(WindowHandle::shows_app_menu);
+        for window in windows.iter() {
+            window.install_menubar(menu.clone());
+        }
+        *self.menu.borrow_mut() = Some(menu);
+    }
+
+    /// Makes `window` show the application's menu, now and whenever it is replaced.
+    ///
+    /// Returns the menu for the window to start with, if there is one yet.
+    pub(crate) fn add_menu_window(&self, window: &WindowHandle) -> Option<Menu> {
+        self.menu_windows.borrow_mut().push(window.clone());
+        self.menu.borrow().clone()
     }
 
     pub fn clipboard(&self) -> Clipboard {
@@ -81,6 +327,222 @@ impl Application {
         }
     }
 
+    pub fn open_uri(&self, uri: &str) -> Result<(), anyhow::Error> {
+        AppInfo::launch_default_for_uri(uri, AppLaunchContext::NONE)
+            .map_err(|err| anyhow!("failed to open {}: {}", uri, err))
+    }
+
+    pub fn reveal_in_file_manager(&self, path: &Path) -> Result<(), anyhow::Error> {
+        // There's no portable way to select a file, so we open the containing directory.
+        let dir = path.parent().unwrap_or(path);
+        self.open_uri(&File::for_path(dir).uri())
+    }
+
+    pub fn note_recent_document(&self, path: &Path) {
+        if let Some(manager) = gtk::RecentManager::default() {
+            manager.add_item(&File::for_path(path).uri());
+        }
+    }
+
+    pub fn clear_recent_documents(&self) {
+        let manager = match gtk::RecentManager::default() {
+            Some(manager) => manager,
+            None => return,
+        };
+        // The list is shared by all applications, so only remove the items we added.
+        let app_name = match gtk::glib::application_name() {
+            Some(app_name) => app_name,
+            None => return,
+        };
+        for info in manager.items() {
+            if !info.has_application(&app_name) {
+                continue;
+            }
+            if let Some(uri) = info.uri() {
+                if let Err(err) = manager.remove_item(&uri) {
+                    tracing::warn!("failed to remove recent document {}: {}", uri, err);
+                }
+            }
+        }
+    }
+
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        // GDK doesn't expose the name of the layout.
+        None
+    }
+
+    pub fn layout_direction(&self) -> LayoutDirection {
+        match gtk::Widget::default_direction() {
+            gtk::TextDirection::Rtl => LayoutDirection::RightToLeft,
+            _ => LayoutDirection::LeftToRight,
+        }
+    }
+
+    pub fn prefers_high_contrast(&self) -> bool {
+        gtk::Settings::default()
+            .map(|settings| is_high_contrast_theme(&settings))
+            .unwrap_or(false)
+    }
+
+    pub fn selection_color(&self) -> Option<Color> {
+        theme_color("theme_selected_bg_color")
+    }
+
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        // Themes don't usually set a cursor color, in which case GTK uses the text color.
+        theme_color("theme_text_color")
+    }
+
+    pub fn is_active(&self) -> bool {
+        is_app_active(&self.gtk_app)
+    }
+
+    pub fn activate(&self, ignore_other_apps: bool) {
+        let window = match self
+            .gtk_app
+            .active_window()
+            .or_else(|| self.gtk_app.windows().into_iter().next())
+        {
+            Some(window) => window,
+            None => return,
+        };
+        if ignore_other_apps {
+            window.present();
+        } else {
+            // We can't tell whether another application has focus, but the window manager
+            // can: given the time of our last user input, its focus stealing prevention only
+            // marks the window as urgent if the user has been using another application since.
+            window.present_with_time(last_user_time(&window.display()));
+        }
+    }
+
+    pub fn beep(&self) {
+        if let Some(display) = gtk::gdk::Display::default() {
+            display.beep();
+        }
+    }
+
+    pub fn play_system_sound(&self, _sound: SystemSound) {
+        // Playing the sounds of the sound theme would need libcanberra.
+        self.beep();
+    }
+
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        session::idle_time()
+    }
+
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn max_cursor_size(&self) -> Size {
+        match gdk::Display::default() {
+            Some(display) => {
+                let (width, height) = display.maximal_cursor_size();
+                Size::new(width as f64, height as f64)
+            }
+            None => Size::new(f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn mouse_buttons(&self) -> MouseButtons {
+        let pointer = gdk::Display::default()
+            .and_then(|display| display.default_seat())
+            .and_then(|seat| seat.pointer());
+        match pointer {
+            Some(pointer) => {
+                let root = gdk::Window::default_root_window();
+                let (_, _, _, mask) = root.device_position(&pointer);
+                get_mouse_buttons_from_modifiers(mask)
+            }
+            None => MouseButtons::new(),
+        }
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        match gdk::Display::default().and_then(|display| gdk::Keymap::for_display(&display)) {
+            Some(keymap) => {
+                let state = gdk::ModifierType::from_bits_truncate(keymap.modifier_state());
+                get_modifiers(state)
+            }
+            None => Modifiers::empty(),
+        }
+    }
+
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        // GDK only knows about the windows of this application.
+        Vec::new()
+    }
+
+    pub fn has_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn request_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn check_accessibility_permission(&self, _prompt: bool) -> PermissionState {
+        PermissionState::NotRequired
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "gtk"
+    }
+
+    pub fn os_version(&self) -> Option<OsVersion> {
+        crate::backend::shared::kernel_version()
+    }
+
+    pub fn add_global_mouse_monitor(
+        &self,
+        callback: Box<dyn FnMut(Point)>,
+    ) -> Result<GlobalMouseMonitor, ShellError> {
+        let seat = gdk::Display::default()
+            .and_then(|display| display.default_seat())
+            .ok_or_else(|| anyhow!("no seat to grab the pointer of"))?;
+        let invisible = Invisible::new();
+        invisible.add_events(EventMask::POINTER_MOTION_MASK | EventMask::BUTTON_PRESS_MASK);
+        let callback = Rc::new(RefCell::new(callback));
+        let motion_callback = callback.clone();
+        invisible.connect_motion_notify_event(move |_, event| {
+            report_pointer(&motion_callback, event.root());
+            Inhibit(true)
+        });
+        invisible.connect_button_press_event(move |_, event| {
+            report_pointer(&callback, event.root());
+            Inhibit(true)
+        });
+        invisible.show();
+        // Dropping this cleans up if the grab fails.
+        let monitor = GlobalMouseMonitor { seat, invisible };
+        let window = monitor
+            .invisible
+            .window()
+            .ok_or_else(|| anyhow!("the invisible window has no GDK window"))?;
+        // Without owner events, the pointer events of our own windows come here too.
+        let status = monitor
+            .seat
+            .grab(&window, SeatCapabilities::POINTER, false, None, None, None);
+        if status != GrabStatus::Success {
+            return Err(anyhow!("failed to grab pointer: {}", status).into());
+        }
+        Ok(monitor)
+    }
+
+    pub fn run_on_main<T: Send + 'static>(
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, crate::Error> {
+        let (sender, receiver) = mpsc::sync_channel(1);
+        gtk::glib::MainContext::default().invoke(move || {
+            // The caller is blocked on the receiver, so it is still there.
+            let _ = sender.send(f());
+        });
+        receiver
+            .recv()
+            .map_err(|_| anyhow!("the main context dropped the function without running it").into())
+    }
+
     pub fn get_locale() -> String {
         let mut locale: String = gtk::glib::language_names()[0].as_str().into();
         // This is done because the locale parsing library we use expects an unicode locale, but these vars have an ISO locale
@@ -89,12 +551,71 @@ impl Application {
         }
         locale
     }
+
+    pub fn get_locale_tag() -> String {
+        Self::get_locale()
+    }
+}
+
+/// Returns GNOME's locale settings, if they are installed.
+fn locale_settings() -> Option<gio::Settings> {
+    const SCHEMA: &str = "org.gnome.system.locale";
+    // Creating settings for a schema that isn't installed aborts the process.
+    gio::SettingsSchemaSource::default()?.lookup(SCHEMA, true)?;
+    Some(gio::Settings::new(SCHEMA))
+}
+
+/// Returns `true` if one of the application's windows has focus.
+fn is_app_active(gtk_app: &GtkApplication) -> bool {
+    gtk_app.windows().iter().any(|window| window.is_active())
+}
+
+/// Returns `true` if the theme is one of the HighContrast themes, such as "HighContrastInverse".
+fn is_high_contrast_theme(settings: &gtk::Settings) -> bool {
+    settings
+        .gtk_theme_name()
+        .map_or(false, |name| name.starts_with("HighContrast"))
+}
+
+/// Looks up one of the colors defined by the theme with `@define-color`.
+fn theme_color(name: &str) -> Option<Color> {
+    let screen = gtk::gdk::Screen::default()?;
+    let context = gtk::StyleContext::new();
+    context.set_screen(&screen);
+    let rgba = context.lookup_color(name)?;
+    Some(Color::rgba(
+        rgba.red(),
+        rgba.green(),
+        rgba.blue(),
+        rgba.alpha(),
+    ))
+}
+
+/// Calls `f` with the [`AppHandler`], if there is one and it isn't already in use.
+pub(crate) fn with_handler(handler: &SharedHandler, f: impl FnOnce(&mut dyn AppHandler)) {
+    match handler.try_borrow_mut() {
+        Ok(mut handler) => {
+            if let Some(handler) = handler.as_mut() {
+                f(&mut **handler);
+            }
+        }
+        Err(_) => tracing::warn!("app handler already borrowed"),
+    }
+}
+
+/// Calls `callback` with a pointer position in root window coordinates.
+fn report_pointer(callback: &RefCell<Box<dyn FnMut(Point)>>, (x, y): (f64, f64)) {
+    // The callback may run a nested main loop, which would deliver another event to it.
+    if let Ok(mut callback) = callback.try_borrow_mut() {
+        callback(Point::new(x, y));
+    }
 }
 
 impl crate::platform::linux::ApplicationExt for crate::Application {
     fn primary_clipboard(&self) -> crate::Clipboard {
-        crate::Clipboard(Clipboard {
+        Clipboard {
             selection: gtk::gdk::SELECTION_PRIMARY,
-        })
+        }
+        .into()
     }
 }
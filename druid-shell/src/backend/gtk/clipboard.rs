@@ -14,10 +14,14 @@
 
 //! Interactions with the system pasteboard on GTK+.
 
+use std::path::PathBuf;
+use std::ptr;
+
 use gtk::gdk::Atom;
+use gtk::glib::translate::ToGlibPtr;
 use gtk::{TargetEntry, TargetFlags};
 
-use crate::clipboard::{ClipboardFormat, FormatId};
+use crate::clipboard::{uri_list, ClipboardFormat, DataProvider, FormatId};
 
 const CLIPBOARD_TARGETS: [&str; 5] = [
     "UTF8_STRING",
@@ -94,6 +98,30 @@ impl Clipboard {
         }
     }
 
+    /// Put data on the system clipboard that is made when it is pasted.
+    pub fn put_lazy(&mut self, format: FormatId, provider: DataProvider) {
+        let display = gtk::gdk::Display::default().unwrap();
+        let clipboard = gtk::Clipboard::for_display(&display, &self.selection);
+        let entries = [TargetEntry::new(format, TargetFlags::all(), 0)];
+        let success = clipboard.set_with_data(&entries, move |_, sel, _| {
+            let data = provider();
+            match (format, std::str::from_utf8(&data)) {
+                (ClipboardFormat::TEXT, Ok(text)) => {
+                    sel.set_text(text);
+                }
+                _ => sel.set(&Atom::intern(format), 8, &data),
+            }
+        });
+        if !success {
+            tracing::warn!("failed to set clipboard data.");
+            return;
+        }
+        // Lets the clipboard manager take the data when we quit, which calls the provider.
+        unsafe {
+            gtk::ffi::gtk_clipboard_set_can_store(clipboard.to_glib_none().0, ptr::null(), 0);
+        }
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         let display = gtk::gdk::Display::default().unwrap();
@@ -109,6 +137,19 @@ impl Clipboard {
         None
     }
 
+    /// Put a list of files onto the system clipboard.
+    pub fn put_file_list(&mut self, paths: &[PathBuf]) {
+        let list = uri_list::from_paths(paths);
+        self.put_formats(&[ClipboardFormat::new(uri_list::URI_LIST, list)]);
+    }
+
+    /// Get the list of files on the system clipboard, if there is one.
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        let list = self.get_format(uri_list::URI_LIST)?;
+        let paths = uri_list::to_paths(std::str::from_utf8(&list).ok()?);
+        (!paths.is_empty()).then(|| paths)
+    }
+
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
     pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
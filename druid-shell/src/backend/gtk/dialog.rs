@@ -15,8 +15,10 @@
 //! File open/save dialogs, GTK implementation.
 
 use std::ffi::OsString;
+use std::path::Path;
 
 use anyhow::anyhow;
+use gtk::gio::{self, prelude::FileExt};
 use gtk::{FileChooserAction, FileFilter, ResponseType, Window};
 
 use gtk::prelude::{FileChooserExt, NativeDialogExt};
@@ -33,6 +35,29 @@ fn file_filter(fs: &FileSpec) -> FileFilter {
     ret
 }
 
+/// Returns the MIME type of the file at `path`.
+///
+/// Existing files are queried through gio, so that their contents can be sniffed.
+/// Otherwise the type is guessed from the file name alone.
+pub(crate) fn content_type(path: &Path) -> Option<String> {
+    let queried = gio::File::for_path(path)
+        .query_info(
+            "standard::content-type",
+            gio::FileQueryInfoFlags::NONE,
+            gio::Cancellable::NONE,
+        )
+        .ok()
+        .and_then(|info| info.content_type());
+    let content_type = match queried {
+        Some(content_type) => content_type,
+        None => {
+            let (guess, _uncertain) = gio::content_type_guess(Some(path), &[]);
+            guess
+        }
+    };
+    gio::content_type_get_mime_type(&content_type).map(|mime| mime.to_string())
+}
+
 pub(crate) fn get_file_dialog_path(
     window: &Window,
     ty: FileDialogType,
@@ -20,7 +20,7 @@ use gtk::{
 };
 use gtk_rs::SeparatorMenuItem;
 
-use gtk::prelude::{GtkMenuExt, GtkMenuItemExt, MenuShellExt, WidgetExt};
+use gtk::prelude::{Cast, GtkMenuExt, GtkMenuItemExt, MenuShellExt, ObjectExt, WidgetExt};
 
 use super::keycodes;
 use super::window::WindowHandle;
@@ -28,12 +28,12 @@ use crate::common_util::strip_access_key;
 use crate::hotkey::{HotKey, RawMods};
 use crate::keyboard::{KbKey, Modifiers};
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Menu {
     items: Vec<MenuItem>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum MenuItem {
     Entry {
         name: String,
@@ -44,6 +44,8 @@ enum MenuItem {
     },
     SubMenu(String, Menu),
     Separator,
+    /// A section header, which is an insensitive item with the title.
+    Section(String),
 }
 
 impl Menu {
@@ -82,6 +84,13 @@ impl Menu {
         self.items.push(MenuItem::Separator)
     }
 
+    pub fn add_section(&mut self, title: &str) {
+        if !self.items.is_empty() {
+            self.items.push(MenuItem::Separator);
+        }
+        self.items.push(MenuItem::Section(strip_access_key(title)));
+    }
+
     fn append_items_to_menu<M: gtk::prelude::IsA<gtk::MenuShell>>(
         self,
         menu: &mut M,
@@ -115,6 +124,11 @@ impl Menu {
                     menu.append(&item);
                 }
                 MenuItem::Separator => menu.append(&SeparatorMenuItem::new()),
+                MenuItem::Section(title) => {
+                    let item = GtkMenuItem::with_label(&title);
+                    item.set_sensitive(false);
+                    menu.append(&item);
+                }
             }
         }
     }
@@ -159,6 +173,23 @@ fn add_menu_entry<
         register_accelerator(item, accel_group, k);
     }
 
+    // The menu owns the item, so only a weak reference can be kept in its handler.
+    let weak_item = item.downgrade();
+    let show_handle = handle.clone();
+    let shell = menu.upcast_ref::<gtk::MenuShell>();
+    shell.connect_show(move |_| {
+        if let (Some(item), Some(state)) = (weak_item.upgrade(), show_handle.state.upgrade()) {
+            match state.handler.try_borrow_mut() {
+                Ok(mut handler) => {
+                    if let Some(enabled) = handler.menu_item_enabled(id) {
+                        item.set_sensitive(enabled);
+                    }
+                }
+                Err(_) => tracing::warn!("handler already borrowed when showing a menu"),
+            }
+        }
+    });
+
     let handle = handle.clone();
     item.connect_activate(move |_| {
         if let Some(state) = handle.state.upgrade() {
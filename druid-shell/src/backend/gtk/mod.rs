@@ -20,6 +20,8 @@ pub mod dialog;
 pub mod error;
 pub mod keycodes;
 pub mod menu;
+pub mod power;
 pub mod screen;
+pub mod session;
 pub mod util;
 pub mod window;
@@ -0,0 +1,173 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Sleep, wake and power source notifications, using logind and UPower over D-Bus.
+
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs::File;
+use std::os::unix::io::FromRawFd;
+
+use gtk::gio::prelude::UnixFDListExtManual;
+use gtk::gio::{
+    self, BusType, Cancellable, DBusCallFlags, DBusConnection, DBusSignalFlags,
+    SignalSubscriptionId, UnixFDList,
+};
+use gtk::glib::{self, ToVariant};
+
+use crate::application::PowerSource;
+
+use super::application::{with_handler, SharedHandler};
+
+pub(crate) const LOGIND_NAME: &str = "org.freedesktop.login1";
+pub(crate) const LOGIND_PATH: &str = "/org/freedesktop/login1";
+pub(crate) const LOGIND_MANAGER: &str = "org.freedesktop.login1.Manager";
+
+const UPOWER_NAME: &str = "org.freedesktop.UPower";
+const UPOWER_PATH: &str = "/org/freedesktop/UPower";
+
+pub(crate) const PROPERTIES: &str = "org.freedesktop.DBus.Properties";
+
+/// Forwards system power events to the [`AppHandler`](crate::AppHandler) for as long as it is alive.
+pub(crate) struct PowerMonitor {
+    connection: DBusConnection,
+    subscriptions: Vec<SignalSubscriptionId>,
+}
+
+impl PowerMonitor {
+    /// Starts listening for power events on the system bus.
+    ///
+    /// Returns `None` if the system bus isn't available.
+    pub fn new(handler: SharedHandler) -> Option<PowerMonitor> {
+        let connection = match gio::bus_get_sync(BusType::System, Cancellable::NONE) {
+            Ok(connection) => connection,
+            Err(err) => {
+                tracing::warn!("failed to connect to the system bus: {}", err);
+                return None;
+            }
+        };
+
+        // logind only waits for us before suspending if we hold a "delay" inhibitor lock.
+        let inhibitor = RefCell::new(take_sleep_inhibitor(&connection));
+        let sleep_handler = handler.clone();
+        let sleep = connection.signal_subscribe(
+            Some(LOGIND_NAME),
+            Some(LOGIND_MANAGER),
+            Some("PrepareForSleep"),
+            Some(LOGIND_PATH),
+            None,
+            DBusSignalFlags::NONE,
+            move |connection, _, _, _, _, params| {
+                let (starting,) = match params.get::<(bool,)>() {
+                    Some(params) => params,
+                    None => return,
+                };
+                if starting {
+                    with_handler(&sleep_handler, |h| h.system_will_sleep());
+                    // Releasing the lock lets the system go to sleep.
+                    inhibitor.borrow_mut().take();
+                } else {
+                    inhibitor.replace(take_sleep_inhibitor(connection));
+                    with_handler(&sleep_handler, |h| h.system_did_wake());
+                }
+            },
+        );
+
+        let on_battery = Cell::new(query_on_battery(&connection));
+        let power = connection.signal_subscribe(
+            Some(UPOWER_NAME),
+            Some(PROPERTIES),
+            Some("PropertiesChanged"),
+            Some(UPOWER_PATH),
+            Some(UPOWER_NAME),
+            DBusSignalFlags::NONE,
+            move |_, _, _, _, _, params| {
+                let changed = params
+                    .child_value(1)
+                    .get::<HashMap<String, glib::Variant>>()
+                    .and_then(|props| props.get("OnBattery").and_then(|v| v.get::<bool>()));
+                if let Some(now_on_battery) = changed {
+                    if on_battery.replace(Some(now_on_battery)) != Some(now_on_battery) {
+                        let source = if now_on_battery {
+                            PowerSource::Battery
+                        } else {
+                            PowerSource::AC
+                        };
+                        with_handler(&handler, |h| h.power_source_changed(source));
+                    }
+                }
+            },
+        );
+
+        Some(PowerMonitor {
+            connection,
+            subscriptions: vec![sleep, power],
+        })
+    }
+}
+
+impl Drop for PowerMonitor {
+    fn drop(&mut self) {
+        for id in self.subscriptions.drain(..) {
+            self.connection.signal_unsubscribe(id);
+        }
+    }
+}
+
+/// Asks logind to delay sleep until the returned file is closed.
+fn take_sleep_inhibitor(connection: &DBusConnection) -> Option<File> {
+    let params = ("sleep", "druid", "Notifying the application", "delay").to_variant();
+    let result = connection.call_with_unix_fd_list_sync(
+        Some(LOGIND_NAME),
+        LOGIND_PATH,
+        LOGIND_MANAGER,
+        "Inhibit",
+        Some(&params),
+        None,
+        DBusCallFlags::NONE,
+        -1,
+        UnixFDList::NONE,
+        Cancellable::NONE,
+    );
+    match result {
+        Ok((_, fds)) => {
+            let fd = fds.steal_fds().into_iter().next()?;
+            // Safety: we took ownership of the descriptor out of the list.
+            Some(unsafe { File::from_raw_fd(fd) })
+        }
+        Err(err) => {
+            tracing::warn!("failed to take sleep inhibitor lock: {}", err);
+            None
+        }
+    }
+}
+
+fn query_on_battery(connection: &DBusConnection) -> Option<bool> {
+    connection
+        .call_sync(
+            Some(UPOWER_NAME),
+            UPOWER_PATH,
+            PROPERTIES,
+            "Get",
+            Some(&(UPOWER_NAME, "OnBattery").to_variant()),
+            None,
+            DBusCallFlags::NONE,
+            -1,
+            Cancellable::NONE,
+        )
+        .ok()?
+        .child_value(0)
+        .as_variant()?
+        .get::<bool>()
+}
@@ -14,9 +14,33 @@
 
 //! GTK Monitors and Screen information.
 
+use crate::backend::shared::strut;
+use crate::error::Error;
 use crate::kurbo::{Point, Rect, Size};
-use crate::screen::Monitor;
-use gtk::gdk::{Display, DisplayManager, Rectangle};
+use crate::piet::{ImageBuf, ImageFormat};
+use crate::scale::Scale;
+use crate::screen::{is_internal_connector, DisplayMode, Monitor, SubpixelOrder};
+use anyhow::anyhow;
+use gdkx11::{X11Display, X11Screen};
+use gtk::gdk::prelude::WindowExtManual;
+use gtk::gdk::{Display, DisplayManager, Rectangle, SubpixelLayout};
+use gtk::glib::prelude::Cast;
+use std::cell::RefCell;
+use std::ffi::c_void;
+use std::rc::Rc;
+use x11rb::connection::Connection;
+use x11rb::xcb_ffi::XCBConnection;
+
+#[link(name = "X11-xcb")]
+extern "C" {
+    fn XGetXCBConnection(display: *mut c_void) -> *mut c_void;
+}
+
+thread_local! {
+    /// The x11rb connections that share GDK's connection to each X11 display.
+    static X11_CONNECTIONS: RefCell<Vec<(X11Display, Rc<XCBConnection>)>> =
+        RefCell::new(Vec::new());
+}
 
 fn translate_gdk_rectangle(r: Rectangle) -> Rect {
     Rect::from_origin_size(
@@ -25,13 +49,29 @@ fn translate_gdk_rectangle(r: Rectangle) -> Rect {
     )
 }
 
-fn translate_gdk_monitor(mon: gtk::gdk::Monitor) -> Monitor {
+fn translate_gdk_monitor(mon: gtk::gdk::Monitor, work_area: Rect, is_x11: bool) -> Monitor {
     let area = translate_gdk_rectangle(mon.geometry());
-    Monitor::new(
-        mon.is_primary(),
-        area,
-        translate_gdk_rectangle(mon.workarea()),
-    )
+    let scale = mon.scale_factor() as f64;
+    let mut monitor = Monitor::new(mon.is_primary(), area, work_area);
+    // On Wayland the model is the monitor's real model, which identical monitors share.
+    if let Some(connector) = mon.model().filter(|_| is_x11) {
+        monitor = monitor.with_device_id(connector);
+    }
+    monitor
+        .with_scale(Scale::new(scale, scale))
+        // On X11 GDK reports the RandR output name as the model, like `eDP-1`.
+        .with_internal(
+            mon.model()
+                .map_or(false, |model| is_internal_connector(&model)),
+        )
+        .with_subpixel_order(match mon.subpixel_layout() {
+            SubpixelLayout::HorizontalRgb => SubpixelOrder::Rgb,
+            SubpixelLayout::HorizontalBgr => SubpixelOrder::Bgr,
+            SubpixelLayout::VerticalRgb => SubpixelOrder::VRgb,
+            SubpixelLayout::VerticalBgr => SubpixelOrder::VBgr,
+            SubpixelLayout::None => SubpixelOrder::None,
+            _ => SubpixelOrder::Unknown,
+        })
 }
 
 pub(crate) fn get_monitors() -> Vec<Monitor> {
@@ -44,9 +84,196 @@ pub(crate) fn get_monitors() -> Vec<Monitor> {
     DisplayManager::get()
         .list_displays()
         .iter()
-        .flat_map(|display: &Display| {
-            (0..display.n_monitors())
-                .filter_map(move |i| display.monitor(i).map(translate_gdk_monitor))
+        .flat_map(display_monitors)
+        .collect()
+}
+
+fn display_monitors(display: &Display) -> Vec<Monitor> {
+    let monitors: Vec<gtk::gdk::Monitor> = (0..display.n_monitors())
+        .filter_map(|i| display.monitor(i))
+        .collect();
+    let mut work_areas: Vec<Rect> = monitors
+        .iter()
+        .map(|mon| translate_gdk_rectangle(mon.workarea()))
+        .collect();
+    // Some window managers don't tell GDK about panels, so it reports the whole monitor.
+    let reports_geometry = |mon: &gtk::gdk::Monitor| mon.workarea() == mon.geometry();
+    let x11_display = display.downcast_ref::<X11Display>();
+    let is_x11 = x11_display.is_some();
+    let use_struts = strut::fallback_enabled() && monitors.iter().any(reports_geometry);
+    if let Some(x11_display) = x11_display.filter(|_| use_struts) {
+        match strut_work_areas(x11_display, &monitors) {
+            Ok(areas) => {
+                for ((work_area, mon), area) in work_areas.iter_mut().zip(&monitors).zip(areas) {
+                    if reports_geometry(mon) {
+                        *work_area = area;
+                    }
+                }
+            }
+            Err(err) => tracing::warn!("failed to get the work areas from struts: {}", err),
+        }
+    }
+    monitors
+        .into_iter()
+        .zip(work_areas)
+        .map(|(mon, work_area)| translate_gdk_monitor(mon, work_area, is_x11))
+        .collect()
+}
+
+/// Returns an x11rb connection that shares GDK's connection to `display`.
+fn x11_connection(display: &X11Display) -> Result<Rc<XCBConnection>, anyhow::Error> {
+    X11_CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        if let Some((_, conn)) = connections.iter().find(|(other, _)| other == display) {
+            return Ok(Rc::clone(conn));
+        }
+        // GDK owns the connection, so dropping ours must not close it.
+        let conn = unsafe {
+            let xcb = XGetXCBConnection(display.xdisplay() as *mut c_void);
+            XCBConnection::from_raw_xcb_connection(xcb, false)?
+        };
+        let conn = Rc::new(conn);
+        connections.push((display.clone(), Rc::clone(&conn)));
+        display.connect_closed(|display, _| {
+            X11_CONNECTIONS.with(|connections| {
+                connections
+                    .borrow_mut()
+                    .retain(|(other, _)| other.upcast_ref::<Display>() != display)
+            });
+        });
+        Ok(conn)
+    })
+}
+
+/// Computes the work areas of `monitors` from the struts of the other X11 clients.
+fn strut_work_areas(
+    display: &X11Display,
+    monitors: &[gtk::gdk::Monitor],
+) -> Result<Vec<Rect>, anyhow::Error> {
+    let conn = x11_connection(display)?;
+    let screen_num = display
+        .default_screen()
+        .downcast::<X11Screen>()
+        .map_or(0, |screen| screen.screen_number() as usize);
+    let screen = &conn.setup().roots[screen_num];
+    // Struts are in device pixels, while GDK scales the monitor geometry down.
+    let scale = |mon: &gtk::gdk::Monitor| mon.scale_factor() as f64;
+    let rects: Vec<Rect> = monitors
+        .iter()
+        .map(|mon| translate_gdk_rectangle(mon.geometry()).scale_from_origin(scale(mon)))
+        .collect();
+    let size = Size::new(
+        screen.width_in_pixels as f64,
+        screen.height_in_pixels as f64,
+    );
+    let areas = strut::work_areas(conn.as_ref(), screen.root, size, &rects)?;
+    Ok(areas
+        .into_iter()
+        .zip(monitors)
+        .map(|(area, mon)| area.scale_from_origin(1.0 / scale(mon)))
+        .collect())
+}
+
+pub(crate) fn num_monitors() -> usize {
+    if !gtk::is_initialized() {
+        if let Err(err) = gtk::init() {
+            tracing::error!("{}", err.message);
+            return 0;
+        }
+    }
+    DisplayManager::get()
+        .list_displays()
+        .iter()
+        .map(|display| display.n_monitors() as usize)
+        .sum()
+}
+
+pub(crate) fn get_mouse_position() -> Option<Point> {
+    if !gtk::is_initialized() {
+        if let Err(err) = gtk::init() {
+            tracing::error!("{}", err.message);
+            return None;
+        }
+    }
+    let pointer = DisplayManager::get()
+        .default_display()?
+        .default_seat()?
+        .pointer()?;
+    let (_, x, y) = pointer.position_double();
+    Some(Point::new(x, y))
+}
+
+pub(crate) fn display_modes(monitor: &Monitor) -> Vec<DisplayMode> {
+    if !gtk::is_initialized() {
+        if let Err(err) = gtk::init() {
+            tracing::error!("{}", err.message);
+            return Vec::new();
+        }
+    }
+    DisplayManager::get()
+        .list_displays()
+        .iter()
+        .flat_map(|display| (0..display.n_monitors()).filter_map(|i| display.monitor(i)))
+        .find(|mon| translate_gdk_rectangle(mon.geometry()) == monitor.virtual_rect())
+        .map(|mon| {
+            // GDK only reports the current mode, and scales the geometry down by the scale factor.
+            let scale = mon.scale_factor() as f64;
+            let refresh = mon.refresh_rate();
+            DisplayMode::new(
+                monitor.virtual_rect().size() * scale,
+                (refresh > 0).then(|| refresh as f64 / 1000.0),
+                Scale::new(scale, scale),
+            )
+            .with_current(true)
         })
+        .into_iter()
         .collect()
 }
+
+pub(crate) fn set_mode(_monitor: &Monitor, _mode: &DisplayMode) -> Result<(), Error> {
+    // GDK doesn't change modes, and on Wayland only the compositor can.
+    tracing::warn!("Monitor::set_mode is unimplemented for the GTK backend");
+    Err(anyhow!("changing the display mode is not supported with GTK").into())
+}
+
+pub(crate) fn capture_region(rect: Rect) -> Result<ImageBuf, Error> {
+    if !gtk::is_initialized() {
+        gtk::init().map_err(|err| anyhow!("{}", err.message))?;
+    }
+    let rect = rect.round();
+    // This only works on X11, since Wayland doesn't let clients read the screen.
+    let pixbuf = gtk::gdk::Window::default_root_window()
+        .pixbuf(
+            rect.x0 as i32,
+            rect.y0 as i32,
+            rect.width() as i32,
+            rect.height() as i32,
+        )
+        .ok_or_else(|| anyhow!("failed to capture {}", rect))?;
+    let bytes = pixbuf
+        .read_pixel_bytes()
+        .ok_or_else(|| anyhow!("failed to read the captured pixels"))?;
+    let width = pixbuf.width() as usize;
+    let height = pixbuf.height() as usize;
+    let channels = pixbuf.n_channels() as usize;
+    let mut pixels = Vec::with_capacity(width * height * 4);
+    // The last row can be shorter than the rowstride.
+    for row in bytes.chunks(pixbuf.rowstride() as usize).take(height) {
+        for pixel in row[..width * channels].chunks_exact(channels) {
+            pixels.extend_from_slice(&pixel[..3]);
+            pixels.push(if channels == 4 { pixel[3] } else { 255 });
+        }
+    }
+    Ok(ImageBuf::from_raw(
+        pixels,
+        ImageFormat::RgbaSeparate,
+        width,
+        height,
+    ))
+}
+
+impl crate::platform::linux::ScreenExt for crate::Screen {
+    fn set_work_area_fallback(enabled: bool) {
+        strut::set_fallback_enabled(enabled);
+    }
+}
@@ -0,0 +1,195 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Session lock notifications and idle time, using the screensaver and logind over D-Bus.
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use gtk::gio::{
+    self, BusType, Cancellable, DBusCallFlags, DBusConnection, DBusSignalFlags,
+    SignalSubscriptionId,
+};
+use gtk::glib::{self, ToVariant};
+
+use super::application::{with_handler, SharedHandler};
+use super::power::{LOGIND_MANAGER, LOGIND_NAME, LOGIND_PATH, PROPERTIES};
+
+const LOGIND_SESSION: &str = "org.freedesktop.login1.Session";
+
+/// Desktops disagree on which of these to implement, and some implement both.
+const SCREENSAVERS: &[&str] = &["org.freedesktop.ScreenSaver", "org.gnome.ScreenSaver"];
+
+/// Forwards session lock events to the [`AppHandler`](crate::AppHandler) for as long as
+/// it is alive.
+///
+/// Locking is reported by several sources, which may or may not all fire,
+/// so we keep track of the lock state and only report changes to it.
+pub(crate) struct SessionMonitor {
+    subscriptions: Vec<(DBusConnection, SignalSubscriptionId)>,
+}
+
+impl SessionMonitor {
+    pub fn new(handler: SharedHandler) -> SessionMonitor {
+        let locked = Rc::new(Cell::new(false));
+        let mut subscriptions = Vec::new();
+
+        // The screensaver reports being activated both by the user and by the idle timeout.
+        match gio::bus_get_sync(BusType::Session, Cancellable::NONE) {
+            Ok(connection) => {
+                for interface in SCREENSAVERS {
+                    let handler = handler.clone();
+                    let locked = locked.clone();
+                    let id = connection.signal_subscribe(
+                        None,
+                        Some(*interface),
+                        Some("ActiveChanged"),
+                        None,
+                        None,
+                        DBusSignalFlags::NONE,
+                        move |_, _, _, _, _, params| {
+                            if let Some((active,)) = params.get::<(bool,)>() {
+                                set_locked(&handler, &locked, active);
+                            }
+                        },
+                    );
+                    subscriptions.push((connection.clone(), id));
+                }
+            }
+            Err(err) => tracing::warn!("failed to connect to the session bus: {}", err),
+        }
+
+        // Lock screens also set logind's `LockedHint`, which covers ones that
+        // don't implement the screensaver interface.
+        match gio::bus_get_sync(BusType::System, Cancellable::NONE) {
+            Ok(connection) => {
+                if let Some(path) = session_path(&connection) {
+                    let id = connection.signal_subscribe(
+                        Some(LOGIND_NAME),
+                        Some(PROPERTIES),
+                        Some("PropertiesChanged"),
+                        Some(&path),
+                        Some(LOGIND_SESSION),
+                        DBusSignalFlags::NONE,
+                        move |_, _, _, _, _, params| {
+                            let hint = params
+                                .child_value(1)
+                                .get::<HashMap<String, glib::Variant>>()
+                                .and_then(|props| {
+                                    props.get("LockedHint").and_then(|v| v.get::<bool>())
+                                });
+                            if let Some(hint) = hint {
+                                set_locked(&handler, &locked, hint);
+                            }
+                        },
+                    );
+                    subscriptions.push((connection, id));
+                }
+            }
+            Err(err) => tracing::warn!("failed to connect to the system bus: {}", err),
+        }
+
+        SessionMonitor { subscriptions }
+    }
+}
+
+impl Drop for SessionMonitor {
+    fn drop(&mut self) {
+        for (connection, id) in self.subscriptions.drain(..) {
+            connection.signal_unsubscribe(id);
+        }
+    }
+}
+
+fn set_locked(handler: &SharedHandler, locked: &Cell<bool>, now_locked: bool) {
+    if locked.replace(now_locked) == now_locked {
+        return;
+    }
+    if now_locked {
+        with_handler(handler, |h| h.session_locked());
+    } else {
+        with_handler(handler, |h| h.session_unlocked());
+    }
+}
+
+/// Returns the object path of the logind session this process belongs to.
+fn session_path(connection: &DBusConnection) -> Option<String> {
+    let result = connection.call_sync(
+        Some(LOGIND_NAME),
+        LOGIND_PATH,
+        LOGIND_MANAGER,
+        "GetSessionByPID",
+        Some(&(std::process::id(),).to_variant()),
+        None,
+        DBusCallFlags::NONE,
+        -1,
+        Cancellable::NONE,
+    );
+    match result {
+        Ok(reply) => reply.child_value(0).str().map(Into::into),
+        Err(err) => {
+            tracing::warn!("failed to find the logind session: {}", err);
+            None
+        }
+    }
+}
+
+/// Returns how long the user has been idle, from GNOME's idle monitor or
+/// the freedesktop screensaver, whichever is available.
+pub(crate) fn idle_time() -> Option<Duration> {
+    let connection = match gio::bus_get_sync(BusType::Session, Cancellable::NONE) {
+        Ok(connection) => connection,
+        Err(err) => {
+            tracing::warn!("failed to connect to the session bus: {}", err);
+            return None;
+        }
+    };
+    let call = |name, path, interface, method| {
+        connection
+            .call_sync(
+                Some(name),
+                path,
+                interface,
+                method,
+                None,
+                None,
+                DBusCallFlags::NONE,
+                -1,
+                Cancellable::NONE,
+            )
+            .ok()
+    };
+    // Mutter reports milliseconds.
+    if let Some(reply) = call(
+        "org.gnome.Mutter.IdleMonitor",
+        "/org/gnome/Mutter/IdleMonitor/Core",
+        "org.gnome.Mutter.IdleMonitor",
+        "GetIdletime",
+    ) {
+        if let Some((millis,)) = reply.get::<(u64,)>() {
+            return Some(Duration::from_millis(millis));
+        }
+    }
+    // KDE and others report seconds.
+    let reply = call(
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+        "org.freedesktop.ScreenSaver",
+        "GetSessionIdleTime",
+    )?;
+    let (seconds,) = reply.get::<(u32,)>()?;
+    Some(Duration::from_secs(seconds.into()))
+}
@@ -19,15 +19,17 @@ use std::convert::TryInto;
 use std::ffi::c_void;
 use std::os::raw::{c_int, c_uint};
 use std::panic::Location;
+use std::path::Path;
 use std::ptr;
 use std::slice;
 use std::sync::{Arc, Mutex, Weak};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use gtk::gdk_pixbuf::Colorspace::Rgb;
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::glib::source::Continue;
-use gtk::glib::translate::FromGlib;
+use gtk::glib::subclass::SignalId;
+use gtk::glib::translate::{FromGlib, ToGlibPtr};
 use gtk::prelude::*;
 use gtk::traits::SettingsExt;
 use gtk::{AccelGroup, ApplicationWindow, DrawingArea};
@@ -37,7 +39,8 @@ use gdk_sys::GdkKeymapKey;
 use anyhow::anyhow;
 use cairo::Surface;
 use gtk::gdk::{
-    EventKey, EventMask, EventType, ModifierType, ScrollDirection, Window, WindowTypeHint,
+    AxisUse, CursorType, EventKey, EventMask, EventType, GrabStatus, Gravity, InputSource,
+    ModifierType, NotifyType, Rectangle, ScrollDirection, SeatCapabilities, Window, WindowTypeHint,
 };
 
 use instant::Duration;
@@ -47,19 +50,23 @@ use tracing::{error, warn};
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle, XcbWindowHandle};
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::piet::{Color, Piet, PietText, RenderContext};
 
-use crate::common_util::{ClickCounter, IdleCallback};
-use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
+use crate::accessibility::AccessibilityTree;
+use crate::common_util::{AnimationClock, ClickCounter, CursorStack, IdleCallback};
+use crate::dialog::{file_info, FileDialogOptions, FileDialogType};
 use crate::error::Error as ShellError;
-use crate::keyboard::{KbKey, KeyEvent, KeyState, Modifiers};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::keyboard::{KbKey, KeyEvent, KeyState, ModifierSides, Modifiers};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::piet::ImageFormat;
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
+use crate::screen::Screen;
 use crate::text::{simulate_input, Event};
+use crate::touch::{TouchEvent, TouchId, TouchPhase};
 use crate::window::{
     self, FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    WindowType,
 };
 
 use super::application::Application;
@@ -73,6 +80,10 @@ use super::util;
 /// GTK considers 96 the default value which represents a 1.0 scale factor.
 const SCALE_TARGET_DPI: f64 = 96.0;
 
+/// How long a window has to stay put before we report that it moved, so that a drag is
+/// reported once rather than at every step.
+const MOVE_REPORT_DELAY: Duration = Duration::from_millis(100);
+
 /// Taken from <https://gtk-rs.org/docs-src/tutorial/closures>
 /// It is used to reduce the boilerplate of setting up gtk callbacks
 /// Example:
@@ -135,7 +146,7 @@ unsafe impl HasRawWindowHandle for WindowHandle {
 enum DeferredOp {
     SaveAs(FileDialogOptions, FileDialogToken),
     Open(FileDialogOptions, FileDialogToken),
-    ContextMenu(Menu, WindowHandle),
+    ContextMenu(Menu, Point, WindowHandle),
 }
 
 /// Builder abstraction for creating new windows
@@ -152,7 +163,11 @@ pub(crate) struct WindowBuilder {
     resizable: bool,
     show_titlebar: bool,
     transparent: bool,
+    background_color: Option<Color>,
     always_on_top: bool,
+    owner: Option<WindowHandle>,
+    modal: bool,
+    window_type: WindowType,
 }
 
 #[derive(Clone)]
@@ -175,6 +190,8 @@ pub(crate) struct WindowState {
     scale: Cell<Scale>,
     area: Cell<ScaledArea>,
     is_transparent: Cell<bool>,
+    // The style of the window's background, which shows where we haven't painted yet.
+    background_css: gtk::CssProvider,
     handle_titlebar: Cell<bool>,
     /// Used to determine whether to honor close requests from the system: we inhibit them unless
     /// this is true, and this gets set to true when our client requests a close.
@@ -198,14 +215,31 @@ pub(crate) struct WindowState {
     pub(crate) handler: RefCell<Box<dyn WinHandler>>,
     idle_queue: Arc<Mutex<Vec<IdleKind>>>,
     current_keycode: Cell<Option<u16>>,
+    // GDK doesn't say which side's modifier keys are pressed, so we track them.
+    mod_sides: Cell<ModifierSides>,
     click_counter: ClickCounter,
     active_text_input: Cell<Option<TextFieldToken>>,
     deferred_queue: RefCell<Vec<DeferredOp>>,
 
     request_animation: Cell<bool>,
     in_draw: Cell<bool>,
+    // Timed by the frame clock, which is synchronized with the display.
+    anim_clock: Cell<AnimationClock<Duration>>,
 
     parent: Option<crate::WindowHandle>,
+
+    // The most recently set and pushed cursors, which we restore when the cursor is shown again.
+    cursor: RefCell<CursorStack>,
+    // The number of unbalanced `set_cursor_visible(false)` calls.
+    cursor_hide_count: Cell<u32>,
+    cursor_confined: Cell<bool>,
+    // Whether we last reported the mouse as inside the window.
+    mouse_inside: Cell<bool>,
+    // Whether the window shows the application's menu, rather than one of its own.
+    app_menu: Cell<bool>,
+    // The position from the last configure event, and the pending report of a move.
+    configured_position: Cell<Option<(i32, i32)>>,
+    move_report: Cell<Option<gtk::glib::SourceId>>,
 }
 
 impl std::fmt::Debug for WindowState {
@@ -235,7 +269,11 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             transparent: false,
+            background_color: None,
             always_on_top: false,
+            owner: None,
+            modal: false,
+            window_type: WindowType::Normal,
         }
     }
 
@@ -267,6 +305,10 @@ impl WindowBuilder {
         self.transparent = transparent;
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_position(&mut self, position: Point) {
         self.position = Some(position);
     }
@@ -275,6 +317,18 @@ impl WindowBuilder {
         self.level = Some(level);
     }
 
+    pub fn set_parent(&mut self, parent: WindowHandle) {
+        self.owner = Some(parent);
+    }
+
+    pub fn set_modal(&mut self, modal: bool) {
+        self.modal = modal;
+    }
+
+    pub fn set_type(&mut self, window_type: WindowType) {
+        self.window_type = window_type;
+    }
+
     pub fn set_window_state(&mut self, state: window::WindowState) {
         self.state = Some(state);
     }
@@ -306,6 +360,13 @@ impl WindowBuilder {
             }
         }
         window.set_app_paintable(transparent);
+        let background_css = gtk::CssProvider::new();
+        window
+            .style_context()
+            .add_provider(&background_css, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION);
+        if let Some(color) = self.background_color {
+            set_background_css(&background_css, color);
+        }
 
         // Get the scale factor based on the GTK reported DPI
         let scale_factor = window.display().default_screen().resolution() / SCALE_TARGET_DPI;
@@ -327,9 +388,11 @@ impl WindowBuilder {
         if let Some(level) = &self.level {
             let hint = match level {
                 WindowLevel::AppWindow => WindowTypeHint::Normal,
+                WindowLevel::Floating => WindowTypeHint::Utility,
                 WindowLevel::Tooltip(_) => WindowTypeHint::Tooltip,
                 WindowLevel::DropDown(_) => WindowTypeHint::DropdownMenu,
                 WindowLevel::Modal(_) => WindowTypeHint::Dialog,
+                WindowLevel::Overlay => WindowTypeHint::Notification,
             };
 
             window.set_type_hint(hint);
@@ -346,6 +409,9 @@ impl WindowBuilder {
                     window.set_urgency_hint(true);
                     window.set_modal(true);
                 }
+                WindowLevel::Floating | WindowLevel::Overlay => {
+                    window.set_keep_above(true);
+                }
                 _ => (),
             };
             if let Some(parent) = &parent {
@@ -355,11 +421,25 @@ impl WindowBuilder {
             }
         }
 
+        if self.window_type == WindowType::Popup {
+            window.set_type_hint(WindowTypeHint::PopupMenu);
+            window.set_decorated(false);
+            window.set_accept_focus(false);
+            window.set_focus_on_map(false);
+        }
+
+        // GTK blocks input to the owner of a modal window until it is destroyed.
+        if let Some(owner_state) = self.owner.and_then(|owner| owner.state.upgrade()) {
+            window.set_transient_for(Some(&owner_state.window));
+            window.set_modal(self.modal);
+        }
+
         let state = WindowState {
             window,
             scale: Cell::new(scale),
             area: Cell::new(area),
             is_transparent: Cell::new(transparent),
+            background_css,
             handle_titlebar: Cell::new(false),
             closing: Cell::new(false),
             drawing_area,
@@ -369,12 +449,21 @@ impl WindowBuilder {
             handler: RefCell::new(handler),
             idle_queue: Arc::new(Mutex::new(vec![])),
             current_keycode: Cell::new(None),
+            mod_sides: Cell::new(ModifierSides::empty()),
             click_counter: ClickCounter::default(),
             active_text_input: Cell::new(None),
             deferred_queue: RefCell::new(Vec::new()),
             request_animation: Cell::new(false),
             in_draw: Cell::new(false),
+            anim_clock: Cell::new(AnimationClock::default()),
             parent,
+            cursor: RefCell::new(CursorStack::default()),
+            cursor_hide_count: Cell::new(0),
+            cursor_confined: Cell::new(false),
+            mouse_inside: Cell::new(false),
+            app_menu: Cell::new(self.menu.is_none()),
+            configured_position: Cell::new(None),
+            move_report: Cell::new(None),
         };
 
         let win_state = Arc::new(state);
@@ -400,7 +489,13 @@ impl WindowBuilder {
             handle.set_window_state(state)
         }
 
-        if let Some(menu) = self.menu {
+        // Only application windows show the application menu, not tooltips or dropdowns.
+        let menu = match (self.menu, &self.level) {
+            (Some(menu), _) => Some(menu),
+            (None, None | Some(WindowLevel::AppWindow)) => self.app.add_menu_window(&handle),
+            (None, Some(_)) => None,
+        };
+        if let Some(menu) = menu {
             let menu = menu.into_gtk_menubar(&handle, &accel_group);
             vbox.pack_start(&menu, false, false, 0);
         }
@@ -416,6 +511,7 @@ impl WindowBuilder {
                 | EventMask::KEY_RELEASE_MASK
                 | EventMask::SCROLL_MASK
                 | EventMask::SMOOTH_SCROLL_MASK
+                | EventMask::TOUCH_MASK
                 | EventMask::FOCUS_CHANGE_MASK,
         );
 
@@ -493,6 +589,15 @@ impl WindowBuilder {
                     state.invalidate_rect(size_dp.to_rect());
                 }
 
+                if let Some(clock) = widget.frame_clock() {
+                    let mut anim_clock = state.anim_clock.get();
+                    let frame_time = Duration::from_micros(clock.frame_time() as u64);
+                    let interval = anim_clock.frame(frame_time);
+                    state.anim_clock.set(anim_clock);
+                    if let Some(interval) = interval {
+                        state.with_handler(|h| h.anim_frame(interval));
+                    }
+                }
                 state.with_handler(|h| h.prepare_paint());
 
                 let invalid = match state.invalid.try_borrow_mut() {
@@ -557,6 +662,7 @@ impl WindowBuilder {
 
         win_state.drawing_area.connect_button_press_event(clone!(handle => move |_widget, event| {
             if let Some(state) = handle.state.upgrade() {
+                dismiss_windows(|other| !Arc::ptr_eq(other, &state));
                 state.with_handler(|handler| {
                     if let Some(button) = get_mouse_button(event.button()) {
                         let scale = state.scale.get();
@@ -575,15 +681,21 @@ impl WindowBuilder {
                             0
                         };
                         if gtk_count == 0 || gtk_count == 1 {
+                            let buttons = get_mouse_buttons_from_modifiers(button_state).with(button);
+                            let (pointer_type, pressure, tilt_x, tilt_y) = get_pointer_details(event, buttons);
                             handler.mouse_down(
                                 &MouseEvent {
                                     pos: pos.to_dp(scale),
-                                    buttons: get_mouse_buttons_from_modifiers(button_state).with(button),
+                                    buttons,
                                     mods: get_modifiers(button_state),
                                     count,
                                     focus: false,
                                     button,
-                                    wheel_delta: Vec2::ZERO
+                                    wheel_delta: Vec2::ZERO,
+                                    pointer_type,
+                                    pressure,
+                                    tilt_x,
+                                    tilt_y,
                                 },
                             );
                         }
@@ -604,15 +716,21 @@ impl WindowBuilder {
                     if let Some(button) = get_mouse_button(event.button()) {
                         let scale = state.scale.get();
                         let button_state = event.state();
+                        let buttons = get_mouse_buttons_from_modifiers(button_state).without(button);
+                        let (pointer_type, pressure, tilt_x, tilt_y) = get_pointer_details(event, buttons);
                         handler.mouse_up(
                             &MouseEvent {
                                 pos: Point::from(event.position()).to_dp(scale),
-                                buttons: get_mouse_buttons_from_modifiers(button_state).without(button),
+                                buttons,
                                 mods: get_modifiers(button_state),
                                 count: 0,
                                 focus: false,
                                 button,
-                                wheel_delta: Vec2::ZERO
+                                wheel_delta: Vec2::ZERO,
+                                pointer_type,
+                                pressure,
+                                tilt_x,
+                                tilt_y,
                             },
                         );
                         if button.is_left() {
@@ -630,14 +748,20 @@ impl WindowBuilder {
                 if let Some(state) = handle.state.upgrade() {
                     let scale = state.scale.get();
                     let motion_state = motion.state();
+                    let buttons = get_mouse_buttons_from_modifiers(motion_state);
+                    let (pointer_type, pressure, tilt_x, tilt_y) = get_pointer_details(motion, buttons);
                     let mouse_event = MouseEvent {
                         pos: Point::from(motion.position()).to_dp(scale),
-                        buttons: get_mouse_buttons_from_modifiers(motion_state),
+                        buttons,
                         mods: get_modifiers(motion_state),
                         count: 0,
                         focus: false,
                         button: MouseButton::None,
-                        wheel_delta: Vec2::ZERO
+                        wheel_delta: Vec2::ZERO,
+                        pointer_type,
+                        pressure,
+                        tilt_x,
+                        tilt_y,
                     };
 
                     state.with_handler(|h| h.mouse_move(&mouse_event));
@@ -647,10 +771,51 @@ impl WindowBuilder {
             }),
         );
 
+        win_state.drawing_area.connect_touch_event(clone!(handle => move |_widget, event| {
+            if let Some(state) = handle.state.upgrade() {
+                let phase = match event.event_type() {
+                    EventType::TouchBegin => TouchPhase::Began,
+                    EventType::TouchUpdate => TouchPhase::Moved,
+                    EventType::TouchEnd => TouchPhase::Ended,
+                    EventType::TouchCancel => TouchPhase::Cancelled,
+                    _ => return Inhibit(false),
+                };
+                // GTK identifies touches by their sequence, which stays the same while the
+                // finger is down.
+                if let (Some(sequence), Some(coords)) = (event.event_sequence(), event.coords()) {
+                    let touch_event = TouchEvent {
+                        id: TouchId::from_raw(sequence.as_ptr() as usize as u64),
+                        pos: Point::from(coords).to_dp(state.scale.get()),
+                        phase,
+                    };
+                    state.with_handler(|h| h.touch(&touch_event));
+                }
+            }
+
+            Inhibit(true)
+        }));
+
+        win_state.drawing_area.connect_enter_notify_event(
+            clone!(handle => move |_widget, crossing| {
+                if let Some(state) = handle.state.upgrade() {
+                    // Crossings from a child window don't change whether we're inside.
+                    let inferior = crossing.detail() == NotifyType::Inferior;
+                    if !inferior && !state.mouse_inside.replace(true) {
+                        state.with_handler(|h| h.mouse_enter());
+                    }
+                }
+
+                Inhibit(true)
+            }),
+        );
+
         win_state.drawing_area.connect_leave_notify_event(
-            clone!(handle => move |_widget, _crossing| {
+            clone!(handle => move |_widget, crossing| {
                 if let Some(state) = handle.state.upgrade() {
-                    state.with_handler(|h| h.mouse_leave());
+                    let inferior = crossing.detail() == NotifyType::Inferior;
+                    if !inferior && state.mouse_inside.replace(false) {
+                        state.with_handler(|h| h.mouse_leave());
+                    }
                 }
 
                 Inhibit(true)
@@ -695,14 +860,20 @@ impl WindowBuilder {
                     };
 
                     if let Some(wheel_delta) = wheel_delta {
+                        let buttons = get_mouse_buttons_from_modifiers(scroll.state());
+                        let (pointer_type, pressure, tilt_x, tilt_y) = get_pointer_details(scroll, buttons);
                         let mouse_event = MouseEvent {
                             pos: Point::from(scroll.position()).to_dp(scale),
-                            buttons: get_mouse_buttons_from_modifiers(scroll.state()),
+                            buttons,
                             mods,
                             count: 0,
                             focus: false,
                             button: MouseButton::None,
-                            wheel_delta
+                            wheel_delta,
+                            pointer_type,
+                            pressure,
+                            tilt_x,
+                            tilt_y,
                         };
 
                         state.with_handler(|h| h.wheel(&mouse_event));
@@ -722,8 +893,9 @@ impl WindowBuilder {
 
                     state.current_keycode.set(Some(hw_keycode));
 
+                    let event = state.make_key_event(key, repeat, KeyState::Down);
                     state.with_handler(|h|
-                        simulate_input(h, state.active_text_input.get(), make_key_event(key, repeat, KeyState::Down))
+                        simulate_input(h, state.active_text_input.get(), event)
                     );
                 }
 
@@ -740,9 +912,8 @@ impl WindowBuilder {
                     }
 
 
-                    state.with_handler(|h|
-                        h.key_up(make_key_event(key, false, KeyState::Up))
-                    );
+                    let event = state.make_key_event(key, false, KeyState::Up);
+                    state.with_handler(|h| h.key_up(event));
                 }
 
                 Inhibit(true)
@@ -766,12 +937,68 @@ impl WindowBuilder {
                 Inhibit(true)
             }));
 
+        win_state
+            .window
+            .connect_window_state_event(clone!(handle => move |_widget, event| {
+                let iconified = gtk::gdk::WindowState::ICONIFIED;
+                if event.changed_mask().contains(iconified) {
+                    let visible = !event.new_window_state().contains(iconified);
+                    if let Some(state) = handle.state.upgrade() {
+                        state.with_handler(|h| h.occlusion_changed(visible));
+                    }
+                }
+                Inhibit(false)
+            }));
+
+        win_state
+            .window
+            .connect_configure_event(clone!(handle => move |_widget, event| {
+                if let Some(state) = handle.state.upgrade() {
+                    // This is also sent when the window is resized, and when it is first shown.
+                    let position = Some(event.position());
+                    let old = state.configured_position.replace(position);
+                    if old.is_some() && old != position {
+                        if let Some(source) = state.move_report.take() {
+                            source.remove();
+                        }
+                        let source = gtk::glib::timeout_add_local_once(
+                            MOVE_REPORT_DELAY,
+                            clone!(handle => move || report_move(&handle)),
+                        );
+                        state.move_report.set(Some(source));
+                    }
+                }
+                false
+            }));
+
+        win_state
+            .window
+            .connect_is_active_notify(clone!(handle => move |window| {
+                if window.is_active() {
+                    return;
+                }
+                if let Some(state) = handle.state.upgrade() {
+                    dismiss_windows(|other| Arc::ptr_eq(other, &state));
+                }
+                // Once focus has moved on, check whether it left the application.
+                gtk::glib::idle_add_local_once(|| {
+                    let active = gtk::Window::list_toplevels().iter().any(|widget| {
+                        widget
+                            .downcast_ref::<gtk::Window>()
+                            .map_or(false, |window| window.is_active())
+                    });
+                    if !active {
+                        dismiss_windows(|_| true);
+                    }
+                });
+            }));
+
         win_state
             .window
             .connect_delete_event(clone!(handle => move |_widget, _ev| {
                 if let Some(state) = handle.state.upgrade() {
-                    state.with_handler(|h| h.request_close());
-                    Inhibit(!state.closing.get())
+                    let should_close = state.with_handler(|h| h.should_close()).unwrap_or(false);
+                    Inhibit(!(should_close || state.closing.get()))
                 } else {
                     Inhibit(false)
                 }
@@ -795,7 +1022,7 @@ impl WindowBuilder {
 
         if let Some(level) = self.level {
             let override_redirect = match level {
-                WindowLevel::AppWindow => false,
+                WindowLevel::AppWindow | WindowLevel::Floating | WindowLevel::Overlay => false,
                 WindowLevel::Tooltip(_) | WindowLevel::DropDown(_) | WindowLevel::Modal(_) => true,
             };
             if let Some(window) = win_state.window.window() {
@@ -815,6 +1042,31 @@ impl WindowBuilder {
 }
 
 impl WindowState {
+    /// Makes a key event, keeping track of the sides of the pressed modifier keys.
+    fn make_key_event(&self, key: &EventKey, repeat: bool, state: KeyState) -> KeyEvent {
+        let mut event = make_key_event(key, repeat, state);
+        let mut mod_sides = self.mod_sides.get();
+        mod_sides.update(&event);
+        self.mod_sides.set(mod_sides);
+        event.mod_sides = mod_sides;
+        event
+    }
+
+    /// Shows the current cursor, unless the cursor is hidden.
+    fn update_cursor(&self) {
+        if self.cursor_hide_count.get() > 0 {
+            return;
+        }
+        if let Some(gdk_window) = self.window.window() {
+            let cursor = self
+                .cursor
+                .borrow()
+                .current()
+                .and_then(|cursor| make_gdk_cursor(cursor, &gdk_window));
+            gdk_window.set_cursor(cursor.as_ref());
+        }
+    }
+
     #[track_caller]
     fn with_handler<T, F: FnOnce(&mut dyn WinHandler) -> T>(&self, f: F) -> Option<T> {
         if self.invalid.try_borrow_mut().is_err() || self.surface.try_borrow_mut().is_err() {
@@ -881,6 +1133,9 @@ impl WindowState {
     /// Queues a call to `prepare_paint` and `paint`, but without marking any region for
     /// invalidation.
     fn request_anim_frame(&self) {
+        let mut anim_clock = self.anim_clock.get();
+        anim_clock.request();
+        self.anim_clock.set(anim_clock);
         if self.in_draw.get() {
             self.request_animation.set(true);
         } else {
@@ -893,9 +1148,15 @@ impl WindowState {
         if let Ok(mut region) = self.invalid.try_borrow_mut() {
             let scale = self.scale.get();
             // We prefer to invalidate an integer number of pixels.
-            let rect = rect.to_px(scale).expand().to_dp(scale);
-            region.add_rect(rect);
-            self.window.queue_draw();
+            let rect = rect.to_px(scale).expand();
+            region.add_rect(rect.to_dp(scale));
+            // GTK coalesces the queued areas, and only redraws those.
+            self.drawing_area.queue_draw_area(
+                rect.x0 as i32,
+                rect.y0 as i32,
+                rect.width() as i32,
+                rect.height() as i32,
+            );
         } else {
             warn!("Not invalidating rect because region already borrowed");
         }
@@ -920,10 +1181,7 @@ impl WindowState {
                     ) {
                         Ok(infos) => infos
                             .iter()
-                            .map(|path| FileInfo {
-                                path: path.into(),
-                                format: None,
-                            })
+                            .map(|path| file_info(path.into(), dialog::content_type))
                             .collect(),
                         Err(err) => {
                             tracing::error!("Error trying to open file: {}", err);
@@ -943,22 +1201,28 @@ impl WindowState {
                         options,
                     )
                     .ok()
-                    .map(|s| FileInfo {
-                        // `get_file_dialog_path` guarantees that save dialogs
-                        // only return on path
-                        path: s.first().unwrap().into(),
-                        format: None,
-                    });
+                    // `get_file_dialog_path` guarantees that save dialogs
+                    // only return on path
+                    .map(|s| file_info(s.first().unwrap().into(), dialog::content_type));
                     self.with_handler(|h| h.save_as(token, file_info));
                 }
-                DeferredOp::ContextMenu(menu, handle) => {
+                DeferredOp::ContextMenu(menu, pos, handle) => {
                     let accel_group = AccelGroup::new();
                     self.window.add_accel_group(&accel_group);
 
                     let menu = menu.into_gtk_menu(&handle, &accel_group);
                     menu.set_attach_widget(Some(&self.window));
                     menu.show_all();
-                    menu.popup_easy(3, gtk::current_event_time());
+                    match self.drawing_area.window() {
+                        Some(window) => {
+                            let pos = pos.to_px(self.scale.get()).round();
+                            let rect = Rectangle::new(pos.x as i32, pos.y as i32, 1, 1);
+                            // By default the menu flips and slides to stay on screen.
+                            let gravity = Gravity::NorthWest;
+                            menu.popup_at_rect(&window, &rect, gravity, gravity, None);
+                        }
+                        None => menu.popup_easy(3, gtk::current_event_time()),
+                    }
                 }
             }
         }
@@ -972,12 +1236,49 @@ impl WindowHandle {
         }
     }
 
-    pub fn resizable(&self, resizable: bool) {
+    pub fn set_resizable(&self, resizable: bool) {
         if let Some(state) = self.state.upgrade() {
+            if !resizable {
+                // Non-resizable windows shrink to their default size, so pin it to the current one.
+                let (width, height) = state.window.size();
+                state.window.set_default_size(width, height);
+            }
             state.window.set_resizable(resizable)
         }
     }
 
+    pub fn set_aspect_ratio(&self, ratio: Option<f64>) {
+        if let Some(state) = self.state.upgrade() {
+            match ratio {
+                Some(ratio) => {
+                    let geometry = gtk::gdk::Geometry::new(
+                        0,
+                        0,
+                        0,
+                        0,
+                        0,
+                        0,
+                        0,
+                        0,
+                        ratio,
+                        ratio,
+                        gtk::gdk::Gravity::NorthWest,
+                    );
+                    state.window.set_geometry_hints(
+                        None::<&gtk::Widget>,
+                        Some(&geometry),
+                        gtk::gdk::WindowHints::ASPECT,
+                    );
+                }
+                None => state.window.set_geometry_hints(
+                    None::<&gtk::Widget>,
+                    None,
+                    gtk::gdk::WindowHints::empty(),
+                ),
+            }
+        }
+    }
+
     pub fn show_titlebar(&self, show_titlebar: bool) {
         if let Some(state) = self.state.upgrade() {
             state.window.set_decorated(show_titlebar)
@@ -1133,6 +1434,61 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_always_on_bottom(&self, on_bottom: bool) {
+        if let Some(state) = self.state.upgrade() {
+            let window = &state.window;
+            // Window managers only read the type hint when the window is mapped.
+            if !window.is_mapped() {
+                window.set_type_hint(if on_bottom {
+                    WindowTypeHint::Desktop
+                } else {
+                    WindowTypeHint::Normal
+                });
+            }
+            window.set_keep_below(on_bottom);
+            window.set_accept_focus(!on_bottom);
+            window.set_skip_taskbar_hint(on_bottom);
+            window.set_skip_pager_hint(on_bottom);
+            if on_bottom {
+                window.stick();
+            } else {
+                window.unstick();
+            }
+        }
+    }
+
+    pub fn set_level(&self, level: WindowLevel) {
+        if let Some(state) = self.state.upgrade() {
+            // The type hint can't be changed once the window is shown.
+            let keep_above = !matches!(level, WindowLevel::AppWindow | WindowLevel::Modal(_));
+            state.window.set_keep_above(keep_above);
+        }
+    }
+
+    pub fn set_dismiss_on_focus_loss(&self, dismiss: bool) {
+        if let Some(state) = self.state.upgrade() {
+            DISMISSABLE_WINDOWS.with(|windows| {
+                let mut windows = windows.borrow_mut();
+                windows.retain(|window| !Weak::ptr_eq(window, &self.state));
+                if dismiss {
+                    windows.push(Arc::downgrade(&state));
+                }
+            });
+        }
+    }
+
+    pub fn set_opacity(&self, opacity: f64) {
+        if let Some(state) = self.state.upgrade() {
+            state.window.set_opacity(opacity);
+        }
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        if let Some(state) = self.state.upgrade() {
+            set_background_css(&state.background_css, color);
+        }
+    }
+
     pub fn handle_titlebar(&self, val: bool) {
         if let Some(state) = self.state.upgrade() {
             state.handle_titlebar.set(val);
@@ -1185,6 +1541,21 @@ impl WindowHandle {
         }
     }
 
+    pub fn paint_now(&self) {
+        if let Some(state) = self.state.upgrade() {
+            if let Some(window) = state.window.window() {
+                // This is deprecated in favor of the frame clock, but it still draws the queued
+                // areas right away.
+                unsafe {
+                    gtk::gdk::ffi::gdk_window_process_updates(
+                        window.to_glib_none().0,
+                        gtk::glib::ffi::GTRUE,
+                    );
+                }
+            }
+        }
+    }
+
     pub fn text(&self) -> PietText {
         PietText::new()
     }
@@ -1211,6 +1582,14 @@ impl WindowHandle {
         // noop until we get a real text input implementation
     }
 
+    pub fn set_ime_enabled(&self, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
+    pub fn set_text_field_ime_enabled(&self, _token: TextFieldToken, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
     pub fn request_timer(&self, deadline: Instant) -> TimerToken {
         let interval = deadline
             .checked_duration_since(Instant::now())
@@ -1230,15 +1609,113 @@ impl WindowHandle {
     }
 
     pub fn set_cursor(&mut self, cursor: &Cursor) {
-        if let Some(gdk_window) = self.state.upgrade().and_then(|s| s.window.window()) {
-            let cursor = make_gdk_cursor(cursor, &gdk_window);
-            gdk_window.set_cursor(cursor.as_ref());
+        if let Some(state) = self.state.upgrade() {
+            if state.cursor.borrow_mut().set(cursor) {
+                state.update_cursor();
+            }
+        }
+    }
+
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        if let Some(state) = self.state.upgrade() {
+            state.cursor.borrow_mut().push(cursor);
+            state.update_cursor();
+        }
+    }
+
+    pub fn pop_cursor(&mut self) {
+        if let Some(state) = self.state.upgrade() {
+            if state.cursor.borrow_mut().pop() {
+                state.update_cursor();
+            }
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(state) = self.state.upgrade() {
+            let count = state.cursor_hide_count.get();
+            let new_count = if visible {
+                count.saturating_sub(1)
+            } else {
+                count + 1
+            };
+            state.cursor_hide_count.set(new_count);
+            if (count == 0) == (new_count == 0) {
+                return;
+            }
+            if new_count == 0 {
+                state.update_cursor();
+            } else if let Some(gdk_window) = state.window.window() {
+                let cursor =
+                    gtk::gdk::Cursor::for_display(&gdk_window.display(), CursorType::BlankCursor);
+                gdk_window.set_cursor(cursor.as_ref());
+            }
         }
     }
 
+    pub fn confine_cursor(&self, confine: bool) {
+        if let Some(state) = self.state.upgrade() {
+            if state.cursor_confined.replace(confine) == confine {
+                return;
+            }
+            if let Some(gdk_window) = state.window.window() {
+                if let Some(seat) = gdk_window.display().default_seat() {
+                    if confine {
+                        // Grab the pointer, so that its events keep coming to this window.
+                        let status = seat.grab(
+                            &gdk_window,
+                            SeatCapabilities::POINTER,
+                            true,
+                            None,
+                            None,
+                            None,
+                        );
+                        if status != GrabStatus::Success {
+                            warn!("failed to grab pointer: {}", status);
+                            state.cursor_confined.set(false);
+                        }
+                    } else {
+                        seat.ungrab();
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn announce(&self, message: &str, politeness: window::Politeness) {
+        if let Some(accessible) = self.state.upgrade().and_then(|s| s.window.accessible()) {
+            // `notification` was added in ATK 2.50, and `announcement` in ATK 2.46.
+            if SignalId::lookup("notification", accessible.type_()).is_some() {
+                // The values of `AtkLive`.
+                let live: i32 = match politeness {
+                    window::Politeness::Polite => 1,
+                    window::Politeness::Assertive => 2,
+                };
+                accessible.emit_by_name::<()>("notification", &[&message, &live]);
+            } else if SignalId::lookup("announcement", accessible.type_()).is_some() {
+                accessible.emit_by_name::<()>("announcement", &[&message]);
+            } else {
+                warn!("announcements need ATK 2.46 or later");
+            }
+        }
+    }
+
+    pub fn set_accessibility_tree(&self, _tree: AccessibilityTree) {
+        warn!("WindowHandle::set_accessibility_tree is currently unimplemented for GTK.");
+    }
+
     pub fn make_cursor(&self, desc: &CursorDesc) -> Option<Cursor> {
         if let Some(state) = self.state.upgrade() {
             if let Some(gdk_window) = state.window.window() {
+                let (max_width, max_height) = gdk_window.display().maximal_cursor_size();
+                let (width, height) = (desc.image.width(), desc.image.height());
+                if width > max_width as usize || height > max_height as usize {
+                    warn!(
+                        "cursor image is {}x{}, larger than the maximum of {}x{}",
+                        width, height, max_width, max_height
+                    );
+                    return None;
+                }
                 // TODO: Pixbuf expects unpremultiplied alpha. We should convert.
                 let has_alpha = !matches!(desc.image.format(), ImageFormat::Rgb);
                 let bytes_per_pixel = desc.image.format().bytes_per_pixel();
@@ -1307,6 +1784,23 @@ impl WindowHandle {
     }
 
     pub fn set_menu(&self, menu: Menu) {
+        if let Some(state) = self.state.upgrade() {
+            state.app_menu.set(false);
+        }
+        self.install_menubar(menu);
+    }
+
+    /// Whether the window shows the menu set with [`Application::set_menu`].
+    ///
+    /// [`Application::set_menu`]: super::application::Application::set_menu
+    pub(crate) fn shows_app_menu(&self) -> bool {
+        self.state
+            .upgrade()
+            .map_or(false, |state| state.app_menu.get())
+    }
+
+    /// Replaces the window's menu bar with `menu`.
+    pub(crate) fn install_menubar(&self, menu: Menu) {
         if let Some(state) = self.state.upgrade() {
             let window = &state.window;
             let accel_group = AccelGroup::new();
@@ -1325,9 +1819,9 @@ impl WindowHandle {
         }
     }
 
-    pub fn show_context_menu(&self, menu: Menu, _pos: Point) {
+    pub fn show_context_menu(&self, menu: Menu, pos: Point) {
         if let Some(state) = self.state.upgrade() {
-            state.defer(DeferredOp::ContextMenu(menu, self.clone()));
+            state.defer(DeferredOp::ContextMenu(menu, pos, self.clone()));
         }
     }
 
@@ -1336,6 +1830,10 @@ impl WindowHandle {
             state.window.set_title(&(title.into()));
         }
     }
+
+    pub fn set_represented_file(&self, _path: Option<&Path>) {}
+
+    pub fn set_document_edited(&self, _edited: bool) {}
 }
 
 // WindowState needs to be Send + Sync so it can be passed into glib closures.
@@ -1382,6 +1880,51 @@ impl IdleHandle {
     }
 }
 
+thread_local! {
+    /// The windows that close when the user clicks outside of them or switches apps.
+    static DISMISSABLE_WINDOWS: RefCell<Vec<Weak<WindowState>>> = RefCell::new(Vec::new());
+}
+
+/// Closes the windows that close on focus loss, if `dismiss` returns true for them.
+///
+/// A window that never takes focus doesn't know when it is clicked outside of, so every
+/// click in one of our windows dismisses the others.
+fn dismiss_windows(dismiss: impl Fn(&Arc<WindowState>) -> bool) {
+    let dismissed: Vec<Arc<WindowState>> = DISMISSABLE_WINDOWS.with(|windows| {
+        let mut dismissed = Vec::new();
+        windows
+            .borrow_mut()
+            .retain(|window| match window.upgrade() {
+                Some(state) if dismiss(&state) => {
+                    dismissed.push(state);
+                    false
+                }
+                Some(_) => true,
+                None => false,
+            });
+        dismissed
+    });
+    for state in dismissed {
+        state.with_handler(|h| h.dismissed());
+        state.closing.set(true);
+        state.window.close();
+    }
+}
+
+/// Tells the handler where the window is now, and which monitor it is on.
+fn report_move(handle: &WindowHandle) {
+    if let Some(state) = handle.state.upgrade() {
+        // The report has run, so its source must not be removed anymore.
+        state.move_report.take();
+        let (x, y) = state.window.position();
+        let (width, height) = state.window.size();
+        // The monitors are in the same coordinates as the window's position.
+        let frame = Rect::new(x as f64, y as f64, (x + width) as f64, (y + height) as f64);
+        let position = handle.get_position();
+        state.with_handler(|h| h.window_moved(position, Screen::monitor_for_rect(frame)));
+    }
+}
+
 fn run_idle(state: &Arc<WindowState>) -> Continue {
     util::assert_main_thread();
     let result = state.with_handler(|handler| {
@@ -1407,6 +1950,18 @@ fn run_idle(state: &Arc<WindowState>) -> Continue {
     Continue(false)
 }
 
+/// Makes `provider` give the window the background `color`.
+fn set_background_css(provider: &gtk::CssProvider, color: Color) {
+    let (r, g, b, a) = color.as_rgba8();
+    let css = format!(
+        "window {{ background-color: rgba({r}, {g}, {b}, {}); }}",
+        a as f64 / 255.0
+    );
+    if let Err(err) = provider.load_from_data(css.as_bytes()) {
+        tracing::warn!("failed to set the window background: {}", err);
+    }
+}
+
 fn make_gdk_cursor(cursor: &Cursor, gdk_window: &Window) -> Option<gtk::gdk::Cursor> {
     if let Cursor::Custom(custom) = cursor {
         Some(custom.0.clone())
@@ -1424,6 +1979,8 @@ fn make_gdk_cursor(cursor: &Cursor, gdk_window: &Window) -> Option<gtk::gdk::Cur
                 Cursor::NotAllowed => "not-allowed",
                 Cursor::ResizeLeftRight => "ew-resize",
                 Cursor::ResizeUpDown => "ns-resize",
+                Cursor::Wait => "wait",
+                Cursor::Progress => "progress",
                 Cursor::Custom(_) => unreachable!(),
             },
         )
@@ -1442,7 +1999,28 @@ fn get_mouse_button(button: u32) -> Option<MouseButton> {
     }
 }
 
-fn get_mouse_buttons_from_modifiers(modifiers: ModifierType) -> MouseButtons {
+/// Returns the pointer type, pressure and tilt of a button or motion event.
+fn get_pointer_details(
+    event: &gtk::gdk::Event,
+    buttons: MouseButtons,
+) -> (PointerType, f64, f64, f64) {
+    let pointer_type = match event.source_device().map(|device| device.source()) {
+        Some(InputSource::Pen) => PointerType::Pen,
+        Some(InputSource::Eraser) => PointerType::Eraser,
+        Some(InputSource::Touchscreen) => PointerType::Touch,
+        Some(InputSource::Touchpad) => PointerType::Touchpad,
+        _ => PointerType::Mouse,
+    };
+    let pressure = event
+        .axis(AxisUse::Pressure)
+        .unwrap_or_else(|| buttons.default_pressure());
+    // GDK reports tilt from -1.0 to 1.0.
+    let tilt_x = event.axis(AxisUse::Xtilt).unwrap_or(0.0) * 90.0;
+    let tilt_y = event.axis(AxisUse::Ytilt).unwrap_or(0.0) * 90.0;
+    (pointer_type, pressure, tilt_x, tilt_y)
+}
+
+pub(crate) fn get_mouse_buttons_from_modifiers(modifiers: ModifierType) -> MouseButtons {
     let mut buttons = MouseButtons::new();
     if modifiers.contains(ModifierType::BUTTON1_MASK) {
         buttons.insert(MouseButton::Left);
@@ -1490,7 +2068,7 @@ const MODIFIER_MAP: &[(ModifierType, Modifiers)] = &[
     (ModifierType::MOD4_MASK, Modifiers::META),
 ];
 
-fn get_modifiers(modifiers: ModifierType) -> Modifiers {
+pub(crate) fn get_modifiers(modifiers: ModifierType) -> Modifiers {
     let mut result = Modifiers::empty();
     for &(gdk_mod, modifier) in MODIFIER_MAP {
         if modifiers.contains(gdk_mod) {
@@ -1529,6 +2107,7 @@ fn make_key_event(key: &EventKey, repeat: bool, state: KeyState) -> KeyEvent {
         code,
         location,
         mods,
+        mod_sides: ModifierSides::empty(),
         repeat,
         is_composing,
     }
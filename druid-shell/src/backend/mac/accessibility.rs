@@ -0,0 +1,164 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Exposing an [`AccessibilityTree`] through `NSAccessibilityElement`s.
+
+use cocoa::base::{id, nil, YES};
+use cocoa::foundation::{NSArray, NSPoint, NSRect, NSSize};
+use objc::{class, msg_send, sel, sel_impl};
+
+use super::appkit::{
+    NSAccessibilityButtonRole, NSAccessibilityFocusedUIElementChangedNotification,
+    NSAccessibilityGroupRole, NSAccessibilityLayoutChangedNotification, NSAccessibilityListRole,
+    NSAccessibilityPostNotification, NSAccessibilityRowRole, NSAccessibilityStaticTextRole,
+};
+use super::util::make_nsstring;
+use crate::accessibility::{AccessibilityNode, AccessibilityNodeId, AccessibilityTree, Role};
+use crate::kurbo::Rect;
+
+/// The accessibility elements of a view.
+pub(super) struct AccessibilityState {
+    /// The top level elements, as a retained `NSArray`, or `nil`.
+    children: id,
+    /// The focused element, which is owned by `children`, or `nil`.
+    focus: id,
+    focus_id: Option<AccessibilityNodeId>,
+}
+
+impl AccessibilityState {
+    pub fn new() -> Self {
+        AccessibilityState {
+            children: nil,
+            focus: nil,
+            focus_id: None,
+        }
+    }
+
+    /// The value for the view's `accessibilityChildren`.
+    pub fn children(&self) -> id {
+        unsafe {
+            if self.children == nil {
+                msg_send![class!(NSArray), array]
+            } else {
+                self.children
+            }
+        }
+    }
+
+    /// The focused element, if any.
+    pub fn focus(&self) -> Option<id> {
+        if self.focus == nil {
+            None
+        } else {
+            Some(self.focus)
+        }
+    }
+
+    /// Replaces the elements of `view` with ones made from `tree`.
+    pub fn update(&mut self, view: id, tree: &AccessibilityTree) {
+        unsafe {
+            let mut focus = nil;
+            let elements: Vec<id> = tree
+                .nodes
+                .iter()
+                .map(|node| make_element(node, view, None, tree.focus, &mut focus))
+                .collect();
+            let children: id = NSArray::arrayWithObjects(nil, &elements);
+            let children: id = msg_send![children, retain];
+            self.release();
+            self.children = children;
+            self.focus = focus;
+
+            NSAccessibilityPostNotification(view, NSAccessibilityLayoutChangedNotification);
+            if self.focus_id != tree.focus {
+                self.focus_id = tree.focus;
+                if focus != nil {
+                    NSAccessibilityPostNotification(
+                        focus,
+                        NSAccessibilityFocusedUIElementChangedNotification,
+                    );
+                }
+            }
+        }
+    }
+
+    fn release(&mut self) {
+        if self.children != nil {
+            unsafe {
+                let () = msg_send![self.children, release];
+            }
+        }
+        self.children = nil;
+        self.focus = nil;
+    }
+}
+
+impl Drop for AccessibilityState {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// Makes an autoreleased element for `node` and its children.
+unsafe fn make_element(
+    node: &AccessibilityNode,
+    parent: id,
+    parent_bounds: Option<Rect>,
+    focus_id: Option<AccessibilityNodeId>,
+    focus: &mut id,
+) -> id {
+    let element: id = msg_send![class!(NSAccessibilityElement), new];
+    let role = match node.role {
+        Role::Button => NSAccessibilityButtonRole,
+        Role::StaticText => NSAccessibilityStaticTextRole,
+        Role::List => NSAccessibilityListRole,
+        // VoiceOver reads the rows of a list as its items.
+        Role::ListItem => NSAccessibilityRowRole,
+        Role::Group => NSAccessibilityGroupRole,
+    };
+    let () = msg_send![element, setAccessibilityRole: role];
+    let () = msg_send![element, setAccessibilityParent: parent];
+    let frame = frame_in_parent_space(node.bounds, parent_bounds);
+    let () = msg_send![element, setAccessibilityFrameInParentSpace: frame];
+    if let Some(label) = &node.label {
+        let () = msg_send![element, setAccessibilityLabel: make_nsstring(label)];
+    }
+    if let Some(value) = &node.value {
+        let () = msg_send![element, setAccessibilityValue: make_nsstring(value)];
+    }
+    if focus_id == Some(node.id) {
+        let () = msg_send![element, setAccessibilityFocused: YES];
+        *focus = element;
+    }
+    if !node.children.is_empty() {
+        let children: Vec<id> = node
+            .children
+            .iter()
+            .map(|child| make_element(child, element, Some(node.bounds), focus_id, focus))
+            .collect();
+        let children = NSArray::arrayWithObjects(nil, &children);
+        let () = msg_send![element, setAccessibilityChildren: children];
+    }
+    msg_send![element, autorelease]
+}
+
+fn frame_in_parent_space(bounds: Rect, parent_bounds: Option<Rect>) -> NSRect {
+    let origin = match parent_bounds {
+        // Our view is flipped, so it uses the same coordinates as druid.
+        None => NSPoint::new(bounds.x0, bounds.y0),
+        // Other elements are laid out like the screen, where y goes up.
+        Some(parent) => NSPoint::new(bounds.x0 - parent.x0, parent.y1 - bounds.y1),
+    };
+    NSRect::new(origin, NSSize::new(bounds.width(), bounds.height()))
+}
@@ -17,13 +17,63 @@
 #![allow(clippy::upper_case_acronyms, non_snake_case, non_upper_case_globals)]
 
 use bitflags::bitflags;
+use cocoa::appkit::CGFloat;
 use cocoa::base::id;
-use cocoa::foundation::NSRect;
-use objc::{class, msg_send, sel, sel_impl};
+use cocoa::foundation::{NSInteger, NSRect};
+use core_graphics::window::CGWindowListOption;
+use objc::{class, msg_send, sel, sel_impl, Encode, Encoding};
 
 #[link(name = "AppKit", kind = "framework")]
 extern "C" {
     pub static NSRunLoopCommonModes: id;
+    pub static NSWorkspaceWillSleepNotification: id;
+    pub static NSWorkspaceDidWakeNotification: id;
+    pub static NSWorkspaceAccessibilityDisplayOptionsDidChangeNotification: id;
+    pub static NSAccessibilityAnnouncementRequestedNotification: id;
+    pub static NSAccessibilityAnnouncementKey: id;
+    pub static NSAccessibilityPriorityKey: id;
+    pub static NSAccessibilityFocusedUIElementChangedNotification: id;
+    pub static NSAccessibilityLayoutChangedNotification: id;
+
+    pub static NSAccessibilityButtonRole: id;
+    pub static NSAccessibilityGroupRole: id;
+    pub static NSAccessibilityListRole: id;
+    pub static NSAccessibilityRowRole: id;
+    pub static NSAccessibilityStaticTextRole: id;
+
+    pub fn NSBeep();
+    pub fn NSAccessibilityPostNotification(element: id, notification: id);
+    pub fn NSAccessibilityPostNotificationWithUserInfo(element: id, notification: id, userInfo: id);
+}
+
+// NSAccessibilityPriorityLevel
+pub const NSAccessibilityPriorityMedium: NSInteger = 50;
+pub const NSAccessibilityPriorityHigh: NSInteger = 90;
+
+// CGWindowListOption, which core-graphics 0.22 has shifted by one bit.
+pub const kCGWindowListOptionOnScreenOnly: CGWindowListOption = 1 << 0;
+pub const kCGWindowListExcludeDesktopElements: CGWindowListOption = 1 << 4;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NSEdgeInsets {
+    pub top: CGFloat,
+    pub left: CGFloat,
+    pub bottom: CGFloat,
+    pub right: CGFloat,
+}
+
+unsafe impl Encode for NSEdgeInsets {
+    fn encode() -> Encoding {
+        let encoding = format!(
+            "{{NSEdgeInsets={}{}{}{}}}",
+            CGFloat::encode().as_str(),
+            CGFloat::encode().as_str(),
+            CGFloat::encode().as_str(),
+            CGFloat::encode().as_str(),
+        );
+        unsafe { Encoding::from_str(&encoding) }
+    }
 }
 
 bitflags! {
@@ -18,24 +18,153 @@
 
 use std::cell::RefCell;
 use std::ffi::c_void;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
+use std::time::Duration;
 
-use cocoa::appkit::{NSApp, NSApplication, NSApplicationActivationPolicyRegular};
-use cocoa::base::{id, nil, NO, YES};
-use cocoa::foundation::{NSArray, NSAutoreleasePool};
+use anyhow::anyhow;
+use block::ConcreteBlock;
+use cocoa::appkit::{
+    CGFloat, NSApp, NSApplication, NSApplicationActivationPolicyRegular, NSEventModifierFlags,
+};
+use cocoa::base::{id, nil, BOOL, NO, YES};
+use cocoa::foundation::{NSArray, NSAutoreleasePool, NSInteger, NSPoint, NSUInteger};
+use core_graphics::window::{kCGNullWindowID, CGWindowID, CGWindowListOption};
 use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
 use once_cell::sync::Lazy;
 
-use crate::application::AppHandler;
+use crate::application::{
+    AppHandler, EndSessionResponse, ExternalWindowId, LayoutDirection, OsVersion, PermissionState,
+    PowerSource, RunOutcome, SystemSound, WindowInfo,
+};
+use crate::keyboard::Modifiers;
+use crate::kurbo::{Point, Rect, Size};
+use crate::menu::StandardCommand;
+use crate::mouse::MouseButtons;
+use crate::piet::Color;
+use crate::platform::mac::{ScopedAccess, ServiceDeclaration, ServiceHandler};
 
-use super::clipboard::Clipboard;
-use super::error::Error;
+use super::appkit::{
+    kCGWindowListExcludeDesktopElements, kCGWindowListOptionOnScreenOnly, NSBeep,
+    NSWorkspaceAccessibilityDisplayOptionsDidChangeNotification, NSWorkspaceDidWakeNotification,
+    NSWorkspaceWillSleepNotification,
+};
+use super::clipboard::{self, Clipboard};
+use super::keyboard::make_modifiers;
+use super::menu;
+use super::services;
 use super::util;
+use super::window::get_mouse_buttons;
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    fn CGWindowListCopyWindowInfo(option: CGWindowListOption, relative_to: CGWindowID) -> id;
+    static kCGWindowNumber: id;
+    static kCGWindowLayer: id;
+    static kCGWindowBounds: id;
+    static kCGWindowName: id;
+    static kCGWindowOwnerPID: id;
+    static kCGWindowOwnerName: id;
+}
 
 static APP_HANDLER_IVAR: &str = "druidAppHandler";
 
+const NSApplicationActivateIgnoringOtherApps: NSUInteger = 1 << 1;
+
+// `NSApplicationTerminateReply` values.
+const NSTerminateNow: NSUInteger = 1;
+const NSTerminateLater: NSUInteger = 2;
+
+/// The `kInternetEventClass` and `kAEGetURL` Apple event, which asks to open a URL,
+/// and its `keyDirectObject` parameter with the URL.
+const kInternetEventClass: u32 = u32::from_be_bytes(*b"GURL");
+const kAEGetURL: u32 = u32::from_be_bytes(*b"GURL");
+const keyDirectObject: u32 = u32::from_be_bytes(*b"----");
+
+/// The `keyAEQuitReason` attribute of the quit Apple event, and its values for logging out,
+/// restarting and shutting down, with and without the confirmation dialog.
+const keyAEQuitReason: u32 = u32::from_be_bytes(*b"why?");
+const END_SESSION_QUIT_REASONS: [u32; 6] = [
+    u32::from_be_bytes(*b"logo"),
+    u32::from_be_bytes(*b"rlgo"),
+    u32::from_be_bytes(*b"rrst"),
+    u32::from_be_bytes(*b"rest"),
+    u32::from_be_bytes(*b"rsdn"),
+    u32::from_be_bytes(*b"shut"),
+];
+
+/// The key of the forwarded paths in the `userInfo` of the open files notification.
+const OPEN_FILES_PATHS_KEY: &str = "paths";
+
+const NSURLBookmarkResolutionWithSecurityScope: NSUInteger = 1 << 10;
+
+type IOPowerSourceCallbackType = extern "C" fn(context: *mut c_void);
+
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOPSNotificationCreateRunLoopSource(
+        callback: IOPowerSourceCallbackType,
+        context: *mut c_void,
+    ) -> id;
+    fn IOPSCopyPowerSourcesInfo() -> id;
+    fn IOPSGetProvidingPowerSourceType(snapshot: id) -> id;
+}
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    static kTISPropertyLocalizedName: id;
+    static kTISNotifySelectedKeyboardInputSourceChanged: id;
+    fn TISCopyCurrentKeyboardInputSource() -> id;
+    fn TISGetInputSourceProperty(source: id, key: id) -> id;
+}
+
+/// The version that `-[NSProcessInfo operatingSystemVersion]` returns.
+#[repr(C)]
+struct NSOperatingSystemVersion {
+    major: NSInteger,
+    minor: NSInteger,
+    patch: NSInteger,
+}
+
+/// The main dispatch queue, which is what `dispatch_get_main_queue` returns.
+#[repr(C)]
+struct DispatchQueue {
+    _private: [u8; 0],
+}
+
+// libdispatch is part of libSystem, which is always linked.
+extern "C" {
+    static _dispatch_main_q: DispatchQueue;
+    fn dispatch_sync_f(
+        queue: *const DispatchQueue,
+        context: *mut c_void,
+        work: extern "C" fn(*mut c_void),
+    );
+}
+
+#[link(name = "Foundation", kind = "framework")]
+extern "C" {
+    static NSCurrentLocaleDidChangeNotification: id;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    static kCFRunLoopDefaultMode: id;
+    fn CFRunLoopGetMain() -> id;
+    fn CFRunLoopAddSource(rl: id, source: id, mode: id);
+    fn CFRunLoopSourceInvalidate(source: id);
+    fn CFRelease(cf: id);
+}
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    static kAXTrustedCheckOptionPrompt: id;
+    fn AXIsProcessTrustedWithOptions(options: id) -> bool;
+}
+
 #[derive(Clone)]
 pub(crate) struct Application {
     ns_app: id,
@@ -44,39 +173,196 @@ pub(crate) struct Application {
 
 struct State {
     quitting: bool,
+    /// Whether `finishLaunching` has been sent by [`Application::pump_events`].
+    launched: bool,
+    /// The id given to [`Application::new_single_instance`], which names the
+    /// notification that other instances forward their files with.
+    instance_id: Option<String>,
+}
+
+/// The `NSEvent` monitors added by [`Application::add_global_mouse_monitor`].
+pub(crate) struct GlobalMouseMonitor {
+    monitors: Vec<id>,
+}
+
+impl Drop for GlobalMouseMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            for monitor in self.monitors.drain(..) {
+                let () = msg_send![class!(NSEvent), removeMonitor: monitor];
+                let () = msg_send![monitor, release];
+            }
+        }
+    }
 }
 
 impl Application {
-    pub fn new() -> Result<Application, Error> {
+    pub fn new() -> Result<Application, crate::Error> {
         // macOS demands that we run not just on one thread,
         // but specifically the first thread of the app.
-        util::assert_main_thread();
+        if !util::is_main_thread() {
+            return Err(crate::Error::NotMainThread);
+        }
         unsafe {
             let _pool = NSAutoreleasePool::new(nil);
             let ns_app = NSApp();
-            let state = Rc::new(RefCell::new(State { quitting: false }));
+            let state = Rc::new(RefCell::new(State {
+                quitting: false,
+                launched: false,
+                instance_id: None,
+            }));
 
             Ok(Application { ns_app, state })
         }
     }
 
-    pub fn run(self, handler: Option<Box<dyn AppHandler>>) {
+    pub fn new_single_instance(app_id: &str) -> Result<Application, crate::Error> {
+        let app = Application::new()?;
+        unsafe {
+            let bundle_id = util::make_nsstring(app_id);
+            let running: id = msg_send![
+                class!(NSRunningApplication),
+                runningApplicationsWithBundleIdentifier: bundle_id
+            ];
+            let current: id = msg_send![class!(NSRunningApplication), currentApplication];
+            let current_pid: i32 = msg_send![current, processIdentifier];
+            for i in 0..running.count() {
+                let other: id = running.objectAtIndex(i);
+                let pid: i32 = msg_send![other, processIdentifier];
+                if pid != current_pid {
+                    forward_launch_files(app_id);
+                    let options = NSApplicationActivateIgnoringOtherApps;
+                    let _: BOOL = msg_send![other, activateWithOptions: options];
+                    return Err(crate::Error::AlreadyRunning);
+                }
+            }
+        }
+        app.state.borrow_mut().instance_id = Some(app_id.to_owned());
+        Ok(app)
+    }
+
+    pub fn run(self, handler: Option<Box<dyn AppHandler>>) -> Result<RunOutcome, anyhow::Error> {
         unsafe {
             // Initialize the application delegate
             let delegate: id = msg_send![APP_DELEGATE.0, alloc];
             let () = msg_send![delegate, init];
-            let state = DelegateState { handler };
+            let state = DelegateState {
+                handler,
+                power_source: current_power_source(),
+                high_contrast: prefers_high_contrast(),
+                end_session_pending: false,
+            };
             let state_ptr = Box::into_raw(Box::new(state));
             (*delegate).set_ivar(APP_HANDLER_IVAR, state_ptr as *mut c_void);
             let () = msg_send![self.ns_app, setDelegate: delegate];
+            // This has to be installed before launching, to get the URL that launched us.
+            let event_manager: id = msg_send![class!(NSAppleEventManager), sharedAppleEventManager];
+            let () = msg_send![event_manager, setEventHandler: delegate
+                andSelector: sel!(handleGetURLEvent:withReplyEvent:)
+                forEventClass: kInternetEventClass
+                andEventID: kAEGetURL];
+
+            // Sleep and wake notifications are only posted to the workspace notification center.
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let center: id = msg_send![workspace, notificationCenter];
+            let () = msg_send![center, addObserver: delegate
+                selector: sel!(systemWillSleep:)
+                name: NSWorkspaceWillSleepNotification
+                object: nil];
+            let () = msg_send![center, addObserver: delegate
+                selector: sel!(systemDidWake:)
+                name: NSWorkspaceDidWakeNotification
+                object: nil];
+            let () = msg_send![center, addObserver: delegate
+                selector: sel!(accessibilityDisplayOptionsChanged:)
+                name: NSWorkspaceAccessibilityDisplayOptionsDidChangeNotification
+                object: nil];
+            let default_center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+            let () = msg_send![default_center, addObserver: delegate
+                selector: sel!(localeChanged:)
+                name: NSCurrentLocaleDidChangeNotification
+                object: nil];
+            // Lock notifications are only posted to the distributed notification center.
+            let distributed_center: id =
+                msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+            let () = msg_send![distributed_center, addObserver: delegate
+                selector: sel!(sessionLocked:)
+                name: util::make_nsstring("com.apple.screenIsLocked")
+                object: nil];
+            let () = msg_send![distributed_center, addObserver: delegate
+                selector: sel!(sessionUnlocked:)
+                name: util::make_nsstring("com.apple.screenIsUnlocked")
+                object: nil];
+            let () = msg_send![distributed_center, addObserver: delegate
+                selector: sel!(keyboardLayoutChanged:)
+                name: kTISNotifySelectedKeyboardInputSourceChanged
+                object: nil];
+            if let Some(instance_id) = self.state.borrow().instance_id.as_deref() {
+                let () = msg_send![distributed_center, addObserver: delegate
+                    selector: sel!(openForwardedFiles:)
+                    name: util::make_nsstring(&open_files_notification(instance_id))
+                    object: nil];
+            }
+            let power_source =
+                IOPSNotificationCreateRunLoopSource(power_source_changed, state_ptr as *mut c_void);
+            if power_source != nil {
+                CFRunLoopAddSource(CFRunLoopGetMain(), power_source, kCFRunLoopDefaultMode);
+            }
 
             // Run the main app loop
             self.ns_app.run();
+            clipboard::provide_promised_data();
 
             // Clean up the delegate
+            if power_source != nil {
+                CFRunLoopSourceInvalidate(power_source);
+                CFRelease(power_source);
+            }
+            let () = msg_send![center, removeObserver: delegate];
+            let () = msg_send![default_center, removeObserver: delegate];
+            let () = msg_send![distributed_center, removeObserver: delegate];
+            let () = msg_send![event_manager, removeEventHandlerForEventClass: kInternetEventClass
+                andEventID: kAEGetURL];
             let () = msg_send![self.ns_app, setDelegate: nil];
             drop(Box::from_raw(state_ptr));
         }
+        // `run` only returns after `stop:`, since `terminate:` exits the process.
+        let quit_requested = self.state.borrow().quitting;
+        Ok(RunOutcome::new(0, quit_requested))
+    }
+
+    pub fn pump_events(&self, timeout: Option<Duration>) -> bool {
+        if !std::mem::replace(&mut self.state.borrow_mut().launched, true) {
+            // `run` does this for us, and without it the app never becomes active.
+            unsafe {
+                let () = msg_send![self.ns_app, finishLaunching];
+            }
+        }
+        unsafe {
+            let _pool = NSAutoreleasePool::new(nil);
+            let mut until: id = match timeout {
+                None => msg_send![class!(NSDate), distantFuture],
+                Some(timeout) => {
+                    let seconds = timeout.as_secs_f64();
+                    msg_send![class!(NSDate), dateWithTimeIntervalSinceNow: seconds]
+                }
+            };
+            loop {
+                let event: id = msg_send![self.ns_app,
+                    nextEventMatchingMask: NSUInteger::MAX
+                    untilDate: until
+                    inMode: kCFRunLoopDefaultMode
+                    dequeue: YES];
+                if event == nil {
+                    break;
+                }
+                let () = msg_send![self.ns_app, sendEvent: event];
+                // Only wait for the first event, then take whatever else is already queued.
+                until = msg_send![class!(NSDate), distantPast];
+            }
+            let () = msg_send![self.ns_app, updateWindows];
+        }
+        !self.state.borrow().quitting
     }
 
     pub fn quit(&self) {
@@ -101,25 +387,426 @@ impl Application {
         }
     }
 
+    pub fn reply_to_end_session(&self, allow: bool) {
+        unsafe {
+            let delegate: id = msg_send![self.ns_app, delegate];
+            if delegate == nil {
+                return;
+            }
+            // This is likely called from the handler, which the reply calls again, so
+            // answer once it has returned.
+            let allow: id = msg_send![class!(NSNumber), numberWithBool: allow as BOOL];
+            let () = msg_send![delegate, performSelectorOnMainThread: sel!(replyToEndSession:)
+                withObject: allow
+                waitUntilDone: NO];
+        }
+    }
+
+    pub fn set_menu(&self, menu: menu::Menu) {
+        menu.set_as_main_menu();
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
 
+    pub fn open_uri(&self, uri: &str) -> Result<(), anyhow::Error> {
+        unsafe {
+            let url: id = msg_send![class!(NSURL), URLWithString: util::make_nsstring(uri)];
+            if url == nil {
+                return Err(anyhow!("invalid URI: {}", uri));
+            }
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let opened: BOOL = msg_send![workspace, openURL: url];
+            if opened == NO {
+                return Err(anyhow!("failed to open {}", uri));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn reveal_in_file_manager(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let path_str = path
+            .to_str()
+            .ok_or_else(|| anyhow!("path is not valid unicode: {:?}", path))?;
+        unsafe {
+            let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+            let revealed: BOOL = msg_send![workspace,
+                selectFile: util::make_nsstring(path_str)
+                inFileViewerRootedAtPath: util::make_nsstring("")];
+            if revealed == NO {
+                return Err(anyhow!("failed to reveal {:?}", path));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn note_recent_document(&self, path: &Path) {
+        let path_str = match path.to_str() {
+            Some(path_str) => path_str,
+            None => {
+                tracing::warn!("path is not valid unicode: {:?}", path);
+                return;
+            }
+        };
+        unsafe {
+            let url: id = msg_send![class!(NSURL), fileURLWithPath: util::make_nsstring(path_str)];
+            let controller: id = msg_send![class!(NSDocumentController), sharedDocumentController];
+            let () = msg_send![controller, noteNewRecentDocumentURL: url];
+        }
+    }
+
+    pub fn clear_recent_documents(&self) {
+        unsafe {
+            let controller: id = msg_send![class!(NSDocumentController), sharedDocumentController];
+            let () = msg_send![controller, clearRecentDocuments: nil];
+        }
+    }
+
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        unsafe {
+            let source = TISCopyCurrentKeyboardInputSource();
+            if source == nil {
+                return None;
+            }
+            // This follows the get rule, so it's owned by `source`.
+            let name = TISGetInputSourceProperty(source, kTISPropertyLocalizedName);
+            let name = if name == nil {
+                None
+            } else {
+                Some(util::from_nsstring(name))
+            };
+            CFRelease(source);
+            name
+        }
+    }
+
+    pub fn layout_direction(&self) -> LayoutDirection {
+        // This is `NSUserInterfaceLayoutDirectionRightToLeft`.
+        let direction: NSInteger = unsafe { msg_send![self.ns_app, userInterfaceLayoutDirection] };
+        if direction == 1 {
+            LayoutDirection::RightToLeft
+        } else {
+            LayoutDirection::LeftToRight
+        }
+    }
+
+    pub fn prefers_high_contrast(&self) -> bool {
+        prefers_high_contrast()
+    }
+
+    pub fn selection_color(&self) -> Option<Color> {
+        self.system_color(sel!(selectedTextBackgroundColor))
+    }
+
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        // This was only added in macOS 14, before that the cursor used the text color.
+        self.system_color(sel!(textInsertionPointColor))
+            .or_else(|| self.system_color(sel!(textColor)))
+    }
+
+    pub fn is_active(&self) -> bool {
+        let active: BOOL = unsafe { msg_send![self.ns_app, isActive] };
+        active != NO
+    }
+
+    pub fn activate(&self, ignore_other_apps: bool) {
+        let ignore_other_apps = if ignore_other_apps { YES } else { NO };
+        unsafe {
+            let () = msg_send![self.ns_app, activateIgnoringOtherApps: ignore_other_apps];
+        }
+    }
+
+    pub fn beep(&self) {
+        unsafe { NSBeep() }
+    }
+
+    pub fn play_system_sound(&self, sound: SystemSound) {
+        // These are the names of the sounds in /System/Library/Sounds.
+        let name = match sound {
+            SystemSound::Alert => return self.beep(),
+            SystemSound::Error => "Basso",
+            SystemSound::Info => "Glass",
+        };
+        unsafe {
+            let sound: id = msg_send![class!(NSSound), soundNamed: util::make_nsstring(name)];
+            if sound == nil {
+                NSBeep();
+            } else {
+                let _: BOOL = msg_send![sound, play];
+            }
+        }
+    }
+
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        // These are `kCGEventSourceStateCombinedSessionState`, which covers all processes
+        // in the login session, and `kCGAnyInputEventType`.
+        let seconds = unsafe { CGEventSourceSecondsSinceLastEventType(0, !0) };
+        Some(Duration::from_secs_f64(seconds.max(0.0)))
+    }
+
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn max_cursor_size(&self) -> Size {
+        Size::new(f64::INFINITY, f64::INFINITY)
+    }
+
+    pub fn mouse_buttons(&self) -> MouseButtons {
+        let mask: NSUInteger = unsafe { msg_send![class!(NSEvent), pressedMouseButtons] };
+        get_mouse_buttons(mask)
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        let flags: NSUInteger = unsafe { msg_send![class!(NSEvent), modifierFlags] };
+        make_modifiers(NSEventModifierFlags::from_bits_truncate(flags))
+    }
+
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        unsafe {
+            let windows = CGWindowListCopyWindowInfo(
+                kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements,
+                kCGNullWindowID,
+            );
+            if windows == nil {
+                return Vec::new();
+            }
+            let current_pid = std::process::id() as f64;
+            let list = (0..windows.count())
+                .map(|i| windows.objectAtIndex(i))
+                // Menu bar items and other system elements are on higher layers.
+                .filter(|&info| number_for_key(info, kCGWindowLayer) == Some(0.0))
+                .filter(|&info| number_for_key(info, kCGWindowOwnerPID) != Some(current_pid))
+                .filter_map(|info| {
+                    let bounds: id = msg_send![info, objectForKey: kCGWindowBounds];
+                    let number = |key| number_for_key(bounds, util::make_nsstring(key));
+                    Some(WindowInfo {
+                        id: ExternalWindowId::from_raw(
+                            number_for_key(info, kCGWindowNumber)? as u64
+                        ),
+                        // This is only included with screen recording permission.
+                        title: string_for_key(info, kCGWindowName).unwrap_or_default(),
+                        owner_name: string_for_key(info, kCGWindowOwnerName).unwrap_or_default(),
+                        bounds: Rect::from_origin_size(
+                            (number("X")?, number("Y")?),
+                            (number("Width")?, number("Height")?),
+                        ),
+                    })
+                })
+                .collect();
+            CFRelease(windows);
+            list
+        }
+    }
+
+    pub fn has_screen_capture_access(&self) -> bool {
+        super::screen::has_screen_capture_access()
+    }
+
+    pub fn request_screen_capture_access(&self) -> bool {
+        super::screen::request_screen_capture_access()
+    }
+
+    pub fn check_accessibility_permission(&self, prompt: bool) -> PermissionState {
+        unsafe {
+            let prompt: id = msg_send![class!(NSNumber), numberWithBool: prompt as BOOL];
+            let options: id = msg_send![class!(NSDictionary),
+                dictionaryWithObject: prompt forKey: kAXTrustedCheckOptionPrompt];
+            if AXIsProcessTrustedWithOptions(options) {
+                PermissionState::Granted
+            } else {
+                PermissionState::Denied
+            }
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "mac"
+    }
+
+    pub fn os_version(&self) -> Option<OsVersion> {
+        unsafe {
+            let process_info: id = msg_send![class!(NSProcessInfo), processInfo];
+            let version: NSOperatingSystemVersion = msg_send![process_info, operatingSystemVersion];
+            Some(OsVersion::new(
+                version.major as u32,
+                version.minor as u32,
+                version.patch as u32,
+            ))
+        }
+    }
+
+    pub fn add_global_mouse_monitor(
+        &self,
+        callback: Box<dyn FnMut(Point)>,
+    ) -> Result<GlobalMouseMonitor, crate::Error> {
+        // Global monitors don't see the events sent to our own windows, so a local monitor
+        // covers those.
+        let callback = Rc::new(RefCell::new(callback));
+        let global_callback = callback.clone();
+        let global = ConcreteBlock::new(move |_event: id| report_mouse_location(&global_callback));
+        let global = global.copy();
+        let local = ConcreteBlock::new(move |event: id| -> id {
+            report_mouse_location(&callback);
+            event
+        });
+        let local = local.copy();
+        // NSEventMaskMouseMoved, the masks for dragging with each button, and
+        // NSEventMaskLeftMouseDown | NSEventMaskRightMouseDown | NSEventMaskOtherMouseDown
+        let mask: NSUInteger = 1 << 5 | 1 << 6 | 1 << 7 | 1 << 27 | 1 << 1 | 1 << 3 | 1 << 25;
+        unsafe {
+            let global: id = msg_send![class!(NSEvent),
+                addGlobalMonitorForEventsMatchingMask: mask handler: &*global];
+            let local: id = msg_send![class!(NSEvent),
+                addLocalMonitorForEventsMatchingMask: mask handler: &*local];
+            let monitors: Vec<id> = [global, local]
+                .into_iter()
+                .filter(|monitor| *monitor != nil)
+                .map(|monitor| msg_send![monitor, retain])
+                .collect();
+            let monitor = GlobalMouseMonitor { monitors };
+            if monitor.monitors.len() < 2 {
+                return Err(anyhow!("failed to add the NSEvent mouse monitors").into());
+            }
+            Ok(monitor)
+        }
+    }
+
+    /// Returns one of the `NSColor` system colors, resolved for the app's appearance.
+    fn system_color(&self, name: Sel) -> Option<Color> {
+        unsafe {
+            let exists: BOOL = msg_send![class!(NSColor), respondsToSelector: name];
+            if exists == NO {
+                return None;
+            }
+            // System colors are dynamic, and resolve to the current appearance when converted.
+            let previous_appearance: id = msg_send![class!(NSAppearance), currentAppearance];
+            let has_appearance: BOOL =
+                msg_send![self.ns_app, respondsToSelector: sel!(effectiveAppearance)];
+            if has_appearance != NO {
+                let appearance: id = msg_send![self.ns_app, effectiveAppearance];
+                let () = msg_send![class!(NSAppearance), setCurrentAppearance: appearance];
+            }
+            let color: id = msg_send![class!(NSColor), performSelector: name];
+            let color_space: id = msg_send![class!(NSColorSpace), sRGBColorSpace];
+            let color: id = msg_send![color, colorUsingColorSpace: color_space];
+            let () = msg_send![class!(NSAppearance), setCurrentAppearance: previous_appearance];
+            if color == nil {
+                return None;
+            }
+            let (mut r, mut g, mut b, mut a): (CGFloat, CGFloat, CGFloat, CGFloat) =
+                (0.0, 0.0, 0.0, 0.0);
+            let () = msg_send![color, getRed: &mut r green: &mut g blue: &mut b alpha: &mut a];
+            Some(Color::rgba(r, g, b, a))
+        }
+    }
+
+    pub fn run_on_main<T: Send + 'static>(
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, crate::Error> {
+        extern "C" fn run(context: *mut c_void) {
+            let work = unsafe { &mut *(context as *mut &mut dyn FnMut()) };
+            work();
+        }
+        let mut f = Some(f);
+        let mut result = None;
+        let mut work = || result = f.take().map(|f| f());
+        let mut work: &mut dyn FnMut() = &mut work;
+        // This blocks until the main thread has run `work`.
+        unsafe {
+            dispatch_sync_f(
+                &_dispatch_main_q,
+                &mut work as *mut &mut dyn FnMut() as *mut c_void,
+                run,
+            )
+        };
+        result.ok_or_else(|| anyhow!("the main queue didn't run the function").into())
+    }
+
     pub fn get_locale() -> String {
+        language_identifier(&Self::get_locale_tag())
+    }
+
+    pub fn get_locale_tag() -> String {
         unsafe {
             let nslocale_class = class!(NSLocale);
             let locale: id = msg_send![nslocale_class, currentLocale];
             let ident: id = msg_send![locale, localeIdentifier];
-            let mut locale = util::from_nsstring(ident);
-            // This is done because the locale parsing library we use expects an unicode locale, but these vars have an ISO locale
-            if let Some(idx) = locale.chars().position(|c| c == '@') {
-                locale.truncate(idx);
-            }
-            locale
+            bcp47_from_icu(&util::from_nsstring(ident))
         }
     }
 }
 
+/// Returns the language identifier part of a BCP-47 tag, without extensions like
+/// `-u-ca-japanese`, which start with a single letter subtag.
+fn language_identifier(tag: &str) -> String {
+    tag.split('-')
+        .take_while(|subtag| subtag.len() > 1)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Converts an ICU locale identifier like `en_US@calendar=japanese;numbers=arab`
+/// to a BCP-47 language tag like `en-US-u-ca-japanese-nu-arab`.
+///
+/// Keywords without a BCP-47 equivalent are dropped.
+fn bcp47_from_icu(identifier: &str) -> String {
+    let (base, keywords) = identifier.split_once('@').unwrap_or((identifier, ""));
+    let mut tag = base.replace('_', "-");
+    let mut extensions: Vec<(String, String)> = keywords
+        .split(';')
+        .filter_map(|keyword| {
+            let (key, value) = keyword.split_once('=')?;
+            let key = match key.trim().to_ascii_lowercase().as_str() {
+                "calendar" => "ca".to_string(),
+                "collation" => "co".to_string(),
+                "currency" => "cu".to_string(),
+                "numbers" => "nu".to_string(),
+                "hours" => "hc".to_string(),
+                "measure" => "ms".to_string(),
+                "colnumeric" => "kn".to_string(),
+                "colcasefirst" => "kf".to_string(),
+                // Keys like `fw` or `rg` are already BCP-47 keys.
+                key if key.len() == 2 && key.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                    key.to_string()
+                }
+                _ => return None,
+            };
+            let value = match value.trim().to_ascii_lowercase().as_str() {
+                "gregorian" => "gregory".to_string(),
+                "ethiopic-amete-alem" => "ethioaa".to_string(),
+                "phonebook" => "phonebk".to_string(),
+                "traditional" => "trad".to_string(),
+                "dictionary" => "dict".to_string(),
+                "gb2312han" => "gb2312".to_string(),
+                "yes" => "true".to_string(),
+                "no" => "false".to_string(),
+                value => value.to_string(),
+            };
+            // Values like time zone names can't be written as subtags.
+            let valid = value.split('-').all(|subtag| {
+                (3..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric())
+            });
+            valid.then_some((key, value))
+        })
+        .collect();
+    if extensions.is_empty() {
+        return tag;
+    }
+    extensions.sort();
+    if !tag.contains("-u-") {
+        tag.push_str("-u");
+    }
+    for (key, value) in extensions {
+        tag.push('-');
+        tag.push_str(&key);
+        tag.push('-');
+        tag.push_str(&value);
+    }
+    tag
+}
+
 impl crate::platform::mac::ApplicationExt for crate::Application {
     fn hide(&self) {
         unsafe {
@@ -136,14 +823,65 @@ impl crate::platform::mac::ApplicationExt for crate::Application {
     }
 
     fn set_menu(&self, menu: crate::Menu) {
+        self.backend_app.set_menu(menu.0);
+    }
+
+    fn set_menu_bar_name(&self, name: &str) {
+        menu::set_menu_bar_name(name);
+    }
+
+    fn set_automatic_menu_items(&self, enabled: bool) {
+        menu::set_automatic_menu_items(self.backend_app.ns_app, enabled);
+    }
+
+    fn resolve_bookmark(&self, bookmark: &[u8]) -> Option<(PathBuf, ScopedAccess)> {
         unsafe {
-            NSApp().setMainMenu_(menu.0.menu);
+            let data = util::make_nsdata(bookmark);
+            let mut stale: BOOL = NO;
+            let url: id = msg_send![class!(NSURL),
+                URLByResolvingBookmarkData: data
+                options: NSURLBookmarkResolutionWithSecurityScope
+                relativeToURL: nil
+                bookmarkDataIsStale: &mut stale
+                error: nil];
+            if url == nil {
+                return None;
+            }
+            if stale != NO {
+                tracing::info!("resolved a stale bookmark, it should be recreated");
+            }
+            let accessing: BOOL = msg_send![url, startAccessingSecurityScopedResource];
+            if accessing == NO {
+                tracing::warn!("failed to access security-scoped resource");
+                return None;
+            }
+            let path: id = msg_send![url, path];
+            let path = PathBuf::from(util::from_nsstring(path));
+            let url: id = msg_send![url, retain];
+            let access = ScopedAccess::new(move || {
+                let () = msg_send![url, stopAccessingSecurityScopedResource];
+                let () = msg_send![url, release];
+            });
+            Some((path, access))
         }
     }
+
+    fn set_services_provider(&self, handler: Box<dyn ServiceHandler>) {
+        services::set_services_provider(self.backend_app.ns_app, handler);
+    }
+
+    fn declared_services(&self) -> Vec<ServiceDeclaration> {
+        services::declared_services()
+    }
 }
 
 struct DelegateState {
     handler: Option<Box<dyn AppHandler>>,
+    power_source: Option<PowerSource>,
+    high_contrast: bool,
+    /// Whether `applicationShouldTerminate:` answered `NSTerminateLater`, and is waiting
+    /// for [`Application::reply_to_end_session`].
+    end_session_pending: bool,
 }
 
 impl DelegateState {
@@ -152,6 +890,151 @@ impl DelegateState {
             inner.command(command)
         }
     }
+
+    fn menu_item_enabled(&mut self, command: u32) -> Option<bool> {
+        self.handler.as_mut()?.menu_item_enabled(command)
+    }
+
+    fn system_will_sleep(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.system_will_sleep()
+        }
+    }
+
+    fn system_did_wake(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.system_did_wake()
+        }
+    }
+
+    fn session_locked(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.session_locked()
+        }
+    }
+
+    fn session_unlocked(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.session_unlocked()
+        }
+    }
+
+    fn keyboard_layout_changed(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.keyboard_layout_changed()
+        }
+    }
+
+    fn locale_changed(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.locale_changed()
+        }
+    }
+
+    fn did_become_active(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.did_become_active()
+        }
+    }
+
+    fn did_resign_active(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.did_resign_active()
+        }
+    }
+
+    fn open_url(&mut self, url: &str) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.open_url(url)
+        }
+    }
+
+    fn query_end_session(&mut self) -> EndSessionResponse {
+        match self.handler.as_mut() {
+            Some(inner) => inner.query_end_session(),
+            None => EndSessionResponse::Allow,
+        }
+    }
+
+    fn end_session(&mut self) {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.end_session()
+        }
+    }
+
+    fn open_file(&mut self, path: &Path) -> bool {
+        if let Some(inner) = self.handler.as_mut() {
+            inner.open_file(path);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn update_high_contrast(&mut self) {
+        let high_contrast = prefers_high_contrast();
+        if high_contrast == self.high_contrast {
+            return;
+        }
+        self.high_contrast = high_contrast;
+        if let Some(inner) = self.handler.as_mut() {
+            inner.high_contrast_changed(high_contrast)
+        }
+    }
+
+    fn update_power_source(&mut self) {
+        let source = current_power_source();
+        if source.is_none() || source == self.power_source {
+            return;
+        }
+        self.power_source = source;
+        if let (Some(inner), Some(source)) = (self.handler.as_mut(), source) {
+            inner.power_source_changed(source)
+        }
+    }
+}
+
+/// Calls `callback` with the mouse location, in virtual screen coordinates.
+fn report_mouse_location(callback: &RefCell<Box<dyn FnMut(Point)>>) {
+    let location: NSPoint = unsafe { msg_send![class!(NSEvent), mouseLocation] };
+    let point = Point::new(location.x, super::screen::max_y() - location.y);
+    // The callback may run a nested event loop, which would deliver another event to it.
+    if let Ok(mut callback) = callback.try_borrow_mut() {
+        callback(point);
+    }
+}
+
+/// Returns whether the "Increase contrast" accessibility setting is on.
+fn prefers_high_contrast() -> bool {
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let increase_contrast: BOOL =
+            msg_send![workspace, accessibilityDisplayShouldIncreaseContrast];
+        increase_contrast != NO
+    }
+}
+
+/// Returns the source the system is currently drawing power from.
+fn current_power_source() -> Option<PowerSource> {
+    unsafe {
+        let snapshot = IOPSCopyPowerSourcesInfo();
+        if snapshot == nil {
+            return None;
+        }
+        let source_type = IOPSGetProvidingPowerSourceType(snapshot);
+        let source = if source_type == nil {
+            None
+        } else {
+            // These are the values of the `kIOPMACPowerKey` and `kIOPMBatteryPowerKey` constants.
+            match util::from_nsstring(source_type).as_str() {
+                "AC Power" => Some(PowerSource::AC),
+                "Battery Power" => Some(PowerSource::Battery),
+                _ => None,
+            }
+        };
+        CFRelease(snapshot);
+        source
+    }
 }
 
 struct AppDelegate(*const Class);
@@ -172,6 +1055,95 @@ static APP_DELEGATE: Lazy<AppDelegate> = Lazy::new(|| unsafe {
         sel!(handleMenuItem:),
         handle_menu_item as extern "C" fn(&mut Object, Sel, id),
     );
+
+    decl.add_method(
+        sel!(updateMenuItem:),
+        update_menu_item as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    for command in StandardCommand::ALL {
+        if let Some(action) = menu::standard_action(command) {
+            decl.add_method(
+                action,
+                standard_action as extern "C" fn(&mut Object, Sel, id),
+            );
+        }
+    }
+
+    decl.add_method(
+        sel!(systemWillSleep:),
+        system_will_sleep as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(systemDidWake:),
+        system_did_wake as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(sessionLocked:),
+        session_locked as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(sessionUnlocked:),
+        session_unlocked as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(keyboardLayoutChanged:),
+        keyboard_layout_changed as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(accessibilityDisplayOptionsChanged:),
+        accessibility_display_options_changed as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(localeChanged:),
+        locale_changed as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(applicationDidChangeScreenParameters:),
+        application_did_change_screen_parameters as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(applicationDidBecomeActive:),
+        application_did_become_active as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(applicationDidResignActive:),
+        application_did_resign_active as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(openForwardedFiles:),
+        open_forwarded_files as extern "C" fn(&mut Object, Sel, id),
+    );
+
+    decl.add_method(
+        sel!(application:openFile:),
+        application_open_file as extern "C" fn(&mut Object, Sel, id, id) -> BOOL,
+    );
+
+    decl.add_method(
+        sel!(handleGetURLEvent:withReplyEvent:),
+        handle_get_url_event as extern "C" fn(&mut Object, Sel, id, id),
+    );
+
+    decl.add_method(
+        sel!(applicationShouldTerminate:),
+        application_should_terminate as extern "C" fn(&mut Object, Sel, id) -> NSUInteger,
+    );
+
+    decl.add_method(
+        sel!(replyToEndSession:),
+        reply_to_end_session as extern "C" fn(&mut Object, Sel, id),
+    );
     AppDelegate(decl.register())
 });
 
@@ -185,6 +1157,153 @@ extern "C" fn application_did_finish_launching(_this: &mut Object, _: Sel, _noti
     }
 }
 
+/// Called for files dropped on the Dock icon, and for documents picked from the
+/// recent documents list.
+extern "C" fn application_open_file(this: &mut Object, _: Sel, _app: id, filename: id) -> BOOL {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        let path = PathBuf::from(util::from_nsstring(filename));
+        if (*inner).open_file(&path) {
+            YES
+        } else {
+            NO
+        }
+    }
+}
+
+/// Handler for the `kAEGetURL` Apple event, which is sent for URLs with one of the schemes
+/// in the `CFBundleURLTypes` of the bundle.
+extern "C" fn handle_get_url_event(this: &mut Object, _: Sel, event: id, _reply: id) {
+    unsafe {
+        let url: id = msg_send![event, paramDescriptorForKeyword: keyDirectObject];
+        let url: id = msg_send![url, stringValue];
+        if url == nil {
+            return;
+        }
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).open_url(&util::from_nsstring(url));
+    }
+}
+
+/// Returns whether the quit Apple event being handled is for logging out, restarting
+/// or shutting down.
+unsafe fn is_end_session_quit() -> bool {
+    let manager: id = msg_send![class!(NSAppleEventManager), sharedAppleEventManager];
+    let event: id = msg_send![manager, currentAppleEvent];
+    if event == nil {
+        return false;
+    }
+    let reason: id = msg_send![event, attributeDescriptorForKeyword: keyAEQuitReason];
+    if reason == nil {
+        return false;
+    }
+    let reason: u32 = msg_send![reason, enumCodeValue];
+    END_SESSION_QUIT_REASONS.contains(&reason)
+}
+
+extern "C" fn application_should_terminate(this: &mut Object, _: Sel, _app: id) -> NSUInteger {
+    unsafe {
+        if !is_end_session_quit() {
+            return NSTerminateNow;
+        }
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        match (*inner).query_end_session() {
+            EndSessionResponse::Allow => {
+                (*inner).end_session();
+                NSTerminateNow
+            }
+            EndSessionResponse::Block(reason) => {
+                tracing::info!("blocking the end of the session: {}", reason);
+                (*inner).end_session_pending = true;
+                NSTerminateLater
+            }
+        }
+    }
+}
+
+/// Performed by [`Application::reply_to_end_session`], with the answer as an `NSNumber`.
+extern "C" fn reply_to_end_session(this: &mut Object, _: Sel, allow: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        if !std::mem::replace(&mut (*inner).end_session_pending, false) {
+            return;
+        }
+        let allow: BOOL = msg_send![allow, boolValue];
+        if allow != NO {
+            (*inner).end_session();
+        }
+        let () = msg_send![NSApp(), replyToApplicationShouldTerminate: allow];
+    }
+}
+
+unsafe fn number_for_key(dict: id, key: id) -> Option<f64> {
+    // Messages to nil return nil.
+    let number: id = msg_send![dict, objectForKey: key];
+    (number != nil).then(|| msg_send![number, doubleValue])
+}
+
+unsafe fn string_for_key(dict: id, key: id) -> Option<String> {
+    let string: id = msg_send![dict, objectForKey: key];
+    (string != nil).then(|| util::from_nsstring(string))
+}
+
+/// Observer for the distributed notification that other instances post from
+/// [`Application::new_single_instance`].
+extern "C" fn open_forwarded_files(this: &mut Object, _: Sel, notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        let user_info: id = msg_send![notification, userInfo];
+        if user_info == nil {
+            return;
+        }
+        let paths: id =
+            msg_send![user_info, objectForKey: util::make_nsstring(OPEN_FILES_PATHS_KEY)];
+        if paths == nil {
+            return;
+        }
+        for i in 0..paths.count() {
+            let path = PathBuf::from(util::from_nsstring(paths.objectAtIndex(i)));
+            if path.is_file() {
+                (*inner).open_file(&path);
+            }
+        }
+    }
+}
+
+/// The name of the distributed notification that forwards files to the instance with `app_id`.
+fn open_files_notification(app_id: &str) -> String {
+    format!("{app_id}.openFiles")
+}
+
+/// Posts the files on the command line to the running instance with `app_id`.
+unsafe fn forward_launch_files(app_id: &str) {
+    // Relative paths have to be resolved here, since the other instance has its own
+    // working directory.
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let paths: Vec<id> = std::env::args_os()
+        .skip(1)
+        .map(|arg| util::make_nsstring(&current_dir.join(arg).to_string_lossy()))
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    let paths = NSArray::arrayWithObjects(nil, &paths);
+    let user_info: id = msg_send![class!(NSDictionary),
+        dictionaryWithObject: paths
+        forKey: util::make_nsstring(OPEN_FILES_PATHS_KEY)];
+    let center: id = msg_send![class!(NSDistributedNotificationCenter), defaultCenter];
+    let name = util::make_nsstring(&open_files_notification(app_id));
+    let () = msg_send![center, postNotificationName: name
+        object: nil
+        userInfo: user_info
+        deliverImmediately: YES];
+}
+
 /// This handles menu items in the case that all windows are closed.
 extern "C" fn handle_menu_item(this: &mut Object, _: Sel, item: id) {
     unsafe {
@@ -194,3 +1313,165 @@ extern "C" fn handle_menu_item(this: &mut Object, _: Sel, item: id) {
         (*inner).command(tag as u32);
     }
 }
+
+extern "C" fn update_menu_item(this: &mut Object, _: Sel, item: id) {
+    unsafe {
+        let tag: isize = msg_send![item, tag];
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        if let Some(enabled) = (*inner).menu_item_enabled(tag as u32) {
+            let () = msg_send![item, setEnabled: enabled as BOOL];
+        }
+    }
+}
+
+/// Delivers a standard action, such as `copy:`, as the command of its standard id.
+extern "C" fn standard_action(this: &mut Object, action: Sel, _sender: id) {
+    if let Some(command) = menu::standard_command_for_action(action) {
+        unsafe {
+            let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+            let inner = &mut *(inner as *mut DelegateState);
+            (*inner).command(command.id());
+        }
+    }
+}
+
+/// Observer for `NSWorkspaceWillSleepNotification`. The system doesn't sleep
+/// until all observers have returned.
+extern "C" fn system_will_sleep(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).system_will_sleep();
+    }
+}
+
+/// Observer for `NSWorkspaceDidWakeNotification`.
+extern "C" fn system_did_wake(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).system_did_wake();
+    }
+}
+
+/// Observer for the `com.apple.screenIsLocked` distributed notification. This is posted
+/// both when the user locks the screen and when it locks after being idle.
+extern "C" fn session_locked(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).session_locked();
+    }
+}
+
+/// Observer for the `com.apple.screenIsUnlocked` distributed notification.
+extern "C" fn session_unlocked(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).session_unlocked();
+    }
+}
+
+extern "C" fn application_did_become_active(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).did_become_active();
+    }
+}
+
+extern "C" fn application_did_resign_active(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).did_resign_active();
+    }
+}
+
+/// Observer for `kTISNotifySelectedKeyboardInputSourceChanged`.
+extern "C" fn keyboard_layout_changed(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).keyboard_layout_changed();
+    }
+}
+
+/// Called when monitors are added, removed or rearranged, and when the dock or menu bar
+/// changes their visible frames.
+extern "C" fn application_did_change_screen_parameters(
+    _this: &mut Object,
+    _: Sel,
+    _notification: id,
+) {
+    crate::screen::monitors_changed();
+}
+
+/// Observer for `NSCurrentLocaleDidChangeNotification`.
+extern "C" fn locale_changed(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).locale_changed();
+    }
+}
+
+/// Observer for `NSWorkspaceAccessibilityDisplayOptionsDidChangeNotification`. This covers
+/// several display options, so `DelegateState` filters out the ones that don't change contrast.
+extern "C" fn accessibility_display_options_changed(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let inner: *mut c_void = *this.get_ivar(APP_HANDLER_IVAR);
+        let inner = &mut *(inner as *mut DelegateState);
+        (*inner).update_high_contrast();
+    }
+}
+
+/// Callback for the IOKit power source run loop source. This fires on any change to
+/// the power sources, so `DelegateState` filters out the ones that don't change the source.
+extern "C" fn power_source_changed(context: *mut c_void) {
+    unsafe {
+        let inner = &mut *(context as *mut DelegateState);
+        inner.update_power_source();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bcp47_from_icu, language_identifier};
+
+    #[test]
+    fn icu_identifiers_to_bcp47() {
+        assert_eq!(bcp47_from_icu("en_US"), "en-US");
+        assert_eq!(bcp47_from_icu("zh-Hant_TW"), "zh-Hant-TW");
+        assert_eq!(
+            bcp47_from_icu("ja_JP@calendar=japanese"),
+            "ja-JP-u-ca-japanese"
+        );
+        assert_eq!(
+            bcp47_from_icu("de_DE@numbers=arab;collation=phonebook;calendar=gregorian"),
+            "de-DE-u-ca-gregory-co-phonebk-nu-arab"
+        );
+        assert_eq!(bcp47_from_icu("en_GB@rg=uszzzz"), "en-GB-u-rg-uszzzz");
+        assert_eq!(bcp47_from_icu("en-US-u-hc-h23"), "en-US-u-hc-h23");
+    }
+
+    #[test]
+    fn language_identifiers_drop_extensions() {
+        assert_eq!(language_identifier("en-US"), "en-US");
+        assert_eq!(language_identifier("ja-JP-u-ca-japanese"), "ja-JP");
+        assert_eq!(language_identifier("zh-Hant-TW-u-nu-hanidec"), "zh-Hant-TW");
+        assert_eq!(language_identifier("de-x-private"), "de");
+    }
+
+    #[test]
+    fn unknown_keywords_are_dropped() {
+        assert_eq!(bcp47_from_icu("en_US@"), "en-US");
+        assert_eq!(bcp47_from_icu("en_US@timezone=America/New_York"), "en-US");
+        assert_eq!(
+            bcp47_from_icu("fr_FR@somekeyword=value;currency=EUR"),
+            "fr-FR-u-cu-eur"
+        );
+    }
+}
@@ -14,17 +14,49 @@
 
 //! Interactions with the system pasteboard on macOS.
 
+use std::cell::RefCell;
+use std::path::PathBuf;
+
 use cocoa::appkit::NSPasteboardTypeString;
 use cocoa::base::{id, nil, BOOL, YES};
 use cocoa::foundation::{NSArray, NSInteger, NSUInteger};
+use objc::declare::ClassDecl;
+use objc::runtime::{Class, Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
 
 use super::util;
-use crate::clipboard::{ClipboardFormat, FormatId};
+use crate::clipboard::{ClipboardFormat, DataProvider, FormatId};
 
 #[derive(Debug, Clone, Default)]
 pub struct Clipboard;
 
+struct PasteboardOwner(id);
+unsafe impl Sync for PasteboardOwner {}
+unsafe impl Send for PasteboardOwner {}
+
+/// The owner of the types put with `put_lazy`, which the pasteboard asks for their data.
+static PASTEBOARD_OWNER: Lazy<PasteboardOwner> = Lazy::new(|| unsafe {
+    let mut decl = ClassDecl::new("DruidPasteboardOwner", class!(NSObject))
+        .expect("Pasteboard owner definition failed");
+    decl.add_method(
+        sel!(pasteboard:provideDataForType:),
+        provide_data as extern "C" fn(&mut Object, Sel, id, id),
+    );
+    decl.add_method(
+        sel!(pasteboardChangedOwner:),
+        changed_owner as extern "C" fn(&mut Object, Sel, id),
+    );
+    let class = decl.register();
+    PasteboardOwner(msg_send![class, new])
+});
+
+thread_local! {
+    /// The type put with `put_lazy` and its provider, with the change count of the
+    /// pasteboard after putting it.
+    static LAZY_FORMATS: RefCell<Option<(NSInteger, FormatId, DataProvider)>> = RefCell::new(None);
+}
+
 impl Clipboard {
     /// Put a string onto the system clipboard.
     pub fn put_string(&mut self, s: impl AsRef<str>) {
@@ -70,6 +102,18 @@ impl Clipboard {
         }
     }
 
+    /// Put data on the system clipboard that is made when it is pasted.
+    pub fn put_lazy(&mut self, format: FormatId, provider: DataProvider) {
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let types = NSArray::arrayWithObjects(nil, &[util::make_nsstring(format)]);
+            // The owner is asked for the data when it is pasted.
+            let change_count: NSInteger =
+                msg_send![pasteboard, declareTypes: types owner: PASTEBOARD_OWNER.0];
+            LAZY_FORMATS.with(|lazy| *lazy.borrow_mut() = Some((change_count, format, provider)));
+        }
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         unsafe {
@@ -83,6 +127,50 @@ impl Clipboard {
         }
     }
 
+    /// Put a list of files onto the system clipboard.
+    pub fn put_file_list(&mut self, paths: &[PathBuf]) {
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let _: NSInteger = msg_send![pasteboard, clearContents];
+            let urls = paths
+                .iter()
+                .map(|path| {
+                    let path = util::make_nsstring(&path.to_string_lossy());
+                    msg_send![class!(NSURL), fileURLWithPath: path]
+                })
+                .collect::<Vec<id>>();
+            let array = NSArray::arrayWithObjects(nil, &urls);
+            let result: BOOL = msg_send![pasteboard, writeObjects: array];
+            if result != YES {
+                tracing::warn!("failed to put files on the clipboard");
+            }
+        }
+    }
+
+    /// Get the list of files on the system clipboard, if there is one.
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        unsafe {
+            let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+            let classes = NSArray::arrayWithObjects(nil, &[class!(NSURL) as *const Class as id]);
+            let yes: id = msg_send![class!(NSNumber), numberWithBool: YES];
+            // The value of `NSPasteboardURLReadingFileURLsOnlyKey` is its name.
+            let key = util::make_nsstring("NSPasteboardURLReadingFileURLsOnlyKey");
+            let options: id =
+                msg_send![class!(NSDictionary), dictionaryWithObject: yes forKey: key];
+            let urls: id = msg_send![pasteboard, readObjectsForClasses: classes options: options];
+            if urls.is_null() || urls.count() == 0 {
+                return None;
+            }
+            let paths = (0..urls.count())
+                .map(|i| {
+                    let path: id = msg_send![urls.objectAtIndex(i), path];
+                    PathBuf::from(util::from_nsstring(path))
+                })
+                .collect();
+            Some(paths)
+        }
+    }
+
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
     pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
@@ -138,3 +226,42 @@ impl Clipboard {
         }
     }
 }
+
+/// Puts the data that `put_lazy` promised on `pasteboard`, if it is still ours.
+unsafe fn fulfill_promise(pasteboard: id) {
+    let change_count: NSInteger = msg_send![pasteboard, changeCount];
+    let promised = LAZY_FORMATS.with(|lazy| match &*lazy.borrow() {
+        Some((count, format, provider)) if *count == change_count => {
+            Some((*format, provider.clone()))
+        }
+        _ => None,
+    });
+    if let Some((format, provider)) = promised {
+        let data = util::make_nsdata(&provider());
+        let data_type = util::make_nsstring(format);
+        let result: BOOL = msg_send![pasteboard, setData: data forType: data_type];
+        if result != YES {
+            tracing::warn!("failed to provide clipboard contents for type '{}'", format);
+        }
+    }
+}
+
+/// Puts the promised data on the pasteboard before we quit, so that it can still be pasted.
+///
+/// AppKit only asks for it when the application is terminated, while we stop the run loop.
+pub(crate) fn provide_promised_data() {
+    unsafe {
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        fulfill_promise(pasteboard);
+    }
+    LAZY_FORMATS.with(|lazy| lazy.borrow_mut().take());
+}
+
+extern "C" fn provide_data(_this: &mut Object, _: Sel, pasteboard: id, _data_type: id) {
+    // We only ever promise a single type, so it must be the one asked for.
+    unsafe { fulfill_promise(pasteboard) }
+}
+
+extern "C" fn changed_owner(_this: &mut Object, _: Sel, _pasteboard: id) {
+    LAZY_FORMATS.with(|lazy| lazy.borrow_mut().take());
+}
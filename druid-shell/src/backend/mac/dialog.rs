@@ -17,20 +17,31 @@
 #![allow(non_upper_case_globals, clippy::upper_case_acronyms)]
 
 use std::ffi::OsString;
+use std::path::PathBuf;
 
 use cocoa::appkit::NSView;
 use cocoa::base::{id, nil, NO, YES};
-use cocoa::foundation::{NSArray, NSAutoreleasePool, NSInteger, NSPoint, NSRect, NSSize, NSURL};
+use cocoa::foundation::{
+    NSArray, NSAutoreleasePool, NSInteger, NSPoint, NSRect, NSSize, NSUInteger, NSURL,
+};
 use objc::{class, msg_send, sel, sel_impl};
 
-use super::util::{from_nsstring, make_nsstring};
-use crate::dialog::{FileDialogOptions, FileDialogType};
+use super::util::{from_nsdata, from_nsstring, make_nsstring};
+use crate::dialog::{file_info, FileDialogOptions, FileDialogType};
 use crate::{FileInfo, FileSpec};
 
+const NSURLBookmarkCreationWithSecurityScope: NSUInteger = 1 << 11;
+
 pub(crate) type NSModalResponse = NSInteger;
 const NSModalResponseOK: NSInteger = 1;
 const NSModalResponseCancel: NSInteger = 0;
 
+#[link(name = "CoreServices", kind = "framework")]
+extern "C" {
+    static kUTTagClassFilenameExtension: id;
+    fn UTTypeCreatePreferredIdentifierForTag(tag_class: id, tag: id, conforming_to: id) -> id;
+}
+
 pub(crate) unsafe fn get_file_info(
     panel: id,
     options: FileDialogOptions,
@@ -41,10 +52,12 @@ pub(crate) unsafe fn get_file_info(
             let url: id = msg_send![panel, URL];
             let path: id = msg_send![url, path];
             let (path, format) = rewritten_path(panel, path, options);
+            let content_type = content_type(path);
             let path: OsString = from_nsstring(path).into();
+            let path: PathBuf = path.into();
             Some(FileInfo {
-                path: path.into(),
                 format,
+                ..file_info(path, |_| content_type)
             })
         }
         NSModalResponseCancel => None,
@@ -52,6 +65,51 @@ pub(crate) unsafe fn get_file_info(
     }
 }
 
+impl crate::platform::mac::FileInfoExt for FileInfo {
+    fn security_scoped_bookmark(&self) -> Option<Vec<u8>> {
+        let path = self.path.to_str()?;
+        unsafe {
+            let url = NSURL::alloc(nil)
+                .initFileURLWithPath_(make_nsstring(path))
+                .autorelease();
+            let data: id = msg_send![url,
+                bookmarkDataWithOptions: NSURLBookmarkCreationWithSecurityScope
+                includingResourceValuesForKeys: nil
+                relativeToURL: nil
+                error: nil];
+            if data == nil {
+                tracing::warn!("failed to create a bookmark for {:?}", self.path);
+                return None;
+            }
+            Some(from_nsdata(data))
+        }
+    }
+}
+
+/// Returns the UTI of the file at `path`.
+///
+/// Existing files are asked about directly, so that the system can take the file's
+/// contents into account. Otherwise the type is derived from the path extension,
+/// which for save dialogs has already been rewritten to match the chosen format.
+unsafe fn content_type(path: id) -> Option<String> {
+    let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+    let uti: id = msg_send![workspace, typeOfFile: path error: nil];
+    if uti != nil {
+        return Some(from_nsstring(uti));
+    }
+    let extension: id = msg_send![path, pathExtension];
+    let len: usize = msg_send![extension, length];
+    if len == 0 {
+        return None;
+    }
+    let uti = UTTypeCreatePreferredIdentifierForTag(kUTTagClassFilenameExtension, extension, nil);
+    if uti == nil {
+        return None;
+    }
+    let uti = uti.autorelease();
+    Some(from_nsstring(uti))
+}
+
 #[allow(clippy::cognitive_complexity)]
 pub(crate) unsafe fn build_panel(ty: FileDialogType, mut options: FileDialogOptions) -> id {
     let panel: id = match ty {
@@ -18,7 +18,7 @@ use cocoa::appkit::{NSEvent, NSEventModifierFlags, NSEventType};
 use cocoa::base::id;
 use objc::{msg_send, sel, sel_impl};
 
-use crate::keyboard::{Code, KbKey, KeyEvent, KeyState, Modifiers};
+use crate::keyboard::{Code, KbKey, KeyEvent, KeyState, ModifierSides, Modifiers};
 
 use super::super::shared;
 use super::util::from_nsstring;
@@ -330,6 +330,7 @@ impl KeyboardState {
                 code,
                 location,
                 mods,
+                mod_sides: make_mod_sides(raw_mods),
                 repeat,
                 is_composing,
             };
@@ -347,6 +348,19 @@ const MODIFIER_MAP: &[(NSEventModifierFlags, Modifiers)] = &[
         NSEventModifierFlags::NSAlphaShiftKeyMask,
         Modifiers::CAPS_LOCK,
     ),
+    (NSEventModifierFlags::NSFunctionKeyMask, Modifiers::FN),
+];
+
+/// The device-dependent bits of `NSEventModifierFlags`, from `IOLLEvent.h`.
+const MOD_SIDES_MAP: &[(u64, ModifierSides)] = &[
+    (0x01, ModifierSides::LEFT_CONTROL),
+    (0x02, ModifierSides::LEFT_SHIFT),
+    (0x04, ModifierSides::RIGHT_SHIFT),
+    (0x08, ModifierSides::LEFT_META),
+    (0x10, ModifierSides::RIGHT_META),
+    (0x20, ModifierSides::LEFT_ALT),
+    (0x40, ModifierSides::RIGHT_ALT),
+    (0x2000, ModifierSides::RIGHT_CONTROL),
 ];
 
 pub(crate) fn make_modifiers(raw: NSEventModifierFlags) -> Modifiers {
@@ -358,3 +372,13 @@ pub(crate) fn make_modifiers(raw: NSEventModifierFlags) -> Modifiers {
     }
     modifiers
 }
+
+fn make_mod_sides(raw: NSEventModifierFlags) -> ModifierSides {
+    let mut sides = ModifierSides::empty();
+    for &(bits, side) in MOD_SIDES_MAP {
+        if raw.bits() & bits != 0 {
+            sides |= side;
+        }
+    }
+    sides
+}
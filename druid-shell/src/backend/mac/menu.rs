@@ -14,20 +14,159 @@
 
 //! macOS implementation of menus.
 
-use cocoa::appkit::{NSEventModifierFlags, NSMenu, NSMenuItem};
-use cocoa::base::{id, nil, NO};
-use cocoa::foundation::{NSAutoreleasePool, NSString};
-use objc::{msg_send, sel, sel_impl};
+use std::cell::RefCell;
+
+use cocoa::appkit::{NSApp, NSApplication, NSEventModifierFlags, NSMenu, NSMenuItem};
+use cocoa::base::{id, nil, BOOL, NO, YES};
+use cocoa::foundation::{NSAutoreleasePool, NSInteger, NSString};
+use objc::declare::ClassDecl;
+use objc::runtime::{Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
 
 use super::util::make_nsstring;
 use crate::common_util::strip_access_key;
 use crate::hotkey::HotKey;
 use crate::keyboard::{KbKey, Modifiers};
+use crate::menu::StandardCommand;
 
 pub struct Menu {
     pub menu: id,
 }
 
+struct MenuDelegate(id);
+unsafe impl Sync for MenuDelegate {}
+unsafe impl Send for MenuDelegate {}
+
+/// The delegate of all our menus, which updates the items before a menu is shown.
+///
+/// Menus don't retain their delegate, so this lives for the rest of the process.
+static MENU_DELEGATE: Lazy<MenuDelegate> = Lazy::new(|| unsafe {
+    let mut decl = ClassDecl::new("DruidMenuDelegate", class!(NSObject))
+        .expect("Menu delegate definition failed");
+    decl.add_method(
+        sel!(menuNeedsUpdate:),
+        menu_needs_update as extern "C" fn(&mut Object, Sel, id),
+    );
+    let class = decl.register();
+    MenuDelegate(msg_send![class, new])
+});
+
+thread_local! {
+    /// The name set with `ApplicationExt::set_menu_bar_name`.
+    static MENU_BAR_NAME: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// Sets the name of the application in the menu bar, and of the process.
+pub(crate) fn set_menu_bar_name(name: &str) {
+    MENU_BAR_NAME.with(|menu_bar_name| *menu_bar_name.borrow_mut() = Some(name.to_string()));
+    unsafe {
+        let process_info: id = msg_send![class!(NSProcessInfo), processInfo];
+        let () = msg_send![process_info, setProcessName: make_nsstring(name)];
+        apply_menu_bar_name(NSApp().mainMenu());
+    }
+}
+
+/// Sets whether AppKit adds items to the menus that we set.
+pub(crate) fn set_automatic_menu_items(ns_app: id, enabled: bool) {
+    unsafe {
+        let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+        // These are the defaults that AppKit checks before adding each item, and the values
+        // that keep it from adding them.
+        for (key, off) in [
+            ("NSDisabledDictationMenuItem", true),
+            ("NSDisabledCharacterPaletteMenuItem", true),
+            ("NSFullScreenMenuItemEverywhere", false),
+        ] {
+            let key = make_nsstring(key);
+            if enabled {
+                let () = msg_send![defaults, removeObjectForKey: key];
+            } else {
+                let value = if off { YES } else { NO };
+                let () = msg_send![defaults, setBool: value forKey: key];
+            }
+        }
+        let value = if enabled { YES } else { NO };
+        let window_class = class!(NSWindow);
+        let supported: BOOL =
+            msg_send![window_class, respondsToSelector: sel!(setAllowsAutomaticWindowTabbing:)];
+        if supported != NO {
+            let () = msg_send![window_class, setAllowsAutomaticWindowTabbing: value];
+        }
+        let sel = sel!(setAutomaticCustomizeTouchBarMenuItemEnabled:);
+        let supported: BOOL = msg_send![ns_app, respondsToSelector: sel];
+        if supported != NO {
+            let () = msg_send![ns_app, setAutomaticCustomizeTouchBarMenuItemEnabled: value];
+        }
+    }
+}
+
+/// Renames the application menu, which is the first item of `menu`.
+unsafe fn apply_menu_bar_name(menu: id) {
+    if menu == nil {
+        return;
+    }
+    MENU_BAR_NAME.with(|name| {
+        let name = match &*name.borrow() {
+            Some(name) => make_nsstring(name),
+            None => return,
+        };
+        let count: NSInteger = msg_send![menu, numberOfItems];
+        if count == 0 {
+            return;
+        }
+        let item: id = msg_send![menu, itemAtIndex: 0 as NSInteger];
+        let () = msg_send![item, setTitle: name];
+        let submenu: id = msg_send![item, submenu];
+        if submenu != nil {
+            let () = msg_send![submenu, setTitle: name];
+        }
+    })
+}
+
+/// Returns the action that AppKit sends to the first responder for `command`, if it has one.
+pub(super) fn standard_action(command: StandardCommand) -> Option<Sel> {
+    match command {
+        StandardCommand::Cut => Some(sel!(cut:)),
+        StandardCommand::Copy => Some(sel!(copy:)),
+        StandardCommand::Paste => Some(sel!(paste:)),
+        StandardCommand::SelectAll => Some(sel!(selectAll:)),
+        StandardCommand::Undo => Some(sel!(undo:)),
+        StandardCommand::Redo => Some(sel!(redo:)),
+        _ => None,
+    }
+}
+
+/// Returns the standard command whose action is `action`, if there is one.
+pub(super) fn standard_command_for_action(action: Sel) -> Option<StandardCommand> {
+    StandardCommand::ALL
+        .into_iter()
+        .find(|&command| standard_action(command) == Some(action))
+}
+
+/// Asks whoever handles the commands of `menu` whether its items should be enabled.
+extern "C" fn menu_needs_update(_this: &mut Object, _: Sel, menu: id) {
+    unsafe {
+        let count: NSInteger = msg_send![menu, numberOfItems];
+        for index in 0..count {
+            let item: id = msg_send![menu, itemAtIndex: index];
+            let action: Sel = msg_send![item, action];
+            if action != sel!(handleMenuItem:) && standard_command_for_action(action).is_none() {
+                continue;
+            }
+            // This is the view of the key window, or the app delegate if there is none.
+            let target: id = msg_send![NSApp(), targetForAction: action to: nil from: item];
+            if target == nil {
+                continue;
+            }
+            let responds: BOOL = msg_send![target, respondsToSelector: sel!(updateMenuItem:)];
+            if responds != NO {
+                let () = msg_send![target, updateMenuItem: item];
+            }
+        }
+    }
+}
+
 fn make_menu_item(
     id: u32,
     text: &str,
@@ -37,11 +176,15 @@ fn make_menu_item(
 ) -> id {
     let key_equivalent = key.map(HotKey::key_equivalent).unwrap_or("");
     let stripped_text = strip_access_key(text);
+    // The standard edit commands go to the first responder, which may be a native text field.
+    let action = StandardCommand::from_id(id)
+        .and_then(standard_action)
+        .unwrap_or(sel!(handleMenuItem:));
     unsafe {
         let item = NSMenuItem::alloc(nil)
             .initWithTitle_action_keyEquivalent_(
                 make_nsstring(&stripped_text),
-                sel!(handleMenuItem:),
+                action,
                 make_nsstring(key_equivalent),
             )
             .autorelease();
@@ -68,10 +211,19 @@ impl Menu {
             let title = NSString::alloc(nil).init_str("").autorelease();
             let menu = NSMenu::alloc(nil).initWithTitle_(title).autorelease();
             let () = msg_send![menu, setAutoenablesItems: NO];
+            let () = msg_send![menu, setDelegate: MENU_DELEGATE.0];
             Menu { menu }
         }
     }
 
+    /// Makes this the application's main menu.
+    pub fn set_as_main_menu(&self) {
+        unsafe {
+            NSApp().setMainMenu_(self.menu);
+            apply_menu_bar_name(self.menu);
+        }
+    }
+
     pub fn new_for_popup() -> Menu {
         // mac doesn't distinguish between application and context menu types.
         Menu::new()
@@ -111,6 +263,29 @@ impl Menu {
             self.menu.addItem_(sep);
         }
     }
+
+    pub fn add_section(&mut self, title: &str) {
+        unsafe {
+            let count: NSInteger = msg_send![self.menu, numberOfItems];
+            if count > 0 {
+                self.add_separator();
+            }
+            let title = make_nsstring(&strip_access_key(title));
+            let item_class = class!(NSMenuItem);
+            let sel = sel!(sectionHeaderWithTitle:);
+            let native: BOOL = msg_send![item_class, respondsToSelector: sel];
+            let header: id = if native != NO {
+                msg_send![item_class, sectionHeaderWithTitle: title]
+            } else {
+                // Items without an action are disabled, since the menu doesn't autoenable.
+                let item = NSMenuItem::alloc(nil).autorelease();
+                let () = msg_send![item, setTitle: title];
+                let () = msg_send![item, setEnabled: NO];
+                item
+            };
+            self.menu.addItem_(header);
+        }
+    }
 }
 
 impl HotKey {
@@ -16,6 +16,7 @@
 
 #![allow(clippy::let_unit_value)]
 
+mod accessibility;
 pub mod appkit;
 pub mod application;
 pub mod clipboard;
@@ -24,6 +25,7 @@ pub mod error;
 mod keyboard;
 pub mod menu;
 pub mod screen;
+mod services;
 pub mod text_input;
 pub mod util;
 pub mod window;
@@ -14,65 +14,465 @@
 
 //! macOS Monitors and Screen information.
 
-use crate::kurbo::Rect;
-use crate::screen::Monitor;
-use cocoa::appkit::NSScreen;
-use cocoa::base::id;
-use cocoa::foundation::NSArray;
+use crate::error::Error;
+use crate::kurbo::{Affine, Insets, Point, Rect, Size};
+use crate::piet::{ImageBuf, ImageFormat};
+use crate::platform::mac::DockEdge;
+use crate::scale::Scale;
+use crate::screen::{DisplayMode, Monitor, Orientation, Screen, SubpixelOrder};
+use anyhow::anyhow;
+use cocoa::appkit::{CGFloat, NSApp, NSScreen};
+use cocoa::base::{id, nil, BOOL, NO, YES};
+use cocoa::foundation::{NSArray, NSPoint, NSUInteger};
+use core_graphics::base::kCGImageAlphaPremultipliedLast;
+use core_graphics::color_space::CGColorSpace;
+use core_graphics::context::CGContext;
+use core_graphics::display::{CGConfigureOption, CGDisplay, CGDisplayMode};
+use core_graphics::geometry::{CGPoint, CGRect, CGSize};
+use core_graphics::window::{create_image, kCGNullWindowID, kCGWindowImageDefault};
 use objc::{class, msg_send, sel, sel_impl};
+use std::ffi::c_void;
+use std::ptr::null;
+
+use super::appkit::{kCGWindowListOptionOnScreenOnly, NSEdgeInsets};
+use super::util::{from_nsstring, make_nsstring};
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGPreflightScreenCaptureAccess() -> bool;
+    fn CGRequestScreenCaptureAccess() -> bool;
+}
+
+#[link(name = "ColorSync", kind = "framework")]
+extern "C" {
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> *const c_void;
+}
+
+#[link(name = "CoreFoundation", kind = "framework")]
+extern "C" {
+    fn CFUUIDCreateString(allocator: *const c_void, uuid: *const c_void) -> id;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// The space between the Dock's icons and its edges, in display points.
+const DOCK_PADDING: f64 = 8.0;
+
+/// `NSApplicationPresentationAutoHideDock`, from `NSApplicationPresentationOptions`.
+const PRESENTATION_AUTO_HIDE_DOCK: NSUInteger = 1 << 0;
+/// `NSApplicationPresentationAutoHideMenuBar`, from `NSApplicationPresentationOptions`.
+const PRESENTATION_AUTO_HIDE_MENU_BAR: NSUInteger = 1 << 2;
+
+/// The settings of the Dock that we can't get from the screens.
+struct DockPrefs {
+    edge: Option<DockEdge>,
+    autohide: bool,
+    tile_size: f64,
+}
 
 pub(crate) fn get_monitors() -> Vec<Monitor> {
     unsafe {
         let screens: id = msg_send![class![NSScreen], screens];
-        let mut monitors = Vec::<(Rect, Rect)>::new();
-        let mut total_rect = Rect::ZERO;
-
-        for idx in 0..screens.count() {
-            let screen = screens.objectAtIndex(idx);
-            let frame = NSScreen::frame(screen);
-
-            let frame_r = Rect::from_origin_size(
-                (frame.origin.x, frame.origin.y),
-                (frame.size.width, frame.size.height),
-            );
-            let vis_frame = NSScreen::visibleFrame(screen);
-            let vis_frame_r = Rect::from_origin_size(
-                (vis_frame.origin.x, vis_frame.origin.y),
-                (vis_frame.size.width, vis_frame.size.height),
-            );
-            monitors.push((frame_r, vis_frame_r));
-            total_rect = total_rect.union(frame_r)
+        let scales: Vec<Scale> = (0..screens.count())
+            .map(|idx| {
+                let scale: CGFloat = msg_send![screens.objectAtIndex(idx), backingScaleFactor];
+                Scale::new(scale, scale)
+            })
+            .collect();
+        let insets: Vec<Insets> = (0..screens.count())
+            .map(|idx| safe_area_insets(screens.objectAtIndex(idx)))
+            .collect();
+        let displays: Vec<CGDisplay> = (0..screens.count())
+            .map(|idx| display_of(screens.objectAtIndex(idx)))
+            .collect();
+        let (monitors, max_y) = screen_frames();
+        // Cocoa's y axis goes up from the bottom of the primary screen.
+        let flip = Affine::new([1.0, 0.0, 0.0, -1.0, 0.0, max_y]);
+        transform_coords(monitors, max_y)
+            .into_iter()
+            .zip(scales)
+            .zip(insets)
+            .zip(displays)
+            .map(|(((monitor, scale), insets), display)| {
+                let monitor = match device_id(&display) {
+                    Some(id) => monitor.with_device_id(id),
+                    None => monitor,
+                };
+                // macOS hasn't used subpixel antialiasing since 10.14.
+                monitor
+                    .with_scale(scale)
+                    .with_safe_area_insets(insets)
+                    .with_internal(display.is_builtin())
+                    .with_subpixel_order(SubpixelOrder::None)
+                    .with_orientation(orientation(display.rotation()))
+                    .with_backend_transform(flip)
+                    .with_native_id(display.id.to_string())
+            })
+            .collect()
+    }
+}
+
+/// Returns the top of the primary screen in Cocoa's coordinates.
+///
+/// Cocoa's y axis points up from the bottom of the primary screen, while ours points down
+/// from its top, like on Windows, so this is what flips between them.
+pub(crate) fn max_y() -> f64 {
+    unsafe { screen_frames().1 }
+}
+
+pub(crate) fn num_monitors() -> usize {
+    unsafe {
+        let screens: id = msg_send![class![NSScreen], screens];
+        screens.count() as usize
+    }
+}
+
+pub(crate) fn get_mouse_position() -> Option<Point> {
+    let location: NSPoint = unsafe { msg_send![class!(NSEvent), mouseLocation] };
+    Some(Point::new(location.x, max_y() - location.y))
+}
+
+pub(crate) fn has_screen_capture_access() -> bool {
+    unsafe { CGPreflightScreenCaptureAccess() }
+}
+
+pub(crate) fn request_screen_capture_access() -> bool {
+    unsafe { CGRequestScreenCaptureAccess() }
+}
+
+pub(crate) fn capture_region(rect: Rect) -> Result<ImageBuf, Error> {
+    // Without permission, the image only has the desktop and the menu bar, instead of failing.
+    if !has_screen_capture_access() {
+        return Err(Error::PermissionDenied);
+    }
+    // Core Graphics has its origin at the top of the primary screen too.
+    let bounds = CGRect::new(
+        &CGPoint::new(rect.x0, rect.y0),
+        &CGSize::new(rect.width(), rect.height()),
+    );
+    let image = create_image(
+        bounds,
+        kCGWindowListOptionOnScreenOnly,
+        kCGNullWindowID,
+        kCGWindowImageDefault,
+    )
+    .ok_or_else(|| anyhow!("failed to capture {}", rect))?;
+    // The layout of the image depends on the display, so we draw it into one we know.
+    let (width, height) = (image.width(), image.height());
+    let mut context = CGContext::create_bitmap_context(
+        None,
+        width,
+        height,
+        8,
+        width * 4,
+        &CGColorSpace::create_device_rgb(),
+        kCGImageAlphaPremultipliedLast,
+    );
+    let image_rect = CGRect::new(
+        &CGPoint::new(0.0, 0.0),
+        &CGSize::new(width as f64, height as f64),
+    );
+    context.draw_image(image_rect, &image);
+    Ok(ImageBuf::from_raw(
+        context.data().to_vec(),
+        ImageFormat::RgbaPremul,
+        width,
+        height,
+    ))
+}
+
+/// Returns the Core Graphics display that shows `screen`.
+unsafe fn display_of(screen: id) -> CGDisplay {
+    let description: id = msg_send![screen, deviceDescription];
+    let number: id = msg_send![description, objectForKey: make_nsstring("NSScreenNumber")];
+    let display_id: u32 = msg_send![number, unsignedIntValue];
+    CGDisplay::new(display_id)
+}
+
+/// Returns the modes of `monitor`, including the scaled ones of Retina displays.
+pub(crate) fn display_modes(monitor: &Monitor) -> Vec<DisplayMode> {
+    match find_display(monitor) {
+        Ok(display) => unsafe { native_modes(display) }
+            .into_iter()
+            .map(|(_, mode)| mode)
+            .collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub(crate) fn set_mode(monitor: &Monitor, mode: &DisplayMode) -> Result<(), Error> {
+    let display = find_display(monitor)?;
+    let native = unsafe { native_modes(display) }
+        .into_iter()
+        .find(|(_, candidate)| candidate.with_current(false) == *mode)
+        .map(|(native, _)| native)
+        .ok_or_else(|| anyhow!("the monitor {} no longer supports {:?}", monitor, mode))?;
+    let config = display
+        .begin_configuration()
+        .map_err(|err| anyhow!("failed to configure the display: error {}", err))?;
+    if let Err(err) = display.configure_display_with_display_mode(&config, &native) {
+        let _ = display.cancel_configuration(&config);
+        return Err(anyhow!("the display rejected {:?}: error {}", mode, err).into());
+    }
+    // The mode is kept until the user logs out, without changing the display settings.
+    display
+        .complete_configuration(&config, CGConfigureOption::ConfigureForSession)
+        .map_err(|err| anyhow!("failed to switch to {:?}: error {}", mode, err).into())
+}
+
+/// Returns the active display that `monitor` was made from.
+fn find_display(monitor: &Monitor) -> Result<CGDisplay, Error> {
+    let display = CGDisplay::active_displays()
+        .map_err(|err| anyhow!("failed to list the displays: error {}", err))?
+        .into_iter()
+        .map(CGDisplay::new)
+        .find(|display| monitor.native_id() == Some(display.id.to_string().as_str()))
+        .ok_or_else(|| anyhow!("the monitor {} is no longer connected", monitor))?;
+    Ok(display)
+}
+
+/// Returns the UUID of `display`, which stays the same across restarts and reconnects.
+///
+/// Unlike the vendor, model and serial numbers, which are often all the same for identical
+/// displays since many don't report a serial number, this is unique to each display.
+fn device_id(display: &CGDisplay) -> Option<String> {
+    unsafe {
+        let uuid = CGDisplayCreateUUIDFromDisplayID(display.id);
+        if uuid.is_null() {
+            return None;
+        }
+        let string = CFUUIDCreateString(null(), uuid);
+        CFRelease(uuid);
+        if string == nil {
+            return None;
         }
-        // TODO save this total_rect.y1 for screen coord transformations in get_position/set_position
-        // and invalidate on monitor changes
-        transform_coords(monitors, total_rect.y1)
+        let id = from_nsstring(string);
+        CFRelease(string as *const c_void);
+        Some(id)
     }
 }
 
-fn transform_coords(monitors_build: Vec<(Rect, Rect)>, max_y: f64) -> Vec<Monitor> {
-    //Flip y and move to opposite horizontal edges (On mac, Y goes up and origin is bottom left corner)
-    let fix_rect = |frame: &Rect| {
-        Rect::new(
-            frame.x0,
-            (max_y - frame.y0) - frame.height(),
-            frame.x1,
-            (max_y - frame.y1) + frame.height(),
-        )
+/// Returns the modes of `display` along with the Core Graphics modes they come from.
+unsafe fn native_modes(display: CGDisplay) -> Vec<(CGDisplayMode, DisplayMode)> {
+    // Without this option the list leaves out the scaled modes, which Retina displays use.
+    let yes: id = msg_send![class!(NSNumber), numberWithBool: YES];
+    let key = make_nsstring("kCGDisplayShowDuplicateLowResolutionModes");
+    let options: id = msg_send![class!(NSDictionary), dictionaryWithObject: yes forKey: key];
+    let current = display.display_mode().map(|mode| mode.mode_id());
+    CGDisplayMode::all_display_modes(display.id, options as _)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|mode| {
+            let size = Size::new(mode.pixel_width() as f64, mode.pixel_height() as f64);
+            let scale = size.width / mode.width().max(1) as f64;
+            // Built-in panels report a refresh rate of zero.
+            let refresh = mode.refresh_rate();
+            let info = DisplayMode::new(
+                size,
+                (refresh > 0.0).then_some(refresh),
+                Scale::new(scale, scale),
+            )
+            .with_current(Some(mode.mode_id()) == current);
+            (mode, info)
+        })
+        .collect()
+}
+
+/// Returns the orientation for a rotation in degrees, as given by `CGDisplayRotation`.
+fn orientation(degrees: f64) -> Orientation {
+    match degrees.round() as i32 {
+        90 => Orientation::Portrait,
+        180 => Orientation::LandscapeFlipped,
+        270 => Orientation::PortraitFlipped,
+        _ => Orientation::Landscape,
+    }
+}
+
+/// Returns the insets that avoid the camera housing of `screen`.
+unsafe fn safe_area_insets(screen: id) -> Insets {
+    // This was added in macOS 12, along with the first notched displays.
+    let supported: BOOL = msg_send![screen, respondsToSelector: sel!(safeAreaInsets)];
+    if supported == NO {
+        return Insets::ZERO;
+    }
+    let insets: NSEdgeInsets = msg_send![screen, safeAreaInsets];
+    Insets::new(insets.left, insets.top, insets.right, insets.bottom)
+}
+
+/// Returns the frame and visible frame of every screen, and the top of the primary one.
+unsafe fn screen_frames() -> (Vec<(Rect, Rect)>, f64) {
+    let screens: id = msg_send![class![NSScreen], screens];
+    let mut frames = Vec::new();
+    for idx in 0..screens.count() {
+        let screen = screens.objectAtIndex(idx);
+        let frame = NSScreen::frame(screen);
+        let frame = Rect::from_origin_size(
+            (frame.origin.x, frame.origin.y),
+            (frame.size.width, frame.size.height),
+        );
+        let vis_frame = NSScreen::visibleFrame(screen);
+        let vis_frame = Rect::from_origin_size(
+            (vis_frame.origin.x, vis_frame.origin.y),
+            (vis_frame.size.width, vis_frame.size.height),
+        );
+        frames.push((frame, vis_frame));
+    }
+    // The first screen is the primary one, which has its origin at (0, 0).
+    let primary_top = frames.first().map_or(0.0, |(frame, _)| frame.y1);
+    (frames, primary_top)
+}
+
+/// Returns how the application asked the menu bar and Dock to behave while it is active.
+unsafe fn presentation_options() -> NSUInteger {
+    msg_send![NSApp(), presentationOptions]
+}
+
+/// Reads the Dock's settings, which might not be readable when sandboxed.
+unsafe fn dock_prefs() -> DockPrefs {
+    let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+    let dock: id = msg_send![defaults, persistentDomainForName: make_nsstring("com.apple.dock")];
+    let mut prefs = DockPrefs {
+        edge: None,
+        autohide: false,
+        tile_size: 48.0,
     };
+    if dock == nil {
+        return prefs;
+    }
+    let orientation: id = msg_send![dock, objectForKey: make_nsstring("orientation")];
+    if orientation != nil {
+        prefs.edge = match from_nsstring(orientation).as_str() {
+            "left" => Some(DockEdge::Left),
+            "right" => Some(DockEdge::Right),
+            _ => Some(DockEdge::Bottom),
+        };
+    }
+    let autohide: id = msg_send![dock, objectForKey: make_nsstring("autohide")];
+    if autohide != nil {
+        let autohide: BOOL = msg_send![autohide, boolValue];
+        prefs.autohide = autohide != NO;
+    }
+    let tile_size: id = msg_send![dock, objectForKey: make_nsstring("tilesize")];
+    if tile_size != nil {
+        prefs.tile_size = msg_send![tile_size, doubleValue];
+    }
+    prefs
+}
+
+/// Finds the Dock on a screen from how much its visible frame is inset from its frame.
+///
+/// Both are in the screen's coordinates, where y goes up.
+fn dock_frame(frame: Rect, vis_frame: Rect, prefs: &DockPrefs) -> Option<(Rect, DockEdge)> {
+    let left = vis_frame.x0 - frame.x0;
+    let right = frame.x1 - vis_frame.x1;
+    let bottom = vis_frame.y0 - frame.y0;
+    let edge = prefs.edge.unwrap_or(if left > right.max(bottom) {
+        DockEdge::Left
+    } else if right > bottom {
+        DockEdge::Right
+    } else {
+        DockEdge::Bottom
+    });
+    let inset = match edge {
+        DockEdge::Bottom => bottom,
+        DockEdge::Left => left,
+        DockEdge::Right => right,
+    };
+    // Monitors without the Dock are not inset at all, while a hidden Dock still leaves
+    // a few points to show it.
+    if inset <= 0.0 {
+        return None;
+    }
+    let thickness = if prefs.autohide {
+        inset.max(prefs.tile_size + 2.0 * DOCK_PADDING)
+    } else {
+        inset
+    };
+    let rect = match edge {
+        DockEdge::Bottom => Rect::new(frame.x0, frame.y0, frame.x1, frame.y0 + thickness),
+        DockEdge::Left => Rect::new(frame.x0, frame.y0, frame.x0 + thickness, vis_frame.y1),
+        DockEdge::Right => Rect::new(frame.x1 - thickness, frame.y0, frame.x1, vis_frame.y1),
+    };
+    Some((rect, edge))
+}
 
+impl crate::platform::mac::ScreenExt for Screen {
+    fn menu_bar_height() -> f64 {
+        unsafe {
+            let (frames, _) = screen_frames();
+            let height = frames
+                .first()
+                .map(|(frame, vis_frame)| frame.y1 - vis_frame.y1)
+                .unwrap_or(0.0);
+            if height > 0.0 {
+                height
+            } else {
+                // The menu bar hides automatically, so it doesn't take any space.
+                let menu: id = msg_send![NSApp(), mainMenu];
+                if menu == nil {
+                    0.0
+                } else {
+                    msg_send![menu, menuBarHeight]
+                }
+            }
+        }
+    }
+
+    fn menu_bar_autohides() -> bool {
+        unsafe {
+            let defaults: id = msg_send![class!(NSUserDefaults), standardUserDefaults];
+            // This is the "Automatically hide and show the menu bar" system setting.
+            let hide: BOOL = msg_send![defaults, boolForKey: make_nsstring("_HIHideMenuBar")];
+            hide != NO || presentation_options() & PRESENTATION_AUTO_HIDE_MENU_BAR != 0
+        }
+    }
+
+    fn dock_autohides() -> bool {
+        unsafe {
+            dock_prefs().autohide || presentation_options() & PRESENTATION_AUTO_HIDE_DOCK != 0
+        }
+    }
+
+    fn dock_frame() -> Option<(Rect, DockEdge)> {
+        unsafe {
+            let (frames, max_y) = screen_frames();
+            let prefs = dock_prefs();
+            frames
+                .iter()
+                .find_map(|(frame, vis_frame)| dock_frame(*frame, *vis_frame, &prefs))
+                .map(|(rect, edge)| (flip_rect(&rect, max_y), edge))
+        }
+    }
+}
+
+/// Flip y and move to opposite horizontal edges (On mac, Y goes up and origin is bottom left corner)
+fn flip_rect(frame: &Rect, max_y: f64) -> Rect {
+    Rect::new(
+        frame.x0,
+        (max_y - frame.y0) - frame.height(),
+        frame.x1,
+        (max_y - frame.y1) + frame.height(),
+    )
+}
+
+fn transform_coords(monitors_build: Vec<(Rect, Rect)>, max_y: f64) -> Vec<Monitor> {
     monitors_build
         .iter()
         .enumerate()
         .map(|(idx, (frame, vis_frame))| {
-            Monitor::new(idx == 0, fix_rect(frame), fix_rect(vis_frame))
+            Monitor::new(
+                idx == 0,
+                flip_rect(frame, max_y),
+                flip_rect(vis_frame, max_y),
+            )
         })
         .collect()
 }
 
 #[cfg(test)]
 mod test {
-    use crate::backend::mac::screen::transform_coords;
+    use crate::backend::mac::screen::{dock_frame, transform_coords, DockPrefs};
     use crate::kurbo::Rect;
+    use crate::platform::mac::DockEdge;
     use crate::Monitor;
     use test_log::test;
 
@@ -128,4 +528,32 @@ mod test {
             mons
         )
     }
+
+    #[test]
+    fn test_dock_frame() {
+        let frame = Rect::new(0., 0., 1000., 800.);
+        let shown = DockPrefs {
+            edge: None,
+            autohide: false,
+            tile_size: 48.,
+        };
+        assert_eq!(
+            dock_frame(frame, Rect::new(60., 0., 1000., 775.), &shown),
+            Some((Rect::new(0., 0., 60., 775.), DockEdge::Left))
+        );
+        assert_eq!(
+            dock_frame(frame, Rect::new(0., 0., 1000., 775.), &shown),
+            None
+        );
+
+        let hidden = DockPrefs {
+            edge: Some(DockEdge::Bottom),
+            autohide: true,
+            tile_size: 48.,
+        };
+        assert_eq!(
+            dock_frame(frame, Rect::new(0., 4., 1000., 775.), &hidden),
+            Some((Rect::new(0., 0., 1000., 64.), DockEdge::Bottom))
+        );
+    }
 }
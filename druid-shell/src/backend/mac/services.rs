@@ -0,0 +1,178 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Providing services to other applications through the Services menu.
+
+use std::ffi::{c_void, CString};
+
+use cocoa::appkit::NSPasteboardTypeString;
+use cocoa::base::{id, nil, BOOL, YES};
+use cocoa::foundation::{NSArray, NSInteger};
+use objc::declare::ClassDecl;
+use objc::runtime::{class_addMethod, Class, Imp, Object, Sel};
+use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
+
+use super::util::{from_nsstring, make_nsstring};
+use crate::platform::mac::{ServiceDeclaration, ServiceHandler, ServiceRequest};
+
+const SERVICE_HANDLER_IVAR: &str = "serviceHandler";
+
+struct ServicesProviderClass(*const Class);
+unsafe impl Sync for ServicesProviderClass {}
+unsafe impl Send for ServicesProviderClass {}
+
+static SERVICES_PROVIDER: Lazy<ServicesProviderClass> = Lazy::new(|| unsafe {
+    let mut decl = ClassDecl::new("DruidServicesProvider", class!(NSObject))
+        .expect("Services provider definition failed");
+    decl.add_ivar::<*mut c_void>(SERVICE_HANDLER_IVAR);
+    decl.add_method(sel!(dealloc), dealloc as extern "C" fn(&mut Object, Sel));
+    ServicesProviderClass(decl.register())
+});
+
+/// Makes a provider that calls `handler` for the declared services, and sets it on `ns_app`.
+pub(crate) fn set_services_provider(ns_app: id, handler: Box<dyn ServiceHandler>) {
+    unsafe {
+        let class = SERVICES_PROVIDER.0;
+        // The selectors are chosen by the Info.plist, so they can only be added at runtime.
+        for service in declared_services() {
+            add_service_method(class, &service.message);
+        }
+        let provider: id = msg_send![class, new];
+        let handler = Box::into_raw(Box::new(handler));
+        (*provider).set_ivar(SERVICE_HANDLER_IVAR, handler as *mut c_void);
+        let () = msg_send![ns_app, setServicesProvider: provider];
+        let () = msg_send![provider, release];
+    }
+}
+
+/// Reads the `NSServices` entries of the main bundle's `Info.plist`.
+pub(crate) fn declared_services() -> Vec<ServiceDeclaration> {
+    unsafe {
+        let bundle: id = msg_send![class!(NSBundle), mainBundle];
+        let info: id = msg_send![bundle, infoDictionary];
+        array_for_key(info, "NSServices")
+            .into_iter()
+            .filter_map(|service| {
+                let menu_item: id = object_for_key(service, "NSMenuItem");
+                Some(ServiceDeclaration {
+                    message: string_for_key(service, "NSMessage")?,
+                    menu_title: string_for_key(menu_item, "default"),
+                    send_types: strings(array_for_key(service, "NSSendTypes")),
+                    return_types: strings(array_for_key(service, "NSReturnTypes")),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Adds the `message:userData:error:` method that the Services system calls for `message`.
+///
+/// This does nothing if the method was already added.
+unsafe fn add_service_method(class: *const Class, message: &str) {
+    let sel = Sel::register(&format!("{message}:userData:error:"));
+    let imp: extern "C" fn(&mut Object, Sel, id, id, *mut id) = perform_service;
+    let types = CString::new("v@:@@^@").unwrap();
+    class_addMethod(
+        class as *mut Class,
+        sel,
+        std::mem::transmute::<_, Imp>(imp),
+        types.as_ptr(),
+    );
+}
+
+extern "C" fn perform_service(
+    this: &mut Object,
+    sel: Sel,
+    pboard: id,
+    user_data: id,
+    error: *mut id,
+) {
+    unsafe {
+        let handler: *mut c_void = *this.get_ivar(SERVICE_HANDLER_IVAR);
+        let handler = &mut *(handler as *mut Box<dyn ServiceHandler>);
+        let text: id = msg_send![pboard, stringForType: NSPasteboardTypeString];
+        let types: id = msg_send![pboard, types];
+        let request = ServiceRequest {
+            message: sel.name().split(':').next().unwrap_or_default().to_string(),
+            user_data: (user_data != nil).then(|| from_nsstring(user_data)),
+            text: (text != nil).then(|| from_nsstring(text)),
+            types: strings(items(types)),
+        };
+        match handler.perform_service(request) {
+            Ok(Some(text)) => {
+                let _: NSInteger = msg_send![pboard, clearContents];
+                let _: BOOL = msg_send![pboard, setString: make_nsstring(&text)
+                                               forType: NSPasteboardTypeString];
+            }
+            Ok(None) => (),
+            Err(message) => {
+                if !error.is_null() {
+                    *error = make_nsstring(&message);
+                }
+            }
+        }
+    }
+}
+
+extern "C" fn dealloc(this: &mut Object, _: Sel) {
+    unsafe {
+        let handler: *mut c_void = *this.get_ivar(SERVICE_HANDLER_IVAR);
+        if !handler.is_null() {
+            drop(Box::from_raw(handler as *mut Box<dyn ServiceHandler>));
+        }
+        let () = msg_send![super(this, class!(NSObject)), dealloc];
+    }
+}
+
+unsafe fn is_kind_of(object: id, class: &Class) -> bool {
+    // Messages to nil return NO.
+    let is_kind: BOOL = msg_send![object, isKindOfClass: class];
+    is_kind == YES
+}
+
+unsafe fn object_for_key(dict: id, key: &str) -> id {
+    if !is_kind_of(dict, class!(NSDictionary)) {
+        return nil;
+    }
+    msg_send![dict, objectForKey: make_nsstring(key)]
+}
+
+unsafe fn as_string(object: id) -> Option<String> {
+    is_kind_of(object, class!(NSString)).then(|| from_nsstring(object))
+}
+
+unsafe fn string_for_key(dict: id, key: &str) -> Option<String> {
+    as_string(object_for_key(dict, key))
+}
+
+/// Returns the items of `array`, or nothing if it isn't an `NSArray`.
+unsafe fn items(array: id) -> Vec<id> {
+    if !is_kind_of(array, class!(NSArray)) {
+        return Vec::new();
+    }
+    (0..array.count()).map(|i| array.objectAtIndex(i)).collect()
+}
+
+unsafe fn array_for_key(dict: id, key: &str) -> Vec<id> {
+    items(object_for_key(dict, key))
+}
+
+/// Converts the strings in `items` to Rust strings, skipping anything else.
+unsafe fn strings(items: Vec<id>) -> Vec<String> {
+    items
+        .into_iter()
+        .filter_map(|item| as_string(item))
+        .collect()
+}
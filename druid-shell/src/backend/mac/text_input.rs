@@ -25,6 +25,7 @@ use std::ffi::c_void;
 use std::ops::Range;
 use std::os::raw::c_uchar;
 
+use super::util::make_nsstring;
 use super::window::with_edit_lock_from_window;
 use crate::kurbo::Point;
 use crate::text::{
@@ -183,9 +184,10 @@ pub extern "C" fn attributed_substring_for_proposed_range(
         }
         let text = edit_lock.slice(range);
         unsafe {
-            let ns_string = NSString::alloc(nil).init_str(&text);
             let attr_string: id = msg_send![class!(NSAttributedString), alloc];
-            msg_send![attr_string, initWithString: ns_string]
+            let attr_string: id = msg_send![attr_string, initWithString: make_nsstring(&text)];
+            // The caller doesn't own what this returns.
+            msg_send![attr_string, autorelease]
         }
     })
     .unwrap_or(nil)
@@ -26,9 +26,14 @@ use objc::{class, msg_send, sel, sel_impl};
 /// undefined behavior is possible if invoked from other threads. If so,
 /// failing on non main thread is necessary for safety.
 pub(crate) fn assert_main_thread() {
+    assert!(is_main_thread(), "Main thread assertion failed");
+}
+
+/// Returns `true` if called on the main thread.
+pub(crate) fn is_main_thread() -> bool {
     unsafe {
         let is_main_thread: BOOL = msg_send!(class!(NSThread), isMainThread);
-        assert_eq!(is_main_thread, YES);
+        is_main_thread == YES
     }
 }
 
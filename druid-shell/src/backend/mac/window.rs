@@ -16,21 +16,25 @@
 
 #![allow(non_snake_case)]
 
+use std::collections::HashSet;
 use std::ffi::c_void;
 use std::mem;
+use std::path::Path;
 use std::sync::{Arc, Mutex, Weak};
 use std::time::Instant;
 
 use block::ConcreteBlock;
 use cocoa::appkit::{
-    CGFloat, NSApp, NSApplication, NSAutoresizingMaskOptions, NSBackingStoreBuffered, NSColor,
-    NSEvent, NSView, NSViewHeightSizable, NSViewWidthSizable, NSWindow, NSWindowStyleMask,
+    CGFloat, NSAutoresizingMaskOptions, NSBackingStoreBuffered, NSColor, NSEvent, NSView,
+    NSViewHeightSizable, NSViewWidthSizable, NSWindow, NSWindowButton, NSWindowStyleMask,
 };
 use cocoa::base::{id, nil, BOOL, NO, YES};
 use cocoa::foundation::{
     NSArray, NSAutoreleasePool, NSInteger, NSPoint, NSRect, NSSize, NSString, NSUInteger,
 };
 use core_graphics::context::CGContextRef;
+use core_graphics::display::CGDisplay;
+use core_graphics::geometry::CGPoint;
 use foreign_types::ForeignTypeRef;
 use objc::declare::ClassDecl;
 use objc::rc::WeakPtr;
@@ -43,12 +47,16 @@ use tracing::{debug, error, info};
 use raw_window_handle::{AppKitWindowHandle, HasRawWindowHandle, RawWindowHandle};
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::piet::{Color, Piet, PietText, RenderContext};
 
 use self::levels::{NSFloatingWindowLevel, NSNormalWindowLevel};
 
+use super::accessibility::AccessibilityState;
 use super::appkit::{
-    NSRunLoopCommonModes, NSTrackingArea, NSTrackingAreaOptions, NSView as NSViewExt,
+    NSAccessibilityAnnouncementKey, NSAccessibilityAnnouncementRequestedNotification,
+    NSAccessibilityPostNotificationWithUserInfo, NSAccessibilityPriorityHigh,
+    NSAccessibilityPriorityKey, NSAccessibilityPriorityMedium, NSRunLoopCommonModes,
+    NSTrackingArea, NSTrackingAreaOptions, NSView as NSViewExt,
 };
 use super::application::Application;
 use super::dialog;
@@ -56,21 +64,30 @@ use super::keyboard::{make_modifiers, KeyboardState};
 use super::menu::Menu;
 use super::text_input::NSRange;
 use super::util::{assert_main_thread, make_nsstring};
-use crate::common_util::IdleCallback;
+use crate::accessibility::AccessibilityTree;
+use crate::common_util::{AnimationClock, CursorStack, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType};
 use crate::keyboard_types::KeyState;
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::menu::StandardCommand;
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
+use crate::platform::mac::{CollectionBehavior, Material};
 use crate::region::Region;
 use crate::scale::Scale;
+use crate::screen::Screen;
 use crate::text::{Event, InputHandler};
+use crate::touch::{TouchEvent, TouchId, TouchPhase};
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel, WindowState,
+    FileDialogToken, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    WindowState, WindowType,
 };
 use crate::Error;
 
 #[allow(non_upper_case_globals)]
 const NSWindowDidBecomeKeyNotification: &str = "NSWindowDidBecomeKeyNotification";
 
+#[allow(non_upper_case_globals)]
+const NSWindowOcclusionStateVisible: NSUInteger = 1 << 1;
+
 #[allow(dead_code)]
 #[allow(non_upper_case_globals)]
 mod levels {
@@ -86,14 +103,27 @@ mod levels {
     pub const NSStatusWindowLevel: i32 = 25;
     pub const NSPopUpMenuWindowLevel: i32 = 101;
     pub const NSScreenSaverWindowLevel: i32 = 1000;
+    const kCGDesktopWindowLevelKey: i32 = 2;
+
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGWindowLevelForKey(key: i32) -> i32;
+    }
+
+    /// The level just above the wallpaper, below every other window.
+    pub fn above_desktop_level() -> i32 {
+        unsafe { CGWindowLevelForKey(kCGDesktopWindowLevelKey) + 1 }
+    }
 
     pub fn as_raw_window_level(window_level: WindowLevel) -> i32 {
         use WindowLevel::*;
         match window_level {
             AppWindow => NSNormalWindowLevel,
+            Floating => NSFloatingWindowLevel,
             Tooltip(_) => NSFloatingWindowLevel,
             DropDown(_) => NSFloatingWindowLevel,
             Modal(_) => NSModalPanelWindowLevel,
+            Overlay => NSScreenSaverWindowLevel,
         }
     }
 }
@@ -146,7 +176,11 @@ pub(crate) struct WindowBuilder {
     resizable: bool,
     show_titlebar: bool,
     transparent: bool,
+    background_color: Option<Color>,
     always_on_top: bool,
+    owner: Option<WindowHandle>,
+    modal: bool,
+    window_type: WindowType,
 }
 
 #[derive(Clone)]
@@ -159,6 +193,8 @@ pub(crate) struct IdleHandle {
 enum DeferredOp {
     SetSize(Size),
     SetPosition(Point),
+    SetImeEnabled(bool),
+    SetTextFieldImeEnabled(TextFieldToken, bool),
 }
 
 /// This represents different Idle Callback Mechanism
@@ -177,10 +213,96 @@ struct ViewState {
     focus_click: bool,
     // Tracks whether we have already received the mouseExited event
     mouse_left: bool,
+    // Tracks whether we last reported the mouse as inside the view
+    mouse_inside: bool,
+    /// The kind of tablet pointer that last came into proximity.
+    tablet_pointer: PointerType,
+    /// The retained identities of the touches in progress, and the ids we gave them.
+    touches: Vec<(id, TouchId)>,
     keyboard_state: KeyboardState,
     text: PietText,
     active_text_input: Option<TextFieldToken>,
+    /// Whether the input method is on for the window.
+    ime_enabled: bool,
+    /// The text fields the input method is turned off for.
+    ime_disabled_fields: HashSet<TextFieldToken>,
     parent: Option<crate::WindowHandle>,
+    /// The window set with `WindowBuilder::set_parent`, if any.
+    owner: Option<WindowHandle>,
+    /// Whether we are shown as a sheet on `owner`.
+    modal: bool,
+    /// The number of unbalanced `set_cursor_visible(false)` calls.
+    cursor_hide_count: u32,
+    cursor_confined: bool,
+    cursor: CursorStack,
+    anim_clock: AnimationClock,
+    accessibility: AccessibilityState,
+    /// The `NSVisualEffectView` behind our view, or `nil`.
+    effect_view: id,
+    /// Whether the window closes when the user clicks outside it or switches apps.
+    dismiss_on_focus_loss: bool,
+    /// The event monitor that looks for clicks outside the window, or `nil`.
+    dismiss_monitor: id,
+    /// Where to keep the titlebar buttons, if they were moved.
+    titlebar_button_inset: Option<Point>,
+    /// The collection behaviors that `set_always_on_bottom` added.
+    always_on_bottom_behavior: NSUInteger,
+}
+
+impl ViewState {
+    /// Whether keys go through the input method for the focused text field.
+    fn ime_active(&self) -> bool {
+        self.ime_enabled
+            && self
+                .active_text_input
+                .map_or(true, |token| !self.ime_disabled_fields.contains(&token))
+    }
+
+    /// Stops looking for clicks outside the window.
+    unsafe fn remove_dismiss_monitor(&mut self) {
+        if self.dismiss_monitor != nil {
+            let () = msg_send![class!(NSEvent), removeMonitor: self.dismiss_monitor];
+            let () = msg_send![self.dismiss_monitor, release];
+            self.dismiss_monitor = nil;
+        }
+    }
+
+    /// Returns the id for the touch with `identity`, and keeps track of which touches
+    /// are in progress.
+    unsafe fn touch_id(&mut self, identity: id, phase: TouchPhase) -> TouchId {
+        // Touch identities can be copies, so they have to be compared with `isEqual:`.
+        let index = self.touches.iter().position(|(other, _)| {
+            let equal: BOOL = msg_send![identity, isEqual: *other];
+            equal != NO
+        });
+        match (index, phase) {
+            (Some(index), TouchPhase::Ended | TouchPhase::Cancelled) => {
+                let (identity, id) = self.touches.remove(index);
+                let () = msg_send![identity, release];
+                id
+            }
+            (Some(index), _) => self.touches[index].1,
+            (None, TouchPhase::Ended | TouchPhase::Cancelled) => TouchId::next(),
+            (None, _) => {
+                let id = TouchId::next();
+                let identity: id = msg_send![identity, retain];
+                self.touches.push((identity, id));
+                id
+            }
+        }
+    }
+
+    /// Reports the mouse entering or leaving, unless we already reported that.
+    fn set_mouse_inside(&mut self, inside: bool) {
+        if self.mouse_inside != inside {
+            self.mouse_inside = inside;
+            if inside {
+                self.handler.mouse_enter();
+            } else {
+                self.handler.mouse_leave();
+            }
+        }
+    }
 }
 
 #[derive(Clone, PartialEq, Eq)]
@@ -201,7 +323,11 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             transparent: false,
+            background_color: None,
             always_on_top: false,
+            owner: None,
+            modal: false,
+            window_type: WindowType::Normal,
         }
     }
 
@@ -229,6 +355,10 @@ impl WindowBuilder {
         self.transparent = transparent;
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_level(&mut self, level: WindowLevel) {
         self.level = Some(level);
     }
@@ -237,6 +367,18 @@ impl WindowBuilder {
         self.always_on_top = always_on_top;
     }
 
+    pub fn set_parent(&mut self, parent: WindowHandle) {
+        self.owner = Some(parent);
+    }
+
+    pub fn set_modal(&mut self, modal: bool) {
+        self.modal = modal;
+    }
+
+    pub fn set_type(&mut self, window_type: WindowType) {
+        self.window_type = window_type;
+    }
+
     pub fn set_position(&mut self, position: Point) {
         self.position = Some(position)
     }
@@ -267,13 +409,20 @@ impl WindowBuilder {
                 style_mask |= NSWindowStyleMask::NSResizableWindowMask;
             }
 
-            let screen_height = crate::Screen::get_display_rect().height();
+            let max_y = super::screen::max_y();
             let position = self.position.unwrap_or_else(|| Point::new(20., 20.));
-            let origin = NSPoint::new(position.x, screen_height - position.y - self.size.height); // Flip back
+            let origin = NSPoint::new(position.x, max_y - position.y - self.size.height); // Flip back
 
             let rect = NSRect::new(origin, NSSize::new(self.size.width, self.size.height));
 
-            let window: id = msg_send![WINDOW_CLASS.0, alloc];
+            let window: id = match self.window_type {
+                WindowType::Normal => msg_send![WINDOW_CLASS.0, alloc],
+                WindowType::Popup => {
+                    // NSWindowStyleMaskNonactivatingPanel, which cocoa doesn't define.
+                    style_mask = NSWindowStyleMask::from_bits_unchecked(1 << 7);
+                    msg_send![PANEL_CLASS.0, alloc]
+                }
+            };
             let window = window.initWithContentRect_styleMask_backing_defer_(
                 rect,
                 style_mask,
@@ -289,6 +438,8 @@ impl WindowBuilder {
             if self.transparent {
                 window.setOpaque_(NO);
                 window.setBackgroundColor_(NSColor::clearColor(nil));
+            } else if let Some(color) = self.background_color {
+                window.setBackgroundColor_(make_nscolor(color));
             }
 
             window.setTitle_(make_nsstring(&self.title));
@@ -313,7 +464,7 @@ impl WindowBuilder {
             let () = msg_send![window, setDelegate: view];
 
             if let Some(menu) = self.menu {
-                NSApp().setMainMenu_(menu.menu);
+                menu.set_as_main_menu();
             }
 
             content_view.addSubview_(view);
@@ -342,6 +493,9 @@ impl WindowBuilder {
                 handle.set_always_on_top(self.always_on_top);
             }
 
+            view_state.modal = self.modal && self.owner.is_some();
+            view_state.owner = self.owner;
+
             // set_window_state above could have invalidated the frame size
             let frame = NSView::frame(content_view);
 
@@ -392,12 +546,50 @@ static VIEW_CLASS: Lazy<ViewClass> = Lazy::new(|| unsafe {
         }
         YES
     }
+    decl.add_method(
+        sel!(accessibilityChildren),
+        accessibility_children as extern "C" fn(&Object, Sel) -> id,
+    );
+    extern "C" fn accessibility_children(this: &Object, _sel: Sel) -> id {
+        unsafe {
+            let view_state: *mut c_void = *this.get_ivar("viewState");
+            let view_state = &*(view_state as *mut ViewState);
+            view_state.accessibility.children()
+        }
+    }
+    decl.add_method(
+        sel!(accessibilityFocusedUIElement),
+        accessibility_focused_ui_element as extern "C" fn(&Object, Sel) -> id,
+    );
+    extern "C" fn accessibility_focused_ui_element(this: &Object, _sel: Sel) -> id {
+        unsafe {
+            let view_state: *mut c_void = *this.get_ivar("viewState");
+            let view_state = &*(view_state as *mut ViewState);
+            view_state
+                .accessibility
+                .focus()
+                .unwrap_or(this as *const Object as id)
+        }
+    }
     decl.add_method(sel!(dealloc), dealloc as extern "C" fn(&Object, Sel));
     extern "C" fn dealloc(this: &Object, _sel: Sel) {
         info!("view is dealloc'ed");
         unsafe {
             let view_state: *mut c_void = *this.get_ivar("viewState");
-            drop(Box::from_raw(view_state as *mut ViewState));
+            let mut view_state = Box::from_raw(view_state as *mut ViewState);
+            view_state.remove_dismiss_monitor();
+            // Don't leave the cursor hidden or stuck once the window is gone. NSCursor counts
+            // the hides, so every one of them needs an unhide.
+            for _ in 0..view_state.cursor_hide_count {
+                let () = msg_send![class!(NSCursor), unhide];
+            }
+            if view_state.cursor_confined {
+                let _ = CGDisplay::associate_mouse_and_mouse_cursor_position(true);
+            }
+            for (identity, _) in &view_state.touches {
+                let () = msg_send![*identity, release];
+            }
+            drop(view_state);
         }
     }
 
@@ -409,6 +601,34 @@ static VIEW_CLASS: Lazy<ViewClass> = Lazy::new(|| unsafe {
         sel!(windowDidResignKey:),
         window_did_resign_key as extern "C" fn(&mut Object, Sel, id),
     );
+    decl.add_method(
+        sel!(windowDidChangeScreen:),
+        window_did_change_screen as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(windowDidResize:),
+        window_did_resize as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(windowDidMove:),
+        window_did_move as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(reportWindowMove:),
+        report_window_move as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(windowDidExitFullScreen:),
+        window_did_resize as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(windowDidChangeOcclusionState:),
+        window_did_change_occlusion_state as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(applicationDidResignActive:),
+        application_did_resign_active as extern "C" fn(&mut Object, Sel, id),
+    );
     decl.add_method(
         sel!(setFrameSize:),
         set_frame_size as extern "C" fn(&mut Object, Sel, NSSize),
@@ -425,6 +645,10 @@ static VIEW_CLASS: Lazy<ViewClass> = Lazy::new(|| unsafe {
         sel!(otherMouseDown:),
         mouse_down_other as extern "C" fn(&mut Object, Sel, id),
     );
+    decl.add_method(
+        sel!(tabletProximity:),
+        tablet_proximity as extern "C" fn(&mut Object, Sel, id),
+    );
     decl.add_method(
         sel!(mouseUp:),
         mouse_up_left as extern "C" fn(&mut Object, Sel, id),
@@ -465,6 +689,22 @@ static VIEW_CLASS: Lazy<ViewClass> = Lazy::new(|| unsafe {
         sel!(magnifyWithEvent:),
         pinch_event as extern "C" fn(&mut Object, Sel, id),
     );
+    decl.add_method(
+        sel!(touchesBeganWithEvent:),
+        touches_began as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(touchesMovedWithEvent:),
+        touches_moved as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(touchesEndedWithEvent:),
+        touches_ended as extern "C" fn(&mut Object, Sel, id),
+    );
+    decl.add_method(
+        sel!(touchesCancelledWithEvent:),
+        touches_cancelled as extern "C" fn(&mut Object, Sel, id),
+    );
     decl.add_method(
         sel!(keyDown:),
         key_down as extern "C" fn(&mut Object, Sel, id),
@@ -492,6 +732,18 @@ static VIEW_CLASS: Lazy<ViewClass> = Lazy::new(|| unsafe {
         sel!(handleMenuItem:),
         handle_menu_item as extern "C" fn(&mut Object, Sel, id),
     );
+    decl.add_method(
+        sel!(updateMenuItem:),
+        update_menu_item as extern "C" fn(&mut Object, Sel, id),
+    );
+    for command in StandardCommand::ALL {
+        if let Some(action) = super::menu::standard_action(command) {
+            decl.add_method(
+                action,
+                standard_action as extern "C" fn(&mut Object, Sel, id),
+            );
+        }
+    }
     decl.add_method(
         sel!(showContextMenu:),
         show_context_menu as extern "C" fn(&mut Object, Sel, id),
@@ -550,6 +802,10 @@ static VIEW_CLASS: Lazy<ViewClass> = Lazy::new(|| unsafe {
         super::text_input::first_rect_for_character_range
             as extern "C" fn(&mut Object, Sel, NSRange, *mut c_void) -> NSRect,
     );
+    decl.add_method(
+        sel!(inputContext),
+        input_context as extern "C" fn(&mut Object, Sel) -> id,
+    );
     decl.add_method(
         sel!(doCommandBySelector:),
         super::text_input::do_command_by_selector as extern "C" fn(&mut Object, Sel, Sel),
@@ -592,16 +848,43 @@ fn make_view(handler: Box<dyn WinHandler>) -> (id, Weak<Mutex<Vec<IdleKind>>>) {
             idle_queue,
             focus_click: false,
             mouse_left: true,
+            mouse_inside: false,
+            tablet_pointer: PointerType::Pen,
+            touches: Vec::new(),
             keyboard_state,
             text: PietText::new_with_unique_state(),
             active_text_input: None,
+            ime_enabled: true,
+            ime_disabled_fields: HashSet::new(),
             parent: None,
+            owner: None,
+            modal: false,
+            cursor_hide_count: 0,
+            cursor_confined: false,
+            cursor: CursorStack::default(),
+            anim_clock: AnimationClock::default(),
+            accessibility: AccessibilityState::new(),
+            effect_view: nil,
+            dismiss_on_focus_loss: false,
+            dismiss_monitor: nil,
+            titlebar_button_inset: None,
+            always_on_bottom_behavior: 0,
         };
         let state_ptr = Box::into_raw(Box::new(state));
         (*view).set_ivar("viewState", state_ptr as *mut c_void);
         let options: NSAutoresizingMaskOptions = NSViewWidthSizable | NSViewHeightSizable;
         view.setAutoresizingMask_(options);
 
+        // We only get indirect touches, from the trackpad.
+        let has_touch_types: BOOL =
+            msg_send![view, respondsToSelector: sel!(setAllowedTouchTypes:)];
+        if has_touch_types != NO {
+            // NSTouchTypeMaskIndirect
+            let () = msg_send![view, setAllowedTouchTypes: (1 << 1) as NSUInteger];
+        } else {
+            let () = msg_send![view, setAcceptsTouchEvents: YES];
+        }
+
         (view.autorelease(), queue_handle)
     }
 }
@@ -622,6 +905,23 @@ static WINDOW_CLASS: Lazy<WindowClass> = Lazy::new(|| unsafe {
     WindowClass(decl.register())
 });
 
+/// The class of popup windows, which never become key so that their owner stays active.
+static PANEL_CLASS: Lazy<WindowClass> = Lazy::new(|| unsafe {
+    let mut decl = ClassDecl::new("DruidPanel", class!(NSPanel)).expect("Panel class defined");
+    decl.add_method(
+        sel!(canBecomeKeyWindow),
+        canBecomeKeyWindow as extern "C" fn(&Object, Sel) -> BOOL,
+    );
+    decl.add_method(
+        sel!(canBecomeMainWindow),
+        canBecomeKeyWindow as extern "C" fn(&Object, Sel) -> BOOL,
+    );
+    extern "C" fn canBecomeKeyWindow(_this: &Object, _sel: Sel) -> BOOL {
+        NO
+    }
+    WindowClass(decl.register())
+});
+
 extern "C" fn set_frame_size(this: &mut Object, _: Sel, size: NSSize) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
@@ -635,6 +935,7 @@ extern "C" fn set_frame_size(this: &mut Object, _: Sel, size: NSSize) {
 fn mouse_event(
     nsevent: id,
     view: id,
+    tablet_pointer: PointerType,
     count: u8,
     focus: bool,
     button: MouseButton,
@@ -646,6 +947,18 @@ fn mouse_event(
         let pos = Point::new(view_point.x, view_point.y);
         let buttons = get_mouse_buttons(NSEvent::pressedMouseButtons(nsevent));
         let modifiers = make_modifiers(nsevent.modifierFlags());
+        let (pointer_type, pressure, tilt_x, tilt_y) = if is_tablet_event(nsevent) {
+            let pressure: f32 = msg_send![nsevent, pressure];
+            let tilt: NSPoint = msg_send![nsevent, tilt];
+            (
+                tablet_pointer,
+                pressure as f64,
+                tilt.x * 90.0,
+                tilt.y * 90.0,
+            )
+        } else {
+            (PointerType::Mouse, buttons.default_pressure(), 0.0, 0.0)
+        };
         MouseEvent {
             pos,
             buttons,
@@ -654,10 +967,48 @@ fn mouse_event(
             focus,
             button,
             wheel_delta,
+            pointer_type,
+            pressure,
+            tilt_x,
+            tilt_y,
         }
     }
 }
 
+/// Returns `true` if `nsevent` is a mouse event that carries tablet data.
+unsafe fn is_tablet_event(nsevent: id) -> bool {
+    match nsevent.eventType() as NSUInteger {
+        // NSEventTypeTabletPoint
+        23 => true,
+        // The mouse down, up, moved and dragged events. Asking other events for
+        // their subtype throws an exception.
+        1..=7 | 25..=27 => {
+            let subtype: i16 = msg_send![nsevent, subtype];
+            // NSEventSubtypeTabletPoint
+            subtype == 1
+        }
+        _ => false,
+    }
+}
+
+extern "C" fn tablet_proximity(this: &mut Object, _: Sel, nsevent: id) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        let entering: BOOL = msg_send![nsevent, isEnteringProximity];
+        if entering == NO {
+            return;
+        }
+        let device_type: NSUInteger = msg_send![nsevent, pointingDeviceType];
+        // NSPointingDeviceTypeEraser
+        view_state.tablet_pointer = if device_type == 3 {
+            PointerType::Eraser
+        } else {
+            PointerType::Pen
+        };
+    }
+}
+
 fn get_mouse_button(button: NSInteger) -> Option<MouseButton> {
     match button {
         0 => Some(MouseButton::Left),
@@ -669,7 +1020,7 @@ fn get_mouse_button(button: NSInteger) -> Option<MouseButton> {
     }
 }
 
-fn get_mouse_buttons(mask: NSUInteger) -> MouseButtons {
+pub(crate) fn get_mouse_buttons(mask: NSUInteger) -> MouseButtons {
     let mut buttons = MouseButtons::new();
     if mask & 1 != 0 {
         buttons.insert(MouseButton::Left);
@@ -711,7 +1062,15 @@ fn mouse_down(this: &mut Object, nsevent: id, button: MouseButton) {
         let view_state = &mut *(view_state as *mut ViewState);
         let count = nsevent.clickCount() as u8;
         let focus = view_state.focus_click && button == MouseButton::Left;
-        let event = mouse_event(nsevent, this as id, count, focus, button, Vec2::ZERO);
+        let event = mouse_event(
+            nsevent,
+            this as id,
+            view_state.tablet_pointer,
+            count,
+            focus,
+            button,
+            Vec2::ZERO,
+        );
         view_state.handler.mouse_down(&event);
     }
 }
@@ -742,7 +1101,15 @@ fn mouse_up(this: &mut Object, nsevent: id, button: MouseButton) {
         } else {
             false
         };
-        let event = mouse_event(nsevent, this as id, 0, focus, button, Vec2::ZERO);
+        let event = mouse_event(
+            nsevent,
+            this as id,
+            view_state.tablet_pointer,
+            0,
+            focus,
+            button,
+            Vec2::ZERO,
+        );
         view_state.handler.mouse_up(&event);
         // If we have already received a mouseExited event then that means
         // we're still receiving mouse events because some buttons are being held down.
@@ -750,7 +1117,7 @@ fn mouse_up(this: &mut Object, nsevent: id, button: MouseButton) {
         // then we will no longer receive mouse events until the next mouseEntered event
         // and need to inform the handler of the mouse leaving.
         if view_state.mouse_left && event.buttons.is_empty() {
-            view_state.handler.mouse_leave();
+            view_state.set_mouse_inside(false);
         }
     }
 }
@@ -759,7 +1126,15 @@ extern "C" fn mouse_move(this: &mut Object, _: Sel, nsevent: id) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
-        let event = mouse_event(nsevent, this as id, 0, false, MouseButton::None, Vec2::ZERO);
+        let event = mouse_event(
+            nsevent,
+            this as id,
+            view_state.tablet_pointer,
+            0,
+            false,
+            MouseButton::None,
+            Vec2::ZERO,
+        );
         view_state.handler.mouse_move(&event);
     }
 }
@@ -769,7 +1144,16 @@ extern "C" fn mouse_enter(this: &mut Object, _sel: Sel, nsevent: id) {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
         view_state.mouse_left = false;
-        let event = mouse_event(nsevent, this, 0, false, MouseButton::None, Vec2::ZERO);
+        view_state.set_mouse_inside(true);
+        let event = mouse_event(
+            nsevent,
+            this,
+            view_state.tablet_pointer,
+            0,
+            false,
+            MouseButton::None,
+            Vec2::ZERO,
+        );
         view_state.handler.mouse_move(&event);
     }
 }
@@ -779,7 +1163,12 @@ extern "C" fn mouse_leave(this: &mut Object, _: Sel, _nsevent: id) {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
         view_state.mouse_left = true;
-        view_state.handler.mouse_leave();
+        // While buttons are held we keep receiving mouse events, and report
+        // the leave once they are released, in `mouse_up`.
+        let buttons: NSUInteger = msg_send![class!(NSEvent), pressedMouseButtons];
+        if buttons == 0 {
+            view_state.set_mouse_inside(false);
+        }
     }
 }
 
@@ -787,35 +1176,90 @@ extern "C" fn scroll_wheel(this: &mut Object, _: Sel, nsevent: id) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
+        let precise = nsevent.hasPreciseScrollingDeltas() == cocoa::base::YES;
         let (dx, dy) = {
             let dx = -nsevent.scrollingDeltaX();
             let dy = -nsevent.scrollingDeltaY();
-            if nsevent.hasPreciseScrollingDeltas() == cocoa::base::YES {
+            if precise {
                 (dx, dy)
             } else {
                 (dx * 32.0, dy * 32.0)
             }
         };
 
-        let event = mouse_event(
+        let mut event = mouse_event(
             nsevent,
             this as id,
+            view_state.tablet_pointer,
             0,
             false,
             MouseButton::None,
             Vec2::new(dx, dy),
         );
+        // Wheels scroll by lines, while trackpads scroll by points.
+        if precise && event.pointer_type == PointerType::Mouse {
+            event.pointer_type = PointerType::Touchpad;
+        }
         view_state.handler.wheel(&event);
     }
 }
 
+extern "C" fn touches_began(this: &mut Object, _: Sel, nsevent: id) {
+    touches(this, nsevent, TouchPhase::Began);
+}
+
+extern "C" fn touches_moved(this: &mut Object, _: Sel, nsevent: id) {
+    touches(this, nsevent, TouchPhase::Moved);
+}
+
+extern "C" fn touches_ended(this: &mut Object, _: Sel, nsevent: id) {
+    touches(this, nsevent, TouchPhase::Ended);
+}
+
+extern "C" fn touches_cancelled(this: &mut Object, _: Sel, nsevent: id) {
+    touches(this, nsevent, TouchPhase::Cancelled);
+}
+
+/// Reports the touches of `nsevent` that are in `phase`.
+fn touches(this: &mut Object, nsevent: id, phase: TouchPhase) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        let phase_mask: NSUInteger = match phase {
+            TouchPhase::Began => 1 << 0,
+            TouchPhase::Moved => 1 << 1,
+            TouchPhase::Ended => 1 << 3,
+            TouchPhase::Cancelled => 1 << 4,
+        };
+        let view = this as *mut Object;
+        let touches: id = msg_send![nsevent, touchesMatchingPhase: phase_mask inView: view];
+        let touches: id = msg_send![touches, allObjects];
+        let bounds: NSRect = msg_send![view, bounds];
+        for i in 0..touches.count() {
+            let touch = touches.objectAtIndex(i);
+            let identity: id = msg_send![touch, identity];
+            let id = view_state.touch_id(identity, phase);
+            // Trackpad positions go from 0.0 to 1.0, starting at the bottom left.
+            let position: NSPoint = msg_send![touch, normalizedPosition];
+            let pos = Point::new(
+                position.x * bounds.size.width,
+                (1.0 - position.y) * bounds.size.height,
+            );
+            view_state.handler.touch(&TouchEvent { id, pos, phase });
+        }
+    }
+}
+
 extern "C" fn pinch_event(this: &mut Object, _: Sel, nsevent: id) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
 
         let delta: CGFloat = msg_send![nsevent, magnification];
-        view_state.handler.zoom(delta);
+        let point = nsevent.locationInWindow();
+        let view_point = (this as id).convertPoint_fromView_(point, nil);
+        let pos = Point::new(view_point.x, view_point.y);
+        view_state.handler.zoom_at(delta, pos);
     }
 }
 
@@ -835,6 +1279,19 @@ extern "C" fn key_down(this: &mut Object, _: Sel, nsevent: id) {
     }
 }
 
+/// Returns no input context while the input method is off, so that keys aren't composed.
+extern "C" fn input_context(this: &mut Object, _: Sel) -> id {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &*(view_state as *mut ViewState);
+        if !view_state.ime_active() {
+            return nil;
+        }
+        let superclass = msg_send![this, superclass];
+        msg_send![super(this, superclass), inputContext]
+    }
+}
+
 extern "C" fn key_up(this: &mut Object, _: Sel, nsevent: id) {
     let view_state = unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
@@ -863,6 +1320,9 @@ extern "C" fn view_will_draw(this: &mut Object, _: Sel) {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
+        if let Some(interval) = view_state.anim_clock.frame(Instant::now()) {
+            view_state.handler.anim_frame(interval);
+        }
         view_state.handler.prepare_paint();
     }
 }
@@ -876,13 +1336,19 @@ extern "C" fn draw_rect(this: &mut Object, _: Sel, dirtyRect: NSRect) {
             msg_send![context, CGContext];
         let cgcontext_ref = CGContextRef::from_ptr_mut(cgcontext_ptr);
 
-        // FIXME: use the actual invalid region instead of just this bounding box.
-        // https://developer.apple.com/documentation/appkit/nsview/1483772-getrectsbeingdrawn?language=objc
-        let rect = Rect::from_origin_size(
-            (dirtyRect.origin.x, dirtyRect.origin.y),
-            (dirtyRect.size.width, dirtyRect.size.height),
-        );
-        let invalid = Region::from(rect);
+        // `dirtyRect` is only the bounding box of the rects passed to `setNeedsDisplayInRect:`.
+        let mut rects: *const NSRect = std::ptr::null();
+        let mut count: NSInteger = 0;
+        let () = msg_send![this, getRectsBeingDrawn: &mut rects count: &mut count];
+        let mut invalid = Region::EMPTY;
+        if !rects.is_null() {
+            for rect in std::slice::from_raw_parts(rects, count as usize) {
+                invalid.add_rect(from_nsrect(*rect));
+            }
+        }
+        if invalid.is_empty() {
+            invalid.add_rect(from_nsrect(dirtyRect));
+        }
 
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
@@ -898,10 +1364,56 @@ extern "C" fn draw_rect(this: &mut Object, _: Sel, dirtyRect: NSRect) {
     }
 }
 
+/// Converts a rect in the coordinates of our view, which is flipped so that y points down.
+fn from_nsrect(rect: NSRect) -> Rect {
+    Rect::from_origin_size(
+        (rect.origin.x, rect.origin.y),
+        (rect.size.width, rect.size.height),
+    )
+}
+
+/// Makes an `NSColor` in the sRGB color space, which is what our colors are in.
+fn make_nscolor(color: Color) -> id {
+    let (r, g, b, a) = color.as_rgba();
+    unsafe { msg_send![class!(NSColor), colorWithSRGBRed: r green: g blue: b alpha: a] }
+}
+
 fn run_deferred(this: &mut Object, view_state: &mut ViewState, op: DeferredOp) {
     match op {
         DeferredOp::SetSize(size) => set_size_deferred(this, view_state, size),
         DeferredOp::SetPosition(pos) => set_position_deferred(this, view_state, pos),
+        DeferredOp::SetImeEnabled(enabled) => {
+            update_ime(this, view_state, |state| state.ime_enabled = enabled)
+        }
+        DeferredOp::SetTextFieldImeEnabled(token, enabled) => {
+            update_ime(this, view_state, |state| {
+                if enabled {
+                    state.ime_disabled_fields.remove(&token);
+                } else {
+                    state.ime_disabled_fields.insert(token);
+                }
+            })
+        }
+    }
+}
+
+/// Changes whether the input method is on, discarding any composition if it is turned off.
+///
+/// This calls the handler, so it is deferred rather than run from inside a handler callback.
+fn update_ime(this: &mut Object, view_state: &mut ViewState, f: impl FnOnce(&mut ViewState)) {
+    unsafe {
+        // Once the input method is off we no longer return the input context.
+        let was_active = view_state.ime_active();
+        let input_context: id = msg_send![this, inputContext];
+        f(view_state);
+        if was_active && !view_state.ime_active() {
+            let _: () = msg_send![input_context, discardMarkedText];
+            if let Some(token) = view_state.active_text_input {
+                let mut edit_lock = view_state.handler.acquire_input_lock(token, true);
+                edit_lock.set_composition_range(None);
+                view_state.handler.release_input_lock(token);
+            }
+        }
     }
 }
 
@@ -926,14 +1438,8 @@ fn set_position_deferred(this: &mut Object, _view_state: &mut ViewState, positio
 
         let mut new_frame = frame;
         new_frame.origin.x = position.x;
-        // TODO Everywhere we use the height for flipping around y it should be the max y in orig mac coords.
-        // Need to set up a 3 screen config to test in this arrangement.
-        // 3
-        // 1
-        // 2
-
-        let screen_height = crate::Screen::get_display_rect().height();
-        new_frame.origin.y = screen_height - position.y - frame.size.height; // Flip back
+        let max_y = super::screen::max_y();
+        new_frame.origin.y = max_y - position.y - frame.size.height; // Flip back
         let () = msg_send![window, setFrame: new_frame display: YES];
         debug!("set_position_deferred {:?}", position);
     }
@@ -984,13 +1490,37 @@ extern "C" fn handle_menu_item(this: &mut Object, _: Sel, item: id) {
     }
 }
 
-extern "C" fn show_context_menu(this: &mut Object, _: Sel, item: id) {
+extern "C" fn update_menu_item(this: &mut Object, _: Sel, item: id) {
     unsafe {
-        let window: id = msg_send![this as *const _, window];
-        let mut location: NSPoint = msg_send![window, mouseLocationOutsideOfEventStream];
-        let bounds: NSRect = msg_send![this as *const _, bounds];
-        location.y = bounds.size.height - location.y;
-        let _: BOOL = msg_send![item, popUpMenuPositioningItem: nil atLocation: location inView: this as *const _];
+        let tag: isize = msg_send![item, tag];
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        if let Some(enabled) = view_state.handler.menu_item_enabled(tag as u32) {
+            let () = msg_send![item, setEnabled: enabled as BOOL];
+        }
+    }
+}
+
+/// Delivers a standard action, such as `copy:`, as the command of its standard id.
+extern "C" fn standard_action(this: &mut Object, action: Sel, _sender: id) {
+    if let Some(command) = super::menu::standard_command_for_action(action) {
+        unsafe {
+            let view_state: *mut c_void = *this.get_ivar("viewState");
+            let view_state = &mut *(view_state as *mut ViewState);
+            view_state.handler.command(command.id());
+        }
+    }
+}
+
+/// Pops up a menu, given as an array of the `NSMenu` and an `NSValue` with the location.
+extern "C" fn show_context_menu(this: &mut Object, _: Sel, args: id) {
+    unsafe {
+        let menu = args.objectAtIndex(0);
+        let location: NSPoint = msg_send![args.objectAtIndex(1), pointValue];
+        // AppKit moves the menu to keep it on screen.
+        let _: BOOL = msg_send![menu, popUpMenuPositioningItem: nil
+                                                  atLocation: location
+                                                      inView: this as *const _];
     }
 }
 
@@ -1007,26 +1537,186 @@ extern "C" fn window_did_resign_key(this: &mut Object, _: Sel, _notification: id
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
         view_state.handler.lost_focus();
+        dismiss(this);
+    }
+}
+
+/// AppKit lays out the titlebar again when the window changes size, which moves the
+/// buttons back to their default position.
+extern "C" fn window_did_resize(this: &mut Object, _: Sel, notification: id) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        if let Some(inset) = view_state.titlebar_button_inset {
+            let window: id = msg_send![notification, object];
+            layout_titlebar_buttons(window, inset);
+        }
+    }
+}
+
+/// Moves the standard window buttons of `window`, see
+/// [`WindowHandleExt::set_titlebar_button_inset`](crate::platform::mac::WindowHandleExt::set_titlebar_button_inset).
+unsafe fn layout_titlebar_buttons(window: id, inset: Point) {
+    // NSWindowCloseButton, NSWindowMiniaturizeButton and NSWindowZoomButton
+    let buttons: Vec<id> = (0..3)
+        .map(|kind: NSUInteger| msg_send![window, standardWindowButton: kind])
+        .collect();
+    if buttons.iter().any(|&button| button == nil) {
+        return;
+    }
+    // The buttons are in the titlebar view, which fills the titlebar container.
+    let titlebar_view: id = msg_send![buttons[0], superview];
+    let container: id = msg_send![titlebar_view, superview];
+    let close_frame: NSRect = msg_send![buttons[0], frame];
+    let miniaturize_frame: NSRect = msg_send![buttons[1], frame];
+    let spacing = miniaturize_frame.origin.x - close_frame.origin.x;
+
+    let window_frame: NSRect = msg_send![window, frame];
+    let mut container_frame: NSRect = msg_send![container, frame];
+    container_frame.size.height = close_frame.size.height + inset.y;
+    container_frame.origin.y = window_frame.size.height - container_frame.size.height;
+    let () = msg_send![container, setFrame: container_frame];
+
+    for (i, &button) in buttons.iter().enumerate() {
+        let frame: NSRect = msg_send![button, frame];
+        let origin = NSPoint::new(inset.x + spacing * i as f64, frame.origin.y);
+        let () = msg_send![button, setFrameOrigin: origin];
+    }
+}
+
+extern "C" fn window_did_change_screen(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        view_state.handler.monitor_changed();
+    }
+}
+
+extern "C" fn window_did_move(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        // Delayed performs only run in the default run loop mode, so while the user drags
+        // the window this waits for the drag to end, and the moves in between are dropped.
+        let target = this as *mut Object;
+        let () = msg_send![class!(NSObject), cancelPreviousPerformRequestsWithTarget: target
+            selector: sel!(reportWindowMove:) object: nil];
+        let () = msg_send![this, performSelector: sel!(reportWindowMove:) withObject: nil
+            afterDelay: 0.0];
     }
 }
 
+extern "C" fn report_window_move(this: &mut Object, _: Sel, _: id) {
+    unsafe {
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let handle = {
+            let view_state = &*(view_state as *const ViewState);
+            WindowHandle {
+                nsview: view_state.nsview.clone(),
+                idle_queue: Arc::downgrade(&view_state.idle_queue),
+            }
+        };
+        let window: id = msg_send![this as *mut Object, window];
+        if window == nil {
+            return;
+        }
+        let frame: NSRect = msg_send![window, frame];
+        let max_y = super::screen::max_y();
+        let frame = Rect::from_origin_size(
+            (frame.origin.x, max_y - frame.origin.y - frame.size.height),
+            (frame.size.width, frame.size.height),
+        );
+        let position = handle.get_position();
+        let view_state = &mut *(view_state as *mut ViewState);
+        view_state
+            .handler
+            .window_moved(position, Screen::monitor_for_rect(frame));
+    }
+}
+
+extern "C" fn window_did_change_occlusion_state(this: &mut Object, _: Sel, notification: id) {
+    unsafe {
+        let window: id = msg_send![notification, object];
+        let occlusion_state: NSUInteger = msg_send![window, occlusionState];
+        let visible = occlusion_state & NSWindowOcclusionStateVisible != 0;
+        let view_state: *mut c_void = *this.get_ivar("viewState");
+        let view_state = &mut *(view_state as *mut ViewState);
+        view_state.handler.occlusion_changed(visible);
+    }
+}
+
+extern "C" fn application_did_resign_active(this: &mut Object, _: Sel, _notification: id) {
+    unsafe {
+        dismiss(this);
+    }
+}
+
+/// Closes the window of `view` if it should be dismissed when it loses focus.
+unsafe fn dismiss(view: id) {
+    let view_state: *mut c_void = *(*view).get_ivar("viewState");
+    let view_state = &mut *(view_state as *mut ViewState);
+    if !view_state.dismiss_on_focus_loss {
+        return;
+    }
+    view_state.dismiss_on_focus_loss = false;
+    view_state.remove_dismiss_monitor();
+    view_state.handler.dismissed();
+    let window: id = msg_send![view, window];
+    let () = msg_send![window, performSelectorOnMainThread: sel!(close) withObject: nil waitUntilDone: NO];
+}
+
 extern "C" fn window_should_close(this: &mut Object, _: Sel, _window: id) -> BOOL {
     unsafe {
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
-        view_state.handler.request_close();
-        NO
+        if view_state.handler.should_close() {
+            YES
+        } else {
+            NO
+        }
     }
 }
 
 extern "C" fn window_will_close(this: &mut Object, _: Sel, _notification: id) {
     unsafe {
+        // Give input back to the owner, however we were closed.
+        let window: id = msg_send![this, window];
+        let sheet_parent: id = msg_send![window, sheetParent];
+        if sheet_parent != nil {
+            let () = msg_send![sheet_parent, endSheet: window];
+        }
+        let parent_window: id = msg_send![window, parentWindow];
+        if parent_window != nil {
+            let () = msg_send![parent_window, removeChildWindow: window];
+        }
+
         let view_state: *mut c_void = *this.get_ivar("viewState");
         let view_state = &mut *(view_state as *mut ViewState);
         view_state.handler.destroy();
     }
 }
 
+impl Cursor {
+    fn ns_cursor(&self) -> id {
+        unsafe {
+            let nscursor = class!(NSCursor);
+            #[allow(deprecated)]
+            match self {
+                Cursor::Arrow => msg_send![nscursor, arrowCursor],
+                Cursor::IBeam => msg_send![nscursor, IBeamCursor],
+                Cursor::Pointer => msg_send![nscursor, pointingHandCursor],
+                Cursor::Crosshair => msg_send![nscursor, crosshairCursor],
+                Cursor::OpenHand => msg_send![nscursor, openHandCursor],
+                Cursor::NotAllowed => msg_send![nscursor, operationNotAllowedCursor],
+                Cursor::ResizeLeftRight => msg_send![nscursor, resizeLeftRightCursor],
+                Cursor::ResizeUpDown => msg_send![nscursor, resizeUpDownCursor],
+                // There is no public busy cursor; the spinning one is shown by the system.
+                Cursor::Wait | Cursor::Progress => msg_send![nscursor, arrowCursor],
+                // TODO: support custom cursors
+                Cursor::Custom(_) => msg_send![nscursor, arrowCursor],
+            }
+        }
+    }
+}
+
 impl WindowHandle {
     pub fn show(&self) {
         unsafe {
@@ -1038,7 +1728,34 @@ impl WindowHandle {
                 .autorelease();
             let notif_center: id = msg_send![notif_center_class, defaultCenter];
             let () = msg_send![notif_center, addObserver:*self.nsview.load() selector: sel!(windowDidBecomeKey:) name: notif_string object: window];
-            window.makeKeyAndOrderFront_(nil)
+
+            let (owner_window, modal): (id, bool) = match self.nsview.load().as_ref() {
+                Some(view) => {
+                    let state: *mut c_void = *view.get_ivar("viewState");
+                    let state = &*(state as *const ViewState);
+                    match &state.owner {
+                        Some(owner) => (msg_send![*owner.nsview.load(), window], state.modal),
+                        None => (nil, false),
+                    }
+                }
+                None => (nil, false),
+            };
+            if owner_window == nil {
+                window.makeKeyAndOrderFront_(nil);
+            } else if modal {
+                // A sheet blocks input to its parent, and moves along with it.
+                let sheet_parent: id = msg_send![window, sheetParent];
+                if sheet_parent == nil {
+                    let () = msg_send![owner_window, beginSheet: window completionHandler: nil];
+                }
+            } else {
+                window.makeKeyAndOrderFront_(nil);
+                let parent_window: id = msg_send![window, parentWindow];
+                if parent_window == nil {
+                    // NSWindowAbove
+                    let () = msg_send![owner_window, addChildWindow: window ordered: 1isize];
+                }
+            }
         }
     }
 
@@ -1068,6 +1785,11 @@ impl WindowHandle {
 
     pub fn request_anim_frame(&self) {
         unsafe {
+            if let Some(view) = self.nsview.load().as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                state.anim_clock.request();
+            }
             // TODO: synchronize with screen refresh rate using CVDisplayLink instead.
             let () = msg_send![*self.nsview.load(), performSelectorOnMainThread: sel!(redraw)
                 withObject: nil waitUntilDone: NO];
@@ -1082,6 +1804,12 @@ impl WindowHandle {
         }
     }
 
+    pub fn paint_now(&self) {
+        unsafe {
+            let () = msg_send![*self.nsview.load(), displayIfNeeded];
+        }
+    }
+
     /// Request invalidation of one rectangle.
     pub fn invalidate_rect(&self, rect: Rect) {
         let rect = NSRect::new(
@@ -1096,21 +1824,37 @@ impl WindowHandle {
 
     pub fn set_cursor(&mut self, cursor: &Cursor) {
         unsafe {
-            let nscursor = class!(NSCursor);
-            #[allow(deprecated)]
-            let cursor: id = match cursor {
-                Cursor::Arrow => msg_send![nscursor, arrowCursor],
-                Cursor::IBeam => msg_send![nscursor, IBeamCursor],
-                Cursor::Pointer => msg_send![nscursor, pointingHandCursor],
-                Cursor::Crosshair => msg_send![nscursor, crosshairCursor],
-                Cursor::OpenHand => msg_send![nscursor, openHandCursor],
-                Cursor::NotAllowed => msg_send![nscursor, operationNotAllowedCursor],
-                Cursor::ResizeLeftRight => msg_send![nscursor, resizeLeftRightCursor],
-                Cursor::ResizeUpDown => msg_send![nscursor, resizeUpDownCursor],
-                // TODO: support custom cursors
-                Cursor::Custom(_) => msg_send![nscursor, arrowCursor],
-            };
-            let () = msg_send![cursor, set];
+            if let Some(view) = self.nsview.load().as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                if state.cursor.set(cursor) {
+                    let () = msg_send![cursor.ns_cursor(), set];
+                }
+            }
+        }
+    }
+
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        unsafe {
+            if let Some(view) = self.nsview.load().as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                let () = msg_send![cursor.ns_cursor(), set];
+                state.cursor.push(cursor);
+            }
+        }
+    }
+
+    pub fn pop_cursor(&mut self) {
+        unsafe {
+            if let Some(view) = self.nsview.load().as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                if state.cursor.pop() {
+                    let cursor = state.cursor.current().unwrap_or(&Cursor::Arrow);
+                    let () = msg_send![cursor.ns_cursor(), set];
+                }
+            }
         }
     }
 
@@ -1119,6 +1863,190 @@ impl WindowHandle {
         None
     }
 
+    pub fn set_cursor_visible(&self, visible: bool) {
+        unsafe {
+            if let Some(view) = self.nsview.load().as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                // NSCursor balances hide and unhide itself, but we still need to keep count
+                // so that we can restore the cursor when the window goes away.
+                if visible {
+                    if state.cursor_hide_count == 0 {
+                        return;
+                    }
+                    state.cursor_hide_count -= 1;
+                    let () = msg_send![class!(NSCursor), unhide];
+                } else {
+                    state.cursor_hide_count += 1;
+                    let () = msg_send![class!(NSCursor), hide];
+                }
+            }
+        }
+    }
+
+    pub fn confine_cursor(&self, confine: bool) {
+        unsafe {
+            if let Some(view) = self.nsview.load().as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                if state.cursor_confined == confine {
+                    return;
+                }
+                state.cursor_confined = confine;
+                if confine {
+                    // macOS can't clip the cursor to a rect, so we move it to the middle of
+                    // the window and detach it from the mouse. Mouse deltas are still reported.
+                    let window: id = msg_send![view, window];
+                    let frame: NSRect = msg_send![window, frame];
+                    let screens: id = msg_send![class!(NSScreen), screens];
+                    let primary: id = msg_send![screens, objectAtIndex: 0];
+                    let primary_frame: NSRect = msg_send![primary, frame];
+                    // Quartz coordinates have their origin at the top left of the primary screen.
+                    let center = CGPoint::new(
+                        frame.origin.x + frame.size.width / 2.0,
+                        primary_frame.size.height - (frame.origin.y + frame.size.height / 2.0),
+                    );
+                    if let Err(err) = CGDisplay::warp_mouse_cursor_position(center) {
+                        tracing::warn!("failed to warp cursor: {}", err);
+                    }
+                }
+                if let Err(err) = CGDisplay::associate_mouse_and_mouse_cursor_position(!confine) {
+                    tracing::warn!("failed to confine cursor: {}", err);
+                }
+            }
+        }
+    }
+
+    pub fn announce(&self, message: &str, politeness: Politeness) {
+        unsafe {
+            if let Some(view) = self.nsview.load().as_ref() {
+                let window: id = msg_send![view, window];
+                let priority = match politeness {
+                    Politeness::Polite => NSAccessibilityPriorityMedium,
+                    Politeness::Assertive => NSAccessibilityPriorityHigh,
+                };
+                let priority: id = msg_send![class!(NSNumber), numberWithInteger: priority];
+                let keys = NSArray::arrayWithObjects(
+                    nil,
+                    &[NSAccessibilityAnnouncementKey, NSAccessibilityPriorityKey],
+                );
+                let values = NSArray::arrayWithObjects(nil, &[make_nsstring(message), priority]);
+                let user_info: id =
+                    msg_send![class!(NSDictionary), dictionaryWithObjects: values forKeys: keys];
+                NSAccessibilityPostNotificationWithUserInfo(
+                    window,
+                    NSAccessibilityAnnouncementRequestedNotification,
+                    user_info,
+                );
+            }
+        }
+    }
+
+    pub fn set_accessibility_tree(&self, tree: AccessibilityTree) {
+        unsafe {
+            let nsview = self.nsview.load();
+            if let Some(view) = nsview.as_ref() {
+                let state: *mut c_void = *view.get_ivar("viewState");
+                let state = &mut (*(state as *mut ViewState));
+                state.accessibility.update(*nsview, &tree);
+            }
+        }
+    }
+
+    pub fn set_dismiss_on_focus_loss(&self, dismiss_on_focus_loss: bool) {
+        unsafe {
+            let nsview = self.nsview.load();
+            let view = match nsview.as_ref() {
+                Some(view) => view,
+                None => return,
+            };
+            let state: *mut c_void = *view.get_ivar("viewState");
+            let state = &mut (*(state as *mut ViewState));
+            state.dismiss_on_focus_loss = dismiss_on_focus_loss;
+            if !dismiss_on_focus_loss {
+                state.remove_dismiss_monitor();
+                return;
+            }
+            if state.dismiss_monitor != nil {
+                return;
+            }
+            let weak_view = self.nsview.clone();
+            let block = ConcreteBlock::new(move |event: id| -> id {
+                unsafe {
+                    let view = weak_view.load();
+                    if !view.is_null() {
+                        let window: id = msg_send![*view, window];
+                        let event_window: id = msg_send![event, window];
+                        if event_window != window {
+                            dismiss(*view);
+                        }
+                    }
+                }
+                event
+            });
+            let block = block.copy();
+            // NSEventMaskLeftMouseDown | NSEventMaskRightMouseDown | NSEventMaskOtherMouseDown
+            let mask: NSUInteger = 1 << 1 | 1 << 3 | 1 << 25;
+            let monitor: id = msg_send![class!(NSEvent), addLocalMonitorForEventsMatchingMask: mask handler: &*block];
+            state.dismiss_monitor = msg_send![monitor, retain];
+
+            let notif_center: id = msg_send![class!(NSNotificationCenter), defaultCenter];
+            let notif_string = make_nsstring("NSApplicationDidResignActiveNotification");
+            let () = msg_send![notif_center, addObserver: *nsview selector: sel!(applicationDidResignActive:) name: notif_string object: nil];
+        }
+    }
+
+    pub fn set_background_material(&self, material: Option<Material>) {
+        unsafe {
+            let nsview = self.nsview.load();
+            let view = match nsview.as_ref() {
+                Some(view) => view,
+                None => return,
+            };
+            let state: *mut c_void = *view.get_ivar("viewState");
+            let state = &mut (*(state as *mut ViewState));
+            if state.effect_view != nil {
+                let () = msg_send![state.effect_view, removeFromSuperview];
+                state.effect_view = nil;
+            }
+            let material = match material {
+                Some(material) => material,
+                None => return,
+            };
+            // NSVisualEffectMaterial
+            let material: NSInteger = match material {
+                Material::Titlebar => 3,
+                Material::Menu => 5,
+                Material::Popover => 6,
+                Material::Sidebar => 7,
+                Material::Sheet => 11,
+                Material::WindowBackground => 12,
+                Material::HudWindow => 13,
+                Material::ToolTip => 17,
+                Material::ContentBackground => 18,
+                Material::UnderWindowBackground => 21,
+            };
+            let window: id = msg_send![*nsview, window];
+            window.setOpaque_(NO);
+            window.setBackgroundColor_(NSColor::clearColor(nil));
+
+            let content_view = window.contentView();
+            let effect_view: id = msg_send![class!(NSVisualEffectView), alloc];
+            let effect_view: id =
+                msg_send![effect_view, initWithFrame: NSView::bounds(content_view)];
+            let () = msg_send![effect_view, setMaterial: material];
+            // NSVisualEffectBlendingModeBehindWindow
+            let () = msg_send![effect_view, setBlendingMode: 0 as NSInteger];
+            // NSVisualEffectStateFollowsWindowActiveState
+            let () = msg_send![effect_view, setState: 0 as NSInteger];
+            effect_view.setAutoresizingMask_(NSViewWidthSizable | NSViewHeightSizable);
+            // NSWindowBelow, so that our content is drawn on top of it.
+            let () = msg_send![content_view, addSubview: effect_view positioned: -1 as NSInteger relativeTo: *nsview];
+            let () = msg_send![effect_view, release];
+            state.effect_view = effect_view;
+        }
+    }
+
     pub fn request_timer(&self, deadline: std::time::Instant) -> TimerToken {
         let ti = time_interval_from_deadline(deadline);
         let token = TimerToken::next();
@@ -1161,6 +2089,7 @@ impl WindowHandle {
                 if state.active_text_input == Some(token) {
                     state.active_text_input = None;
                 }
+                state.ime_disabled_fields.remove(&token);
             }
         }
     }
@@ -1208,6 +2137,14 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        self.defer(DeferredOp::SetImeEnabled(enabled));
+    }
+
+    pub fn set_text_field_ime_enabled(&self, token: TextFieldToken, enabled: bool) {
+        self.defer(DeferredOp::SetTextFieldImeEnabled(token, enabled));
+    }
+
     pub fn open_file(&mut self, options: FileDialogOptions) -> Option<FileDialogToken> {
         Some(self.open_save_impl(FileDialogType::Open, options))
     }
@@ -1249,6 +2186,27 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_represented_file(&self, path: Option<&Path>) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            let url: id = match path {
+                Some(path) => {
+                    let path = make_nsstring(&path.to_string_lossy());
+                    msg_send![class!(NSURL), fileURLWithPath: path]
+                }
+                None => nil,
+            };
+            let () = msg_send![window, setRepresentedURL: url];
+        }
+    }
+
+    pub fn set_document_edited(&self, edited: bool) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            let () = msg_send![window, setDocumentEdited: edited as BOOL];
+        }
+    }
+
     // TODO: Implement this
     pub fn show_titlebar(&self, _show_titlebar: bool) {}
 
@@ -1273,15 +2231,14 @@ impl WindowHandle {
 
     pub fn get_position(&self) -> Point {
         unsafe {
-            // TODO this should be the max y in orig mac coords
-            let screen_height = crate::Screen::get_display_rect().height();
+            let max_y = super::screen::max_y();
 
             let window: id = msg_send![*self.nsview.load(), window];
             let current_frame: NSRect = msg_send![window, frame];
 
             let mut position = Point::new(
                 current_frame.origin.x,
-                screen_height - current_frame.origin.y - current_frame.size.height,
+                max_y - current_frame.origin.y - current_frame.size.height,
             );
             if let Some(view) = self.nsview.load().as_ref() {
                 let state: *mut c_void = *view.get_ivar("viewState");
@@ -1297,7 +2254,7 @@ impl WindowHandle {
 
     pub fn content_insets(&self) -> Insets {
         unsafe {
-            let screen_height = crate::Screen::get_display_rect().height();
+            let max_y = super::screen::max_y();
 
             let window: id = msg_send![*self.nsview.load(), window];
             let clr: NSRect = msg_send![window, contentLayoutRect];
@@ -1308,14 +2265,14 @@ impl WindowHandle {
             let window_frame_rk = Rect::from_origin_size(
                 (
                     window_frame_r.origin.x,
-                    screen_height - window_frame_r.origin.y - window_frame_r.size.height,
+                    max_y - window_frame_r.origin.y - window_frame_r.size.height,
                 ),
                 (window_frame_r.size.width, window_frame_r.size.height),
             );
             let content_frame_rk = Rect::from_origin_size(
                 (
                     content_frame_r.origin.x,
-                    screen_height - content_frame_r.origin.y - content_frame_r.size.height,
+                    max_y - content_frame_r.origin.y - content_frame_r.size.height,
                 ),
                 (content_frame_r.size.width, content_frame_r.size.height),
             );
@@ -1346,7 +2303,55 @@ impl WindowHandle {
         }
     }
 
-    fn set_level(&self, level: WindowLevel) {
+    pub fn set_always_on_bottom(&self, on_bottom: bool) {
+        unsafe {
+            let nsview = self.nsview.load();
+            let view = match nsview.as_ref() {
+                Some(view) => view,
+                None => return,
+            };
+            let state: *mut c_void = *view.get_ivar("viewState");
+            let state = &mut (*(state as *mut ViewState));
+            let window: id = msg_send![*nsview, window];
+            let current: NSUInteger = msg_send![window, collectionBehavior];
+            // Only the behaviors we add are taken away again, so that the ones set with
+            // `WindowHandleExt::set_collection_behavior` are kept.
+            let behavior = if on_bottom {
+                // Stationary keeps it in place when Mission Control moves the other windows away.
+                let wanted = (CollectionBehavior::CAN_JOIN_ALL_SPACES
+                    | CollectionBehavior::STATIONARY)
+                    .bits() as NSUInteger;
+                state.always_on_bottom_behavior |= wanted & !current;
+                let () = msg_send![window, setLevel: levels::above_desktop_level()];
+                current | wanted
+            } else {
+                let added = mem::take(&mut state.always_on_bottom_behavior);
+                let () = msg_send![window, setLevel: NSNormalWindowLevel];
+                current & !added
+            };
+            let () = msg_send![window, setCollectionBehavior: behavior];
+        }
+    }
+
+    pub fn set_opacity(&self, opacity: f64) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            let () = msg_send![window, setAlphaValue: opacity];
+        }
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            // Transparent windows keep their clear background.
+            let opaque: BOOL = msg_send![window, isOpaque];
+            if opaque != NO {
+                window.setBackgroundColor_(make_nscolor(color));
+            }
+        }
+    }
+
+    pub fn set_level(&self, level: WindowLevel) {
         unsafe {
             let level = levels::as_raw_window_level(level);
             let window: id = msg_send![*self.nsview.load(), window];
@@ -1354,6 +2359,28 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_collection_behavior(&self, behavior: CollectionBehavior) {
+        unsafe {
+            let window: id = msg_send![*self.nsview.load(), window];
+            let () = msg_send![window, setCollectionBehavior: behavior.bits() as NSUInteger];
+        }
+    }
+
+    pub fn set_titlebar_button_inset(&self, inset: Point) {
+        unsafe {
+            let nsview = self.nsview.load();
+            let view = match nsview.as_ref() {
+                Some(view) => view,
+                None => return,
+            };
+            let state: *mut c_void = *view.get_ivar("viewState");
+            let state = &mut (*(state as *mut ViewState));
+            state.titlebar_button_inset = Some(inset);
+            let window: id = msg_send![*nsview, window];
+            layout_titlebar_buttons(window, inset);
+        }
+    }
+
     pub fn set_size(&self, size: Size) {
         self.defer(DeferredOp::SetSize(size));
     }
@@ -1408,7 +2435,7 @@ impl WindowHandle {
         tracing::warn!("WindowHandle::handle_titlebar is currently unimplemented for Mac.");
     }
 
-    pub fn resizable(&self, resizable: bool) {
+    pub fn set_resizable(&self, resizable: bool) {
         unsafe {
             let window: id = msg_send![*self.nsview.load(), window];
             let mut style_mask: NSWindowStyleMask = window.styleMask();
@@ -1420,21 +2447,44 @@ impl WindowHandle {
             }
 
             window.setStyleMask_(style_mask);
+
+            let zoom_button = window.standardWindowButton_(NSWindowButton::NSWindowZoomButton);
+            if zoom_button != nil {
+                let () = msg_send![zoom_button, setEnabled: resizable as BOOL];
+            }
         }
     }
 
-    pub fn set_menu(&self, menu: Menu) {
+    pub fn set_aspect_ratio(&self, ratio: Option<f64>) {
         unsafe {
-            NSApp().setMainMenu_(menu.menu);
+            let window: id = msg_send![*self.nsview.load(), window];
+            match ratio {
+                Some(ratio) => {
+                    let () = msg_send![window, setContentAspectRatio: NSSize::new(ratio, 1.0)];
+                }
+                // Setting resize increments is what clears the aspect ratio.
+                None => {
+                    let () = msg_send![window, setContentResizeIncrements: NSSize::new(1.0, 1.0)];
+                }
+            }
         }
     }
 
-    //FIXME: we should be using the x, y values passed by the caller, but then
-    //we have to figure out some way to pass them along with this performSelector:
-    //call. This isn't super hard, I'm just not up for it right now.
-    pub fn show_context_menu(&self, menu: Menu, _pos: Point) {
+    pub fn set_menu(&self, menu: Menu) {
+        menu.set_as_main_menu();
+    }
+
+    pub fn show_context_menu(&self, menu: Menu, pos: Point) {
         unsafe {
-            let () = msg_send![*self.nsview.load(), performSelectorOnMainThread: sel!(showContextMenu:) withObject: menu.menu waitUntilDone: NO];
+            // The view is flipped, so `pos` is already in its coordinate space.
+            let location: id =
+                msg_send![class!(NSValue), valueWithPoint: NSPoint::new(pos.x, pos.y)];
+            let args = NSArray::arrayWithObjects(nil, &[menu.menu, location]);
+            let view = *self.nsview.load();
+            let sel = sel!(showContextMenu:);
+            let () = msg_send![view, performSelectorOnMainThread: sel
+                                                      withObject: args
+                                                   waitUntilDone: NO];
         }
     }
 
@@ -1463,6 +2513,20 @@ impl WindowHandle {
     }
 }
 
+impl crate::platform::mac::WindowHandleExt for crate::WindowHandle {
+    fn set_background_material(&self, material: Option<Material>) {
+        self.0.set_background_material(material)
+    }
+
+    fn set_collection_behavior(&self, behavior: CollectionBehavior) {
+        self.0.set_collection_behavior(behavior)
+    }
+
+    fn set_titlebar_button_inset(&self, inset: Point) {
+        self.0.set_titlebar_button_inset(inset)
+    }
+}
+
 #[cfg(feature = "raw-win-handle")]
 unsafe impl HasRawWindowHandle for WindowHandle {
     fn raw_window_handle(&self) -> RawWindowHandle {
@@ -14,3 +14,4 @@
 
 // environment based utilities
 pub mod env;
+pub mod xdg;
@@ -0,0 +1,37 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Helpers for the freedesktop.org desktop integration tools.
+
+use std::ffi::OsStr;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::{anyhow, Context};
+
+/// Opens `target` with the user's preferred application, using `xdg-open`.
+pub fn open(target: impl AsRef<OsStr>) -> Result<(), anyhow::Error> {
+    let target = target.as_ref();
+    // `xdg-open` blocks until the launched application has started, so we don't wait on it.
+    Command::new("xdg-open")
+        .arg(target)
+        .spawn()
+        .map(|_child| ())
+        .with_context(|| anyhow!("failed to run xdg-open for {:?}", target))
+}
+
+/// Opens the directory containing `path` in the user's file manager.
+pub fn reveal(path: &Path) -> Result<(), anyhow::Error> {
+    open(path.parent().unwrap_or(path))
+}
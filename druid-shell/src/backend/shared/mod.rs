@@ -28,3 +28,14 @@ cfg_if::cfg_if! {
         pub(crate) mod linux;
     }
 }
+cfg_if::cfg_if! {
+    if #[cfg(any(target_os = "freebsd", target_os = "linux", target_os = "openbsd"))] {
+        mod os_version;
+        pub(crate) use os_version::*;
+    }
+}
+cfg_if::cfg_if! {
+    if #[cfg(all(any(target_os = "freebsd", target_os = "linux", target_os = "openbsd"), any(feature = "gtk", feature = "x11")))] {
+        pub(crate) mod strut;
+    }
+}
@@ -0,0 +1,25 @@
+// Copyright 2023 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Finding out the version of the system on Linux.
+
+use crate::application::OsVersion;
+
+/// Returns the version of the running kernel, which is what `uname -r` prints.
+///
+/// This is only available on Linux, since the BSDs don't have `/proc/sys`.
+pub(crate) fn kernel_version() -> Option<OsVersion> {
+    let release = std::fs::read_to_string("/proc/sys/kernel/osrelease").ok()?;
+    OsVersion::parse(release.trim())
+}
@@ -0,0 +1,306 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Monitor work areas computed from the space that X11 panels and docks reserve.
+//!
+//! Window managers are supposed to report work areas, but `_NET_WORKAREA` only has one
+//! rectangle for all monitors, and some window managers don't set it at all.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use x11rb::connection::Connection;
+use x11rb::errors::ReplyError;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt as _, Window};
+
+use crate::kurbo::{Rect, Size};
+
+static FALLBACK_ENABLED: AtomicBool = AtomicBool::new(true);
+
+x11rb::atom_manager! {
+    StrutAtoms: StrutAtomsCookie {
+        _NET_CLIENT_LIST,
+        _NET_CURRENT_DESKTOP,
+        _NET_WORKAREA,
+        _NET_WM_STRUT,
+        _NET_WM_STRUT_PARTIAL,
+    }
+}
+
+/// Sets whether work areas should be computed from struts.
+///
+/// See [`ScreenExt::set_work_area_fallback`](crate::platform::linux::ScreenExt).
+pub(crate) fn set_fallback_enabled(enabled: bool) {
+    FALLBACK_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns true if work areas should be computed from struts.
+pub(crate) fn fallback_enabled() -> bool {
+    FALLBACK_ENABLED.load(Ordering::Relaxed)
+}
+
+/// The space a window reserves along the edges of the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Strut {
+    left: u32,
+    right: u32,
+    top: u32,
+    bottom: u32,
+    left_start_y: u32,
+    left_end_y: u32,
+    right_start_y: u32,
+    right_end_y: u32,
+    top_start_x: u32,
+    top_end_x: u32,
+    bottom_start_x: u32,
+    bottom_end_x: u32,
+}
+
+impl Strut {
+    /// Reads the values of `_NET_WM_STRUT_PARTIAL`, or of the older `_NET_WM_STRUT`,
+    /// whose struts span whole edges.
+    fn from_cardinals(values: &[u32]) -> Option<Strut> {
+        let whole_edges = [0, u32::MAX, 0, u32::MAX, 0, u32::MAX, 0, u32::MAX];
+        let spans = match values.len() {
+            4 => &whole_edges[..],
+            12 => &values[4..],
+            _ => return None,
+        };
+        Some(Strut {
+            left: values[0],
+            right: values[1],
+            top: values[2],
+            bottom: values[3],
+            left_start_y: spans[0],
+            left_end_y: spans[1],
+            right_start_y: spans[2],
+            right_end_y: spans[3],
+            top_start_x: spans[4],
+            top_end_x: spans[5],
+            bottom_start_x: spans[6],
+            bottom_end_x: spans[7],
+        })
+    }
+}
+
+/// Returns the work areas of `monitors`, which are in device pixels.
+///
+/// This removes the struts of the client windows if the window manager lists them,
+/// and otherwise uses the `_NET_WORKAREA` of the current desktop.
+pub(crate) fn work_areas(
+    conn: &impl Connection,
+    root: Window,
+    screen: Size,
+    monitors: &[Rect],
+) -> Result<Vec<Rect>, ReplyError> {
+    let atoms = StrutAtoms::new(conn)?.reply()?;
+    let clients = conn
+        .get_property(
+            false,
+            root,
+            atoms._NET_CLIENT_LIST,
+            AtomEnum::WINDOW,
+            0,
+            u32::MAX,
+        )?
+        .reply()?;
+    if let Some(clients) = clients.value32() {
+        // Windows can be destroyed while we query them, so those are skipped.
+        let struts: Vec<Strut> = clients
+            .filter_map(|window| window_strut(conn, &atoms, window).ok().flatten())
+            .collect();
+        return Ok(monitors
+            .iter()
+            .map(|&monitor| remove_struts(monitor, screen, &struts))
+            .collect());
+    }
+
+    let desktop = conn
+        .get_property(
+            false,
+            root,
+            atoms._NET_CURRENT_DESKTOP,
+            AtomEnum::CARDINAL,
+            0,
+            1,
+        )?
+        .reply()?
+        .value32()
+        .and_then(|mut values| values.next())
+        .unwrap_or(0);
+    let workarea = conn
+        .get_property(
+            false,
+            root,
+            atoms._NET_WORKAREA,
+            AtomEnum::CARDINAL,
+            desktop * 4,
+            4,
+        )?
+        .reply()?;
+    let values: Vec<u32> = workarea.value32().into_iter().flatten().collect();
+    Ok(match values[..] {
+        [x, y, width, height] => {
+            let workarea = Rect::new(
+                x as f64,
+                y as f64,
+                x as f64 + width as f64,
+                y as f64 + height as f64,
+            );
+            monitors
+                .iter()
+                .map(|&monitor| {
+                    let area = monitor.intersect(workarea);
+                    if area.area() > 0.0 {
+                        area
+                    } else {
+                        monitor
+                    }
+                })
+                .collect()
+        }
+        _ => monitors.to_vec(),
+    })
+}
+
+fn window_strut(
+    conn: &impl Connection,
+    atoms: &StrutAtoms,
+    window: Window,
+) -> Result<Option<Strut>, ReplyError> {
+    for property in [atoms._NET_WM_STRUT_PARTIAL, atoms._NET_WM_STRUT] {
+        let reply = conn
+            .get_property(false, window, property, AtomEnum::CARDINAL, 0, 12)?
+            .reply()?;
+        let values: Vec<u32> = reply.value32().into_iter().flatten().collect();
+        if let Some(strut) = Strut::from_cardinals(&values) {
+            return Ok(Some(strut));
+        }
+    }
+    Ok(None)
+}
+
+/// Removes the space that `struts` reserve from `monitor`.
+///
+/// Struts are measured from the edges of the whole screen, which is `screen` big.
+fn remove_struts(monitor: Rect, screen: Size, struts: &[Strut]) -> Rect {
+    // A strut only applies to a monitor if their spans along the edge overlap. Struts that
+    // cover a whole monitor were meant for another one.
+    let overlaps =
+        |start: u32, end: u32, min: f64, max: f64| (start as f64) < max && (end as f64) >= min;
+    let mut area = monitor;
+    for strut in struts {
+        let left = strut.left as f64;
+        if left > monitor.x0
+            && left < monitor.x1
+            && overlaps(strut.left_start_y, strut.left_end_y, monitor.y0, monitor.y1)
+        {
+            area.x0 = area.x0.max(left);
+        }
+        let right = screen.width - strut.right as f64;
+        if right > monitor.x0
+            && right < monitor.x1
+            && overlaps(
+                strut.right_start_y,
+                strut.right_end_y,
+                monitor.y0,
+                monitor.y1,
+            )
+        {
+            area.x1 = area.x1.min(right);
+        }
+        let top = strut.top as f64;
+        if top > monitor.y0
+            && top < monitor.y1
+            && overlaps(strut.top_start_x, strut.top_end_x, monitor.x0, monitor.x1)
+        {
+            area.y0 = area.y0.max(top);
+        }
+        let bottom = screen.height - strut.bottom as f64;
+        if bottom > monitor.y0
+            && bottom < monitor.y1
+            && overlaps(
+                strut.bottom_start_x,
+                strut.bottom_end_x,
+                monitor.x0,
+                monitor.x1,
+            )
+        {
+            area.y1 = area.y1.min(bottom);
+        }
+    }
+    // Struts on opposite edges can still leave no room.
+    if area.width() > 0.0 && area.height() > 0.0 {
+        area
+    } else {
+        monitor
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn panels_on_one_monitor() {
+        let screen = Size::new(3840.0, 1080.0);
+        let left = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        let right = Rect::new(1920.0, 0.0, 3840.0, 1080.0);
+        // A 30px top panel, and a 40px bottom panel only on the right monitor.
+        let struts = [
+            Strut::from_cardinals(&[0, 0, 30, 0]).unwrap(),
+            Strut::from_cardinals(&[0, 0, 0, 40, 0, 0, 0, 0, 0, 0, 1920, 3839]).unwrap(),
+        ];
+        assert_eq!(
+            remove_struts(left, screen, &struts),
+            Rect::new(0.0, 30.0, 1920.0, 1080.0)
+        );
+        assert_eq!(
+            remove_struts(right, screen, &struts),
+            Rect::new(1920.0, 30.0, 3840.0, 1040.0)
+        );
+    }
+
+    #[test]
+    fn struts_from_screen_edges() {
+        // A taller monitor next to a shorter one, with a dock on the left edge.
+        let screen = Size::new(3000.0, 1200.0);
+        let left = Rect::new(0.0, 0.0, 1920.0, 1200.0);
+        let right = Rect::new(1920.0, 0.0, 3000.0, 800.0);
+        let struts = [
+            Strut::from_cardinals(&[64, 0, 0, 0, 0, 1199, 0, 0, 0, 0, 0, 0]).unwrap(),
+            Strut::from_cardinals(&[0, 50, 0, 0, 0, 0, 0, 799, 0, 0, 0, 0]).unwrap(),
+        ];
+        assert_eq!(
+            remove_struts(left, screen, &struts),
+            Rect::new(64.0, 0.0, 1920.0, 1200.0)
+        );
+        assert_eq!(
+            remove_struts(right, screen, &struts),
+            Rect::new(1920.0, 0.0, 2950.0, 800.0)
+        );
+    }
+
+    #[test]
+    fn invalid_struts() {
+        assert_eq!(Strut::from_cardinals(&[]), None);
+        assert_eq!(Strut::from_cardinals(&[1, 2, 3]), None);
+        // A strut wider than the monitor is ignored.
+        let monitor = Rect::new(0.0, 0.0, 800.0, 600.0);
+        let struts = [Strut::from_cardinals(&[900, 0, 0, 0]).unwrap()];
+        assert_eq!(
+            remove_struts(monitor, Size::new(800.0, 600.0), &struts),
+            monitor
+        );
+    }
+}
@@ -18,7 +18,7 @@ mod keycodes;
 mod xkbcommon_sys;
 use crate::{
     backend::shared::{code_to_location, hardware_keycode_to_code},
-    KeyEvent, KeyState, Modifiers,
+    KeyEvent, KeyState, ModifierSides, Modifiers,
 };
 use keyboard_types::{Code, Key};
 use std::convert::TryFrom;
@@ -159,6 +159,7 @@ impl Drop for Keymap {
 pub struct State {
     state: *mut xkb_state,
     mods: ModsIndices,
+    mod_sides: ModifierSides,
 }
 
 #[derive(Clone, Copy)]
@@ -188,6 +189,7 @@ impl State {
                 caps_lock: mod_idx(XKB_MOD_NAME_CAPS),
                 num_lock: mod_idx(XKB_MOD_NAME_NUM),
             },
+            mod_sides: ModifierSides::empty(),
         }
     }
 
@@ -233,15 +235,19 @@ impl State {
                 }
             }
         }
-        KeyEvent {
+        let mut event = KeyEvent {
             state,
             key,
             code,
             location,
             mods,
+            mod_sides: ModifierSides::empty(),
             repeat,
             is_composing,
-        }
+        };
+        self.mod_sides.update(&event);
+        event.mod_sides = self.mod_sides;
+        event
     }
 
     fn get_logical_key(&mut self, scancode: u32) -> Key {
@@ -285,6 +291,7 @@ impl Clone for State {
         Self {
             state: unsafe { xkb_state_ref(self.state) },
             mods: self.mods,
+            mod_sides: self.mod_sides,
         }
     }
 }
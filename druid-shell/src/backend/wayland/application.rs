@@ -15,17 +15,23 @@
 #![allow(clippy::single_match)]
 
 use super::{
-    clipboard, display, error::Error, events::WaylandSource, keyboard, outputs, pointers, surfaces,
-    window::WindowHandle,
+    clipboard, display, error::Error, events::WaylandSource, keyboard, menu::Menu, outputs,
+    pointers, surfaces, window::WindowHandle,
 };
 
-use crate::{backend, mouse, AppHandler, TimerToken};
+use crate::kurbo::{Point, Size};
+use crate::piet::Color;
+use crate::{
+    backend, mouse, AppHandler, LayoutDirection, OsVersion, PermissionState, RunOutcome,
+    SystemSound, TimerToken, WindowInfo,
+};
 
 use calloop;
 
 use std::{
     cell::{Cell, RefCell},
     collections::{BTreeMap, BinaryHeap},
+    path::Path,
     rc::Rc,
     time::{Duration, Instant},
 };
@@ -85,6 +91,9 @@ impl std::cmp::PartialOrd for Timer {
     }
 }
 
+/// Global mouse monitors aren't supported on wayland, so this can't be created.
+pub enum GlobalMouseMonitor {}
+
 #[derive(Clone)]
 pub struct Application {
     pub(super) data: std::sync::Arc<Data>,
@@ -322,7 +331,15 @@ impl Application {
         Ok(Application { data: appdata })
     }
 
-    pub fn run(mut self, _handler: Option<Box<dyn AppHandler>>) {
+    pub fn new_single_instance(_app_id: &str) -> Result<Self, crate::Error> {
+        tracing::warn!("new_single_instance is unimplemented on wayland");
+        Ok(Self::new()?)
+    }
+
+    pub fn run(
+        mut self,
+        _handler: Option<Box<dyn AppHandler>>,
+    ) -> Result<RunOutcome, anyhow::Error> {
         tracing::info!("wayland event loop initiated");
         // NOTE if we want to call this function more than once, we will need to put the timer
         // source back.
@@ -350,6 +367,7 @@ impl Application {
                                 .outputs
                                 .borrow_mut()
                                 .insert(output.id(), output.clone());
+                            crate::screen::monitors_changed();
                             for (_, win) in appdata.handles_iter() {
                                 surfaces::Outputs::inserted(&win, &output);
                             }
@@ -357,6 +375,7 @@ impl Application {
                         outputs::Event::Removed(output) => {
                             tracing::debug!("output removed {:?} {:?}", output.gid, output.id());
                             appdata.outputs.borrow_mut().remove(&output.id());
+                            crate::screen::monitors_changed();
                             for (_, win) in appdata.handles_iter() {
                                 surfaces::Outputs::removed(&win, &output);
                             }
@@ -383,6 +402,12 @@ impl Application {
                 return;
             }
 
+            for (_id, winhandle) in appdata.handles_iter() {
+                if matches!(winhandle.data(), Some(data) if data.close_requested.get()) {
+                    winhandle.close();
+                }
+            }
+
             if appdata.handles.borrow().len() == 0 {
                 tracing::debug!("shutting down, no window remaining");
                 signal.stop();
@@ -394,21 +419,155 @@ impl Application {
 
         match res {
             Ok(_) => tracing::info!("wayland event loop completed"),
-            Err(cause) => tracing::error!("wayland event loop failed {:?}", cause),
+            Err(cause) => return Err(anyhow::anyhow!("wayland event loop failed {:?}", cause)),
         }
+        Ok(RunOutcome::new(0, self.data.shutdown.get()))
+    }
+
+    pub fn pump_events(&self, _timeout: Option<Duration>) -> bool {
+        tracing::warn!("pump_events is unimplemented on wayland");
+        !self.data.shutdown.get()
     }
 
     pub fn quit(&self) {
         self.data.shutdown.set(true);
     }
 
+    pub fn reply_to_end_session(&self, _allow: bool) {
+        // noop, since the end of the session is never blocked here
+    }
+
+    pub fn set_menu(&self, _menu: Menu) {
+        tracing::warn!("set_menu is unimplemented on wayland");
+    }
+
     pub fn clipboard(&self) -> clipboard::Clipboard {
         clipboard::Clipboard::from(&self.data.clipboard)
     }
 
+    pub fn open_uri(&self, uri: &str) -> Result<(), anyhow::Error> {
+        linux::xdg::open(uri)
+    }
+
+    pub fn reveal_in_file_manager(&self, path: &Path) -> Result<(), anyhow::Error> {
+        linux::xdg::reveal(path)
+    }
+
+    pub fn note_recent_document(&self, _path: &Path) {
+        tracing::warn!("note_recent_document is unimplemented on wayland");
+    }
+
+    pub fn clear_recent_documents(&self) {
+        tracing::warn!("clear_recent_documents is unimplemented on wayland");
+    }
+
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        None
+    }
+
+    pub fn layout_direction(&self) -> LayoutDirection {
+        LayoutDirection::from_locale(&linux::env::locale())
+    }
+
+    pub fn prefers_high_contrast(&self) -> bool {
+        false
+    }
+
+    pub fn selection_color(&self) -> Option<Color> {
+        None
+    }
+
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        None
+    }
+
+    pub fn is_active(&self) -> bool {
+        false
+    }
+
+    pub fn activate(&self, _ignore_other_apps: bool) {
+        tracing::warn!("activate is unimplemented on wayland");
+    }
+
+    pub fn beep(&self) {
+        tracing::warn!("beep is unimplemented on wayland");
+    }
+
+    pub fn play_system_sound(&self, _sound: SystemSound) {
+        tracing::warn!("play_system_sound is unimplemented on wayland");
+    }
+
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        tracing::warn!("system_idle_time is unimplemented on wayland");
+        None
+    }
+
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        tracing::warn!("mouse_button_count is unimplemented on wayland");
+        None
+    }
+
+    pub fn max_cursor_size(&self) -> Size {
+        Size::new(f64::INFINITY, f64::INFINITY)
+    }
+
+    pub fn mouse_buttons(&self) -> mouse::MouseButtons {
+        tracing::warn!("mouse_buttons is unimplemented on wayland");
+        mouse::MouseButtons::new()
+    }
+
+    pub fn modifiers(&self) -> crate::Modifiers {
+        tracing::warn!("modifiers is unimplemented on wayland");
+        crate::Modifiers::empty()
+    }
+
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        // Wayland doesn't let clients see the windows of other clients.
+        Vec::new()
+    }
+
+    pub fn has_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn request_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn check_accessibility_permission(&self, _prompt: bool) -> PermissionState {
+        PermissionState::NotRequired
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "wayland"
+    }
+
+    pub fn os_version(&self) -> Option<OsVersion> {
+        crate::backend::shared::kernel_version()
+    }
+
+    pub fn add_global_mouse_monitor(
+        &self,
+        _callback: Box<dyn FnMut(Point)>,
+    ) -> Result<GlobalMouseMonitor, crate::Error> {
+        tracing::warn!("add_global_mouse_monitor is unimplemented on wayland");
+        Err(anyhow::anyhow!("global mouse monitors are not supported on wayland").into())
+    }
+
+    pub fn run_on_main<T: Send + 'static>(
+        _f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, crate::Error> {
+        tracing::warn!("run_on_main is unimplemented on wayland");
+        Err(anyhow::anyhow!("running on the main thread is not supported on wayland").into())
+    }
+
     pub fn get_locale() -> String {
         linux::env::locale()
     }
+
+    pub fn get_locale_tag() -> String {
+        Self::get_locale()
+    }
 }
 
 impl surfaces::Compositor for Data {
@@ -15,8 +15,9 @@
 //! Interactions with the system pasteboard on wayland compositors.
 use super::application;
 use super::error as waylanderr;
-use crate::clipboard::{ClipboardFormat, FormatId};
+use crate::clipboard::{uri_list, ClipboardFormat, DataProvider, FormatId};
 use std::io::Read;
+use std::path::PathBuf;
 use wayland_client as wl;
 use wayland_client::protocol::wl_data_device;
 use wayland_client::protocol::wl_data_device_manager;
@@ -245,6 +246,11 @@ impl Clipboard {
         tracing::warn!("clipboard copy not implemented");
     }
 
+    /// Put data on the system clipboard that is made when it is pasted.
+    pub fn put_lazy(&mut self, format: FormatId, provider: DataProvider) {
+        self.put_formats(&[ClipboardFormat::new(format, provider())]);
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         vec![Clipboard::UTF8, Clipboard::TEXT, Clipboard::UTF8_STRING]
@@ -260,6 +266,19 @@ impl Clipboard {
             )
     }
 
+    /// Put a list of files onto the system clipboard.
+    pub fn put_file_list(&mut self, paths: &[PathBuf]) {
+        let list = uri_list::from_paths(paths);
+        self.put_formats(&[ClipboardFormat::new(uri_list::URI_LIST, list)]);
+    }
+
+    /// Get the list of files on the system clipboard, if there is one.
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        let list = self.get_format(uri_list::URI_LIST)?;
+        let paths = uri_list::to_paths(std::str::from_utf8(&list).ok()?);
+        (!paths.is_empty()).then(|| paths)
+    }
+
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
     pub fn preferred_format(&self, _formats: &[FormatId]) -> Option<FormatId> {
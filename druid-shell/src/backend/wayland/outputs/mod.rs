@@ -154,6 +154,8 @@ pub struct Meta {
     pub name: String,
     pub description: String,
     pub logical: Dimensions,
+    /// The size of the current mode, in pixels.
+    pub resolution: Dimensions,
     pub refresh: i32,
     pub physical: Dimensions,
     pub subpixel: wl_output::Subpixel,
@@ -173,6 +175,7 @@ impl Default for Meta {
             name: Default::default(),
             description: Default::default(),
             logical: Default::default(),
+            resolution: Default::default(),
             refresh: Default::default(),
             physical: Default::default(),
             position: Default::default(),
@@ -203,6 +203,7 @@ impl Meta {
             } => {
                 if flags.contains(wl_output::Mode::Current) {
                     self.meta.logical = outputs::Dimensions::from((*width, *height));
+                    self.meta.resolution = outputs::Dimensions::from((*width, *height));
                     self.meta.refresh = *refresh;
                 }
 
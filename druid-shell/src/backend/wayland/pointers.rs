@@ -29,6 +29,8 @@ use super::application::Data;
 const BTN_LEFT: u32 = 0x110;
 const BTN_RIGHT: u32 = 0x111;
 const BTN_MIDDLE: u32 = 0x112;
+const BTN_SIDE: u32 = 0x113;
+const BTN_EXTRA: u32 = 0x114;
 
 // used to keep track of click event counts.
 #[derive(Debug, Clone)]
@@ -111,6 +113,8 @@ pub(crate) struct Pointer {
     theme: std::cell::RefCell<CursorTheme>,
     /// Cache the current cursor, so we can see if it changed
     current_cursor: std::cell::RefCell<mouse::Cursor>,
+    /// The source of the axis events in the current frame, if the compositor told us.
+    axis_source: std::cell::Cell<Option<wl_pointer::AxisSource>>,
 }
 
 /// Raw wayland pointer events.
@@ -160,6 +164,7 @@ impl Pointer {
             wl_pointer: std::cell::RefCell::new(None),
             current_cursor: std::cell::RefCell::new(mouse::Cursor::Arrow),
             clickevent: std::cell::RefCell::new(ClickDebouncer::default()),
+            axis_source: std::cell::Cell::new(None),
         }
     }
 
@@ -230,6 +235,8 @@ impl Pointer {
             mouse::Cursor::ResizeLeftRight => self.unpack_image_buffer("row-resize"),
             mouse::Cursor::ResizeUpDown => self.unpack_image_buffer("col-resize"),
             mouse::Cursor::Pointer => self.unpack_image_buffer("pointer"),
+            mouse::Cursor::Wait => self.unpack_image_buffer("watch"),
+            mouse::Cursor::Progress => self.unpack_image_buffer("left_ptr_watch"),
             mouse::Cursor::Custom(_) => {
                 tracing::warn!("custom cursors not implemented");
                 self.unpack_image_buffer("left_ptr")
@@ -282,6 +289,9 @@ impl Pointer {
             wl_pointer::Event::Axis { axis, value, .. } => {
                 appdata.pointer.push(PointerEvent::Axis { axis, value });
             }
+            wl_pointer::Event::AxisSource { axis_source } => {
+                appdata.pointer.axis_source.set(Some(axis_source));
+            }
             wl_pointer::Event::Frame => {
                 let winhandle = match appdata.acquire_current_window().and_then(|w| w.data()) {
                     Some(w) => w,
@@ -303,6 +313,7 @@ impl Pointer {
                         MouseEvtKind::Leave => winhandle.mouse_leave(),
                     }
                 }
+                appdata.pointer.axis_source.set(None);
             }
             evt => {
                 log::warn!("Unhandled pointer event: {:?}", evt);
@@ -327,6 +338,10 @@ impl Pointer {
                         focus: false,
                         button: mouse::MouseButton::None,
                         wheel_delta: Vec2::ZERO,
+                        pointer_type: mouse::PointerType::Mouse,
+                        pressure: self.buttons.borrow().default_pressure(),
+                        tilt_x: 0.0,
+                        tilt_y: 0.0,
                     }));
                 }
                 PointerEvent::Button { button, state } => {
@@ -350,6 +365,10 @@ impl Pointer {
                                     focus: false,
                                     button,
                                     wheel_delta: Vec2::ZERO,
+                                    pointer_type: mouse::PointerType::Mouse,
+                                    pressure: self.buttons.borrow().default_pressure(),
+                                    tilt_x: 0.0,
+                                    tilt_y: 0.0,
                                 },
                             ))
                         }
@@ -364,6 +383,10 @@ impl Pointer {
                                     focus: false,
                                     button,
                                     wheel_delta: Vec2::ZERO,
+                                    pointer_type: mouse::PointerType::Mouse,
+                                    pressure: self.buttons.borrow().default_pressure(),
+                                    tilt_x: 0.0,
+                                    tilt_y: 0.0,
                                 },
                             ))
                         }
@@ -383,6 +406,11 @@ impl Pointer {
                             continue;
                         }
                     };
+                    // Scrolling with fingers means a touchpad.
+                    let pointer_type = match self.axis_source.get() {
+                        Some(wl_pointer::AxisSource::Finger) => mouse::PointerType::Touchpad,
+                        _ => mouse::PointerType::Mouse,
+                    };
                     return Some(MouseEvtKind::Wheel(mouse::MouseEvent {
                         pos: self.pos.get(),
                         buttons: *self.buttons.borrow(),
@@ -391,6 +419,10 @@ impl Pointer {
                         focus: false,
                         button: mouse::MouseButton::None,
                         wheel_delta,
+                        pointer_type,
+                        pressure: self.buttons.borrow().default_pressure(),
+                        tilt_x: 0.0,
+                        tilt_y: 0.0,
                     }));
                 }
                 PointerEvent::Leave => {
@@ -414,6 +446,8 @@ fn linux_to_mouse_button(button: u32) -> Option<mouse::MouseButton> {
         BTN_LEFT => Some(mouse::MouseButton::Left),
         BTN_RIGHT => Some(mouse::MouseButton::Right),
         BTN_MIDDLE => Some(mouse::MouseButton::Middle),
+        BTN_SIDE => Some(mouse::MouseButton::X1),
+        BTN_EXTRA => Some(mouse::MouseButton::X2),
         _ => None,
     }
 }
@@ -14,28 +14,74 @@
 
 //! wayland Monitors and Screen information.
 
-use crate::kurbo::Rect;
+use crate::kurbo::{Point, Rect, Size};
+use crate::piet::ImageBuf;
+use crate::scale::Scale;
+use anyhow::anyhow;
 
-use crate::screen::Monitor;
+use crate::screen::{DisplayMode, Monitor, Orientation, SubpixelOrder};
 
 use super::error;
 use super::outputs;
+use wayland_client::protocol::wl_output;
 
 fn _get_monitors() -> Result<Vec<Monitor>, error::Error> {
     let metas = outputs::current()?;
     let monitors: Vec<Monitor> = metas
         .iter()
         .map(|m| {
-            let rect = Rect::from_origin_size(
-                (m.position.x as f64, m.position.y as f64),
-                (m.logical.width as f64, m.logical.height as f64),
-            );
+            let rect = meta_rect(m);
+            let scale = meta_scale(m);
+            // Outputs are named after their connector, like `DP-1`, since wl_output version 4.
+            let device_id = if m.name.is_empty() {
+                format!("{}:{}", m.make, m.model)
+            } else {
+                m.name.clone()
+            };
             Monitor::new(false, rect, rect)
+                .with_scale(Scale::new(scale, scale))
+                .with_device_id(device_id)
+                .with_subpixel_order(match m.subpixel {
+                    wl_output::Subpixel::HorizontalRgb => SubpixelOrder::Rgb,
+                    wl_output::Subpixel::HorizontalBgr => SubpixelOrder::Bgr,
+                    wl_output::Subpixel::VerticalRgb => SubpixelOrder::VRgb,
+                    wl_output::Subpixel::VerticalBgr => SubpixelOrder::VBgr,
+                    wl_output::Subpixel::None => SubpixelOrder::None,
+                    _ => SubpixelOrder::Unknown,
+                })
+                .with_orientation(match m.transform {
+                    wl_output::Transform::_90 | wl_output::Transform::Flipped90 => {
+                        Orientation::Portrait
+                    }
+                    wl_output::Transform::_180 | wl_output::Transform::Flipped180 => {
+                        Orientation::LandscapeFlipped
+                    }
+                    wl_output::Transform::_270 | wl_output::Transform::Flipped270 => {
+                        Orientation::PortraitFlipped
+                    }
+                    _ => Orientation::Landscape,
+                })
         })
         .collect();
     Ok(monitors)
 }
 
+fn meta_rect(m: &outputs::Meta) -> Rect {
+    Rect::from_origin_size(
+        (m.position.x as f64, m.position.y as f64),
+        (m.logical.width as f64, m.logical.height as f64),
+    )
+}
+
+fn meta_scale(m: &outputs::Meta) -> f64 {
+    // Outputs that haven't reported a scale yet are treated as unscaled.
+    if m.scale > 0.0 {
+        m.scale
+    } else {
+        1.0
+    }
+}
+
 pub(crate) fn get_monitors() -> Vec<Monitor> {
     match _get_monitors() {
         Ok(m) => m,
@@ -48,3 +94,67 @@ pub(crate) fn get_monitors() -> Vec<Monitor> {
         }
     }
 }
+
+pub(crate) fn num_monitors() -> usize {
+    match outputs::current() {
+        Ok(metas) => metas.len(),
+        Err(cause) => {
+            tracing::error!(
+                "unable to detect monitors, failed to connect to wayland server {:?}",
+                cause
+            );
+            0
+        }
+    }
+}
+
+pub(crate) fn get_mouse_position() -> Option<Point> {
+    // Wayland clients only see the pointer over their own surfaces.
+    None
+}
+
+pub(crate) fn display_modes(monitor: &Monitor) -> Vec<DisplayMode> {
+    let metas = match outputs::current() {
+        Ok(metas) => metas,
+        Err(cause) => {
+            tracing::error!(
+                "unable to list display modes, failed to connect to wayland server {:?}",
+                cause
+            );
+            return Vec::new();
+        }
+    };
+    // Only the current mode is reported, since compositors stopped listing the others.
+    metas
+        .iter()
+        .find(|m| meta_rect(m) == monitor.virtual_rect())
+        .map(|m| {
+            let scale = meta_scale(m);
+            DisplayMode::new(
+                Size::new(m.resolution.width as f64, m.resolution.height as f64),
+                (m.refresh > 0).then(|| m.refresh as f64 / 1000.0),
+                Scale::new(scale, scale),
+            )
+            .with_current(true)
+        })
+        .into_iter()
+        .collect()
+}
+
+pub(crate) fn set_mode(_monitor: &Monitor, _mode: &DisplayMode) -> Result<(), crate::Error> {
+    // Only the compositor can change the modes of the outputs.
+    tracing::warn!("set_mode is unimplemented on wayland");
+    Err(anyhow!("changing the display mode is not supported on wayland").into())
+}
+
+pub(crate) fn capture_region(_rect: Rect) -> Result<ImageBuf, crate::Error> {
+    // This needs the desktop portal, since clients can't read the screen directly.
+    tracing::warn!("capture_region is unimplemented on wayland");
+    Err(anyhow!("screen capture is not supported on wayland").into())
+}
+
+impl crate::platform::linux::ScreenExt for crate::Screen {
+    fn set_work_area_fallback(_enabled: bool) {
+        // Wayland clients can't see the panels, so there is nothing to fall back to.
+    }
+}
@@ -75,6 +75,7 @@ impl Surface {
             active_text_input: Cell::new(None),
             damaged_region: RefCell::new(Region::EMPTY),
             deferred_tasks: RefCell::new(std::collections::VecDeque::new()),
+            close_requested: Cell::new(false),
         });
 
         // register to receive wl_surface events.
@@ -268,6 +269,11 @@ pub struct Data {
     /// These call back into user code, and so should only be run after all user code has returned,
     /// to avoid possible re-entrancy.
     deferred_tasks: RefCell<std::collections::VecDeque<DeferredTask>>,
+    /// Whether the compositor asked to close the window and the handler agreed.
+    ///
+    /// The surface can't reach its window handle, so the application closes it after the
+    /// event has been handled.
+    pub(crate) close_requested: Cell<bool>,
 
     idle_queue: std::sync::Arc<std::sync::Mutex<Vec<idle::Kind>>>,
 }
@@ -106,7 +106,9 @@ impl Surface {
                 }
                 xdg_toplevel::Event::Close => {
                     tracing::info!("xdg close event {:?}", event);
-                    wl_surface.inner.handler.borrow_mut().request_close();
+                    if wl_surface.inner.handler.borrow_mut().should_close() {
+                        wl_surface.inner.close_requested.set(true);
+                    }
                 }
                 _ => tracing::info!("unimplemented event {:?}", event),
             }
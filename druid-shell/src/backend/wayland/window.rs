@@ -14,6 +14,9 @@
 
 #![allow(clippy::single_match)]
 
+use std::cell::RefCell;
+use std::path::Path;
+
 use tracing;
 use wayland_protocols::xdg_shell::client::xdg_popup;
 use wayland_protocols::xdg_shell::client::xdg_positioner;
@@ -27,11 +30,13 @@ use super::{error::Error, menu::Menu, outputs, surfaces};
 
 use crate::Region;
 use crate::{
+    accessibility::AccessibilityTree,
+    common_util::CursorStack,
     dialog::FileDialogOptions,
     error::Error as ShellError,
     kurbo::{Insets, Point, Rect, Size},
     mouse::{Cursor, CursorDesc},
-    piet::PietText,
+    piet::{Color, PietText},
     scale::Scale,
     text::Event,
     window::{self, FileDialogToken, TimerToken, WinHandler, WindowLevel},
@@ -48,6 +53,7 @@ struct Inner {
     pub(super) outputs: Box<dyn surfaces::Outputs>,
     pub(super) popup: Box<dyn surfaces::Popup>,
     pub(super) appdata: std::sync::Weak<application::Data>,
+    cursor: RefCell<CursorStack>,
 }
 
 #[derive(Clone)]
@@ -91,6 +97,7 @@ impl WindowHandle {
                 surface: surface.into(),
                 popup: popup.into(),
                 appdata: appdata.into(),
+                cursor: RefCell::new(CursorStack::default()),
             }),
         }
     }
@@ -103,8 +110,12 @@ impl WindowHandle {
         tracing::debug!("show initiated");
     }
 
-    pub fn resizable(&self, _resizable: bool) {
-        tracing::warn!("resizable is unimplemented on wayland");
+    pub fn set_resizable(&self, _resizable: bool) {
+        tracing::warn!("set_resizable is unimplemented on wayland");
+    }
+
+    pub fn set_aspect_ratio(&self, _ratio: Option<f64>) {
+        tracing::warn!("set_aspect_ratio is unimplemented on wayland");
     }
 
     pub fn show_titlebar(&self, _show_titlebar: bool) {
@@ -120,6 +131,26 @@ impl WindowHandle {
         tracing::warn!("set_always_on_top is unimplemented on wayland");
     }
 
+    pub fn set_always_on_bottom(&self, _on_bottom: bool) {
+        tracing::warn!("set_always_on_bottom is unimplemented on wayland");
+    }
+
+    pub fn set_level(&self, _level: WindowLevel) {
+        tracing::warn!("set_level is unimplemented on wayland");
+    }
+
+    pub fn set_dismiss_on_focus_loss(&self, _dismiss: bool) {
+        tracing::warn!("set_dismiss_on_focus_loss is unimplemented on wayland");
+    }
+
+    pub fn set_opacity(&self, _opacity: f64) {
+        tracing::warn!("set_opacity is unimplemented on wayland");
+    }
+
+    pub fn set_background_color(&self, _color: Color) {
+        tracing::warn!("set_background_color is unimplemented on wayland");
+    }
+
     pub fn set_input_region(&self, region: Option<Region>) {
         self.inner.surface.set_input_region(region);
     }
@@ -197,6 +228,10 @@ impl WindowHandle {
         self.inner.surface.invalidate_rect(rect);
     }
 
+    pub fn paint_now(&self) {
+        tracing::warn!("paint_now is unimplemented on wayland");
+    }
+
     pub fn text(&self) -> PietText {
         PietText::new()
     }
@@ -217,6 +252,14 @@ impl WindowHandle {
         // noop until we get a real text input implementation
     }
 
+    pub fn set_ime_enabled(&self, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
+    pub fn set_text_field_ime_enabled(&self, _token: TextFieldToken, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
     pub fn request_timer(&self, deadline: std::time::Instant) -> TimerToken {
         let appdata = match self.inner.appdata.upgrade() {
             Some(d) => d,
@@ -252,16 +295,52 @@ impl WindowHandle {
     }
 
     pub fn set_cursor(&mut self, cursor: &Cursor) {
+        if !self.inner.cursor.borrow_mut().set(cursor) {
+            return;
+        }
         if let Some(appdata) = self.inner.appdata.upgrade() {
             appdata.set_cursor(cursor);
         }
     }
 
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        if let Some(appdata) = self.inner.appdata.upgrade() {
+            appdata.set_cursor(&cursor);
+        }
+        self.inner.cursor.borrow_mut().push(cursor);
+    }
+
+    pub fn pop_cursor(&mut self) {
+        let stack = &mut *self.inner.cursor.borrow_mut();
+        if !stack.pop() {
+            return;
+        }
+        if let Some(appdata) = self.inner.appdata.upgrade() {
+            appdata.set_cursor(stack.current().unwrap_or(&Cursor::Arrow));
+        }
+    }
+
     pub fn make_cursor(&self, _desc: &CursorDesc) -> Option<Cursor> {
         tracing::warn!("unimplemented make_cursor initiated");
         None
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        tracing::warn!("set_cursor_visible is unimplemented on wayland");
+    }
+
+    pub fn confine_cursor(&self, _confine: bool) {
+        tracing::warn!("confine_cursor is unimplemented on wayland");
+    }
+
+    pub fn announce(&self, _message: &str, _politeness: window::Politeness) {
+        tracing::warn!("announce is unimplemented on wayland");
+    }
+
+    pub fn set_accessibility_tree(&self, _tree: AccessibilityTree) {
+        tracing::warn!("set_accessibility_tree is unimplemented on wayland");
+    }
+
     pub fn open_file(&mut self, _options: FileDialogOptions) -> Option<FileDialogToken> {
         tracing::warn!("unimplemented open_file");
         None
@@ -294,6 +373,10 @@ impl WindowHandle {
         self.inner.decor.set_title(title);
     }
 
+    pub fn set_represented_file(&self, _path: Option<&Path>) {}
+
+    pub fn set_document_edited(&self, _edited: bool) {}
+
     pub(super) fn run_idle(&self) {
         self.inner.surface.run_idle();
     }
@@ -321,6 +404,7 @@ impl std::default::Default for WindowHandle {
                 surface: Box::<surfaces::surface::Dead>::default(),
                 popup: Box::<surfaces::surface::Dead>::default(),
                 appdata: std::sync::Weak::new(),
+                cursor: RefCell::new(CursorStack::default()),
             }),
         }
     }
@@ -403,6 +487,10 @@ impl WindowBuilder {
         );
     }
 
+    pub fn set_background_color(&mut self, _color: Color) {
+        tracing::warn!("set_background_color is unimplemented on wayland");
+    }
+
     pub fn set_position(&mut self, position: Point) {
         self.position = Some(position);
     }
@@ -411,6 +499,18 @@ impl WindowBuilder {
         self.level = level;
     }
 
+    pub fn set_parent(&mut self, _parent: WindowHandle) {
+        tracing::warn!("set_parent is unimplemented on wayland");
+    }
+
+    pub fn set_modal(&mut self, _modal: bool) {
+        tracing::warn!("set_modal is unimplemented on wayland");
+    }
+
+    pub fn set_type(&mut self, _window_type: window::WindowType) {
+        tracing::warn!("set_type is unimplemented on wayland");
+    }
+
     pub fn set_window_state(&mut self, state: window::WindowState) {
         self.state = Some(state);
     }
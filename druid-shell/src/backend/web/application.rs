@@ -14,10 +14,25 @@
 
 //! Web implementation of features at the application scope.
 
-use crate::application::AppHandler;
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::anyhow;
+
+use crate::application::{
+    AppHandler, LayoutDirection, OsVersion, PermissionState, RunOutcome, SystemSound, WindowInfo,
+};
+use crate::keyboard::Modifiers;
+use crate::kurbo::{Point, Size};
+use crate::mouse::MouseButtons;
+use crate::piet::Color;
 
 use super::clipboard::Clipboard;
 use super::error::Error;
+use super::menu::Menu;
+
+/// Global mouse monitors aren't supported on web, so this can't be created.
+pub(crate) enum GlobalMouseMonitor {}
 
 #[derive(Clone)]
 pub(crate) struct Application;
@@ -27,17 +42,161 @@ impl Application {
         Ok(Application)
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {}
+    pub fn new_single_instance(_app_id: &str) -> Result<Application, crate::Error> {
+        tracing::warn!("new_single_instance is unimplemented for web");
+        Ok(Application::new()?)
+    }
+
+    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) -> Result<RunOutcome, anyhow::Error> {
+        Ok(RunOutcome::default())
+    }
+
+    pub fn pump_events(&self, _timeout: Option<Duration>) -> bool {
+        // The browser runs the event loop, and we can't block it.
+        tracing::warn!("pump_events is unimplemented for web");
+        true
+    }
 
     pub fn quit(&self) {}
 
+    pub fn reply_to_end_session(&self, _allow: bool) {
+        // noop, since the end of the session is never blocked here
+    }
+
+    pub fn set_menu(&self, _menu: Menu) {
+        tracing::warn!("set_menu is unimplemented for web");
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
 
+    pub fn open_uri(&self, uri: &str) -> Result<(), anyhow::Error> {
+        let window = web_sys::window().ok_or_else(|| anyhow!("no global window"))?;
+        window
+            .open_with_url_and_target(uri, "_blank")
+            .map(|_| ())
+            .map_err(|e| anyhow!("failed to open {}: {:?}", uri, e))
+    }
+
+    pub fn reveal_in_file_manager(&self, _path: &Path) -> Result<(), anyhow::Error> {
+        tracing::warn!("reveal_in_file_manager is unimplemented for web");
+        Err(anyhow!("reveal_in_file_manager is not supported on web"))
+    }
+
+    pub fn note_recent_document(&self, _path: &Path) {
+        tracing::warn!("note_recent_document is unimplemented for web");
+    }
+
+    pub fn clear_recent_documents(&self) {
+        tracing::warn!("clear_recent_documents is unimplemented for web");
+    }
+
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        None
+    }
+
+    pub fn layout_direction(&self) -> LayoutDirection {
+        LayoutDirection::from_locale(&Self::get_locale())
+    }
+
+    pub fn prefers_high_contrast(&self) -> bool {
+        false
+    }
+
+    pub fn selection_color(&self) -> Option<Color> {
+        None
+    }
+
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        None
+    }
+
+    pub fn is_active(&self) -> bool {
+        false
+    }
+
+    pub fn activate(&self, _ignore_other_apps: bool) {
+        tracing::warn!("activate is unimplemented for web");
+    }
+
+    pub fn beep(&self) {
+        tracing::warn!("beep is unimplemented for web");
+    }
+
+    pub fn play_system_sound(&self, _sound: SystemSound) {
+        tracing::warn!("play_system_sound is unimplemented for web");
+    }
+
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        None
+    }
+
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        None
+    }
+
+    pub fn max_cursor_size(&self) -> Size {
+        Size::new(f64::INFINITY, f64::INFINITY)
+    }
+
+    pub fn mouse_buttons(&self) -> MouseButtons {
+        tracing::warn!("mouse_buttons is unimplemented for web");
+        MouseButtons::new()
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        tracing::warn!("modifiers is unimplemented for web");
+        Modifiers::empty()
+    }
+
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        tracing::warn!("window_list is unimplemented for web");
+        Vec::new()
+    }
+
+    pub fn has_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn request_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn check_accessibility_permission(&self, _prompt: bool) -> PermissionState {
+        PermissionState::NotRequired
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "web"
+    }
+
+    pub fn os_version(&self) -> Option<OsVersion> {
+        None
+    }
+
+    pub fn add_global_mouse_monitor(
+        &self,
+        _callback: Box<dyn FnMut(Point)>,
+    ) -> Result<GlobalMouseMonitor, crate::Error> {
+        tracing::warn!("add_global_mouse_monitor is unimplemented for web");
+        Err(anyhow!("global mouse monitors are not supported on web").into())
+    }
+
+    pub fn run_on_main<T: Send + 'static>(
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, crate::Error> {
+        // There is only one thread on the web.
+        Ok(f())
+    }
+
     pub fn get_locale() -> String {
         web_sys::window()
             .and_then(|w| w.navigator().language())
             .unwrap_or_else(|| "en-US".into())
     }
+
+    pub fn get_locale_tag() -> String {
+        Self::get_locale()
+    }
 }
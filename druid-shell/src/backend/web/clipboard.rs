@@ -14,7 +14,9 @@
 
 //! Interactions with the browser pasteboard.
 
-use crate::clipboard::{ClipboardFormat, FormatId};
+use std::path::PathBuf;
+
+use crate::clipboard::{ClipboardFormat, DataProvider, FormatId};
 
 /// The browser clipboard.
 #[derive(Debug, Clone, Default)]
@@ -31,12 +33,28 @@ impl Clipboard {
         tracing::warn!("unimplemented");
     }
 
+    /// Put data on the system clipboard that is made when it is pasted.
+    pub fn put_lazy(&mut self, _format: FormatId, _provider: DataProvider) {
+        tracing::warn!("unimplemented");
+    }
+
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
         tracing::warn!("unimplemented");
         None
     }
 
+    /// Put a list of files onto the system clipboard.
+    pub fn put_file_list(&mut self, _paths: &[PathBuf]) {
+        tracing::warn!("unimplemented");
+    }
+
+    /// Get the list of files on the system clipboard, if there is one.
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        tracing::warn!("unimplemented");
+        None
+    }
+
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
     pub fn preferred_format(&self, _formats: &[FormatId]) -> Option<FormatId> {
@@ -16,7 +16,7 @@
 
 use web_sys::KeyboardEvent;
 
-use crate::keyboard::{Code, KbKey, KeyEvent, KeyState, Location, Modifiers};
+use crate::keyboard::{Code, KbKey, KeyEvent, KeyState, Location, ModifierSides, Modifiers};
 
 /// Convert a web-sys KeyboardEvent into a keyboard-types one.
 pub(crate) fn convert_keyboard_event(
@@ -30,6 +30,7 @@ pub(crate) fn convert_keyboard_event(
         code: convert_code(&event.code()),
         location: convert_location(event.location()),
         mods,
+        mod_sides: ModifierSides::empty(),
         repeat: event.repeat(),
         is_composing: event.is_composing(),
     }
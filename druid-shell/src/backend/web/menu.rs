@@ -53,4 +53,8 @@ impl Menu {
     pub fn add_separator(&mut self) {
         tracing::warn!("unimplemented");
     }
+
+    pub fn add_section(&mut self, _title: &str) {
+        tracing::warn!("unimplemented");
+    }
 }
@@ -14,9 +14,38 @@
 
 //! Monitor and Screen information ignored for web.
 
-use crate::screen::Monitor;
+use crate::error::Error;
+use crate::kurbo::{Point, Rect};
+use crate::piet::ImageBuf;
+use crate::screen::{DisplayMode, Monitor};
+use anyhow::anyhow;
 
 pub(crate) fn get_monitors() -> Vec<Monitor> {
     tracing::warn!("Screen::get_monitors() is not implemented for web.");
     Vec::new()
 }
+
+pub(crate) fn num_monitors() -> usize {
+    tracing::warn!("Screen::num_monitors() is not implemented for web.");
+    0
+}
+
+pub(crate) fn get_mouse_position() -> Option<Point> {
+    tracing::warn!("Screen::get_mouse_position() is not implemented for web.");
+    None
+}
+
+pub(crate) fn capture_region(_rect: Rect) -> Result<ImageBuf, Error> {
+    tracing::warn!("Screen::capture_region() is not implemented for web.");
+    Err(anyhow!("screen capture is not supported on web").into())
+}
+
+pub(crate) fn display_modes(_monitor: &Monitor) -> Vec<DisplayMode> {
+    tracing::warn!("Monitor::available_modes() is not implemented for web.");
+    Vec::new()
+}
+
+pub(crate) fn set_mode(_monitor: &Monitor, _mode: &DisplayMode) -> Result<(), Error> {
+    tracing::warn!("Monitor::set_mode() is not implemented for web.");
+    Err(anyhow!("changing the display mode is not supported on web").into())
+}
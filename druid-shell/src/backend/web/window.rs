@@ -16,6 +16,7 @@
 
 use std::cell::{Cell, RefCell};
 use std::ffi::OsString;
+use std::path::Path;
 use std::rc::{Rc, Weak};
 use std::sync::{Arc, Mutex};
 
@@ -29,19 +30,20 @@ use raw_window_handle::{HasRawWindowHandle, RawWindowHandle, WebWindowHandle};
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
 
-use crate::piet::{PietText, RenderContext};
+use crate::piet::{Color, PietText, RenderContext};
 
 use super::application::Application;
 use super::error::Error;
 use super::keycodes::convert_keyboard_event;
 use super::menu::Menu;
-use crate::common_util::{ClickCounter, IdleCallback};
+use crate::accessibility::AccessibilityTree;
+use crate::common_util::{ClickCounter, CursorStack, IdleCallback};
 use crate::dialog::{FileDialogOptions, FileDialogType};
 use crate::error::Error as ShellError;
 use crate::scale::{Scale, ScaledArea};
 
 use crate::keyboard::{KeyState, Modifiers};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::region::Region;
 use crate::text::{simulate_input, Event};
 use crate::window;
@@ -124,6 +126,7 @@ struct WindowState {
     click_counter: ClickCounter,
     active_text_input: Cell<Option<TextFieldToken>>,
     rendering_soon: Cell<bool>,
+    cursor: RefCell<CursorStack>,
 }
 
 // TODO: support custom cursors
@@ -211,6 +214,10 @@ fn setup_mouse_down_callback(ws: &Rc<WindowState>) {
                 focus: false,
                 button,
                 wheel_delta: Vec2::ZERO,
+                pointer_type: PointerType::Mouse,
+                pressure: buttons.default_pressure(),
+                tilt_x: 0.0,
+                tilt_y: 0.0,
             };
             state.handler.borrow_mut().mouse_down(&event);
         }
@@ -230,6 +237,10 @@ fn setup_mouse_up_callback(ws: &Rc<WindowState>) {
                 focus: false,
                 button,
                 wheel_delta: Vec2::ZERO,
+                pointer_type: PointerType::Mouse,
+                pressure: buttons.default_pressure(),
+                tilt_x: 0.0,
+                tilt_y: 0.0,
             };
             state.handler.borrow_mut().mouse_up(&event);
         }
@@ -248,6 +259,10 @@ fn setup_mouse_move_callback(ws: &Rc<WindowState>) {
             focus: false,
             button: MouseButton::None,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         };
         state.handler.borrow_mut().mouse_move(&event);
     });
@@ -275,14 +290,19 @@ fn setup_scroll_callback(ws: &Rc<WindowState>) {
             }
         };
 
+        let buttons = mouse_buttons(event.buttons());
         let event = MouseEvent {
             pos: Point::new(event.offset_x() as f64, event.offset_y() as f64),
-            buttons: mouse_buttons(event.buttons()),
+            buttons,
             mods: get_modifiers!(event),
             count: 0,
             focus: false,
             button: MouseButton::None,
             wheel_delta,
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         };
         state.handler.borrow_mut().wheel(&event);
     });
@@ -392,6 +412,10 @@ impl WindowBuilder {
         // Ignored
     }
 
+    pub fn set_background_color(&mut self, _color: Color) {
+        // Ignored
+    }
+
     pub fn set_position(&mut self, _position: Point) {
         // Ignored
     }
@@ -408,6 +432,18 @@ impl WindowBuilder {
         // ignored
     }
 
+    pub fn set_parent(&mut self, _parent: WindowHandle) {
+        // ignored
+    }
+
+    pub fn set_modal(&mut self, _modal: bool) {
+        // ignored
+    }
+
+    pub fn set_type(&mut self, _window_type: window::WindowType) {
+        // ignored
+    }
+
     pub fn set_title<S: Into<String>>(&mut self, title: S) {
         self.title = title.into();
     }
@@ -475,6 +511,7 @@ impl WindowBuilder {
             click_counter: ClickCounter::default(),
             active_text_input: Cell::new(None),
             rendering_soon: Cell::new(false),
+            cursor: RefCell::new(CursorStack::default()),
         });
 
         setup_web_callbacks(&window);
@@ -501,8 +538,12 @@ impl WindowHandle {
         self.render_soon();
     }
 
-    pub fn resizable(&self, _resizable: bool) {
-        warn!("resizable unimplemented for web");
+    pub fn set_resizable(&self, _resizable: bool) {
+        warn!("set_resizable unimplemented for web");
+    }
+
+    pub fn set_aspect_ratio(&self, _ratio: Option<f64>) {
+        warn!("WindowHandle::set_aspect_ratio unimplemented for web");
     }
 
     pub fn show_titlebar(&self, _show_titlebar: bool) {
@@ -521,6 +562,26 @@ impl WindowHandle {
         warn!("WindowHandle::set_always_on_top unimplemented for web");
     }
 
+    pub fn set_always_on_bottom(&self, _on_bottom: bool) {
+        warn!("WindowHandle::set_always_on_bottom unimplemented for web");
+    }
+
+    pub fn set_level(&self, _level: WindowLevel) {
+        warn!("WindowHandle::set_level unimplemented for web");
+    }
+
+    pub fn set_dismiss_on_focus_loss(&self, _dismiss: bool) {
+        warn!("WindowHandle::set_dismiss_on_focus_loss unimplemented for web");
+    }
+
+    pub fn set_opacity(&self, _opacity: f64) {
+        warn!("WindowHandle::set_opacity unimplemented for web");
+    }
+
+    pub fn set_background_color(&self, _color: Color) {
+        warn!("WindowHandle::set_background_color unimplemented for web");
+    }
+
     pub fn get_position(&self) -> Point {
         warn!("WindowHandle::get_position unimplemented for web.");
         Point::new(0.0, 0.0)
@@ -585,6 +646,12 @@ impl WindowHandle {
         self.render_soon();
     }
 
+    pub fn paint_now(&self) {
+        if let Some(s) = self.0.upgrade() {
+            s.render();
+        }
+    }
+
     pub fn text(&self) -> PietText {
         let s = self
             .0
@@ -616,6 +683,14 @@ impl WindowHandle {
         // no-op for now, until we get a properly implemented text input
     }
 
+    pub fn set_ime_enabled(&self, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
+    pub fn set_text_field_ime_enabled(&self, _token: TextFieldToken, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
     pub fn request_timer(&self, deadline: Instant) -> TimerToken {
         let interval = deadline.duration_since(Instant::now()).as_millis();
         let interval = match i32::try_from(interval) {
@@ -648,7 +723,25 @@ impl WindowHandle {
 
     pub fn set_cursor(&mut self, cursor: &Cursor) {
         if let Some(s) = self.0.upgrade() {
-            set_cursor(&s.canvas, cursor);
+            if s.cursor.borrow_mut().set(cursor) {
+                set_cursor(&s.canvas, cursor);
+            }
+        }
+    }
+
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        if let Some(s) = self.0.upgrade() {
+            set_cursor(&s.canvas, &cursor);
+            s.cursor.borrow_mut().push(cursor);
+        }
+    }
+
+    pub fn pop_cursor(&mut self) {
+        if let Some(s) = self.0.upgrade() {
+            let mut stack = s.cursor.borrow_mut();
+            if stack.pop() {
+                set_cursor(&s.canvas, stack.current().unwrap_or(&Cursor::Arrow));
+            }
         }
     }
 
@@ -657,6 +750,22 @@ impl WindowHandle {
         None
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("WindowHandle::set_cursor_visible unimplemented for web");
+    }
+
+    pub fn confine_cursor(&self, _confine: bool) {
+        warn!("WindowHandle::confine_cursor unimplemented for web");
+    }
+
+    pub fn announce(&self, _message: &str, _politeness: window::Politeness) {
+        warn!("WindowHandle::announce unimplemented for web");
+    }
+
+    pub fn set_accessibility_tree(&self, _tree: AccessibilityTree) {
+        warn!("WindowHandle::set_accessibility_tree unimplemented for web");
+    }
+
     pub fn open_file(&mut self, _options: FileDialogOptions) -> Option<FileDialogToken> {
         warn!("open_file is currently unimplemented for web.");
         None
@@ -720,6 +829,10 @@ impl WindowHandle {
             state.canvas.set_title(&(title.into()))
         }
     }
+
+    pub fn set_represented_file(&self, _path: Option<&Path>) {}
+
+    pub fn set_document_edited(&self, _edited: bool) {}
 }
 
 unsafe impl Send for IdleHandle {}
@@ -809,6 +922,8 @@ fn set_cursor(canvas: &web_sys::HtmlCanvasElement, cursor: &Cursor) {
                 Cursor::NotAllowed => "not-allowed",
                 Cursor::ResizeLeftRight => "ew-resize",
                 Cursor::ResizeUpDown => "ns-resize",
+                Cursor::Wait => "wait",
+                Cursor::Progress => "progress",
                 // TODO: support custom cursors
                 Cursor::Custom(_) => "default",
             },
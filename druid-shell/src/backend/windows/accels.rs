@@ -15,10 +15,12 @@
 //! Wrappers for Windows of Accelerate Table.
 
 use std::collections::HashMap;
+use std::ptr::null_mut;
 use std::sync::{Arc, Mutex};
 
 use once_cell::sync::Lazy;
 use winapi::ctypes::c_int;
+use winapi::shared::minwindef::{BYTE, WORD};
 use winapi::shared::windef::*;
 use winapi::um::winuser::*;
 
@@ -57,6 +59,26 @@ impl AccelTable {
     pub(crate) fn handle(&self) -> HACCEL {
         self.accel.0
     }
+
+    /// Returns the command of the accelerator for the virtual key `key` with exactly the
+    /// `FSHIFT`, `FCONTROL` and `FALT` flags in `mods`, if there is one.
+    pub(crate) fn find_command(&self, mods: BYTE, key: WORD) -> Option<WORD> {
+        let entries = unsafe {
+            let len = CopyAcceleratorTableW(self.accel.0, null_mut(), 0);
+            let mut entries = Vec::with_capacity(len.max(0) as usize);
+            let len = CopyAcceleratorTableW(self.accel.0, entries.as_mut_ptr(), len);
+            entries.set_len(len.max(0) as usize);
+            entries
+        };
+        entries
+            .iter()
+            .find(|accel: &&ACCEL| {
+                accel.fVirt & FVIRTKEY != 0
+                    && accel.fVirt & (FSHIFT | FCONTROL | FALT) == mods
+                    && accel.key == key
+            })
+            .map(|accel| accel.cmd)
+    }
 }
 
 pub(crate) fn register_accel(hwnd: HWND, accel: &[ACCEL]) {
@@ -16,35 +16,96 @@
 
 use std::cell::RefCell;
 use std::collections::HashSet;
+use std::ffi::OsStr;
 use std::mem;
+use std::path::{Path, PathBuf};
 use std::ptr;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::slice;
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::time::Duration;
 
-use winapi::shared::minwindef::{FALSE, HINSTANCE};
+use anyhow::anyhow;
+use winapi::ctypes::c_int;
+use winapi::shared::minwindef::{
+    BOOL, DWORD, FALSE, HINSTANCE, LPARAM, LRESULT, MAX_PATH, TRUE, UINT, WPARAM,
+};
 use winapi::shared::ntdef::LPCWSTR;
-use winapi::shared::windef::{DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, HCURSOR, HWND};
-use winapi::shared::winerror::HRESULT_FROM_WIN32;
+use winapi::shared::ntstatus::STATUS_SUCCESS;
+use winapi::shared::windef::{
+    DPI_AWARENESS_CONTEXT_PER_MONITOR_AWARE_V2, HCURSOR, HHOOK, HWND, HWND__, RECT,
+};
+use winapi::shared::winerror::{ERROR_ALREADY_EXISTS, HRESULT_FROM_WIN32, SUCCEEDED};
+use winapi::um::combaseapi::{CoInitializeEx, CoUninitialize};
+use winapi::um::dwmapi::DwmGetWindowAttribute;
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::handleapi::CloseHandle;
 use winapi::um::libloaderapi::GetModuleHandleW;
+use winapi::um::objbase::COINIT_APARTMENTTHREADED;
+use winapi::um::processthreadsapi::{GetCurrentProcessId, OpenProcess};
+use winapi::um::shellapi::{
+    ShellExecuteW, SE_ERR_ACCESSDENIED, SE_ERR_ASSOCINCOMPLETE, SE_ERR_DDEBUSY, SE_ERR_DDEFAIL,
+    SE_ERR_DDETIMEOUT, SE_ERR_DLLNOTFOUND, SE_ERR_FNF, SE_ERR_NOASSOC, SE_ERR_OOM, SE_ERR_PNF,
+    SE_ERR_SHARE,
+};
 use winapi::um::shellscalingapi::PROCESS_PER_MONITOR_DPI_AWARE;
-use winapi::um::winnls::GetUserDefaultLocaleName;
-use winapi::um::winnt::LOCALE_NAME_MAX_LENGTH;
+use winapi::um::shlobj::{SHAddToRecentDocs, SHOpenFolderAndSelectItems, SHARD_PATHW};
+use winapi::um::shtypes::PIDLIST_ABSOLUTE;
+use winapi::um::synchapi::CreateMutexW;
+use winapi::um::sysinfoapi::GetTickCount;
+use winapi::um::winbase::{
+    GetSystemPowerStatus, QueryFullProcessImageNameW, INFINITE, SYSTEM_POWER_STATUS,
+};
+use winapi::um::wingdi::{GetBValue, GetGValue, GetRValue, LAYOUT_RTL};
+use winapi::um::winnls::{GetLocaleInfoEx, GetUserDefaultLocaleName};
+use winapi::um::winnt::{
+    LOCALE_NAME_MAX_LENGTH, OSVERSIONINFOW, PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use winapi::um::winreg::HKEY_LOCAL_MACHINE;
 use winapi::um::winuser::{
-    DispatchMessageW, GetAncestor, GetMessageW, LoadIconW, PeekMessageW, PostMessageW,
-    PostQuitMessage, RegisterClassW, TranslateAcceleratorW, TranslateMessage, GA_ROOT,
-    MAKEINTRESOURCEW, MSG, PM_NOREMOVE, WM_TIMER, WNDCLASSW,
+    AllowSetForegroundWindow, CallNextHookEx, CreateWindowExW, DefWindowProcW, DestroyWindow,
+    DispatchMessageW, EnumWindows, FindWindowW, GetAncestor, GetAsyncKeyState, GetForegroundWindow,
+    GetKeyboardLayoutNameW, GetLastInputInfo, GetMessageW, GetProcessDefaultLayout, GetSysColor,
+    GetSystemMetrics, GetWindow, GetWindowLongPtrW, GetWindowLongW, GetWindowRect,
+    GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindowVisible,
+    LoadIconW, MessageBeep, MsgWaitForMultipleObjectsEx, PeekMessageW, PostMessageW,
+    PostQuitMessage, RegisterClassW, SendMessageTimeoutW, SendMessageW, SetForegroundWindow,
+    SetWindowLongPtrW, SetWindowsHookExW, ShowWindow, SystemParametersInfoW, TranslateAcceleratorW,
+    TranslateMessage, UnhookWindowsHookEx, COLOR_HIGHLIGHT, COLOR_WINDOWTEXT, COPYDATASTRUCT,
+    CREATESTRUCTW, GA_ROOT, GWLP_USERDATA, GWL_EXSTYLE, GW_OWNER, HCF_HIGHCONTRASTON, HC_ACTION,
+    HIGHCONTRASTW, KL_NAMELENGTH, LASTINPUTINFO, MAKEINTRESOURCEW, MB_ICONERROR,
+    MB_ICONINFORMATION, MB_ICONWARNING, MB_OK, MSG, MSLLHOOKSTRUCT, MWMO_INPUTAVAILABLE,
+    PBT_APMPOWERSTATUSCHANGE, PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND, PM_NOREMOVE, PM_REMOVE,
+    QS_ALLINPUT, SMTO_ABORTIFHUNG, SM_CMOUSEBUTTONS, SM_SWAPBUTTON, SPI_GETHIGHCONTRAST,
+    SPI_SETFONTSMOOTHING, SPI_SETFONTSMOOTHINGORIENTATION, SPI_SETFONTSMOOTHINGTYPE,
+    SPI_SETHIGHCONTRAST, SPI_SETWORKAREA, SW_RESTORE, SW_SHOWNORMAL, VK_LBUTTON, VK_MBUTTON,
+    VK_RBUTTON, VK_XBUTTON1, VK_XBUTTON2, WH_MOUSE_LL, WM_ACTIVATEAPP, WM_COPYDATA, WM_CREATE,
+    WM_DESTROYCLIPBOARD, WM_DISPLAYCHANGE, WM_ENDSESSION, WM_LBUTTONDOWN, WM_MBUTTONDOWN,
+    WM_MOUSEMOVE, WM_NCDESTROY, WM_POWERBROADCAST, WM_QUERYENDSESSION, WM_QUIT, WM_RBUTTONDOWN,
+    WM_RENDERALLFORMATS, WM_RENDERFORMAT, WM_SETTINGCHANGE, WM_TIMER, WM_USER,
+    WM_WTSSESSION_CHANGE, WM_XBUTTONDOWN, WNDCLASSW, WS_EX_TOOLWINDOW, WTS_SESSION_LOCK,
+    WTS_SESSION_UNLOCK,
 };
 
 use piet_common::D2DLoadedFonts;
 
-use crate::application::AppHandler;
+use crate::application::{
+    is_url, AppHandler, EndSessionResponse, ExternalWindowId, LayoutDirection, OsVersion,
+    PermissionState, PowerSource, RunOutcome, SystemSound, WindowInfo,
+};
+use crate::keyboard::Modifiers;
+use crate::kurbo::{Point, Rect, Size};
+use crate::mouse::{MouseButton, MouseButtons};
+use crate::piet::Color;
+use crate::Error as ShellError;
 
 use super::accels;
-use super::clipboard::Clipboard;
+use super::clipboard::{self, Clipboard};
 use super::error::Error;
-use super::util::{self, FromWide, ToWide, CLASS_NAME, OPTIONAL_FUNCTIONS};
-use super::window::{self, DS_REQUEST_DESTROY};
+use super::keyboard;
+use super::menu::Menu;
+use super::util::{self, FromWide, ToWide, APP_CLASS_NAME, CLASS_NAME, OPTIONAL_FUNCTIONS};
+use super::window::{self, WindowLongPtr, DS_REQUEST_DESTROY};
 
 #[derive(Clone)]
 pub(crate) struct Application {
@@ -55,22 +116,130 @@ pub(crate) struct Application {
 struct State {
     quitting: bool,
     windows: HashSet<HWND>,
+    /// The hidden application window, while the run loop is running.
+    app_hwnd: HWND,
+    /// The id given to [`Application::new_single_instance`], which is used as the title
+    /// of the app window so that other instances can find it.
+    instance_id: Option<String>,
+}
+
+/// The state of the hidden application window.
+///
+/// Some notifications, such as power broadcasts and session changes, are only sent to
+/// top-level windows.
+/// We create a hidden one for the lifetime of the run loop, so that the [`AppHandler`]
+/// receives them even when the application has no windows open.
+struct AppWindowState {
+    handler: Option<Box<dyn AppHandler>>,
+    power_source: Option<PowerSource>,
+    high_contrast: bool,
 }
 
+/// Sent to the app window when the keyboard layout of one of our windows changes.
+const DS_KEYBOARD_LAYOUT_CHANGED: UINT = WM_USER;
+
+/// Posted to the app window when the message loop starts, to open the files on the command line.
+const DS_OPEN_LAUNCH_FILES: UINT = WM_USER + 1;
+
+/// Sent to the app window from other threads, with a `*mut &mut dyn FnMut()` to run in `lParam`.
+const DS_RUN_ON_MAIN: UINT = WM_USER + 2;
+
+/// The app window while the message loop runs, so that other threads can send it messages.
+static APP_HWND: AtomicPtr<HWND__> = AtomicPtr::new(ptr::null_mut());
+
+/// Returns the app window, which is null when the message loop isn't running.
+pub(crate) fn app_hwnd() -> HWND {
+    APP_HWND.load(Ordering::Acquire)
+}
+
+/// Identifies the `WM_COPYDATA` messages with files forwarded by another instance.
+const DS_COPYDATA_OPEN_FILES: usize = 0x4453_4f46;
+
+/// How many times we look for the app window of the running instance, see
+/// [`forward_launch_files`].
+const FIND_INSTANCE_ATTEMPTS: u32 = 20;
+
+/// This is missing from winapi.
+const DWMWA_CLOAKED: DWORD = 14;
+
+/// This is missing from winapi.
+const LOCALE_IREADINGLAYOUT: DWORD = 0x0000_0070;
+
+/// Only receive session notifications for the session we're running in.
+const NOTIFY_FOR_THIS_SESSION: DWORD = 0;
+
 /// Used to ensure the window class is registered only once per process.
 static WINDOW_CLASS_REGISTERED: AtomicBool = AtomicBool::new(false);
 
+type MouseCallback = Rc<RefCell<Box<dyn FnMut(Point)>>>;
+
+/// The low-level mouse hook, and the callbacks of the global mouse monitors using it.
+///
+/// Hook procedures don't get any user data, so this is where they find the callbacks.
+#[derive(Default)]
+struct MouseMonitors {
+    hook: Option<HHOOK>,
+    callbacks: Vec<MouseCallback>,
+}
+
+thread_local! {
+    static MOUSE_MONITORS: RefCell<MouseMonitors> = RefCell::new(MouseMonitors::default());
+}
+
+/// A callback that is called by the low-level mouse hook, until this is dropped.
+pub(crate) struct GlobalMouseMonitor {
+    callback: MouseCallback,
+}
+
+impl Drop for GlobalMouseMonitor {
+    fn drop(&mut self) {
+        MOUSE_MONITORS.with(|monitors| {
+            let mut monitors = monitors.borrow_mut();
+            monitors
+                .callbacks
+                .retain(|callback| !Rc::ptr_eq(callback, &self.callback));
+            if monitors.callbacks.is_empty() {
+                if let Some(hook) = monitors.hook.take() {
+                    unsafe { UnhookWindowsHookEx(hook) };
+                }
+            }
+        });
+    }
+}
+
 impl Application {
     pub fn new() -> Result<Application, Error> {
         Application::init()?;
         let state = Rc::new(RefCell::new(State {
             quitting: false,
             windows: HashSet::new(),
+            app_hwnd: ptr::null_mut(),
+            instance_id: None,
         }));
         let fonts = D2DLoadedFonts::default();
         Ok(Application { state, fonts })
     }
 
+    pub fn new_single_instance(app_id: &str) -> Result<Application, ShellError> {
+        let app = Application::new()?;
+        // Backslashes aren't allowed in the name, other than after the namespace.
+        let mutex_name = format!("Local\\{}", app_id.replace('\\', "/")).to_wide();
+        unsafe {
+            // The mutex is never closed, so it is released when the process exits.
+            let mutex = CreateMutexW(ptr::null_mut(), FALSE, mutex_name.as_ptr());
+            if mutex.is_null() {
+                return Err(Error::Hr(HRESULT_FROM_WIN32(GetLastError())).into());
+            }
+            if GetLastError() == ERROR_ALREADY_EXISTS {
+                CloseHandle(mutex);
+                forward_launch_files(app_id);
+                return Err(ShellError::AlreadyRunning);
+            }
+        }
+        app.state.borrow_mut().instance_id = Some(app_id.to_owned());
+        Ok(app)
+    }
+
     /// Initialize the app. At the moment, this is mostly needed for hi-dpi.
     // TODO: Report back an error instead of panicking
     #[allow(clippy::unnecessary_wraps)]
@@ -108,6 +277,24 @@ impl Application {
             if class_atom == 0 {
                 panic!("Error registering class");
             }
+
+            let app_class_name = APP_CLASS_NAME.to_wide();
+            let app_wnd = WNDCLASSW {
+                style: 0,
+                lpfnWndProc: Some(app_win_proc),
+                cbClsExtra: 0,
+                cbWndExtra: 0,
+                hInstance: 0 as HINSTANCE,
+                hIcon: ptr::null_mut(),
+                hCursor: 0 as HCURSOR,
+                hbrBackground: ptr::null_mut(),
+                lpszMenuName: 0 as LPCWSTR,
+                lpszClassName: app_class_name.as_ptr(),
+            };
+            let class_atom = unsafe { RegisterClassW(&app_wnd) };
+            if class_atom == 0 {
+                panic!("Error registering app class");
+            }
         }
         Ok(())
     }
@@ -120,8 +307,17 @@ impl Application {
         self.state.borrow_mut().windows.remove(&hwnd)
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {
+    pub fn run(self, handler: Option<Box<dyn AppHandler>>) -> Result<RunOutcome, anyhow::Error> {
         unsafe {
+            let instance_id = self.state.borrow().instance_id.clone();
+            let app_hwnd = create_app_window(handler, instance_id.as_deref());
+            let mut result = Ok(0);
+            self.state.borrow_mut().app_hwnd = app_hwnd;
+            APP_HWND.store(app_hwnd, Ordering::Release);
+            if !app_hwnd.is_null() {
+                PostMessageW(app_hwnd, DS_OPEN_LAUNCH_FILES, 0, 0);
+            }
+
             // Handle windows messages.
             //
             // NOTE: Code here will not run when we aren't in charge of the message loop. That
@@ -142,25 +338,59 @@ impl Application {
 
                 let res = GetMessageW(msg.as_mut_ptr(), ptr::null_mut(), 0, 0);
                 if res <= 0 {
-                    if res == -1 {
-                        tracing::error!(
+                    result = if res == -1 {
+                        Err(anyhow!(
                             "GetMessageW failed: {}",
                             Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
-                        );
-                    }
+                        ))
+                    } else {
+                        // This is WM_QUIT, which carries the code given to PostQuitMessage.
+                        Ok(msg.assume_init().wParam as i32)
+                    };
                     break;
                 }
+                dispatch_message(&mut msg.assume_init());
+            }
+
+            self.state.borrow_mut().app_hwnd = ptr::null_mut();
+            APP_HWND.store(ptr::null_mut(), Ordering::Release);
+            if !app_hwnd.is_null() {
+                if let Some(func) = OPTIONAL_FUNCTIONS.WTSUnRegisterSessionNotification {
+                    func(app_hwnd);
+                }
+                DestroyWindow(app_hwnd);
+            }
+            let quit_requested = self.state.borrow().quitting;
+            result.map(|exit_code| RunOutcome::new(exit_code, quit_requested))
+        }
+    }
+
+    pub fn pump_events(&self, timeout: Option<Duration>) -> bool {
+        // Round up, so that a timeout below a millisecond doesn't become a busy loop.
+        let timeout_ms = timeout.map_or(INFINITE, |timeout| {
+            let ms = (timeout.as_nanos() + 999_999) / 1_000_000;
+            ms.min(INFINITE as u128 - 1) as DWORD
+        });
+        unsafe {
+            // This returns as soon as there is input in the queue, even input that
+            // an earlier peek has already seen.
+            MsgWaitForMultipleObjectsEx(
+                0,
+                ptr::null(),
+                timeout_ms,
+                QS_ALLINPUT,
+                MWMO_INPUTAVAILABLE,
+            );
+            let mut msg = mem::MaybeUninit::uninit();
+            while PeekMessageW(msg.as_mut_ptr(), ptr::null_mut(), 0, 0, PM_REMOVE) != 0 {
                 let mut msg: MSG = msg.assume_init();
-                let accels = accels::find_accels(GetAncestor(msg.hwnd, GA_ROOT));
-                let translated = accels.map_or(false, |it| {
-                    TranslateAcceleratorW(msg.hwnd, it.handle(), &mut msg) != 0
-                });
-                if !translated {
-                    TranslateMessage(&msg);
-                    DispatchMessageW(&msg);
+                if msg.message == WM_QUIT {
+                    return false;
                 }
+                dispatch_message(&mut msg);
             }
         }
+        !self.state.borrow().quitting
     }
 
     pub fn quit(&self) {
@@ -190,10 +420,351 @@ impl Application {
         }
     }
 
+    pub fn reply_to_end_session(&self, _allow: bool) {
+        // The session end was cancelled when we blocked it, so all we can do is
+        // take the reason down.
+        unblock_end_session(app_hwnd());
+    }
+
+    pub fn set_menu(&self, _menu: Menu) {
+        // Menus belong to windows here, so there is nothing to put an application menu on.
+        tracing::warn!("Windows has no application menu; set the menu on each window instead");
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         Clipboard
     }
 
+    pub fn open_uri(&self, uri: &str) -> Result<(), anyhow::Error> {
+        shell_execute("open", uri)
+    }
+
+    pub fn reveal_in_file_manager(&self, path: &Path) -> Result<(), anyhow::Error> {
+        let path_wide = path.as_os_str().to_wide();
+        unsafe {
+            // The shell needs COM, which this thread may or may not have initialized already.
+            let com = CoInitializeEx(ptr::null_mut(), COINIT_APARTMENTTHREADED);
+            let pidl = ILCreateFromPathW(path_wide.as_ptr());
+            let result = if pidl.is_null() {
+                Err(anyhow!("failed to reveal {}: no such file", path.display()))
+            } else {
+                // Without child items, this opens the item's folder and selects the item.
+                let hr = SHOpenFolderAndSelectItems(pidl, 0, ptr::null(), 0);
+                ILFree(pidl);
+                if SUCCEEDED(hr) {
+                    Ok(())
+                } else {
+                    Err(anyhow!(
+                        "failed to reveal {}: {}",
+                        path.display(),
+                        Error::Hr(hr)
+                    ))
+                }
+            };
+            if SUCCEEDED(com) {
+                CoUninitialize();
+            }
+            result
+        }
+    }
+
+    pub fn note_recent_document(&self, path: &Path) {
+        let path = path.as_os_str().to_wide();
+        unsafe {
+            SHAddToRecentDocs(SHARD_PATHW, path.as_ptr() as *const _);
+        }
+    }
+
+    pub fn clear_recent_documents(&self) {
+        unsafe {
+            SHAddToRecentDocs(SHARD_PATHW, ptr::null());
+        }
+    }
+
+    /// Notifies the [`AppHandler`] of a keyboard layout change.
+    ///
+    /// `WM_INPUTLANGCHANGE` is only sent to the focused window, which forwards it here.
+    pub fn keyboard_layout_changed(&self) {
+        let app_hwnd = self.state.borrow().app_hwnd;
+        if !app_hwnd.is_null() {
+            unsafe { SendMessageW(app_hwnd, DS_KEYBOARD_LAYOUT_CHANGED, 0, 0) };
+        }
+    }
+
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        let mut klid = [0u16; KL_NAMELENGTH];
+        if unsafe { GetKeyboardLayoutNameW(klid.as_mut_ptr()) } == FALSE {
+            return None;
+        }
+        let klid = klid.split(|c| *c == 0).next()?.to_string()?;
+        util::reg_get_string(
+            HKEY_LOCAL_MACHINE,
+            &format!(
+                "SYSTEM\\CurrentControlSet\\Control\\Keyboard Layouts\\{}",
+                klid
+            ),
+            "Layout Text",
+        )
+    }
+
+    pub fn layout_direction(&self) -> LayoutDirection {
+        let mut layout: DWORD = 0;
+        if unsafe { GetProcessDefaultLayout(&mut layout) } != FALSE && layout & LAYOUT_RTL != 0 {
+            return LayoutDirection::RightToLeft;
+        }
+        // The reading layout is "1" for right-to-left locales.
+        let mut buf = [0u16; 2];
+        let len = unsafe {
+            GetLocaleInfoEx(
+                ptr::null(),
+                LOCALE_IREADINGLAYOUT,
+                buf.as_mut_ptr(),
+                buf.len() as _,
+            )
+        };
+        if len > 0 && buf[0] == u16::from(b'1') {
+            LayoutDirection::RightToLeft
+        } else {
+            LayoutDirection::LeftToRight
+        }
+    }
+
+    pub fn prefers_high_contrast(&self) -> bool {
+        prefers_high_contrast()
+    }
+
+    pub fn selection_color(&self) -> Option<Color> {
+        Some(system_color(COLOR_HIGHLIGHT))
+    }
+
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        // The caret is drawn by inverting, which comes out as the text color on a normal background.
+        Some(system_color(COLOR_WINDOWTEXT))
+    }
+
+    pub fn is_active(&self) -> bool {
+        unsafe { is_foreground_process(GetForegroundWindow()) }
+    }
+
+    pub fn activate(&self, ignore_other_apps: bool) {
+        unsafe {
+            let foreground = GetForegroundWindow();
+            if !ignore_other_apps && !foreground.is_null() && !is_foreground_process(foreground) {
+                return;
+            }
+            let hwnd = self
+                .state
+                .borrow()
+                .windows
+                .iter()
+                .copied()
+                .find(|&hwnd| IsWindowVisible(hwnd) != FALSE);
+            let hwnd = match hwnd {
+                Some(hwnd) => hwnd,
+                None => return,
+            };
+            if IsIconic(hwnd) != FALSE {
+                ShowWindow(hwnd, SW_RESTORE);
+            }
+            // If Windows doesn't let us take the focus, it flashes the taskbar button instead.
+            SetForegroundWindow(hwnd);
+        }
+    }
+
+    pub fn beep(&self) {
+        unsafe {
+            MessageBeep(MB_OK);
+        }
+    }
+
+    pub fn play_system_sound(&self, sound: SystemSound) {
+        let sound = match sound {
+            SystemSound::Alert => MB_ICONWARNING,
+            SystemSound::Error => MB_ICONERROR,
+            SystemSound::Info => MB_ICONINFORMATION,
+        };
+        unsafe {
+            MessageBeep(sound);
+        }
+    }
+
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        unsafe {
+            let mut info = LASTINPUTINFO {
+                cbSize: mem::size_of::<LASTINPUTINFO>() as UINT,
+                dwTime: 0,
+            };
+            if GetLastInputInfo(&mut info) == FALSE {
+                return None;
+            }
+            // Both are in milliseconds since boot, and wrap around after 49.7 days.
+            let millis = GetTickCount().wrapping_sub(info.dwTime);
+            Some(Duration::from_millis(millis as u64))
+        }
+    }
+
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        // This is zero if there is no mouse.
+        let count = unsafe { GetSystemMetrics(SM_CMOUSEBUTTONS) };
+        Some(count as u32)
+    }
+
+    pub fn max_cursor_size(&self) -> Size {
+        let (width, height) = util::cursor_size(util::system_dpi());
+        Size::new(width as f64, height as f64)
+    }
+
+    pub fn mouse_buttons(&self) -> MouseButtons {
+        // These are the physical buttons, so the left and right ones can be swapped.
+        let swapped = unsafe { GetSystemMetrics(SM_SWAPBUTTON) } != 0;
+        let (left, right) = if swapped {
+            (VK_RBUTTON, VK_LBUTTON)
+        } else {
+            (VK_LBUTTON, VK_RBUTTON)
+        };
+        let mut buttons = MouseButtons::new();
+        for (vk, button) in [
+            (left, MouseButton::Left),
+            (right, MouseButton::Right),
+            (VK_MBUTTON, MouseButton::Middle),
+            (VK_XBUTTON1, MouseButton::X1),
+            (VK_XBUTTON2, MouseButton::X2),
+        ] {
+            if unsafe { GetAsyncKeyState(vk) } < 0 {
+                buttons.insert(button);
+            }
+        }
+        buttons
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        keyboard::modifier_state()
+    }
+
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        unsafe extern "system" fn push_window(hwnd: HWND, lparam: LPARAM) -> BOOL {
+            let windows = &mut *(lparam as *mut Vec<HWND>);
+            windows.push(hwnd);
+            TRUE
+        }
+        // This lists the top-level windows from front to back.
+        let mut windows: Vec<HWND> = Vec::new();
+        unsafe {
+            EnumWindows(Some(push_window), &mut windows as *mut Vec<HWND> as LPARAM);
+            let current_process = GetCurrentProcessId();
+            windows
+                .into_iter()
+                .filter(|&hwnd| is_app_window(hwnd))
+                .filter_map(|hwnd| {
+                    let mut process_id = 0;
+                    GetWindowThreadProcessId(hwnd, &mut process_id);
+                    if process_id == current_process {
+                        return None;
+                    }
+                    let mut rect: RECT = mem::zeroed();
+                    if GetWindowRect(hwnd, &mut rect) == FALSE {
+                        return None;
+                    }
+                    Some(WindowInfo {
+                        id: ExternalWindowId::from_raw(hwnd as u64),
+                        title: window_text(hwnd),
+                        owner_name: process_name(process_id).unwrap_or_default(),
+                        bounds: Rect::new(
+                            rect.left as f64,
+                            rect.top as f64,
+                            rect.right as f64,
+                            rect.bottom as f64,
+                        ),
+                    })
+                })
+                .collect()
+        }
+    }
+
+    pub fn has_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn request_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn check_accessibility_permission(&self, _prompt: bool) -> PermissionState {
+        PermissionState::NotRequired
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "windows"
+    }
+
+    pub fn os_version(&self) -> Option<OsVersion> {
+        // `GetVersionEx` pretends to be Windows 8 for applications without a manifest,
+        // so we ask the kernel instead.
+        let func = OPTIONAL_FUNCTIONS.RtlGetVersion?;
+        let mut info: OSVERSIONINFOW = unsafe { mem::zeroed() };
+        info.dwOSVersionInfoSize = mem::size_of::<OSVERSIONINFOW>() as DWORD;
+        if unsafe { func(&mut info) } != STATUS_SUCCESS {
+            return None;
+        }
+        Some(OsVersion::new(
+            info.dwMajorVersion,
+            info.dwMinorVersion,
+            info.dwBuildNumber,
+        ))
+    }
+
+    pub fn add_global_mouse_monitor(
+        &self,
+        callback: Box<dyn FnMut(Point)>,
+    ) -> Result<GlobalMouseMonitor, ShellError> {
+        MOUSE_MONITORS.with(|monitors| {
+            let mut monitors = monitors.borrow_mut();
+            if monitors.hook.is_none() {
+                // The hook is called on this thread, from its message loop.
+                let hook = unsafe {
+                    SetWindowsHookExW(
+                        WH_MOUSE_LL,
+                        Some(mouse_hook_proc),
+                        GetModuleHandleW(ptr::null()),
+                        0,
+                    )
+                };
+                if hook.is_null() {
+                    return Err(Error::Hr(HRESULT_FROM_WIN32(unsafe { GetLastError() })).into());
+                }
+                monitors.hook = Some(hook);
+            }
+            let callback = Rc::new(RefCell::new(callback));
+            monitors.callbacks.push(callback.clone());
+            Ok(GlobalMouseMonitor { callback })
+        })
+    }
+
+    pub fn run_on_main<T: Send + 'static>(
+        f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, crate::Error> {
+        let app_hwnd = APP_HWND.load(Ordering::Acquire);
+        if app_hwnd.is_null() {
+            return Err(anyhow!("the message loop isn't running").into());
+        }
+        let mut f = Some(f);
+        let mut result = None;
+        let mut work = || result = f.take().map(|f| f());
+        let mut work: &mut dyn FnMut() = &mut work;
+        // Messages sent from other threads are handled as soon as the main thread pumps
+        // messages, and SendMessageW waits for that.
+        unsafe {
+            SendMessageW(
+                app_hwnd,
+                DS_RUN_ON_MAIN,
+                0,
+                &mut work as *mut &mut dyn FnMut() as LPARAM,
+            )
+        };
+        // The app window can be destroyed before it gets to the message.
+        result.ok_or_else(|| anyhow!("the message loop quit before running the function").into())
+    }
+
     pub fn get_locale() -> String {
         let mut buf = [0u16; LOCALE_NAME_MAX_LENGTH];
         let len_with_null =
@@ -208,4 +779,558 @@ impl Application {
             "en-US".into()
         })
     }
+
+    pub fn get_locale_tag() -> String {
+        Self::get_locale()
+    }
+}
+
+/// Hands a message from the queue to its window, translating keyboard shortcuts
+/// into menu commands first.
+unsafe fn dispatch_message(msg: &mut MSG) {
+    let accels = accels::find_accels(GetAncestor(msg.hwnd, GA_ROOT));
+    let translated = accels.map_or(false, |it| {
+        TranslateAcceleratorW(msg.hwnd, it.handle(), msg) != 0
+    });
+    if !translated {
+        TranslateMessage(msg);
+        DispatchMessageW(msg);
+    }
+}
+
+fn shell_execute(operation: &str, file: &str) -> Result<(), anyhow::Error> {
+    let operation = operation.to_wide();
+    let file_wide = file.to_wide();
+    let result = unsafe {
+        ShellExecuteW(
+            ptr::null_mut(),
+            operation.as_ptr(),
+            file_wide.as_ptr(),
+            ptr::null(),
+            ptr::null(),
+            SW_SHOWNORMAL,
+        )
+    };
+    // Values greater than 32 indicate success, the others are `SE_ERR_*` codes.
+    let reason = match result as usize as DWORD {
+        code if code > 32 => return Ok(()),
+        0 | SE_ERR_OOM => "out of memory",
+        SE_ERR_FNF => "file not found",
+        SE_ERR_PNF => "path not found",
+        SE_ERR_ACCESSDENIED => "access denied",
+        SE_ERR_NOASSOC | SE_ERR_ASSOCINCOMPLETE => "no application is associated with it",
+        SE_ERR_SHARE => "sharing violation",
+        SE_ERR_DDETIMEOUT | SE_ERR_DDEFAIL | SE_ERR_DDEBUSY => "the DDE transaction failed",
+        SE_ERR_DLLNOTFOUND => "a required library was not found",
+        _ => "unknown error",
+    };
+    Err(anyhow!(
+        "failed to open {}: {} ({})",
+        file,
+        reason,
+        result as usize
+    ))
+}
+
+// These aren't in winapi.
+extern "system" {
+    fn ILCreateFromPathW(path: LPCWSTR) -> PIDLIST_ABSOLUTE;
+    fn ILFree(pidl: PIDLIST_ABSOLUTE);
+}
+
+impl AppWindowState {
+    fn power_broadcast(&mut self, event: WPARAM) {
+        let handler = match self.handler.as_mut() {
+            Some(handler) => handler,
+            None => return,
+        };
+        match event {
+            // We have about two seconds to handle this before the system suspends.
+            PBT_APMSUSPEND => handler.system_will_sleep(),
+            // This is sent on every wake, whether or not the user is present.
+            PBT_APMRESUMEAUTOMATIC => handler.system_did_wake(),
+            // This is also sent when the battery level changes, so filter on the source.
+            PBT_APMPOWERSTATUSCHANGE => {
+                let source = current_power_source();
+                if source.is_some() && source != self.power_source {
+                    self.power_source = source;
+                    if let Some(source) = source {
+                        handler.power_source_changed(source);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn keyboard_layout_changed(&mut self) {
+        if let Some(handler) = self.handler.as_mut() {
+            handler.keyboard_layout_changed();
+        }
+    }
+
+    /// Opens the files and URLs on the command line, which is how Explorer passes the
+    /// documents that are opened with their associated application, and the shell passes
+    /// the URLs with a registered scheme.
+    fn open_launch_files(&mut self) {
+        if let Some(handler) = self.handler.as_mut() {
+            for arg in std::env::args_os().skip(1) {
+                open_launch_arg(&mut **handler, &arg);
+            }
+        }
+    }
+
+    /// Opens the files and URLs that another instance forwarded, as a list of nul
+    /// terminated strings.
+    fn open_forwarded_files(&mut self, paths: &[u16]) {
+        if let Some(handler) = self.handler.as_mut() {
+            for path in paths.split(|&c| c == 0).filter(|path| !path.is_empty()) {
+                open_launch_arg(&mut **handler, &path.to_os_string());
+            }
+        }
+    }
+
+    fn locale_changed(&mut self) {
+        if let Some(handler) = self.handler.as_mut() {
+            handler.locale_changed();
+        }
+    }
+
+    fn high_contrast_changed(&mut self) {
+        let high_contrast = prefers_high_contrast();
+        if high_contrast != self.high_contrast {
+            self.high_contrast = high_contrast;
+            if let Some(handler) = self.handler.as_mut() {
+                handler.high_contrast_changed(high_contrast);
+            }
+        }
+    }
+
+    fn activate_app(&mut self, active: bool) {
+        if let Some(handler) = self.handler.as_mut() {
+            if active {
+                handler.did_become_active();
+            } else {
+                handler.did_resign_active();
+            }
+        }
+    }
+
+    /// Returns whether the session may end, showing the reason on the shutdown screen if not.
+    fn query_end_session(&mut self, hwnd: HWND) -> bool {
+        let response = match self.handler.as_mut() {
+            Some(handler) => handler.query_end_session(),
+            None => EndSessionResponse::Allow,
+        };
+        match response {
+            EndSessionResponse::Allow => true,
+            EndSessionResponse::Block(reason) => {
+                if let Some(func) = OPTIONAL_FUNCTIONS.ShutdownBlockReasonCreate {
+                    unsafe {
+                        func(hwnd, reason.to_wide().as_ptr());
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    fn end_session(&mut self, hwnd: HWND, ending: bool) {
+        if ending {
+            if let Some(handler) = self.handler.as_mut() {
+                handler.end_session();
+            }
+        } else {
+            unblock_end_session(hwnd);
+        }
+    }
+
+    fn session_change(&mut self, event: WPARAM) {
+        if let Some(handler) = self.handler.as_mut() {
+            match event {
+                WTS_SESSION_LOCK => handler.session_locked(),
+                WTS_SESSION_UNLOCK => handler.session_unlocked(),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Passes a command line argument to [`AppHandler::open_file`] if it is a file,
+/// or to [`AppHandler::open_url`] if it is a URL.
+fn open_launch_arg(handler: &mut dyn AppHandler, arg: &OsStr) {
+    let path = Path::new(arg);
+    if path.is_file() {
+        handler.open_file(path);
+    } else if let Some(url) = arg.to_str().filter(|arg| is_url(arg)) {
+        handler.open_url(url);
+    }
+}
+
+/// Removes the reason that `query_end_session` gave for blocking the end of the session.
+fn unblock_end_session(hwnd: HWND) {
+    if hwnd.is_null() {
+        return;
+    }
+    if let Some(func) = OPTIONAL_FUNCTIONS.ShutdownBlockReasonDestroy {
+        unsafe {
+            func(hwnd);
+        }
+    }
+}
+
+/// Returns `true` if `hwnd` belongs to this process.
+unsafe fn is_foreground_process(hwnd: HWND) -> bool {
+    if hwnd.is_null() {
+        return false;
+    }
+    let mut process_id = 0;
+    GetWindowThreadProcessId(hwnd, &mut process_id);
+    process_id == GetCurrentProcessId()
+}
+
+/// Returns one of the `GetSysColor` colors.
+fn system_color(index: c_int) -> Color {
+    let color = unsafe { GetSysColor(index) };
+    Color::rgb8(
+        GetRValue(color) as u8,
+        GetGValue(color) as u8,
+        GetBValue(color) as u8,
+    )
+}
+
+/// Returns whether a high contrast theme is active.
+fn prefers_high_contrast() -> bool {
+    let mut high_contrast: HIGHCONTRASTW = unsafe { mem::zeroed() };
+    high_contrast.cbSize = mem::size_of::<HIGHCONTRASTW>() as UINT;
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETHIGHCONTRAST,
+            high_contrast.cbSize,
+            &mut high_contrast as *mut _ as *mut _,
+            0,
+        )
+    };
+    ok != FALSE && high_contrast.dwFlags & HCF_HIGHCONTRASTON != 0
+}
+
+/// Returns the source the system is currently drawing power from.
+fn current_power_source() -> Option<PowerSource> {
+    let mut status: SYSTEM_POWER_STATUS = unsafe { mem::zeroed() };
+    if unsafe { GetSystemPowerStatus(&mut status) } == FALSE {
+        return None;
+    }
+    match status.ACLineStatus {
+        0 => Some(PowerSource::Battery),
+        1 => Some(PowerSource::AC),
+        _ => None,
+    }
+}
+
+/// Returns whether `hwnd` is a window that would show up in the taskbar, rather than
+/// a hidden window, a dialog or a tool window.
+unsafe fn is_app_window(hwnd: HWND) -> bool {
+    if IsWindowVisible(hwnd) == FALSE || !GetWindow(hwnd, GW_OWNER).is_null() {
+        return false;
+    }
+    if GetWindowLongW(hwnd, GWL_EXSTYLE) as DWORD & WS_EX_TOOLWINDOW != 0 {
+        return false;
+    }
+    // Windows of suspended store apps are visible, but cloaked.
+    let mut cloaked: DWORD = 0;
+    let res = DwmGetWindowAttribute(
+        hwnd,
+        DWMWA_CLOAKED,
+        &mut cloaked as *mut DWORD as *mut _,
+        mem::size_of::<DWORD>() as DWORD,
+    );
+    res != 0 || cloaked == 0
+}
+
+unsafe fn window_text(hwnd: HWND) -> String {
+    let len = GetWindowTextLengthW(hwnd);
+    if len <= 0 {
+        return String::new();
+    }
+    let mut buf = vec![0u16; len as usize + 1];
+    let len = GetWindowTextW(hwnd, buf.as_mut_ptr(), buf.len() as c_int);
+    String::from_utf16_lossy(&buf[..len.max(0) as usize])
+}
+
+/// Returns the file name, without extension, of the executable of a process.
+unsafe fn process_name(process_id: DWORD) -> Option<String> {
+    let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, FALSE, process_id);
+    if process.is_null() {
+        return None;
+    }
+    let mut buf = [0u16; MAX_PATH];
+    let mut len = buf.len() as DWORD;
+    let res = QueryFullProcessImageNameW(process, 0, buf.as_mut_ptr(), &mut len);
+    CloseHandle(process);
+    if res == FALSE {
+        return None;
+    }
+    let path = PathBuf::from(buf[..len as usize].to_os_string());
+    Some(path.file_stem()?.to_string_lossy().into_owned())
+}
+
+/// Calls the callbacks of the global mouse monitors when the mouse moves or a button is pressed.
+unsafe extern "system" fn mouse_hook_proc(code: c_int, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code == HC_ACTION {
+        match wparam as UINT {
+            WM_MOUSEMOVE | WM_LBUTTONDOWN | WM_RBUTTONDOWN | WM_MBUTTONDOWN | WM_XBUTTONDOWN => {
+                // This is in physical pixels, since the process is per monitor DPI aware.
+                let info = &*(lparam as *const MSLLHOOKSTRUCT);
+                let point = Point::new(info.pt.x as f64, info.pt.y as f64);
+                // A callback may drop a monitor, so they can't be called while borrowed.
+                let callbacks = MOUSE_MONITORS.with(|monitors| monitors.borrow().callbacks.clone());
+                for callback in callbacks {
+                    if let Ok(mut callback) = callback.try_borrow_mut() {
+                        callback(point);
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+    CallNextHookEx(ptr::null_mut(), code, wparam, lparam)
+}
+
+/// Creates the hidden application window, see [`AppWindowState`].
+unsafe fn create_app_window(handler: Option<Box<dyn AppHandler>>, title: Option<&str>) -> HWND {
+    let state = Box::new(RefCell::new(AppWindowState {
+        handler,
+        power_source: current_power_source(),
+        high_contrast: prefers_high_contrast(),
+    }));
+    let state_ptr = Box::into_raw(state);
+    let class_name = APP_CLASS_NAME.to_wide();
+    let title = title.map(ToWide::to_wide);
+    let hwnd = CreateWindowExW(
+        0,
+        class_name.as_ptr(),
+        title.as_ref().map_or(ptr::null(), |title| title.as_ptr()),
+        0,
+        0,
+        0,
+        0,
+        0,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        0 as HINSTANCE,
+        state_ptr as *mut _,
+    );
+    if hwnd.is_null() {
+        tracing::warn!(
+            "failed to create app window: {}",
+            Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+        );
+        drop(Box::from_raw(state_ptr));
+    } else if let Some(func) = OPTIONAL_FUNCTIONS.WTSRegisterSessionNotification {
+        if func(hwnd, NOTIFY_FOR_THIS_SESSION) == FALSE {
+            tracing::warn!(
+                "failed to register for session notifications: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+        }
+    }
+    hwnd
+}
+
+/// Sends the files on the command line to the app window of the instance that
+/// created the `app_id` mutex.
+unsafe fn forward_launch_files(app_id: &str) {
+    let class_name = APP_CLASS_NAME.to_wide();
+    let title = app_id.to_wide();
+    // The other instance may still be starting up, it only creates its app window in `run`.
+    let mut hwnd = ptr::null_mut();
+    for _ in 0..FIND_INSTANCE_ATTEMPTS {
+        hwnd = FindWindowW(class_name.as_ptr(), title.as_ptr());
+        if !hwnd.is_null() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    if hwnd.is_null() {
+        tracing::warn!("failed to find the running instance of {}", app_id);
+        return;
+    }
+    // Let the running instance bring its windows to the front.
+    let mut process_id = 0;
+    GetWindowThreadProcessId(hwnd, &mut process_id);
+    AllowSetForegroundWindow(process_id);
+
+    // Relative paths have to be resolved here, since the other instance has its own
+    // working directory. URLs are passed on as they are.
+    let current_dir = std::env::current_dir().unwrap_or_default();
+    let mut paths: Vec<u16> = std::env::args_os()
+        .skip(1)
+        .flat_map(|arg| match arg.to_str() {
+            Some(url) if is_url(url) => url.to_wide(),
+            _ => current_dir.join(&arg).to_wide(),
+        })
+        .collect();
+    if paths.is_empty() {
+        return;
+    }
+    let copy_data = COPYDATASTRUCT {
+        dwData: DS_COPYDATA_OPEN_FILES,
+        cbData: (paths.len() * mem::size_of::<u16>()) as DWORD,
+        lpData: paths.as_mut_ptr() as *mut _,
+    };
+    let res = SendMessageTimeoutW(
+        hwnd,
+        WM_COPYDATA,
+        0,
+        &copy_data as *const COPYDATASTRUCT as LPARAM,
+        SMTO_ABORTIFHUNG,
+        5000,
+        ptr::null_mut(),
+    );
+    if res == 0 {
+        tracing::warn!(
+            "failed to forward the launch files: {}",
+            Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+        );
+    }
+}
+
+/// Returns the area named by the `lParam` of `WM_SETTINGCHANGE`, which is a nul terminated string.
+unsafe fn setting_area(lparam: LPARAM) -> String {
+    let area = lparam as *const u16;
+    let len = (0..).take_while(|&i| *area.add(i) != 0).count();
+    FromWide::to_string(slice::from_raw_parts(area, len)).unwrap_or_default()
+}
+
+/// Calls `f` with the state of the app window, unless it's already borrowed.
+unsafe fn with_app_state<T>(
+    state_ptr: *mut RefCell<AppWindowState>,
+    f: impl FnOnce(&mut AppWindowState) -> T,
+) -> Option<T> {
+    match (*state_ptr).try_borrow_mut() {
+        Ok(mut state) => Some(f(&mut state)),
+        Err(_) => {
+            tracing::warn!("app window state already borrowed");
+            None
+        }
+    }
+}
+
+unsafe extern "system" fn app_win_proc(
+    hwnd: HWND,
+    msg: UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    if msg == WM_CREATE {
+        let create_struct = &*(lparam as *const CREATESTRUCTW);
+        SetWindowLongPtrW(
+            hwnd,
+            GWLP_USERDATA,
+            create_struct.lpCreateParams as WindowLongPtr,
+        );
+    }
+    let state_ptr = GetWindowLongPtrW(hwnd, GWLP_USERDATA) as *mut RefCell<AppWindowState>;
+    if state_ptr.is_null() {
+        return DefWindowProcW(hwnd, msg, wparam, lparam);
+    }
+
+    match msg {
+        WM_POWERBROADCAST => {
+            with_app_state(state_ptr, |state| state.power_broadcast(wparam));
+            TRUE as LRESULT
+        }
+        DS_KEYBOARD_LAYOUT_CHANGED => {
+            with_app_state(state_ptr, |state| state.keyboard_layout_changed());
+            0
+        }
+        DS_RUN_ON_MAIN => {
+            let work = &mut *(lparam as *mut &mut dyn FnMut());
+            work();
+            0
+        }
+        DS_OPEN_LAUNCH_FILES => {
+            with_app_state(state_ptr, |state| state.open_launch_files());
+            0
+        }
+        WM_COPYDATA => {
+            let copy_data = &*(lparam as *const COPYDATASTRUCT);
+            if copy_data.dwData != DS_COPYDATA_OPEN_FILES || copy_data.lpData.is_null() {
+                return FALSE as LRESULT;
+            }
+            let paths = slice::from_raw_parts(
+                copy_data.lpData as *const u16,
+                copy_data.cbData as usize / mem::size_of::<u16>(),
+            );
+            with_app_state(state_ptr, |state| state.open_forwarded_files(paths));
+            TRUE as LRESULT
+        }
+        // This is sent to every top-level window, so we only handle it here.
+        WM_ACTIVATEAPP => {
+            with_app_state(state_ptr, |state| {
+                state.activate_app(wparam != FALSE as WPARAM)
+            });
+            0
+        }
+        // These are sent to every top-level window, so we only handle them here.
+        WM_QUERYENDSESSION => {
+            with_app_state(state_ptr, |state| state.query_end_session(hwnd) as LRESULT)
+                .unwrap_or(TRUE as LRESULT)
+        }
+        WM_ENDSESSION => {
+            with_app_state(state_ptr, |state| {
+                state.end_session(hwnd, wparam != FALSE as WPARAM)
+            });
+            0
+        }
+        WM_WTSSESSION_CHANGE => {
+            with_app_state(state_ptr, |state| state.session_change(wparam));
+            0
+        }
+        WM_SETTINGCHANGE if wparam == SPI_SETHIGHCONTRAST as WPARAM => {
+            with_app_state(state_ptr, |state| state.high_contrast_changed());
+            0
+        }
+        // The regional settings are named "intl", for the section of the old WIN.INI.
+        WM_SETTINGCHANGE if lparam != 0 && setting_area(lparam) == "intl" => {
+            with_app_state(state_ptr, |state| state.locale_changed());
+            0
+        }
+        // These are sent to every top-level window, so we only handle them here.
+        WM_DISPLAYCHANGE => {
+            crate::screen::monitors_changed();
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+        // The ClearType settings are reported as the subpixel order of the monitors.
+        WM_SETTINGCHANGE
+            if matches!(
+                wparam as UINT,
+                SPI_SETWORKAREA
+                    | SPI_SETFONTSMOOTHING
+                    | SPI_SETFONTSMOOTHINGTYPE
+                    | SPI_SETFONTSMOOTHINGORIENTATION
+            ) =>
+        {
+            crate::screen::monitors_changed();
+            0
+        }
+        WM_RENDERFORMAT => {
+            clipboard::render_format(wparam as UINT);
+            0
+        }
+        WM_RENDERALLFORMATS => {
+            clipboard::render_all_formats(hwnd);
+            0
+        }
+        WM_DESTROYCLIPBOARD => {
+            clipboard::release_formats();
+            0
+        }
+        WM_NCDESTROY => {
+            SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+            drop(Box::from_raw(state_ptr));
+            DefWindowProcW(hwnd, msg, wparam, lparam)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
 }
@@ -14,27 +14,60 @@
 
 //! Interactions with the system pasteboard on Windows.
 
+use std::cell::RefCell;
 use std::ffi::CString;
 use std::mem;
+use std::path::PathBuf;
 use std::ptr;
 
-use winapi::shared::minwindef::{FALSE, UINT};
+use winapi::shared::minwindef::{BOOL, DWORD, FALSE, TRUE, UINT};
 use winapi::shared::ntdef::{CHAR, HANDLE, LPWSTR, WCHAR};
+use winapi::shared::windef::{HWND, POINT};
 use winapi::shared::winerror::ERROR_SUCCESS;
 use winapi::um::errhandlingapi::GetLastError;
-use winapi::um::winbase::{GlobalAlloc, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE};
+use winapi::um::shellapi::{DragQueryFileW, HDROP};
+use winapi::um::winbase::{
+    GlobalAlloc, GlobalFree, GlobalLock, GlobalSize, GlobalUnlock, GMEM_MOVEABLE, GMEM_ZEROINIT,
+};
 use winapi::um::winuser::{
     CloseClipboard, EmptyClipboard, EnumClipboardFormats, GetClipboardData,
-    GetClipboardFormatNameA, IsClipboardFormatAvailable, OpenClipboard, RegisterClipboardFormatA,
-    SetClipboardData, CF_UNICODETEXT,
+    GetClipboardFormatNameA, GetClipboardOwner, GetClipboardSequenceNumber,
+    IsClipboardFormatAvailable, OpenClipboard, RegisterClipboardFormatA, SetClipboardData,
+    CF_HDROP, CF_UNICODETEXT,
 };
 
+use super::application;
 use super::util::{FromWide, ToWide};
-use crate::clipboard::{ClipboardFormat, FormatId};
+use crate::clipboard::{ClipboardFormat, DataProvider, FormatId};
 
 #[derive(Debug, Clone, Default)]
 pub struct Clipboard;
 
+thread_local! {
+    /// The formats we last put on the clipboard, with the clipboard's sequence number after
+    /// putting them.
+    ///
+    /// Windows may round the size of clipboard data up, so this lets us give back exactly
+    /// the bytes we put, for as long as nobody else changed the clipboard. The app window
+    /// owns the clipboard, so this is dropped on `WM_DESTROYCLIPBOARD` once something else
+    /// is put on it.
+    static PUT_FORMATS: RefCell<Option<(DWORD, Vec<ClipboardFormat>)>> = RefCell::new(None);
+
+    /// The formats promised by `put_lazy`, which are made when Windows asks the app window
+    /// to render them.
+    static LAZY_FORMATS: RefCell<Vec<(UINT, FormatId, DataProvider)>> = RefCell::new(Vec::new());
+}
+
+/// The `DROPFILES` header of a `CF_HDROP` clipboard item, which is followed by the paths.
+#[repr(C)]
+struct DropFiles {
+    /// The offset of the paths from the start of the header.
+    files_offset: DWORD,
+    point: POINT,
+    non_client: BOOL,
+    wide: BOOL,
+}
+
 impl Clipboard {
     /// Put a string onto the system clipboard.
     pub fn put_string(&mut self, s: impl AsRef<str>) {
@@ -45,11 +78,15 @@ impl Clipboard {
 
     /// Put multi-format data on the system clipboard.
     pub fn put_formats(&mut self, formats: &[ClipboardFormat]) {
-        with_clipboard(|| unsafe {
+        // Owning the clipboard gets us WM_DESTROYCLIPBOARD when it's emptied.
+        with_clipboard_of(application::app_hwnd(), || unsafe {
             EmptyClipboard();
 
             for format in formats {
-                let handle = make_handle(format);
+                let handle = match make_handle(format) {
+                    Some(handle) => handle,
+                    None => continue,
+                };
                 let format_id = match get_format_id(format.identifier) {
                     Some(id) => id,
                     None => {
@@ -69,6 +106,34 @@ impl Clipboard {
                     );
                 }
             }
+            let sequence_number = GetClipboardSequenceNumber();
+            PUT_FORMATS.with(|put| *put.borrow_mut() = Some((sequence_number, formats.to_vec())));
+        });
+    }
+
+    /// Put data on the system clipboard that is made when it is pasted.
+    pub fn put_lazy(&mut self, format: FormatId, provider: DataProvider) {
+        let owner = application::app_hwnd();
+        if owner.is_null() {
+            // Delayed rendering asks the clipboard's owner window, which only exists while
+            // the message loop runs.
+            self.put_formats(&[ClipboardFormat::new(format, provider())]);
+            return;
+        }
+        let format_id = match get_format_id(format) {
+            Some(id) => id,
+            None => {
+                tracing::warn!("failed to register clipboard format {}", format);
+                return;
+            }
+        };
+        with_clipboard_of(owner, || unsafe {
+            // This tells the previous owner to drop its promises, which may be us.
+            EmptyClipboard();
+            // A null handle promises the data, which we make on WM_RENDERFORMAT.
+            SetClipboardData(format_id, ptr::null_mut());
+            LAZY_FORMATS.with(|lazy| *lazy.borrow_mut() = vec![(format_id, format, provider)]);
+            PUT_FORMATS.with(|put| *put.borrow_mut() = None);
         });
     }
 
@@ -88,6 +153,45 @@ impl Clipboard {
         .flatten()
     }
 
+    /// Put a list of files onto the system clipboard.
+    pub fn put_file_list(&mut self, paths: &[PathBuf]) {
+        with_clipboard(|| unsafe {
+            EmptyClipboard();
+            let handle = match make_hdrop(paths) {
+                Some(handle) => handle,
+                None => return,
+            };
+            if SetClipboardData(CF_HDROP, handle).is_null() {
+                tracing::warn!(
+                    "failed to put files on the clipboard, error: {}",
+                    GetLastError()
+                );
+            }
+        });
+    }
+
+    /// Get the list of files on the system clipboard, if there is one.
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        with_clipboard(|| unsafe {
+            let hdrop = GetClipboardData(CF_HDROP) as HDROP;
+            if hdrop.is_null() {
+                return None;
+            }
+            // Passing `0xFFFFFFFF` as the index returns the number of files.
+            let count = DragQueryFileW(hdrop, 0xFFFF_FFFF, ptr::null_mut(), 0);
+            let paths = (0..count)
+                .map(|i| {
+                    let len = DragQueryFileW(hdrop, i, ptr::null_mut(), 0) as usize;
+                    let mut buf = vec![0u16; len + 1];
+                    DragQueryFileW(hdrop, i, buf.as_mut_ptr(), buf.len() as UINT);
+                    PathBuf::from(buf[..len].to_os_string())
+                })
+                .collect();
+            Some(paths)
+        })
+        .flatten()
+    }
+
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
     pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
@@ -115,6 +219,21 @@ impl Clipboard {
             return self.get_string().map(String::into_bytes);
         }
 
+        let put_data = PUT_FORMATS.with(|put| match &*put.borrow() {
+            Some((sequence_number, formats))
+                if *sequence_number == unsafe { GetClipboardSequenceNumber() } =>
+            {
+                formats
+                    .iter()
+                    .find(|it| it.identifier == format)
+                    .map(|it| it.data.clone())
+            }
+            _ => None,
+        });
+        if put_data.is_some() {
+            return put_data;
+        }
+
         with_clipboard(|| {
             let format_id = match get_format_id(format) {
                 Some(id) => id,
@@ -153,8 +272,13 @@ impl Clipboard {
 }
 
 fn with_clipboard<V>(f: impl FnOnce() -> V) -> Option<V> {
+    with_clipboard_of(ptr::null_mut(), f)
+}
+
+/// Opens the clipboard for `owner`, which becomes its owner when it is emptied.
+fn with_clipboard_of<V>(owner: HWND, f: impl FnOnce() -> V) -> Option<V> {
     unsafe {
-        if OpenClipboard(ptr::null_mut()) == FALSE {
+        if OpenClipboard(owner) == FALSE {
             return None;
         }
 
@@ -166,22 +290,123 @@ fn with_clipboard<V>(f: impl FnOnce() -> V) -> Option<V> {
     }
 }
 
-unsafe fn make_handle(format: &ClipboardFormat) -> HANDLE {
+/// Makes the data of a format promised by `put_lazy`, for `WM_RENDERFORMAT`.
+///
+/// Windows has already opened the clipboard for whoever is pasting.
+pub(crate) fn render_format(format_id: UINT) {
+    let promised = LAZY_FORMATS.with(|lazy| {
+        lazy.borrow()
+            .iter()
+            .find(|(id, _, _)| *id == format_id)
+            .map(|(_, identifier, provider)| (*identifier, provider.clone()))
+    });
+    if let Some((identifier, provider)) = promised {
+        unsafe {
+            let handle = match make_handle(&ClipboardFormat::new(identifier, provider())) {
+                Some(handle) => handle,
+                None => return,
+            };
+            if SetClipboardData(format_id, handle).is_null() {
+                tracing::warn!(
+                    "failed to render clipboard format {}, error: {}",
+                    identifier,
+                    GetLastError()
+                );
+            }
+        }
+    }
+}
+
+/// Makes the data of every format that `owner` promised, for `WM_RENDERALLFORMATS`.
+///
+/// This is sent when `owner` is destroyed, so the data can still be pasted after we quit.
+pub(crate) fn render_all_formats(owner: HWND) {
+    with_clipboard_of(owner, || unsafe {
+        // Someone else could have taken the clipboard before we got to open it.
+        if GetClipboardOwner() != owner {
+            return;
+        }
+        let format_ids: Vec<UINT> =
+            LAZY_FORMATS.with(|lazy| lazy.borrow().iter().map(|(id, _, _)| *id).collect());
+        for format_id in format_ids {
+            render_format(format_id);
+        }
+    });
+}
+
+/// Drops the promised formats and the copies of the formats we put, for
+/// `WM_DESTROYCLIPBOARD` when something else is put on the clipboard.
+pub(crate) fn release_formats() {
+    LAZY_FORMATS.with(|lazy| lazy.borrow_mut().clear());
+    PUT_FORMATS.with(|put| *put.borrow_mut() = None);
+}
+
+/// Makes the clipboard data for `format`, or `None` if it can't be allocated.
+unsafe fn make_handle(format: &ClipboardFormat) -> Option<HANDLE> {
     if format.identifier == ClipboardFormat::TEXT {
         let s = std::str::from_utf8_unchecked(&format.data);
         let wstr = s.to_wide();
-        let handle = GlobalAlloc(GMEM_MOVEABLE, wstr.len() * mem::size_of::<WCHAR>());
-        let locked = GlobalLock(handle) as LPWSTR;
-        ptr::copy_nonoverlapping(wstr.as_ptr(), locked, wstr.len());
-        GlobalUnlock(handle);
-        handle
+        global_alloc(
+            GMEM_MOVEABLE,
+            wstr.len() * mem::size_of::<WCHAR>(),
+            |locked| {
+                ptr::copy_nonoverlapping(wstr.as_ptr(), locked as LPWSTR, wstr.len());
+            },
+        )
     } else {
-        let handle = GlobalAlloc(GMEM_MOVEABLE, format.data.len() * mem::size_of::<CHAR>());
-        let locked = GlobalLock(handle) as *mut u8;
-        ptr::copy_nonoverlapping(format.data.as_ptr(), locked, format.data.len());
-        GlobalUnlock(handle);
-        handle
+        global_alloc(
+            GMEM_MOVEABLE,
+            format.data.len() * mem::size_of::<CHAR>(),
+            |locked| ptr::copy_nonoverlapping(format.data.as_ptr(), locked, format.data.len()),
+        )
+    }
+}
+
+/// Allocates `size` bytes of global memory and lets `fill` write them.
+///
+/// Returns `None` after logging a warning if the memory can't be allocated or locked.
+unsafe fn global_alloc(flags: UINT, size: usize, fill: impl FnOnce(*mut u8)) -> Option<HANDLE> {
+    let handle = GlobalAlloc(flags, size);
+    if handle.is_null() {
+        tracing::warn!(
+            "failed to allocate {} bytes of clipboard data, error: {}",
+            size,
+            GetLastError()
+        );
+        return None;
     }
+    let locked = GlobalLock(handle) as *mut u8;
+    if locked.is_null() {
+        tracing::warn!("failed to lock clipboard data, error: {}", GetLastError());
+        GlobalFree(handle);
+        return None;
+    }
+    fill(locked);
+    GlobalUnlock(handle);
+    Some(handle)
+}
+
+/// Makes a `CF_HDROP` clipboard item listing `paths`.
+unsafe fn make_hdrop(paths: &[PathBuf]) -> Option<HANDLE> {
+    // Each path is null terminated, and the list ends with an extra null.
+    let mut list: Vec<u16> = paths.iter().flat_map(|path| path.to_wide()).collect();
+    list.push(0);
+    let header_size = mem::size_of::<DropFiles>();
+    let size = header_size + list.len() * mem::size_of::<WCHAR>();
+    global_alloc(GMEM_MOVEABLE | GMEM_ZEROINIT, size, |locked| {
+        let header = DropFiles {
+            files_offset: header_size as DWORD,
+            point: POINT { x: 0, y: 0 },
+            non_client: FALSE,
+            wide: TRUE,
+        };
+        ptr::write_unaligned(locked as *mut DropFiles, header);
+        ptr::copy_nonoverlapping(
+            list.as_ptr(),
+            locked.add(header_size) as *mut u16,
+            list.len(),
+        );
+    })
 }
 
 fn get_format_id(format: FormatId) -> Option<UINT> {
@@ -24,6 +24,7 @@
 
 use std::convert::TryInto;
 use std::ffi::OsString;
+use std::path::Path;
 use std::ptr::null_mut;
 
 use winapi::ctypes::c_void;
@@ -35,11 +36,12 @@ use winapi::um::combaseapi::*;
 use winapi::um::shobjidl::*;
 use winapi::um::shobjidl_core::*;
 use winapi::um::shtypes::COMDLG_FILTERSPEC;
+use winapi::um::winreg::HKEY_CLASSES_ROOT;
 use winapi::{Interface, DEFINE_GUID};
 use wio::com::ComPtr;
 
 use super::error::Error;
-use super::util::{as_result, FromWide, ToWide};
+use super::util::{as_result, reg_get_string, FromWide, ToWide};
 use crate::dialog::{FileDialogOptions, FileDialogType, FileSpec};
 
 // TODO: remove these when they get added to winapi
@@ -72,6 +74,12 @@ fn normalize_extension(ext: &str) -> String {
     format!("*.{}", ext.trim_start_matches('*').trim_start_matches('.'))
 }
 
+/// Returns the MIME type registered for the extension of `path`.
+pub(crate) fn content_type(path: &Path) -> Option<String> {
+    let extension = format!(".{}", path.extension()?.to_str()?);
+    reg_get_string(HKEY_CLASSES_ROOT, &extension, "Content Type")
+}
+
 pub(crate) unsafe fn get_file_dialog_path(
     hwnd_owner: HWND,
     ty: FileDialogType,
@@ -20,7 +20,7 @@ use std::convert::TryInto;
 use std::mem;
 use std::ops::RangeInclusive;
 
-use crate::keyboard::{Code, KbKey, KeyEvent, KeyState, Location, Modifiers};
+use crate::keyboard::{Code, KbKey, KeyEvent, KeyState, Location, ModifierSides, Modifiers};
 
 use winapi::shared::minwindef::{HKL, INT, LPARAM, UINT, WPARAM};
 use winapi::shared::ntdef::SHORT;
@@ -113,6 +113,45 @@ const MODIFIER_MAP: &[(INT, Modifiers, SHORT)] = &[
     (VK_SHIFT, Modifiers::SHIFT, 0x80),
 ];
 
+/// Get the modifier state, without the knowledge of the keyboard layout that is needed
+/// to tell [AltGr] apart from Ctrl+Alt.
+///
+/// Like [`KeyboardState::get_modifiers`], this is the state at the time of the message
+/// that is being handled.
+///
+/// [AltGr]: https://en.wikipedia.org/wiki/AltGr_key
+pub(crate) fn modifier_state() -> Modifiers {
+    let mut modifiers = Modifiers::empty();
+    for &(vk, modifier, mask) in MODIFIER_MAP {
+        if unsafe { GetKeyState(vk) } & mask != 0 {
+            modifiers |= modifier;
+        }
+    }
+    modifiers
+}
+
+const MOD_SIDES_MAP: &[(INT, ModifierSides)] = &[
+    (VK_LSHIFT, ModifierSides::LEFT_SHIFT),
+    (VK_RSHIFT, ModifierSides::RIGHT_SHIFT),
+    (VK_LCONTROL, ModifierSides::LEFT_CONTROL),
+    (VK_RCONTROL, ModifierSides::RIGHT_CONTROL),
+    (VK_LMENU, ModifierSides::LEFT_ALT),
+    (VK_RMENU, ModifierSides::RIGHT_ALT),
+    (VK_LWIN, ModifierSides::LEFT_META),
+    (VK_RWIN, ModifierSides::RIGHT_META),
+];
+
+/// Get which sides of the modifier keys are currently pressed.
+fn get_mod_sides() -> ModifierSides {
+    let mut sides = ModifierSides::empty();
+    for &(vk, side) in MOD_SIDES_MAP {
+        if unsafe { GetKeyState(vk) } & 0x80 != 0 {
+            sides |= side;
+        }
+    }
+    sides
+}
+
 /// Convert scan code to W3C standard code.
 ///
 /// It's hard to get an authoritative source for this; it's mostly based
@@ -608,6 +647,7 @@ impl KeyboardState {
                     let event = KeyEvent {
                         state,
                         mods,
+                        mod_sides: get_mod_sides(),
                         code,
                         key,
                         is_composing: false,
@@ -633,6 +673,7 @@ impl KeyboardState {
                 let event = KeyEvent {
                     state,
                     mods,
+                    mod_sides: get_mod_sides(),
                     code,
                     key,
                     is_composing: false,
@@ -667,6 +708,7 @@ impl KeyboardState {
                     let event = KeyEvent {
                         state,
                         mods,
+                        mod_sides: get_mod_sides(),
                         code,
                         key,
                         is_composing: false,
@@ -700,19 +742,12 @@ impl KeyboardState {
     /// [`GetKeyState`]: https://docs.microsoft.com/en-us/windows/win32/api/winuser/nf-winuser-getkeystate
     /// [AltGr]: https://en.wikipedia.org/wiki/AltGr_key
     pub(crate) fn get_modifiers(&self) -> Modifiers {
-        unsafe {
-            let mut modifiers = Modifiers::empty();
-            for &(vk, modifier, mask) in MODIFIER_MAP {
-                if GetKeyState(vk) & mask != 0 {
-                    modifiers |= modifier;
-                }
-            }
-            if self.has_altgr && GetKeyState(VK_RMENU) & 0x80 != 0 {
-                modifiers |= Modifiers::ALT_GRAPH;
-                modifiers &= !(Modifiers::CONTROL | Modifiers::ALT);
-            }
-            modifiers
+        let mut modifiers = modifier_state();
+        if self.has_altgr && unsafe { GetKeyState(VK_RMENU) } & 0x80 != 0 {
+            modifiers |= Modifiers::ALT_GRAPH;
+            modifiers &= !(Modifiers::CONTROL | Modifiers::ALT);
         }
+        modifiers
     }
 
     /// Load a keyboard layout.
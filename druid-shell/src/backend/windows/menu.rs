@@ -19,13 +19,20 @@ use std::fmt::Write as _;
 use std::mem;
 use std::ptr::null;
 
+use winapi::ctypes::c_int;
 use winapi::shared::basetsd::*;
+use winapi::shared::minwindef::{FALSE, TRUE, UINT};
+use winapi::shared::ntdef::LONG;
 use winapi::shared::windef::*;
 use winapi::um::winuser::*;
 
 use super::util::ToWide;
 use crate::hotkey::HotKey;
 use crate::keyboard::{KbKey, Modifiers};
+use crate::window::WinHandler;
+
+/// Marks section headers in the `dwItemData` of their item, so that they stay disabled.
+const SECTION_HEADER_DATA: ULONG_PTR = 1;
 
 /// A menu object, which can be either a top-level menubar or a
 /// submenu.
@@ -137,6 +144,29 @@ impl Menu {
         }
     }
 
+    /// Add a section header, which is a separator followed by a grayed out item.
+    ///
+    /// Windows can't show text in separators, so the item holds the title.
+    pub fn add_section(&mut self, title: &str) {
+        unsafe {
+            if GetMenuItemCount(self.hmenu) > 0 {
+                self.add_separator();
+            }
+            let index = GetMenuItemCount(self.hmenu);
+            AppendMenuW(
+                self.hmenu,
+                MF_STRING | MF_GRAYED,
+                0,
+                title.to_wide().as_ptr(),
+            );
+            let mut info: MENUITEMINFOW = mem::zeroed();
+            info.cbSize = mem::size_of::<MENUITEMINFOW>() as UINT;
+            info.fMask = MIIM_DATA;
+            info.dwItemData = SECTION_HEADER_DATA;
+            SetMenuItemInfoW(self.hmenu, index as UINT, TRUE, &info);
+        }
+    }
+
     /// Get the accels table
     pub fn accels(&self) -> Option<Vec<ACCEL>> {
         if self.accels.is_empty() {
@@ -146,6 +176,30 @@ impl Menu {
     }
 }
 
+/// Asks `handler` whether the items of `hmenu` should be enabled, before it is shown.
+///
+/// # Safety
+///
+/// `hmenu` must be a valid menu handle.
+pub(crate) unsafe fn update_enabled_items(hmenu: HMENU, handler: &mut dyn WinHandler) {
+    for index in 0..GetMenuItemCount(hmenu).max(0) as UINT {
+        let mut info: MENUITEMINFOW = mem::zeroed();
+        info.cbSize = mem::size_of::<MENUITEMINFOW>() as UINT;
+        info.fMask = MIIM_FTYPE | MIIM_ID | MIIM_SUBMENU | MIIM_DATA;
+        if GetMenuItemInfoW(hmenu, index, TRUE, &mut info) == FALSE
+            || !info.hSubMenu.is_null()
+            || info.fType & MFT_SEPARATOR != 0
+            || info.dwItemData == SECTION_HEADER_DATA
+        {
+            continue;
+        }
+        if let Some(enabled) = handler.menu_item_enabled(info.wID) {
+            let flags = if enabled { MF_ENABLED } else { MF_GRAYED };
+            EnableMenuItem(hmenu, index, MF_BYPOSITION | flags);
+        }
+    }
+}
+
 /// Convert a hotkey to an accelerator.
 ///
 /// Note that this conversion is dependent on the keyboard map.
@@ -188,17 +242,38 @@ fn convert_hotkey(id: u32, key: &HotKey) -> Option<ACCEL> {
     })
 }
 
+/// Returns the name of a modifier key in the language of the keyboard layout.
+fn modifier_name(vk: c_int, default: &str) -> String {
+    let mut buf = [0u16; 32];
+    let len = unsafe {
+        let scan_code = MapVirtualKeyW(vk as UINT, MAPVK_VK_TO_VSC);
+        GetKeyNameTextW(
+            (scan_code << 16) as LONG,
+            buf.as_mut_ptr(),
+            buf.len() as c_int,
+        )
+    };
+    if len > 0 {
+        String::from_utf16_lossy(&buf[..len as usize])
+    } else {
+        default.to_string()
+    }
+}
+
 /// Format the hotkey in a Windows-native way.
 fn format_hotkey(key: &HotKey, s: &mut String) {
     let key_mods: Modifiers = key.mods.into();
     if key_mods.ctrl() {
-        s.push_str("Ctrl+");
+        s.push_str(&modifier_name(VK_CONTROL, "Ctrl"));
+        s.push('+');
     }
     if key_mods.shift() {
-        s.push_str("Shift+");
+        s.push_str(&modifier_name(VK_SHIFT, "Shift"));
+        s.push('+');
     }
     if key_mods.alt() {
-        s.push_str("Alt+");
+        s.push_str(&modifier_name(VK_MENU, "Alt"));
+        s.push('+');
     }
     if key_mods.meta() {
         s.push_str("Windows+");
@@ -15,17 +15,36 @@
 //! Windows Monitors and Screen information.
 
 use super::error::Error;
-use std::mem::size_of;
+use anyhow::anyhow;
+use std::mem::{self, size_of};
 use std::ptr::null_mut;
+use std::slice;
 use tracing::warn;
 use winapi::shared::minwindef::*;
+use winapi::shared::ntdef::LONG;
 use winapi::shared::windef::*;
 use winapi::shared::winerror::*;
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::shellscalingapi::MDT_EFFECTIVE_DPI;
+use winapi::um::wingdi::{
+    BitBlt, CreateCompatibleDC, CreateDIBSection, DeleteDC, DeleteObject, SelectObject, BITMAPINFO,
+    BITMAPINFOHEADER, BI_RGB, CAPTUREBLT, CCHDEVICENAME, DEVMODEW, DIB_RGB_COLORS,
+    DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME, DISPLAYCONFIG_MODE_INFO,
+    DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL,
+    DISPLAYCONFIG_OUTPUT_TECHNOLOGY_LVDS, DISPLAYCONFIG_OUTPUT_TECHNOLOGY_UDI_EMBEDDED,
+    DISPLAYCONFIG_PATH_INFO, DISPLAYCONFIG_SOURCE_DEVICE_NAME, DISPLAY_DEVICEW,
+    DISPLAY_DEVICE_ACTIVE, DMDO_180, DMDO_270, DMDO_90, DM_DISPLAYFREQUENCY, DM_PELSHEIGHT,
+    DM_PELSWIDTH, QDC_ONLY_ACTIVE_PATHS, SRCCOPY,
+};
 use winapi::um::winuser::*;
 
-use crate::kurbo::Rect;
-use crate::screen::Monitor;
+use super::util::{system_dpi, ToWide, OPTIONAL_FUNCTIONS};
+use super::window::SCALE_TARGET_DPI;
+use crate::kurbo::{Point, Rect, Size};
+use crate::piet::{ImageBuf, ImageFormat};
+use crate::scale::Scale;
+use crate::screen::{DisplayMode, Monitor, Orientation, SubpixelOrder};
+use crate::Error as ShellError;
 
 unsafe extern "system" fn monitorenumproc(
     hmonitor: HMONITOR,
@@ -39,13 +58,18 @@ unsafe extern "system" fn monitorenumproc(
         right: 0,
         bottom: 0,
     };
-    let mut info = MONITORINFO {
-        cbSize: size_of::<MONITORINFO>() as u32,
+    let mut info = MONITORINFOEXW {
+        cbSize: size_of::<MONITORINFOEXW>() as u32,
         rcMonitor: rect,
         rcWork: rect,
         dwFlags: 0,
+        szDevice: [0; CCHDEVICENAME],
     };
-    if GetMonitorInfoW(hmonitor, &mut info) == 0 {
+    if GetMonitorInfoW(
+        hmonitor,
+        &mut info as *mut MONITORINFOEXW as *mut MONITORINFO,
+    ) == 0
+    {
         warn!(
             "failed to get Monitor Info: {}",
             Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
@@ -64,11 +88,193 @@ unsafe extern "system" fn monitorenumproc(
         info.rcWork.right as f64,
         info.rcWork.bottom as f64,
     );
+    let (mut dpi_x, mut dpi_y) = (0, 0);
+    let dpi_known = match OPTIONAL_FUNCTIONS.GetDpiForMonitor {
+        Some(func) => SUCCEEDED(func(hmonitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y)),
+        None => false,
+    };
+    if !dpi_known {
+        // Without per-monitor DPI, every monitor is scaled like the primary one.
+        dpi_x = system_dpi();
+        dpi_y = dpi_x;
+    }
+    let scale = Scale::new(
+        dpi_x as f64 / SCALE_TARGET_DPI,
+        dpi_y as f64 / SCALE_TARGET_DPI,
+    );
+    let mut monitor = Monitor::new(primary, rect, work_rect)
+        .with_scale(scale)
+        .with_pixel_coords()
+        .with_orientation(display_orientation(&info.szDevice))
+        .with_native_id(String::from_utf16_lossy(trim_nul(&info.szDevice)));
+    if let Some(path) = monitor_interface_path(&info.szDevice) {
+        monitor = monitor.with_device_id(path);
+    }
     let monitors = _lparam as *mut Vec<Monitor>;
-    (*monitors).push(Monitor::new(primary, rect, work_rect));
+    (*monitors).push(monitor);
     TRUE
 }
 
+/// Returns the device interface path of the monitor shown by the GDI device `device`.
+///
+/// Unlike the GDI device names, like `\\.\DISPLAY1`, which are handed out again when
+/// monitors are plugged in, the path stays the same for a monitor on the same port.
+unsafe fn monitor_interface_path(device: &[u16]) -> Option<String> {
+    let mut display: DISPLAY_DEVICEW = mem::zeroed();
+    display.cb = size_of::<DISPLAY_DEVICEW>() as DWORD;
+    let mut index = 0;
+    // A mirrored GDI device lists every monitor that shows it; we take the first active one.
+    while EnumDisplayDevicesW(
+        device.as_ptr(),
+        index,
+        &mut display,
+        EDD_GET_DEVICE_INTERFACE_NAME,
+    ) != 0
+    {
+        if display.StateFlags & DISPLAY_DEVICE_ACTIVE != 0 {
+            return Some(String::from_utf16_lossy(trim_nul(&display.DeviceID)));
+        }
+        index += 1;
+    }
+    None
+}
+
+/// Returns how the display with the GDI device name `device` is rotated.
+unsafe fn display_orientation(device: &[u16]) -> Orientation {
+    let mut mode: DEVMODEW = mem::zeroed();
+    mode.dmSize = size_of::<DEVMODEW>() as WORD;
+    if EnumDisplaySettingsW(device.as_ptr(), ENUM_CURRENT_SETTINGS, &mut mode) == 0 {
+        return Orientation::Landscape;
+    }
+    match mode.u1.s2().dmDisplayOrientation {
+        DMDO_90 => Orientation::Portrait,
+        DMDO_180 => Orientation::LandscapeFlipped,
+        DMDO_270 => Orientation::PortraitFlipped,
+        _ => Orientation::Landscape,
+    }
+}
+
+/// Returns the modes of `monitor`, found by its GDI device name.
+pub(crate) fn display_modes(monitor: &Monitor) -> Vec<DisplayMode> {
+    match monitor.native_id() {
+        Some(device) => unsafe { device_modes(&device.to_wide(), monitor.scale()) },
+        None => Vec::new(),
+    }
+}
+
+/// Returns the modes of the display with the GDI device name `device`.
+unsafe fn device_modes(device: &[u16], scale: Scale) -> Vec<DisplayMode> {
+    let to_mode = |mode: &DEVMODEW| {
+        let size = Size::new(mode.dmPelsWidth as f64, mode.dmPelsHeight as f64);
+        // 0 and 1 stand for the hardware's default rate.
+        let refresh = (mode.dmDisplayFrequency > 1).then_some(mode.dmDisplayFrequency as f64);
+        DisplayMode::new(size, refresh, scale)
+    };
+    let mut mode: DEVMODEW = mem::zeroed();
+    mode.dmSize = size_of::<DEVMODEW>() as WORD;
+    let current = (EnumDisplaySettingsW(device.as_ptr(), ENUM_CURRENT_SETTINGS, &mut mode) != 0)
+        .then(|| to_mode(&mode));
+    let mut modes = Vec::new();
+    let mut index = 0;
+    while EnumDisplaySettingsW(device.as_ptr(), index, &mut mode) != 0 {
+        // Modes are listed once for every color depth, which we don't report.
+        let entry = to_mode(&mode);
+        if !modes.contains(&entry) {
+            modes.push(entry);
+        }
+        index += 1;
+    }
+    modes
+        .into_iter()
+        .map(|mode| mode.with_current(Some(mode) == current))
+        .collect()
+}
+
+pub(crate) fn set_mode(monitor: &Monitor, mode: &DisplayMode) -> Result<(), ShellError> {
+    let device = monitor
+        .native_id()
+        .ok_or_else(|| anyhow!("the monitor has no device name"))?
+        .to_wide();
+    let (width, height) = (mode.size().width as DWORD, mode.size().height as DWORD);
+    unsafe {
+        let mut devmode: DEVMODEW = mem::zeroed();
+        devmode.dmSize = size_of::<DEVMODEW>() as WORD;
+        devmode.dmPelsWidth = width;
+        devmode.dmPelsHeight = height;
+        devmode.dmFields = DM_PELSWIDTH | DM_PELSHEIGHT;
+        if let Some(refresh) = mode.refresh_rate() {
+            devmode.dmDisplayFrequency = refresh.round() as DWORD;
+            devmode.dmFields |= DM_DISPLAYFREQUENCY;
+        }
+        // Without CDS_UPDATEREGISTRY the change only lasts for this session.
+        match ChangeDisplaySettingsExW(device.as_ptr(), &mut devmode, null_mut(), 0, null_mut()) {
+            DISP_CHANGE_SUCCESSFUL => Ok(()),
+            DISP_CHANGE_BADMODE => {
+                Err(anyhow!("the display doesn't support {}x{}", width, height).into())
+            }
+            DISP_CHANGE_RESTART => Err(anyhow!(
+                "the display needs a restart to switch to {}x{}",
+                width,
+                height
+            )
+            .into()),
+            code => Err(anyhow!("changing the display mode failed with code {}", code).into()),
+        }
+    }
+}
+
+/// Returns the GDI device names, like `\\.\DISPLAY1`, of the active displays that are built
+/// into the device.
+unsafe fn internal_display_names() -> Vec<Vec<u16>> {
+    let mut path_count = 0;
+    let mut mode_count = 0;
+    if GetDisplayConfigBufferSizes(QDC_ONLY_ACTIVE_PATHS, &mut path_count, &mut mode_count)
+        != ERROR_SUCCESS as LONG
+    {
+        return Vec::new();
+    }
+    let mut paths: Vec<DISPLAYCONFIG_PATH_INFO> = vec![mem::zeroed(); path_count as usize];
+    let mut modes: Vec<DISPLAYCONFIG_MODE_INFO> = vec![mem::zeroed(); mode_count as usize];
+    if QueryDisplayConfig(
+        QDC_ONLY_ACTIVE_PATHS,
+        &mut path_count,
+        paths.as_mut_ptr(),
+        &mut mode_count,
+        modes.as_mut_ptr(),
+        null_mut(),
+    ) != ERROR_SUCCESS as LONG
+    {
+        return Vec::new();
+    }
+    paths.truncate(path_count as usize);
+    paths
+        .iter()
+        .filter(|path| {
+            matches!(
+                path.targetInfo.outputTechnology,
+                DISPLAYCONFIG_OUTPUT_TECHNOLOGY_INTERNAL
+                    | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_LVDS
+                    | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_DISPLAYPORT_EMBEDDED
+                    | DISPLAYCONFIG_OUTPUT_TECHNOLOGY_UDI_EMBEDDED
+            )
+        })
+        .filter_map(|path| {
+            let mut name: DISPLAYCONFIG_SOURCE_DEVICE_NAME = mem::zeroed();
+            name.header.type_ = DISPLAYCONFIG_DEVICE_INFO_GET_SOURCE_NAME;
+            name.header.size = size_of::<DISPLAYCONFIG_SOURCE_DEVICE_NAME>() as u32;
+            name.header.adapterId = path.sourceInfo.adapterId;
+            name.header.id = path.sourceInfo.id;
+            (DisplayConfigGetDeviceInfo(&mut name.header) == ERROR_SUCCESS as LONG)
+                .then(|| trim_nul(&name.viewGdiDeviceName).to_vec())
+        })
+        .collect()
+}
+
+fn trim_nul(name: &[u16]) -> &[u16] {
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    &name[..len]
+}
+
 pub(crate) fn get_monitors() -> Vec<Monitor> {
     unsafe {
         let monitors = Vec::<Monitor>::new();
@@ -79,6 +285,128 @@ pub(crate) fn get_monitors() -> Vec<Monitor> {
                 Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
             );
         };
+        let order = cleartype_order();
+        let internal = internal_display_names();
         monitors
+            .into_iter()
+            .map(|monitor| {
+                let is_internal = monitor.native_id().map_or(false, |id| {
+                    internal.iter().any(|name| *name == id.to_wide_sized())
+                });
+                monitor
+                    .with_subpixel_order(order)
+                    .with_internal(is_internal)
+            })
+            .collect()
+    }
+}
+
+/// Returns the subpixel order that ClearType uses, which is the same for all monitors.
+unsafe fn cleartype_order() -> SubpixelOrder {
+    let get = |action: UINT| {
+        let mut value: UINT = 0;
+        let ok = SystemParametersInfoW(action, 0, &mut value as *mut UINT as _, 0) != 0;
+        ok.then_some(value)
+    };
+    let (smoothing, smoothing_type, orientation) = match (
+        get(SPI_GETFONTSMOOTHING),
+        get(SPI_GETFONTSMOOTHINGTYPE),
+        get(SPI_GETFONTSMOOTHINGORIENTATION),
+    ) {
+        (Some(smoothing), Some(smoothing_type), Some(orientation)) => {
+            (smoothing, smoothing_type, orientation)
+        }
+        _ => return SubpixelOrder::Unknown,
+    };
+    if smoothing == 0 || smoothing_type != FE_FONTSMOOTHINGCLEARTYPE {
+        // Without ClearType text is only antialiased in grayscale, if at all.
+        SubpixelOrder::None
+    } else if orientation == FE_FONTSMOOTHINGORIENTATIONBGR {
+        SubpixelOrder::Bgr
+    } else {
+        SubpixelOrder::Rgb
+    }
+}
+
+pub(crate) fn num_monitors() -> usize {
+    unsafe { GetSystemMetrics(SM_CMONITORS) as usize }
+}
+
+pub(crate) fn get_mouse_position() -> Option<Point> {
+    unsafe {
+        let mut point = POINT { x: 0, y: 0 };
+        if GetCursorPos(&mut point) == FALSE {
+            warn!(
+                "GetCursorPos failed: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+            return None;
+        }
+        Some(Point::new(point.x as f64, point.y as f64))
+    }
+}
+
+pub(crate) fn capture_region(rect: Rect) -> Result<ImageBuf, ShellError> {
+    let rect = rect.round();
+    let (width, height) = (rect.width() as i32, rect.height() as i32);
+    if width <= 0 || height <= 0 {
+        return Err(anyhow!("can't capture the empty rect {}", rect).into());
+    }
+    unsafe {
+        // This covers the virtual screen, with its origin at the top-left of the primary monitor.
+        let screen_dc = GetDC(null_mut());
+        if screen_dc.is_null() {
+            return Err(Error::Hr(HRESULT_FROM_WIN32(GetLastError())).into());
+        }
+        let mem_dc = CreateCompatibleDC(screen_dc);
+        let mut info: BITMAPINFO = mem::zeroed();
+        info.bmiHeader.biSize = size_of::<BITMAPINFOHEADER>() as DWORD;
+        info.bmiHeader.biWidth = width;
+        // A negative height makes the rows go from top to bottom.
+        info.bmiHeader.biHeight = -height;
+        info.bmiHeader.biPlanes = 1;
+        info.bmiHeader.biBitCount = 32;
+        info.bmiHeader.biCompression = BI_RGB;
+        let mut bits = null_mut();
+        let bitmap = CreateDIBSection(mem_dc, &info, DIB_RGB_COLORS, &mut bits, null_mut(), 0);
+        let result = if bitmap.is_null() {
+            Err(Error::Hr(HRESULT_FROM_WIN32(GetLastError())).into())
+        } else {
+            let old_bitmap = SelectObject(mem_dc, bitmap as _);
+            // CAPTUREBLT includes layered windows, such as tooltips.
+            let res = BitBlt(
+                mem_dc,
+                0,
+                0,
+                width,
+                height,
+                screen_dc,
+                rect.x0 as i32,
+                rect.y0 as i32,
+                SRCCOPY | CAPTUREBLT,
+            );
+            SelectObject(mem_dc, old_bitmap);
+            let result = if res == FALSE {
+                Err(Error::Hr(HRESULT_FROM_WIN32(GetLastError())).into())
+            } else {
+                // The pixels are BGR, and the fourth byte is unused.
+                let bgrx = slice::from_raw_parts(bits as *const u8, (width * height * 4) as usize);
+                let pixels: Vec<u8> = bgrx
+                    .chunks_exact(4)
+                    .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], 255])
+                    .collect();
+                Ok(ImageBuf::from_raw(
+                    pixels,
+                    ImageFormat::RgbaSeparate,
+                    width as usize,
+                    height as usize,
+                ))
+            };
+            DeleteObject(bitmap as _);
+            result
+        };
+        DeleteDC(mem_dc);
+        ReleaseDC(null_mut(), screen_dc);
+        result
     }
 }
@@ -24,21 +24,30 @@ use std::ptr;
 use std::slice;
 
 use once_cell::sync::Lazy;
-use winapi::ctypes::c_void;
+use winapi::ctypes::{c_int, c_void};
+use winapi::shared::basetsd::UINT32;
 use winapi::shared::dxgi::IDXGIDevice;
 use winapi::shared::guiddef::REFIID;
-use winapi::shared::minwindef::{BOOL, HMODULE, UINT};
-use winapi::shared::ntdef::{HRESULT, LPWSTR};
+use winapi::shared::minwindef::{BOOL, DWORD, HKEY, HMODULE, MAX_PATH, UINT};
+use winapi::shared::ntdef::{HRESULT, LPCWSTR, LPWSTR, NTSTATUS};
 use winapi::shared::windef::{HMONITOR, HWND, RECT};
-use winapi::shared::winerror::SUCCEEDED;
+use winapi::shared::winerror::{ERROR_SUCCESS, SUCCEEDED};
+use winapi::shared::wtypes::BSTR;
 use winapi::um::fileapi::{CreateFileA, GetFileType, OPEN_EXISTING};
 use winapi::um::handleapi::INVALID_HANDLE_VALUE;
 use winapi::um::libloaderapi::{GetModuleHandleW, GetProcAddress, LoadLibraryW};
 use winapi::um::processenv::{GetStdHandle, SetStdHandle};
 use winapi::um::shellscalingapi::{MONITOR_DPI_TYPE, PROCESS_DPI_AWARENESS};
+use winapi::um::unknwnbase::IUnknown;
 use winapi::um::winbase::{FILE_TYPE_UNKNOWN, STD_ERROR_HANDLE, STD_OUTPUT_HANDLE};
 use winapi::um::wincon::{AttachConsole, ATTACH_PARENT_PROCESS};
-use winapi::um::winnt::{FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE};
+use winapi::um::wingdi::{GetDeviceCaps, LOGPIXELSX};
+use winapi::um::winnt::{FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE, OSVERSIONINFOW};
+use winapi::um::winreg::{RegGetValueW, RRF_RT_REG_SZ};
+use winapi::um::winuser::{
+    GetDC, GetSystemMetrics, ReleaseDC, POINTER_INFO, POINTER_INPUT_TYPE, POINTER_PEN_INFO,
+    SM_CXCURSOR, SM_CYCURSOR,
+};
 
 use crate::kurbo::Rect;
 use crate::region::Region;
@@ -139,6 +148,40 @@ pub(crate) fn region_to_rectis(region: &Region, scale: Scale) -> Vec<RECT> {
         .collect()
 }
 
+/// Reads a string value from the registry.
+pub(crate) fn reg_get_string(key: HKEY, subkey: &str, value: &str) -> Option<String> {
+    let mut buf = [0u16; MAX_PATH];
+    let mut cb_data = mem::size_of_val(&buf) as DWORD;
+    let res = unsafe {
+        RegGetValueW(
+            key,
+            subkey.to_wide().as_ptr(),
+            value.to_wide().as_ptr(),
+            RRF_RT_REG_SZ,
+            ptr::null_mut(),
+            buf.as_mut_ptr() as _,
+            &mut cb_data,
+        )
+    };
+    if res != ERROR_SUCCESS as i32 {
+        return None;
+    }
+    // The returned size is in bytes and includes the terminating null.
+    let len = (cb_data as usize / 2).saturating_sub(1);
+    buf[..len].to_string()
+}
+
+/// The device that sent the message being handled, which winapi doesn't define.
+#[repr(C)]
+#[allow(non_snake_case)]
+pub(crate) struct INPUT_MESSAGE_SOURCE {
+    pub deviceType: DWORD,
+    pub originId: DWORD,
+}
+
+/// The `INPUT_MESSAGE_SOURCE` device type of touchpads.
+pub(crate) const IMDT_TOUCHPAD: DWORD = 0x0000_0008;
+
 // Types for functions we want to load, which are only supported on newer windows versions
 // from user32.dll
 type GetDpiForSystem = unsafe extern "system" fn() -> UINT;
@@ -148,13 +191,34 @@ type SetProcessDpiAwarenessContext =
 type GetSystemMetricsForDpi =
     unsafe extern "system" fn(winapi::ctypes::c_int, UINT) -> winapi::ctypes::c_int;
 // from shcore.dll
-type GetDpiForMonitor = unsafe extern "system" fn(HMONITOR, MONITOR_DPI_TYPE, *mut UINT, *mut UINT);
+type GetDpiForMonitor =
+    unsafe extern "system" fn(HMONITOR, MONITOR_DPI_TYPE, *mut UINT, *mut UINT) -> HRESULT;
 type SetProcessDpiAwareness = unsafe extern "system" fn(PROCESS_DPI_AWARENESS) -> HRESULT;
 type DCompositionCreateDevice = unsafe extern "system" fn(
     dxgiDevice: *const IDXGIDevice,
     iid: REFIID,
     dcompositionDevice: *mut *mut c_void,
 ) -> HRESULT;
+// from wtsapi32.dll
+type WTSRegisterSessionNotification = unsafe extern "system" fn(HWND, DWORD) -> BOOL;
+type WTSUnRegisterSessionNotification = unsafe extern "system" fn(HWND) -> BOOL;
+// from uiautomationcore.dll
+type UiaHostProviderFromHwnd = unsafe extern "system" fn(HWND, *mut *mut IUnknown) -> HRESULT;
+type UiaRaiseNotificationEvent =
+    unsafe extern "system" fn(*mut IUnknown, c_int, c_int, BSTR, BSTR) -> HRESULT;
+// from imm32.dll
+type ImmAssociateContextEx = unsafe extern "system" fn(HWND, *mut c_void, DWORD) -> BOOL;
+// from user32.dll, for pen input
+type GetPointerType = unsafe extern "system" fn(UINT32, *mut POINTER_INPUT_TYPE) -> BOOL;
+type GetPointerPenInfo = unsafe extern "system" fn(UINT32, *mut POINTER_PEN_INFO) -> BOOL;
+type GetPointerInfo = unsafe extern "system" fn(UINT32, *mut POINTER_INFO) -> BOOL;
+// from user32.dll, for telling touchpads from mice
+type GetCurrentInputMessageSource = unsafe extern "system" fn(*mut INPUT_MESSAGE_SOURCE) -> BOOL;
+// from user32.dll, for blocking the end of the session
+type ShutdownBlockReasonCreate = unsafe extern "system" fn(HWND, LPCWSTR) -> BOOL;
+type ShutdownBlockReasonDestroy = unsafe extern "system" fn(HWND) -> BOOL;
+// from ntdll.dll
+type RtlGetVersion = unsafe extern "system" fn(*mut OSVERSIONINFOW) -> NTSTATUS;
 
 #[allow(non_snake_case)] // For member fields
 pub struct OptionalFunctions {
@@ -165,6 +229,18 @@ pub struct OptionalFunctions {
     pub SetProcessDpiAwareness: Option<SetProcessDpiAwareness>,
     pub GetSystemMetricsForDpi: Option<GetSystemMetricsForDpi>,
     pub DCompositionCreateDevice: Option<DCompositionCreateDevice>,
+    pub WTSRegisterSessionNotification: Option<WTSRegisterSessionNotification>,
+    pub WTSUnRegisterSessionNotification: Option<WTSUnRegisterSessionNotification>,
+    pub UiaHostProviderFromHwnd: Option<UiaHostProviderFromHwnd>,
+    pub UiaRaiseNotificationEvent: Option<UiaRaiseNotificationEvent>,
+    pub GetPointerType: Option<GetPointerType>,
+    pub GetPointerPenInfo: Option<GetPointerPenInfo>,
+    pub GetPointerInfo: Option<GetPointerInfo>,
+    pub GetCurrentInputMessageSource: Option<GetCurrentInputMessageSource>,
+    pub ImmAssociateContextEx: Option<ImmAssociateContextEx>,
+    pub ShutdownBlockReasonCreate: Option<ShutdownBlockReasonCreate>,
+    pub ShutdownBlockReasonDestroy: Option<ShutdownBlockReasonDestroy>,
+    pub RtlGetVersion: Option<RtlGetVersion>,
 }
 
 #[allow(non_snake_case)] // For local variables
@@ -211,6 +287,10 @@ fn load_optional_functions() -> OptionalFunctions {
     let shcore = load_library("shcore.dll");
     let user32 = load_library("user32.dll");
     let dcomp = load_library("dcomp.dll");
+    let wtsapi32 = load_library("wtsapi32.dll");
+    let uiautomationcore = load_library("uiautomationcore.dll");
+    let imm32 = load_library("imm32.dll");
+    let ntdll = load_library("ntdll.dll");
 
     let mut GetDpiForSystem = None;
     let mut GetDpiForMonitor = None;
@@ -219,6 +299,18 @@ fn load_optional_functions() -> OptionalFunctions {
     let mut SetProcessDpiAwareness = None;
     let mut GetSystemMetricsForDpi = None;
     let mut DCompositionCreateDevice = None;
+    let mut WTSRegisterSessionNotification = None;
+    let mut WTSUnRegisterSessionNotification = None;
+    let mut UiaHostProviderFromHwnd = None;
+    let mut UiaRaiseNotificationEvent = None;
+    let mut GetPointerType = None;
+    let mut GetPointerPenInfo = None;
+    let mut GetPointerInfo = None;
+    let mut GetCurrentInputMessageSource = None;
+    let mut ImmAssociateContextEx = None;
+    let mut ShutdownBlockReasonCreate = None;
+    let mut ShutdownBlockReasonDestroy = None;
+    let mut RtlGetVersion = None;
 
     if shcore.is_null() {
         tracing::info!("No shcore.dll");
@@ -234,6 +326,12 @@ fn load_optional_functions() -> OptionalFunctions {
         load_function!(user32, GetDpiForWindow, "10");
         load_function!(user32, SetProcessDpiAwarenessContext, "10");
         load_function!(user32, GetSystemMetricsForDpi, "10");
+        load_function!(user32, GetPointerType, "8");
+        load_function!(user32, GetPointerPenInfo, "8");
+        load_function!(user32, GetPointerInfo, "8");
+        load_function!(user32, GetCurrentInputMessageSource, "8");
+        load_function!(user32, ShutdownBlockReasonCreate, "Vista");
+        load_function!(user32, ShutdownBlockReasonDestroy, "Vista");
     }
 
     if dcomp.is_null() {
@@ -242,6 +340,32 @@ fn load_optional_functions() -> OptionalFunctions {
         load_function!(dcomp, DCompositionCreateDevice, "8.1");
     }
 
+    if wtsapi32.is_null() {
+        tracing::info!("No wtsapi32.dll");
+    } else {
+        load_function!(wtsapi32, WTSRegisterSessionNotification, "XP");
+        load_function!(wtsapi32, WTSUnRegisterSessionNotification, "XP");
+    }
+
+    if uiautomationcore.is_null() {
+        tracing::info!("No uiautomationcore.dll");
+    } else {
+        load_function!(uiautomationcore, UiaHostProviderFromHwnd, "7");
+        load_function!(uiautomationcore, UiaRaiseNotificationEvent, "10");
+    }
+
+    if imm32.is_null() {
+        tracing::info!("No imm32.dll");
+    } else {
+        load_function!(imm32, ImmAssociateContextEx, "2000");
+    }
+
+    if ntdll.is_null() {
+        tracing::info!("No ntdll.dll");
+    } else {
+        load_function!(ntdll, RtlGetVersion, "2000");
+    }
+
     OptionalFunctions {
         GetDpiForSystem,
         GetDpiForWindow,
@@ -250,12 +374,53 @@ fn load_optional_functions() -> OptionalFunctions {
         SetProcessDpiAwareness,
         GetSystemMetricsForDpi,
         DCompositionCreateDevice,
+        WTSRegisterSessionNotification,
+        WTSUnRegisterSessionNotification,
+        UiaHostProviderFromHwnd,
+        UiaRaiseNotificationEvent,
+        GetPointerType,
+        GetPointerPenInfo,
+        GetPointerInfo,
+        GetCurrentInputMessageSource,
+        ImmAssociateContextEx,
+        ShutdownBlockReasonCreate,
+        ShutdownBlockReasonDestroy,
+        RtlGetVersion,
     }
 }
 
 pub static OPTIONAL_FUNCTIONS: Lazy<OptionalFunctions> = Lazy::new(load_optional_functions);
 
+/// Returns the DPI of the primary monitor when the process started.
+pub(crate) fn system_dpi() -> UINT {
+    unsafe {
+        if let Some(func) = OPTIONAL_FUNCTIONS.GetDpiForSystem {
+            return func();
+        }
+        let hdc = GetDC(ptr::null_mut());
+        if hdc.is_null() {
+            return 96;
+        }
+        let dpi = GetDeviceCaps(hdc, LOGPIXELSX);
+        ReleaseDC(ptr::null_mut(), hdc);
+        dpi as UINT
+    }
+}
+
+/// Returns the size in pixels of the system's cursors at `dpi`.
+///
+/// Before Windows 10 this is the size at the system DPI, whatever `dpi` is.
+pub(crate) fn cursor_size(dpi: UINT) -> (c_int, c_int) {
+    unsafe {
+        match OPTIONAL_FUNCTIONS.GetSystemMetricsForDpi {
+            Some(func) => (func(SM_CXCURSOR, dpi), func(SM_CYCURSOR, dpi)),
+            None => (GetSystemMetrics(SM_CXCURSOR), GetSystemMetrics(SM_CYCURSOR)),
+        }
+    }
+}
+
 pub(crate) const CLASS_NAME: &str = "druid";
+pub(crate) const APP_CLASS_NAME: &str = "druid_app";
 
 /// Convenience macro for defining accelerator tables.
 #[macro_export]
@@ -17,8 +17,10 @@
 #![allow(non_snake_case, clippy::cast_lossless)]
 
 use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::mem;
 use std::panic::Location;
+use std::path::Path;
 use std::ptr::{null, null_mut};
 use std::rc::{Rc, Weak};
 use std::sync::{Arc, Mutex};
@@ -37,6 +39,7 @@ use winapi::shared::winerror::*;
 use winapi::um::dcomp::{IDCompositionDevice, IDCompositionTarget, IDCompositionVisual};
 use winapi::um::dwmapi::{DwmExtendFrameIntoClientArea, DwmSetWindowAttribute};
 use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::oleauto::{SysAllocString, SysFreeString};
 use winapi::um::shellscalingapi::MDT_EFFECTIVE_DPI;
 use winapi::um::unknwnbase::*;
 use winapi::um::uxtheme::*;
@@ -54,30 +57,33 @@ use piet_common::d2d::{D2DFactory, DeviceContext};
 use piet_common::dwrite::DwriteFactory;
 
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::piet::{Color, Piet, PietText, RenderContext};
 
-use super::accels::register_accel;
+use super::accels::{find_accels, register_accel};
 use super::application::Application;
 use super::dcomp::D3D11Device;
-use super::dialog::get_file_dialog_path;
+use super::dialog::{self, get_file_dialog_path};
 use super::error::Error;
 use super::keyboard::{self, KeyboardState};
-use super::menu::Menu;
+use super::menu::{self, Menu};
 use super::paint;
 use super::timers::TimerSlots;
 use super::util::{self, as_result, FromWide, ToWide, OPTIONAL_FUNCTIONS};
 
-use crate::common_util::IdleCallback;
-use crate::dialog::{FileDialogOptions, FileDialogType, FileInfo};
+use crate::accessibility::AccessibilityTree;
+use crate::common_util::{AnimationClock, CursorStack, IdleCallback};
+use crate::dialog::{file_info, FileDialogOptions, FileDialogType};
 use crate::error::Error as ShellError;
 use crate::keyboard::{KbKey, KeyState};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 use crate::region::Region;
 use crate::scale::{Scalable, Scale, ScaledArea};
+use crate::screen::Screen;
 use crate::text::{simulate_input, Event};
+use crate::touch::{TouchEvent, TouchId, TouchPhase};
 use crate::window;
 use crate::window::{
-    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel,
+    FileDialogToken, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowLevel, WindowType,
 };
 
 /// The backend target DPI.
@@ -85,6 +91,11 @@ use crate::window::{
 /// Windows considers 96 the default value which represents a 1.0 scale factor.
 pub(crate) const SCALE_TARGET_DPI: f64 = 96.0;
 
+// UI Automation notification values, which aren't in winapi yet.
+const NOTIFICATION_KIND_OTHER: c_int = 4;
+const NOTIFICATION_PROCESSING_IMPORTANT_MOST_RECENT: c_int = 1;
+const NOTIFICATION_PROCESSING_ALL: c_int = 2;
+
 /// Builder abstraction for creating new windows.
 pub(crate) struct WindowBuilder {
     app: Application,
@@ -96,11 +107,15 @@ pub(crate) struct WindowBuilder {
     show_titlebar: bool,
     size: Option<Size>,
     transparent: bool,
+    background_color: Option<Color>,
     min_size: Option<Size>,
     position: Option<Point>,
     level: Option<WindowLevel>,
     always_on_top: bool,
     state: window::WindowState,
+    owner: Option<WindowHandle>,
+    modal: bool,
+    window_type: WindowType,
 }
 
 #[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
@@ -167,6 +182,9 @@ enum DeferredOp {
     ReleaseMouseCapture,
     SetRegion(Option<Region>),
     SetAlwaysOnTop(bool),
+    SetAlwaysOnBottom(bool),
+    SetOpacity(f64),
+    UpdateIme,
 }
 
 #[derive(Clone, Debug)]
@@ -238,11 +256,27 @@ struct WindowState {
     is_resizable: Cell<bool>,
     handle_titlebar: Cell<bool>,
     active_text_input: Cell<Option<TextFieldToken>>,
+    // Whether the input method is on for the window, and the text fields it's off for.
+    ime_enabled: Cell<bool>,
+    ime_disabled_fields: RefCell<HashSet<TextFieldToken>>,
     // Is the window focusable ("activatable" in Win32 terminology)?
     // False for tooltips, to prevent stealing focus from owner window.
     is_focusable: bool,
     window_level: WindowLevel,
+    // The owner window that is disabled while this window is shown.
+    modal_owner: Option<HWND>,
     is_always_on_top: Cell<bool>,
+    // Whether the window stays below all the others, which `WM_WINDOWPOSCHANGING` enforces.
+    is_always_on_bottom: Cell<bool>,
+    // The number of unbalanced `set_cursor_visible(false)` calls.
+    cursor_hide_count: Cell<u32>,
+    is_cursor_confined: Cell<bool>,
+    cursor: RefCell<CursorStack>,
+    anim_clock: Cell<AnimationClock>,
+    // The ratio of client width to height kept while the user resizes the window.
+    aspect_ratio: Cell<Option<f64>>,
+    // The color that `WM_ERASEBKGND` fills the window with, until it is painted.
+    background_color: Cell<Option<COLORREF>>,
 }
 
 impl std::fmt::Debug for WindowState {
@@ -254,6 +288,16 @@ impl std::fmt::Debug for WindowState {
     }
 }
 
+impl WindowState {
+    /// Whether keys go through the input method for the focused text field.
+    fn ime_active(&self) -> bool {
+        self.ime_enabled.get()
+            && self.active_text_input.get().map_or(true, |token| {
+                !self.ime_disabled_fields.borrow().contains(&token)
+            })
+    }
+}
+
 /// Generic handler trait for the winapi window procedure entry point.
 trait WndProc {
     fn connect(&self, handle: &WindowHandle, state: WndState);
@@ -293,6 +337,21 @@ struct WndState {
     last_click_time: Instant,
     last_click_pos: (i32, i32),
     click_count: u8,
+    // The most recent pen input, which Windows goes on to report as mouse messages.
+    pen_input: Option<PenInput>,
+    minimized: bool,
+    // Whether the user is moving or resizing the window, and whether it moved since.
+    in_size_move: bool,
+    moved_in_size_move: bool,
+}
+
+/// The details of pen input that don't fit in mouse messages.
+#[derive(Clone, Copy)]
+struct PenInput {
+    pointer_type: PointerType,
+    pressure: f64,
+    tilt_x: f64,
+    tilt_y: f64,
 }
 
 /// State for DXGI swapchains.
@@ -337,6 +396,30 @@ const DS_RUN_IDLE: UINT = WM_USER;
 /// time it is handled, we can successfully borrow the handler.
 pub(crate) const DS_REQUEST_DESTROY: UINT = WM_USER + 1;
 
+/// Message asking a window that closes on focus loss to close, see [`dismiss_windows`].
+const DS_DISMISS: UINT = WM_USER + 2;
+
+thread_local! {
+    /// The windows that close when the user clicks outside of them or switches apps.
+    static DISMISSABLE_WINDOWS: RefCell<Vec<HWND>> = RefCell::new(Vec::new());
+}
+
+/// Asks the windows that close on focus loss to close, except for `except`.
+///
+/// A window that never takes focus doesn't know when it is clicked outside of, so every
+/// click in one of our windows dismisses the others.
+fn dismiss_windows(except: Option<HWND>) {
+    DISMISSABLE_WINDOWS.with(|windows| {
+        for &hwnd in windows.borrow().iter() {
+            if Some(hwnd) != except {
+                unsafe {
+                    PostMessageW(hwnd, DS_DISMISS, 0, 0);
+                }
+            }
+        }
+    });
+}
+
 /// Extract the buttons that are being held down from wparam in mouse events.
 fn get_buttons(wparam: WPARAM) -> MouseButtons {
     let mut buttons = MouseButtons::new();
@@ -426,6 +509,121 @@ fn set_style(hwnd: HWND, resizable: bool, titlebar: bool) {
 }
 
 /// The ex style is different from the non-ex styles.
+/// Makes the window layered if it isn't yet, and sets its alpha.
+fn set_opacity(hwnd: HWND, opacity: f64) {
+    unsafe {
+        let style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        if style == 0 {
+            warn!(
+                "failed to get window ex style: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+            return;
+        }
+        if style & WS_EX_LAYERED == 0
+            && SetWindowLongPtrW(hwnd, GWL_EXSTYLE, (style | WS_EX_LAYERED) as _) == 0
+        {
+            warn!(
+                "failed to set the window ex style: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+            return;
+        }
+        let alpha = (opacity * 255.0).round() as u8;
+        if SetLayeredWindowAttributes(hwnd, 0, alpha, LWA_ALPHA) == FALSE {
+            warn!(
+                "SetLayeredWindowAttributes failed: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+        }
+    }
+}
+
+fn to_colorref(color: Color) -> COLORREF {
+    let (r, g, b, _) = color.as_rgba8();
+    RGB(r, g, b)
+}
+
+/// Fills the client area of `hwnd` with `color`, for `WM_ERASEBKGND`.
+fn erase_background(hwnd: HWND, hdc: HDC, color: COLORREF) {
+    unsafe {
+        let mut rect: RECT = mem::zeroed();
+        if GetClientRect(hwnd, &mut rect) == FALSE {
+            return;
+        }
+        let brush = CreateSolidBrush(color);
+        FillRect(hdc, &rect, brush);
+        DeleteObject(brush as HGDIOBJ);
+    }
+}
+
+/// Returns the position in `hmenu` of the item whose hotkey is the character `ch` with the
+/// modifiers that are currently held down.
+unsafe fn menu_item_for_hotkey(hwnd: HWND, ch: WORD, hmenu: HMENU) -> Option<WORD> {
+    let vk = match ch {
+        // Holding Ctrl turns letters into control characters.
+        1..=26 => b'A' as WORD + ch - 1,
+        _ => {
+            let vk = VkKeyScanW(ch);
+            if vk == -1 {
+                return None;
+            }
+            (vk & 0xff) as WORD
+        }
+    };
+    let mut mods = 0;
+    if GetKeyState(VK_SHIFT) < 0 {
+        mods |= FSHIFT;
+    }
+    if GetKeyState(VK_CONTROL) < 0 {
+        mods |= FCONTROL;
+    }
+    if GetKeyState(VK_MENU) < 0 {
+        mods |= FALT;
+    }
+    let command = find_accels(GetAncestor(hwnd, GA_ROOT))?.find_command(mods, vk)?;
+    (0..GetMenuItemCount(hmenu))
+        .find(|&pos| GetMenuItemID(hmenu, pos) == command as UINT)
+        .map(|pos| pos as WORD)
+}
+
+/// Adjusts the window rect proposed by `WM_SIZING` so that the client area keeps `ratio`,
+/// following the edge that is being dragged.
+fn keep_aspect_ratio(hwnd: HWND, edge: WPARAM, rect: &mut RECT, ratio: f64) {
+    // The ratio is for the client area, so leave the frame out of it.
+    let (frame_width, frame_height) = unsafe {
+        let mut window_rect: RECT = mem::zeroed();
+        let mut client_rect: RECT = mem::zeroed();
+        if GetWindowRect(hwnd, &mut window_rect) == 0 || GetClientRect(hwnd, &mut client_rect) == 0
+        {
+            warn!(
+                "failed to get window frame: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+            return;
+        }
+        (
+            (window_rect.right - window_rect.left) - (client_rect.right - client_rect.left),
+            (window_rect.bottom - window_rect.top) - (client_rect.bottom - client_rect.top),
+        )
+    };
+    let width = (rect.right - rect.left - frame_width) as f64;
+    let height = (rect.bottom - rect.top - frame_height) as f64;
+    match edge {
+        // Dragging the top or bottom edge changes the height, so the width follows it.
+        WMSZ_TOP | WMSZ_BOTTOM => {
+            rect.right = rect.left + (height * ratio).round() as i32 + frame_width;
+        }
+        // Otherwise the width leads, and the height grows away from the dragged corner.
+        WMSZ_TOPLEFT | WMSZ_TOPRIGHT => {
+            rect.top = rect.bottom - (width / ratio).round() as i32 - frame_height;
+        }
+        _ => {
+            rect.bottom = rect.top + (width / ratio).round() as i32 + frame_height;
+        }
+    }
+}
+
 fn set_ex_style(hwnd: HWND, always_on_top: bool) {
     unsafe {
         let mut style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
@@ -473,6 +671,55 @@ fn set_ex_style(hwnd: HWND, always_on_top: bool) {
     }
 }
 
+/// Turns the input method on or off, which discards any composition when it is turned off.
+fn set_ime_enabled(hwnd: HWND, enabled: bool) {
+    const IACE_DEFAULT: DWORD = 0x0010;
+    if let Some(func) = OPTIONAL_FUNCTIONS.ImmAssociateContextEx {
+        // Associating no input context turns the input method off, and the default one
+        // turns it back on.
+        let flags = if enabled { IACE_DEFAULT } else { 0 };
+        unsafe {
+            if func(hwnd, null_mut(), flags) == FALSE {
+                warn!("failed to associate the input context");
+            }
+        }
+    }
+}
+
+/// Keeps the window below all the others, without it taking focus when clicked.
+fn set_always_on_bottom(hwnd: HWND, on_bottom: bool) {
+    unsafe {
+        let mut style = GetWindowLongPtrW(hwnd, GWL_EXSTYLE) as u32;
+        if on_bottom {
+            style |= WS_EX_NOACTIVATE;
+        } else {
+            style &= !WS_EX_NOACTIVATE;
+        }
+        if SetWindowLongPtrW(hwnd, GWL_EXSTYLE, style as _) == 0 {
+            warn!(
+                "failed to set the window ex style: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+        }
+        if on_bottom
+            && SetWindowPos(
+                hwnd,
+                HWND_BOTTOM,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            ) == 0
+        {
+            warn!(
+                "failed to move the window to the bottom: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+        }
+    }
+}
+
 impl WndState {
     fn rebuild_render_target(&mut self, d2d: &D2DFactory, scale: Scale) -> Result<(), Error> {
         unsafe {
@@ -524,6 +771,26 @@ impl WndState {
         self.captured_mouse_buttons.remove(button);
         self.captured_mouse_buttons.is_empty()
     }
+
+    /// Returns the pointer type, pressure and tilt for the mouse message being handled.
+    ///
+    /// Windows reports pen and touch input as mouse messages too, and marks them in the
+    /// message's extra info.
+    fn pointer_details(&self, buttons: MouseButtons) -> (PointerType, f64, f64, f64) {
+        const SIGNATURE_MASK: usize = 0xFFFF_FF00;
+        const MI_WP_SIGNATURE: usize = 0xFF51_5700;
+        const TOUCH_FLAG: usize = 0x80;
+        let extra_info = unsafe { GetMessageExtraInfo() } as usize;
+        if extra_info & SIGNATURE_MASK != MI_WP_SIGNATURE {
+            (PointerType::Mouse, buttons.default_pressure(), 0.0, 0.0)
+        } else if extra_info & TOUCH_FLAG != 0 {
+            (PointerType::Touch, buttons.default_pressure(), 0.0, 0.0)
+        } else if let Some(pen) = self.pen_input {
+            (pen.pointer_type, pen.pressure, pen.tilt_x, pen.tilt_y)
+        } else {
+            (PointerType::Pen, buttons.default_pressure(), 0.0, 0.0)
+        }
+    }
 }
 
 impl MyWndProc {
@@ -541,6 +808,20 @@ impl MyWndProc {
             .unwrap()) // WindowState drops after WM_NCDESTROY, so it's always here.
     }
 
+    /// Gives input back to the owner of a modal window.
+    ///
+    /// This has to happen before the window is destroyed, so that Windows activates the owner
+    /// instead of some other application.
+    fn enable_modal_owner(&self) {
+        self.with_window_state(|state| {
+            if let Some(owner) = state.modal_owner {
+                unsafe {
+                    EnableWindow(owner, TRUE);
+                }
+            }
+        });
+    }
+
     #[track_caller]
     fn with_wnd_state<F, R>(&self, f: F) -> Option<R>
     where
@@ -597,6 +878,31 @@ impl MyWndProc {
         self.with_window_state(|state| state.is_transparent.get())
     }
 
+    /// Tells the handler where the window is now, and which monitor it is on.
+    fn report_move(&self, hwnd: HWND) {
+        let position = self.handle.borrow().get_position();
+        let mut rect: RECT = unsafe { mem::zeroed() };
+        // Monitors are in pixels on Windows, so they are matched with the frame in pixels.
+        let monitor = if unsafe { GetWindowRect(hwnd, &mut rect) } != 0 {
+            Screen::monitor_for_rect(util::recti_to_rect(rect))
+        } else {
+            None
+        };
+        self.with_wnd_state(|s| s.handler.window_moved(position, monitor));
+    }
+
+    /// Windows resets the cursor clip when the window loses focus, and it doesn't follow
+    /// the window around, so we need to reapply it whenever either changes.
+    fn update_cursor_clip(&self, hwnd: HWND, active: bool) {
+        if self.with_window_state(|state| state.is_cursor_confined.get()) {
+            if active {
+                clip_cursor_to_client(hwnd);
+            } else {
+                unsafe { ClipCursor(null()) };
+            }
+        }
+    }
+
     fn handle_deferred_queue(&self) {
         let q = self.with_window_state(move |state| state.deferred_queue.replace(Vec::new()));
         for op in q {
@@ -654,8 +960,26 @@ impl MyWndProc {
                 }
                 DeferredOp::SetAlwaysOnTop(always_on_top) => {
                     self.with_window_state(|s| s.is_always_on_top.set(always_on_top));
+                    // A window can't be on top and on the bottom at once.
+                    if always_on_top
+                        && self.with_window_state(|s| s.is_always_on_bottom.replace(false))
+                    {
+                        set_always_on_bottom(hwnd, false);
+                    }
                     set_ex_style(hwnd, always_on_top);
                 }
+                DeferredOp::SetAlwaysOnBottom(on_bottom) => {
+                    self.with_window_state(|s| s.is_always_on_bottom.set(on_bottom));
+                    if on_bottom {
+                        self.with_window_state(|s| s.is_always_on_top.set(false));
+                        set_ex_style(hwnd, false);
+                    }
+                    set_always_on_bottom(hwnd, on_bottom);
+                }
+                DeferredOp::SetOpacity(opacity) => set_opacity(hwnd, opacity),
+                DeferredOp::UpdateIme => {
+                    set_ime_enabled(hwnd, self.with_window_state(|s| s.ime_active()));
+                }
                 DeferredOp::SetWindowState(val) => {
                     let show = if self.handle.borrow().is_focusable() {
                         match val {
@@ -685,10 +1009,7 @@ impl MyWndProc {
                     let info = unsafe {
                         get_file_dialog_path(hwnd, FileDialogType::Save, options)
                             .ok()
-                            .map(|os_str| FileInfo {
-                                path: os_str.into(),
-                                format: None,
-                            })
+                            .map(|os_str| file_info(os_str.into(), dialog::content_type))
                     };
                     self.with_wnd_state(|s| s.handler.save_as(token, info));
                 }
@@ -696,10 +1017,7 @@ impl MyWndProc {
                     let info = unsafe {
                         get_file_dialog_path(hwnd, FileDialogType::Open, options)
                             .ok()
-                            .map(|s| FileInfo {
-                                path: s.into(),
-                                format: None,
-                            })
+                            .map(|s| file_info(s.into(), dialog::content_type))
                     };
                     self.with_wnd_state(|s| s.handler.open_file(token, info));
                 }
@@ -712,11 +1030,15 @@ impl MyWndProc {
                             y: pos.y as i32,
                         };
                         ClientToScreen(hwnd, &mut point);
-                        if TrackPopupMenu(hmenu, TPM_LEFTALIGN, point.x, point.y, 0, hwnd, null())
+                        // The menu is moved to stay on the monitor containing the point, and
+                        // the command is posted to the window once it closes.
+                        let flags = TPM_LEFTALIGN | TPM_TOPALIGN | TPM_RIGHTBUTTON;
+                        if TrackPopupMenuEx(hmenu, flags, point.x, point.y, hwnd, null_mut())
                             == FALSE
                         {
                             warn!("failed to track popup menu");
                         }
+                        DestroyMenu(hmenu);
                     }
                 }
                 DeferredOp::ReleaseMouseCapture => unsafe {
@@ -933,7 +1255,9 @@ impl WndProc for MyWndProc {
                         let monitor = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
                         let mut dpiX = 0;
                         let mut dpiY = 0;
-                        func(monitor, MDT_EFFECTIVE_DPI, &mut dpiX, &mut dpiY);
+                        if !SUCCEEDED(func(monitor, MDT_EFFECTIVE_DPI, &mut dpiX, &mut dpiY)) {
+                            dpiX = util::system_dpi();
+                        }
                         dpiX as f64 / SCALE_TARGET_DPI
                     }
                 } else {
@@ -965,9 +1289,15 @@ impl WndProc for MyWndProc {
                 Some(0)
             }
             WM_ACTIVATE => {
+                if LOWORD(wparam as u32) as u32 == WA_INACTIVE {
+                    unsafe {
+                        PostMessageW(hwnd, DS_DISMISS, 0, 0);
+                    }
+                }
                 // Check if the low-order word is not 0
                 // If it were 0, then that means we are being deactivated, in which case we do nothing.
                 // If it is != 0, then we have been activated.
+                self.update_cursor_clip(hwnd, LOWORD(wparam as u32) as u32 != 0);
                 if LOWORD(wparam as u32) as u32 != 0 {
                     unsafe {
                         if !self.has_titlebar() && !self.is_transparent() {
@@ -1007,7 +1337,29 @@ impl WndProc for MyWndProc {
                 }
                 Some(0)
             }
-            WM_ERASEBKGND => Some(0),
+            WM_ACTIVATEAPP => {
+                if wparam == FALSE as WPARAM {
+                    dismiss_windows(None);
+                }
+                None
+            }
+            WM_INPUTLANGCHANGE => {
+                self.app.keyboard_layout_changed();
+                None
+            }
+            WM_ERASEBKGND => {
+                // Without a background color, the area is left as it is until we paint it.
+                let color = self.with_window_state(|s| {
+                    s.background_color.get().filter(|_| !s.is_transparent.get())
+                });
+                match color {
+                    Some(color) => {
+                        erase_background(hwnd, wparam as HDC, color);
+                        Some(1)
+                    }
+                    None => Some(0),
+                }
+            }
             WM_SETFOCUS => {
                 self.with_wnd_state(|s| s.handler.got_focus());
                 Some(0)
@@ -1017,7 +1369,16 @@ impl WndProc for MyWndProc {
                 Some(0)
             }
             WM_PAINT => unsafe {
+                let anim_interval = self.handle.borrow().state.upgrade().and_then(|state| {
+                    let mut clock = state.anim_clock.get();
+                    let interval = clock.frame(Instant::now());
+                    state.anim_clock.set(clock);
+                    interval
+                });
                 self.with_wnd_state(|s| {
+                    if let Some(interval) = anim_interval {
+                        s.handler.anim_frame(interval);
+                    }
                     // We call prepare_paint before GetUpdateRect, so that anything invalidated during
                     // prepare_paint will be reflected in GetUpdateRect.
                     s.handler.prepare_paint();
@@ -1049,6 +1410,8 @@ impl WndProc for MyWndProc {
                 Some(0)
             },
             WM_DPICHANGED => unsafe {
+                // This is also how we learn that the scale of a monitor changed.
+                crate::screen::monitors_changed();
                 let x = HIWORD(wparam as u32) as f64 / SCALE_TARGET_DPI;
                 let y = LOWORD(wparam as u32) as f64 / SCALE_TARGET_DPI;
                 let scale = Scale::new(x, y);
@@ -1145,7 +1508,41 @@ impl WndProc for MyWndProc {
                 }
                 Some(hit)
             },
+            WM_MOVE => {
+                self.update_cursor_clip(hwnd, unsafe { GetActiveWindow() } == hwnd);
+                // Moves during a drag are reported together once it ends.
+                let report = self.with_wnd_state(|s| {
+                    s.moved_in_size_move |= s.in_size_move;
+                    !s.in_size_move
+                });
+                if report == Some(true) {
+                    self.report_move(hwnd);
+                }
+                None
+            }
+            WM_ENTERSIZEMOVE => {
+                self.with_wnd_state(|s| s.in_size_move = true);
+                None
+            }
+            WM_EXITSIZEMOVE => {
+                let moved = self.with_wnd_state(|s| {
+                    s.in_size_move = false;
+                    mem::take(&mut s.moved_in_size_move)
+                });
+                if moved == Some(true) {
+                    self.report_move(hwnd);
+                }
+                None
+            }
             WM_SIZE => unsafe {
+                self.update_cursor_clip(hwnd, GetActiveWindow() == hwnd);
+                let minimized = wparam == SIZE_MINIMIZED;
+                self.with_wnd_state(|s| {
+                    if s.minimized != minimized {
+                        s.minimized = minimized;
+                        s.handler.occlusion_changed(!minimized);
+                    }
+                });
                 let width = LOWORD(lparam as u32) as u32;
                 let height = HIWORD(lparam as u32) as u32;
                 if width == 0 || height == 0 {
@@ -1191,6 +1588,13 @@ impl WndProc for MyWndProc {
                 self.with_wnd_state(|s| s.handler.command(LOWORD(wparam as u32) as u32));
                 Some(0)
             }
+            // This is also sent before the hotkey of a menu item is handled.
+            WM_INITMENUPOPUP => {
+                self.with_wnd_state(|s| unsafe {
+                    menu::update_enabled_items(wparam as HMENU, &mut *s.handler)
+                });
+                Some(0)
+            }
             //TODO: WM_SYSCOMMAND
             WM_CHAR | WM_SYSCHAR | WM_KEYDOWN | WM_SYSKEYDOWN | WM_KEYUP | WM_SYSKEYUP
             | WM_INPUTLANGCHANGE => {
@@ -1245,6 +1649,25 @@ impl WndProc for MyWndProc {
                     }
                 }
             }
+            WM_POINTERDOWN | WM_POINTERUPDATE | WM_POINTERUP => {
+                // We let Windows turn these into mouse messages, and only remember the pen
+                // details to add to those. Touches are also reported as they are.
+                let pointer_id = GET_POINTERID_WPARAM(wparam) as u32;
+                let pen_input = unsafe { get_pen_input(pointer_id) };
+                let touch = unsafe { get_touch_info(hwnd, pointer_id, msg) };
+                self.with_wnd_state(|s| {
+                    s.pen_input = pen_input;
+                    if let Some((pos, phase)) = touch {
+                        let event = TouchEvent {
+                            id: TouchId::from_raw(pointer_id as u64),
+                            pos: pos.to_dp(self.scale()),
+                            phase,
+                        };
+                        s.handler.touch(&event);
+                    }
+                });
+                None
+            }
             WM_MOUSEWHEEL | WM_MOUSEHWHEEL => {
                 // TODO: apply mouse sensitivity based on
                 // SPI_GETWHEELSCROLLLINES setting.
@@ -1274,6 +1697,11 @@ impl WndProc for MyWndProc {
                         }
                     }
 
+                    let pointer_type = if unsafe { is_touchpad_message() } {
+                        PointerType::Touchpad
+                    } else {
+                        PointerType::Mouse
+                    };
                     let pos = Point::new(p.x as f64, p.y as f64).to_dp(self.scale());
                     let buttons = get_buttons(down_state);
                     let event = MouseEvent {
@@ -1284,6 +1712,10 @@ impl WndProc for MyWndProc {
                         focus: false,
                         button: MouseButton::None,
                         wheel_delta,
+                        pointer_type,
+                        pressure: buttons.default_pressure(),
+                        tilt_x: 0.0,
+                        tilt_y: 0.0,
                     };
                     s.handler.wheel(&event);
                     true
@@ -1313,6 +1745,7 @@ impl WndProc for MyWndProc {
                         unsafe {
                             if TrackMouseEvent(&mut desc) != FALSE {
                                 s.has_mouse_focus = true;
+                                s.handler.mouse_enter();
                             } else {
                                 warn!(
                                     "failed to TrackMouseEvent: {}",
@@ -1325,6 +1758,7 @@ impl WndProc for MyWndProc {
                     let pos = Point::new(x as f64, y as f64).to_dp(self.scale());
                     let mods = s.keyboard_state.get_modifiers();
                     let buttons = get_buttons(wparam);
+                    let (pointer_type, pressure, tilt_x, tilt_y) = s.pointer_details(buttons);
                     let event = MouseEvent {
                         pos,
                         buttons,
@@ -1333,6 +1767,10 @@ impl WndProc for MyWndProc {
                         focus: false,
                         button: MouseButton::None,
                         wheel_delta: Vec2::ZERO,
+                        pointer_type,
+                        pressure,
+                        tilt_x,
+                        tilt_y,
                     };
                     s.handler.mouse_move(&event);
                 });
@@ -1340,8 +1778,10 @@ impl WndProc for MyWndProc {
             }
             WM_MOUSELEAVE => {
                 self.with_wnd_state(|s| {
-                    s.has_mouse_focus = false;
-                    s.handler.mouse_leave();
+                    if s.has_mouse_focus {
+                        s.has_mouse_focus = false;
+                        s.handler.mouse_leave();
+                    }
                 });
                 Some(0)
             }
@@ -1352,6 +1792,12 @@ impl WndProc for MyWndProc {
             WM_LBUTTONDBLCLK | WM_LBUTTONDOWN | WM_LBUTTONUP | WM_RBUTTONDBLCLK
             | WM_RBUTTONDOWN | WM_RBUTTONUP | WM_MBUTTONDBLCLK | WM_MBUTTONDOWN | WM_MBUTTONUP
             | WM_XBUTTONDBLCLK | WM_XBUTTONDOWN | WM_XBUTTONUP => {
+                if !matches!(
+                    msg,
+                    WM_LBUTTONUP | WM_RBUTTONUP | WM_MBUTTONUP | WM_XBUTTONUP
+                ) {
+                    dismiss_windows(Some(hwnd));
+                }
                 if let Some(button) = match msg {
                     WM_LBUTTONDBLCLK | WM_LBUTTONDOWN | WM_LBUTTONUP => Some(MouseButton::Left),
                     WM_RBUTTONDBLCLK | WM_RBUTTONDOWN | WM_RBUTTONUP => Some(MouseButton::Right),
@@ -1405,6 +1851,7 @@ impl WndProc for MyWndProc {
                         } else {
                             0
                         };
+                        let (pointer_type, pressure, tilt_x, tilt_y) = s.pointer_details(buttons);
                         let event = MouseEvent {
                             pos,
                             buttons,
@@ -1413,6 +1860,10 @@ impl WndProc for MyWndProc {
                             focus: false,
                             button,
                             wheel_delta: Vec2::ZERO,
+                            pointer_type,
+                            pressure,
+                            tilt_x,
+                            tilt_y,
                         };
                         if count > 0 {
                             s.enter_mouse_capture(hwnd, button);
@@ -1428,16 +1879,57 @@ impl WndProc for MyWndProc {
 
                 Some(0)
             }
-            WM_CLOSE => self
-                .with_wnd_state(|s| s.handler.request_close())
-                .map(|_| 0),
+            WM_CLOSE => {
+                let should_close = self.with_wnd_state(|s| s.handler.should_close())?;
+                if should_close {
+                    self.enable_modal_owner();
+                    unsafe {
+                        DestroyWindow(hwnd);
+                    }
+                }
+                Some(0)
+            }
+            WM_NCLBUTTONDOWN | WM_NCRBUTTONDOWN | WM_NCMBUTTONDOWN | WM_NCXBUTTONDOWN => {
+                dismiss_windows(Some(hwnd));
+                None
+            }
             DS_REQUEST_DESTROY => {
+                self.enable_modal_owner();
                 unsafe {
                     DestroyWindow(hwnd);
                 }
                 Some(0)
             }
+            DS_DISMISS => {
+                // The window might have been dismissed already, or stopped being dismissable.
+                let dismissable = DISMISSABLE_WINDOWS.with(|windows| {
+                    let mut windows = windows.borrow_mut();
+                    let len = windows.len();
+                    windows.retain(|&w| w != hwnd);
+                    windows.len() != len
+                });
+                if dismissable {
+                    self.with_wnd_state(|s| s.handler.dismissed());
+                    self.enable_modal_owner();
+                    unsafe {
+                        DestroyWindow(hwnd);
+                    }
+                }
+                Some(0)
+            }
             WM_DESTROY => {
+                DISMISSABLE_WINDOWS.with(|windows| windows.borrow_mut().retain(|&w| w != hwnd));
+                // In case we were destroyed some other way, like with our owner.
+                self.enable_modal_owner();
+                // Don't leave the cursor hidden or stuck once the window is gone.
+                self.with_window_state(|state| unsafe {
+                    if state.cursor_hide_count.get() > 0 {
+                        ShowCursor(TRUE);
+                    }
+                    if state.is_cursor_confined.get() {
+                        ClipCursor(null());
+                    }
+                });
                 self.with_wnd_state(|s| s.handler.destroy());
                 Some(0)
             }
@@ -1455,6 +1947,13 @@ impl WndProc for MyWndProc {
                 self.with_wnd_state(|s| s.captured_mouse_buttons.clear());
                 Some(0)
             }
+            WM_MENUCHAR => {
+                // A key that isn't an access key was pressed while a menu is open, so run the
+                // item of the open menu that has it as its hotkey instead of just beeping.
+                let hmenu = lparam as HMENU;
+                unsafe { menu_item_for_hotkey(hwnd, LOWORD(wparam as u32), hmenu) }
+                    .map(|pos| MAKELONG(pos, MNC_EXECUTE as WORD) as LRESULT)
+            }
             WM_GETMINMAXINFO => {
                 let min_max_info = unsafe { &mut *(lparam as *mut MINMAXINFO) };
                 self.with_wnd_state(|s| {
@@ -1467,6 +1966,28 @@ impl WndProc for MyWndProc {
                 });
                 Some(0)
             }
+            WM_WINDOWPOSCHANGING => {
+                let state = self.handle.borrow().state.upgrade();
+                if state.map_or(false, |state| state.is_always_on_bottom.get()) {
+                    let pos = unsafe { &mut *(lparam as *mut WINDOWPOS) };
+                    // Whatever raises the window, it goes back to the bottom.
+                    if pos.flags & SWP_NOZORDER == 0 {
+                        pos.hwndInsertAfter = HWND_BOTTOM;
+                    }
+                }
+                // The default handling still has to apply the size limits.
+                None
+            }
+            WM_SIZING => {
+                let state = self.handle.borrow().state.upgrade();
+                if let Some(ratio) = state.and_then(|state| state.aspect_ratio.get()) {
+                    let rect = unsafe { &mut *(lparam as *mut RECT) };
+                    keep_aspect_ratio(hwnd, wparam, rect, ratio);
+                    Some(TRUE as LRESULT)
+                } else {
+                    None
+                }
+            }
             DS_RUN_IDLE => self
                 .with_wnd_state(|s| {
                     let queue = self.handle.borrow().take_idle_queue();
@@ -1493,6 +2014,7 @@ impl WindowBuilder {
             resizable: true,
             show_titlebar: true,
             transparent: false,
+            background_color: None,
             present_strategy: Default::default(),
             size: None,
             min_size: None,
@@ -1500,6 +2022,9 @@ impl WindowBuilder {
             level: None,
             always_on_top: false,
             state: window::WindowState::Restored,
+            owner: None,
+            modal: false,
+            window_type: WindowType::Normal,
         }
     }
 
@@ -1541,6 +2066,10 @@ impl WindowBuilder {
         }
     }
 
+    pub fn set_background_color(&mut self, color: Color) {
+        self.background_color = Some(color);
+    }
+
     pub fn set_title<S: Into<String>>(&mut self, title: S) {
         self.title = title.into();
     }
@@ -1561,6 +2090,18 @@ impl WindowBuilder {
         self.level = Some(level)
     }
 
+    pub fn set_parent(&mut self, parent: WindowHandle) {
+        self.owner = Some(parent);
+    }
+
+    pub fn set_modal(&mut self, modal: bool) {
+        self.modal = modal;
+    }
+
+    pub fn set_type(&mut self, window_type: WindowType) {
+        self.window_type = window_type;
+    }
+
     pub fn build(self) -> Result<WindowHandle, Error> {
         unsafe {
             let class_name = super::util::CLASS_NAME.to_wide();
@@ -1593,6 +2134,7 @@ impl WindowBuilder {
                 window_level = level.clone();
                 match level {
                     WindowLevel::AppWindow => (),
+                    WindowLevel::Floating | WindowLevel::Overlay => dwExStyle |= WS_EX_TOPMOST,
                     WindowLevel::Tooltip(parent_window_handle)
                     | WindowLevel::DropDown(parent_window_handle)
                     | WindowLevel::Modal(parent_window_handle) => {
@@ -1610,6 +2152,19 @@ impl WindowBuilder {
                 window_level = WindowLevel::AppWindow;
             }
 
+            if self.window_type == WindowType::Popup {
+                dwStyle = WS_POPUP;
+                dwExStyle |= WS_EX_NOACTIVATE | WS_EX_TOOLWINDOW;
+                focusable = false;
+            }
+
+            // An owned window stays above its owner, but is otherwise a normal window.
+            let owner_hwnd = self.owner.as_ref().and_then(|owner| owner.get_hwnd());
+            if parent_hwnd.is_none() {
+                parent_hwnd = owner_hwnd;
+            }
+            let modal_owner = owner_hwnd.filter(|_| self.modal);
+
             // Calculate the window position in pixels
             if let Some(pos_dp) = self.position {
                 (pos_x, pos_y) = calculate_window_pos(parent_pos_dp, pos_dp, scale);
@@ -1646,9 +2201,19 @@ impl WindowBuilder {
                 is_transparent: Cell::new(self.transparent),
                 handle_titlebar: Cell::new(false),
                 active_text_input: Cell::new(None),
+                ime_enabled: Cell::new(true),
+                ime_disabled_fields: RefCell::new(HashSet::new()),
                 is_focusable: focusable,
                 window_level,
+                modal_owner,
                 is_always_on_top: Cell::new(self.always_on_top),
+                is_always_on_bottom: Cell::new(false),
+                cursor_hide_count: Cell::new(0),
+                is_cursor_confined: Cell::new(false),
+                anim_clock: Cell::new(AnimationClock::default()),
+                aspect_ratio: Cell::new(None),
+                cursor: RefCell::new(CursorStack::default()),
+                background_color: Cell::new(self.background_color.map(to_colorref)),
             };
             let win = Rc::new(window);
             let handle = WindowHandle {
@@ -1668,6 +2233,10 @@ impl WindowBuilder {
                 last_click_time: Instant::now(),
                 last_click_pos: (0, 0),
                 click_count: 0,
+                pen_input: None,
+                minimized: false,
+                in_size_move: false,
+                moved_in_size_move: false,
             };
             win.wndproc.connect(&handle, state);
 
@@ -1768,6 +2337,105 @@ impl WindowBuilder {
     }
 }
 
+/// Returns true if the message being handled comes from a touchpad.
+///
+/// Wheel messages have no pointer id, so they can't be asked about with `GetPointerType`.
+unsafe fn is_touchpad_message() -> bool {
+    let get_source = match OPTIONAL_FUNCTIONS.GetCurrentInputMessageSource {
+        Some(get_source) => get_source,
+        None => return false,
+    };
+    let mut source: util::INPUT_MESSAGE_SOURCE = mem::zeroed();
+    get_source(&mut source) != FALSE && source.deviceType == util::IMDT_TOUCHPAD
+}
+
+/// Returns the pen details of a pointer, or `None` if it isn't a pen.
+unsafe fn get_pen_input(pointer_id: u32) -> Option<PenInput> {
+    let (get_pointer_type, get_pointer_pen_info) = match (
+        OPTIONAL_FUNCTIONS.GetPointerType,
+        OPTIONAL_FUNCTIONS.GetPointerPenInfo,
+    ) {
+        (Some(get_pointer_type), Some(get_pointer_pen_info)) => {
+            (get_pointer_type, get_pointer_pen_info)
+        }
+        _ => return None,
+    };
+    let mut pointer_type: POINTER_INPUT_TYPE = 0;
+    if get_pointer_type(pointer_id, &mut pointer_type) == FALSE || pointer_type != PT_PEN {
+        return None;
+    }
+    let mut info: POINTER_PEN_INFO = mem::zeroed();
+    if get_pointer_pen_info(pointer_id, &mut info) == FALSE {
+        return None;
+    }
+    let pointer_type = if info.penFlags & (PEN_FLAG_ERASER | PEN_FLAG_INVERTED) != 0 {
+        PointerType::Eraser
+    } else {
+        PointerType::Pen
+    };
+    let pressure = if info.penMask & PEN_MASK_PRESSURE != 0 {
+        // The pressure goes from 0 to 1024.
+        info.pressure as f64 / 1024.0
+    } else if info.pointerInfo.pointerFlags & POINTER_FLAG_INCONTACT != 0 {
+        1.0
+    } else {
+        0.0
+    };
+    let tilt_x = if info.penMask & PEN_MASK_TILT_X != 0 {
+        info.tiltX as f64
+    } else {
+        0.0
+    };
+    let tilt_y = if info.penMask & PEN_MASK_TILT_Y != 0 {
+        info.tiltY as f64
+    } else {
+        0.0
+    };
+    Some(PenInput {
+        pointer_type,
+        pressure,
+        tilt_x,
+        tilt_y,
+    })
+}
+
+/// Returns the position in pixels and the phase of a pointer, or `None` if it isn't a touch.
+unsafe fn get_touch_info(hwnd: HWND, pointer_id: u32, msg: UINT) -> Option<(Point, TouchPhase)> {
+    let (get_pointer_type, get_pointer_info) = match (
+        OPTIONAL_FUNCTIONS.GetPointerType,
+        OPTIONAL_FUNCTIONS.GetPointerInfo,
+    ) {
+        (Some(get_pointer_type), Some(get_pointer_info)) => (get_pointer_type, get_pointer_info),
+        _ => return None,
+    };
+    let mut pointer_type: POINTER_INPUT_TYPE = 0;
+    if get_pointer_type(pointer_id, &mut pointer_type) == FALSE || pointer_type != PT_TOUCH {
+        return None;
+    }
+    let mut info: POINTER_INFO = mem::zeroed();
+    if get_pointer_info(pointer_id, &mut info) == FALSE {
+        return None;
+    }
+    let phase = if info.pointerFlags & POINTER_FLAG_CANCELED != 0 {
+        TouchPhase::Cancelled
+    } else {
+        match msg {
+            WM_POINTERDOWN => TouchPhase::Began,
+            WM_POINTERUP => TouchPhase::Ended,
+            _ => TouchPhase::Moved,
+        }
+    };
+    let mut p = info.ptPixelLocation;
+    if ScreenToClient(hwnd, &mut p) == FALSE {
+        warn!(
+            "ScreenToClient failed: {}",
+            Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+        );
+        return None;
+    }
+    Some((Point::new(p.x as f64, p.y as f64), phase))
+}
+
 /// Returns a pair of integers representing the pixel values of the position.
 fn calculate_window_pos(parent_pos_dp: Option<Point>, pos_dp: Point, scale: Scale) -> (i32, i32) {
     let pos_px = if let Some(parent_pos_dp) = parent_pos_dp {
@@ -1957,11 +2625,11 @@ unsafe fn create_dxgi_state(
 }
 
 #[cfg(target_arch = "x86_64")]
-type WindowLongPtr = winapi::shared::basetsd::LONG_PTR;
+pub(crate) type WindowLongPtr = winapi::shared::basetsd::LONG_PTR;
 #[cfg(target_arch = "aarch64")]
-type WindowLongPtr = winapi::shared::basetsd::LONG_PTR;
+pub(crate) type WindowLongPtr = winapi::shared::basetsd::LONG_PTR;
 #[cfg(target_arch = "x86")]
-type WindowLongPtr = LONG;
+pub(crate) type WindowLongPtr = LONG;
 
 pub(crate) unsafe extern "system" fn win_proc_dispatch(
     hwnd: HWND,
@@ -1995,6 +2663,28 @@ pub(crate) unsafe extern "system" fn win_proc_dispatch(
     }
 }
 
+/// Restricts the cursor to the client area of `hwnd`.
+fn clip_cursor_to_client(hwnd: HWND) {
+    unsafe {
+        let mut rect = mem::zeroed();
+        if GetClientRect(hwnd, &mut rect) == 0 {
+            warn!(
+                "failed to get client area: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+            return;
+        }
+        // The rect is in client coordinates, but the clip is in screen coordinates.
+        MapWindowPoints(hwnd, null_mut(), &mut rect as *mut RECT as *mut POINT, 2);
+        if ClipCursor(&rect) == 0 {
+            warn!(
+                "failed to clip cursor: {}",
+                Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+            );
+        }
+    }
+}
+
 /// Create a window (same parameters as CreateWindowExW) with associated WndProc.
 #[allow(clippy::too_many_arguments)]
 unsafe fn create_window(
@@ -2042,6 +2732,8 @@ impl Cursor {
             Cursor::NotAllowed => IDC_NO,
             Cursor::ResizeLeftRight => IDC_SIZEWE,
             Cursor::ResizeUpDown => IDC_SIZENS,
+            Cursor::Wait => IDC_WAIT,
+            Cursor::Progress => IDC_APPSTARTING,
             Cursor::Custom(c) => {
                 return (c.0).0;
             }
@@ -2064,6 +2756,9 @@ impl WindowHandle {
                 SW_SHOWNOACTIVATE
             };
             unsafe {
+                if let Some(owner) = w.modal_owner {
+                    EnableWindow(owner, FALSE);
+                }
                 ShowWindow(hwnd, show);
                 UpdateWindow(hwnd);
             }
@@ -2091,6 +2786,16 @@ impl WindowHandle {
     }
 
     pub fn request_anim_frame(&self) {
+        if let Some(w) = self.state.upgrade() {
+            let mut clock = w.anim_clock.get();
+            clock.request();
+            w.anim_clock.set(clock);
+        }
+        self.request_paint();
+    }
+
+    /// Queues a paint without marking it as an animation frame.
+    fn request_paint(&self) {
         if let Some(w) = self.state.upgrade() {
             let hwnd = w.hwnd.get();
             unsafe {
@@ -2113,7 +2818,22 @@ impl WindowHandle {
                 .borrow_mut()
                 .set_rect(w.area.get().size_dp().to_rect());
         }
-        self.request_anim_frame();
+        self.request_paint();
+    }
+
+    pub fn paint_now(&self) {
+        if let Some(hwnd) = self.get_hwnd() {
+            unsafe {
+                // We paint from the invalid region we keep, so we ask for an internal paint
+                // like `request_anim_frame`, but have it handled before returning.
+                if RedrawWindow(hwnd, null(), null_mut(), RDW_INTERNALPAINT | RDW_UPDATENOW) == 0 {
+                    warn!(
+                        "RedrawWindow failed: {}",
+                        Error::Hr(HRESULT_FROM_WIN32(GetLastError()))
+                    );
+                }
+            }
+        }
     }
 
     pub fn invalidate_rect(&self, rect: Rect) {
@@ -2126,7 +2846,7 @@ impl WindowHandle {
                 .borrow_mut()
                 .add_rect(rect.to_px(scale).expand().to_dp(scale));
         }
-        self.request_anim_frame();
+        self.request_paint();
     }
 
     fn defer(&self, op: DeferredOp) {
@@ -2147,6 +2867,10 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_represented_file(&self, _path: Option<&Path>) {}
+
+    pub fn set_document_edited(&self, _edited: bool) {}
+
     pub fn show_titlebar(&self, show_titlebar: bool) {
         self.defer(DeferredOp::ShowTitlebar(show_titlebar));
     }
@@ -2162,7 +2886,7 @@ impl WindowHandle {
                     let screen_position = parent_window_handle.get_position() + position.to_vec2();
                     self.defer(DeferredOp::SetPosition(screen_position));
                 }
-                WindowLevel::AppWindow => {
+                WindowLevel::AppWindow | WindowLevel::Floating | WindowLevel::Overlay => {
                     self.defer(DeferredOp::SetPosition(position));
                 }
             }
@@ -2261,10 +2985,48 @@ impl WindowHandle {
         self.defer(DeferredOp::SetAlwaysOnTop(always_on_top));
     }
 
-    pub fn resizable(&self, resizable: bool) {
+    pub fn set_level(&self, level: WindowLevel) {
+        // Windows only has one band of windows above the others.
+        let topmost = !matches!(level, WindowLevel::AppWindow | WindowLevel::Modal(_));
+        self.defer(DeferredOp::SetAlwaysOnTop(topmost));
+    }
+
+    pub fn set_dismiss_on_focus_loss(&self, dismiss: bool) {
+        if let Some(hwnd) = self.get_hwnd() {
+            DISMISSABLE_WINDOWS.with(|windows| {
+                let mut windows = windows.borrow_mut();
+                windows.retain(|&w| w != hwnd);
+                if dismiss {
+                    windows.push(hwnd);
+                }
+            });
+        }
+    }
+
+    pub fn set_opacity(&self, opacity: f64) {
+        self.defer(DeferredOp::SetOpacity(opacity));
+    }
+
+    pub fn set_background_color(&self, color: Color) {
+        if let Some(w) = self.state.upgrade() {
+            w.background_color.set(Some(to_colorref(color)));
+        }
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
         self.defer(DeferredOp::SetResizable(resizable));
     }
 
+    pub fn set_always_on_bottom(&self, on_bottom: bool) {
+        self.defer(DeferredOp::SetAlwaysOnBottom(on_bottom));
+    }
+
+    pub fn set_aspect_ratio(&self, ratio: Option<f64>) {
+        if let Some(w) = self.state.upgrade() {
+            w.aspect_ratio.set(ratio);
+        }
+    }
+
     /// Sets the window state.
     pub fn set_window_state(&self, state: window::WindowState) {
         self.defer(DeferredOp::SetWindowState(state));
@@ -2339,13 +3101,16 @@ impl WindowHandle {
         if let Some(state) = self.state.upgrade() {
             if state.active_text_input.get() == Some(token) {
                 state.active_text_input.set(None);
+                self.defer(DeferredOp::UpdateIme);
             }
+            state.ime_disabled_fields.borrow_mut().remove(&token);
         }
     }
 
     pub fn set_focused_text_field(&self, active_field: Option<TextFieldToken>) {
         if let Some(state) = self.state.upgrade() {
             state.active_text_input.set(active_field);
+            self.defer(DeferredOp::UpdateIme);
         }
     }
 
@@ -2353,6 +3118,25 @@ impl WindowHandle {
         // noop until we get a real text input implementation
     }
 
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        if let Some(state) = self.state.upgrade() {
+            state.ime_enabled.set(enabled);
+            self.defer(DeferredOp::UpdateIme);
+        }
+    }
+
+    pub fn set_text_field_ime_enabled(&self, token: TextFieldToken, enabled: bool) {
+        if let Some(state) = self.state.upgrade() {
+            let mut disabled_fields = state.ime_disabled_fields.borrow_mut();
+            if enabled {
+                disabled_fields.remove(&token);
+            } else {
+                disabled_fields.insert(token);
+            }
+            self.defer(DeferredOp::UpdateIme);
+        }
+    }
+
     /// Request a timer event.
     ///
     /// The return value is an identifier.
@@ -2368,11 +3152,114 @@ impl WindowHandle {
 
     /// Set the cursor icon.
     pub fn set_cursor(&mut self, cursor: &Cursor) {
-        unsafe {
-            SetCursor(cursor.get_hcursor());
+        if let Some(w) = self.state.upgrade() {
+            if w.cursor.borrow_mut().set(cursor) {
+                unsafe {
+                    SetCursor(cursor.get_hcursor());
+                }
+            }
         }
     }
 
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        if let Some(w) = self.state.upgrade() {
+            unsafe {
+                SetCursor(cursor.get_hcursor());
+            }
+            w.cursor.borrow_mut().push(cursor);
+        }
+    }
+
+    pub fn pop_cursor(&mut self) {
+        if let Some(w) = self.state.upgrade() {
+            let mut stack = w.cursor.borrow_mut();
+            if stack.pop() {
+                let cursor = stack.current().unwrap_or(&Cursor::Arrow);
+                unsafe {
+                    SetCursor(cursor.get_hcursor());
+                }
+            }
+        }
+    }
+
+    pub fn set_cursor_visible(&self, visible: bool) {
+        if let Some(w) = self.state.upgrade() {
+            // `ShowCursor` keeps a display count, but it isn't clamped, so unbalanced
+            // calls would leave it in the wrong state. We only call it on transitions.
+            let count = w.cursor_hide_count.get();
+            if visible {
+                if count == 1 {
+                    unsafe { ShowCursor(TRUE) };
+                }
+                w.cursor_hide_count.set(count.saturating_sub(1));
+            } else {
+                if count == 0 {
+                    unsafe { ShowCursor(FALSE) };
+                }
+                w.cursor_hide_count.set(count + 1);
+            }
+        }
+    }
+
+    pub fn confine_cursor(&self, confine: bool) {
+        if let Some(w) = self.state.upgrade() {
+            w.is_cursor_confined.set(confine);
+            let hwnd = w.hwnd.get();
+            if confine {
+                if unsafe { GetActiveWindow() } == hwnd {
+                    clip_cursor_to_client(hwnd);
+                }
+            } else {
+                unsafe { ClipCursor(null()) };
+            }
+        }
+    }
+
+    pub fn announce(&self, message: &str, politeness: window::Politeness) {
+        let (host_provider_from_hwnd, raise_notification_event) = match (
+            OPTIONAL_FUNCTIONS.UiaHostProviderFromHwnd,
+            OPTIONAL_FUNCTIONS.UiaRaiseNotificationEvent,
+        ) {
+            (Some(from_hwnd), Some(raise)) => (from_hwnd, raise),
+            _ => {
+                warn!("announcements need Windows 10 version 1709 or later");
+                return;
+            }
+        };
+        if let Some(hwnd) = self.get_hwnd() {
+            unsafe {
+                let mut provider = null_mut();
+                if let Err(e) = as_result(host_provider_from_hwnd(hwnd, &mut provider)) {
+                    warn!("failed to get the UI Automation provider: {}", e);
+                    return;
+                }
+                let provider = ComPtr::from_raw(provider);
+                let processing = match politeness {
+                    window::Politeness::Polite => NOTIFICATION_PROCESSING_ALL,
+                    window::Politeness::Assertive => NOTIFICATION_PROCESSING_IMPORTANT_MOST_RECENT,
+                };
+                let display_string = SysAllocString(message.to_wide().as_ptr());
+                let activity_id = SysAllocString("druid-announcement".to_wide().as_ptr());
+                let hr = raise_notification_event(
+                    provider.as_raw(),
+                    NOTIFICATION_KIND_OTHER,
+                    processing,
+                    display_string,
+                    activity_id,
+                );
+                SysFreeString(display_string);
+                SysFreeString(activity_id);
+                if let Err(e) = as_result(hr) {
+                    warn!("failed to raise the notification event: {}", e);
+                }
+            }
+        }
+    }
+
+    pub fn set_accessibility_tree(&self, _tree: AccessibilityTree) {
+        warn!("WindowHandle::set_accessibility_tree is currently unimplemented for Windows.");
+    }
+
     pub fn make_cursor(&self, cursor_desc: &CursorDesc) -> Option<Cursor> {
         if let Some(hwnd) = self.get_hwnd() {
             unsafe {
@@ -2396,6 +3283,17 @@ impl WindowHandle {
 
                 let width = cursor_desc.image.width();
                 let height = cursor_desc.image.height();
+                // Larger cursors are cropped, or not shown at all.
+                let dpi = (self.get_scale().ok()?.x() * SCALE_TARGET_DPI) as UINT;
+                let (max_width, max_height) = util::cursor_size(dpi);
+                let (max_width, max_height) = (max_width as usize, max_height as usize);
+                if width > max_width || height > max_height {
+                    warn!(
+                        "cursor image is {}x{}, larger than the maximum of {}x{}",
+                        width, height, max_width, max_height
+                    );
+                    return None;
+                }
                 let mask = CreateCompatibleBitmap(hdc, width as c_int, height as c_int);
                 if mask.is_null() {
                     return None;
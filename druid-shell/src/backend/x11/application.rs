@@ -18,16 +18,19 @@ use std::cell::{Cell, RefCell};
 use std::collections::{HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto};
 use std::os::unix::io::RawFd;
+use std::path::Path;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, Context, Error};
 use x11rb::connection::{Connection, RequestConnection};
 use x11rb::protocol::present::ConnectionExt as _;
+use x11rb::protocol::randr::{self, ConnectionExt as _};
 use x11rb::protocol::render::{self, ConnectionExt as _, Pictformat};
+use x11rb::protocol::screensaver::ConnectionExt as _;
 use x11rb::protocol::xfixes::ConnectionExt as _;
 use x11rb::protocol::xproto::{
-    self, ConnectionExt, CreateWindowAux, EventMask, Timestamp, Visualtype, WindowClass,
+    self, AtomEnum, ConnectionExt, CreateWindowAux, EventMask, Timestamp, Visualtype, WindowClass,
 };
 use x11rb::protocol::Event;
 use x11rb::resource_manager::{
@@ -35,11 +38,19 @@ use x11rb::resource_manager::{
 };
 use x11rb::xcb_ffi::XCBConnection;
 
-use crate::application::AppHandler;
+use crate::application::{
+    AppHandler, ExternalWindowId, LayoutDirection, OsVersion, PermissionState, RunOutcome,
+    SystemSound, WindowInfo,
+};
+use crate::keyboard::Modifiers;
+use crate::kurbo::{Point, Rect, Size};
+use crate::mouse::MouseButtons;
+use crate::piet::Color;
 
 use super::clipboard::Clipboard;
+use super::menu::Menu;
 use super::util;
-use super::window::Window;
+use super::window::{key_mods, mouse_buttons, Window};
 use crate::backend::shared::linux;
 use crate::backend::shared::xkb;
 
@@ -106,6 +117,10 @@ x11rb::atom_manager! {
         _NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
         _NET_WM_WINDOW_TYPE_TOOLTIP,
         _NET_WM_WINDOW_TYPE_DIALOG,
+        _NET_WM_STATE,
+        _NET_WM_STATE_ABOVE,
+        _NET_FRAME_EXTENTS,
+        _NET_CLIENT_LIST_STACKING,
         CLIPBOARD,
         PRIMARY,
         TARGETS,
@@ -113,6 +128,9 @@ x11rb::atom_manager! {
     }
 }
 
+/// Global mouse monitors aren't supported on X11, so this can't be created.
+pub(crate) enum GlobalMouseMonitor {}
+
 #[derive(Clone)]
 pub(crate) struct Application {
     /// The connection to the X server.
@@ -199,6 +217,8 @@ pub(crate) struct Cursors {
     pub not_allowed: Option<xproto::Cursor>,
     pub row_resize: Option<xproto::Cursor>,
     pub col_resize: Option<xproto::Cursor>,
+    pub wait: Option<xproto::Cursor>,
+    pub progress: Option<xproto::Cursor>,
 }
 
 impl Application {
@@ -297,6 +317,8 @@ impl Application {
             not_allowed: load_cursor("not-allowed"),
             row_resize: load_cursor("row-resize"),
             col_resize: load_cursor("col-resize"),
+            wait: load_cursor("wait"),
+            progress: load_cursor("progress"),
         };
 
         let atoms = Rc::new(
@@ -313,6 +335,9 @@ impl Application {
         let root_visual_type = util::get_visual_from_screen(screen)
             .ok_or_else(|| anyhow!("Couldn't get visual from screen"))?;
         let argb_visual_type = util::get_argb_visual_type(&connection, screen)?;
+        if let Err(err) = select_randr_input(&connection, screen.root) {
+            tracing::warn!("failed to select RandR events: {}", err);
+        }
 
         let timestamp = Rc::new(Cell::new(x11rb::CURRENT_TIME));
         let pending_events = Default::default();
@@ -355,6 +380,13 @@ impl Application {
         })
     }
 
+    pub fn new_single_instance(_app_id: &str) -> Result<Application, crate::Error> {
+        tracing::warn!(
+            "Application::new_single_instance is currently unimplemented for X11 backend."
+        );
+        Ok(Application::new()?)
+    }
+
     // Check if the Present extension is supported, returning its opcode if it is.
     fn query_present_opcode(conn: &Rc<XCBConnection>) -> Result<Option<u8>, Error> {
         let query = conn
@@ -672,6 +704,9 @@ impl Application {
                     .handle_property_notify(*ev)
                     .context("PROPERTY_NOTIFY event handling for primary")?;
             }
+            Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_) => {
+                crate::screen::monitors_changed();
+            }
             Event::FocusIn(ev) => {
                 let w = self
                     .window(ev.event)
@@ -759,36 +794,105 @@ impl Application {
             let now = Instant::now();
             if let Some(timeout) = next_timeout {
                 if timeout <= now {
-                    if let Ok(state) = self.state.try_borrow() {
-                        let values = state.windows.values().cloned().collect::<Vec<_>>();
-                        drop(state);
-                        for w in values {
-                            w.run_timers(now);
-                        }
-                    } else {
-                        tracing::error!("In timer loop, application state already borrowed");
-                    }
+                    self.run_timers(now);
                 }
             }
             if now >= next_idle_time {
                 last_idle_time = now;
-                drain_idle_pipe(self.idle_read)?;
+                self.run_idle()?;
+            }
+        }
+    }
 
-                if let Ok(state) = self.state.try_borrow() {
-                    for w in state.windows.values() {
-                        w.run_idle();
-                    }
-                } else {
-                    tracing::error!("In idle loop, application state already borrowed");
-                }
+    /// Waits for one round of events and handles them, like one turn of [`run_inner`].
+    ///
+    /// Idle callbacks run right away instead of at the refresh rate, because the caller
+    /// decides how often we get here.
+    ///
+    /// Returns `false` once the application is quitting.
+    ///
+    /// [`run_inner`]: Application::run_inner
+    fn pump_inner(&self, timeout: Option<Duration>) -> Result<bool, Error> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        let next_timeout = if let Ok(state) = self.state.try_borrow() {
+            state
+                .windows
+                .values()
+                .filter_map(|w| w.next_timeout())
+                .min()
+        } else {
+            tracing::error!("Getting next timeout, application state already borrowed");
+            None
+        };
+        let wake_time = match (next_timeout, deadline) {
+            (Some(timer), Some(deadline)) => Some(timer.min(deadline)),
+            (timer, deadline) => timer.or(deadline),
+        };
+
+        self.connection.flush()?;
+        let mut event = self.pending_events.borrow_mut().pop_front();
+        if event.is_none() {
+            event = self.connection.poll_for_event()?;
+        }
+        if event.is_none() {
+            poll_with_timeout(&self.connection, self.idle_read, wake_time, Instant::now())
+                .context("Error while waiting for X11 connection")?;
+            // Handle the event that woke us up, if it was one.
+            event = self.connection.poll_for_event()?;
+        }
+        while let Some(ev) = event {
+            match self.handle_event(&ev) {
+                Ok(true) => return Ok(false),
+                Ok(false) => {}
+                Err(e) => tracing::error!("Error handling event: {:#}", e),
+            }
+            event = self.connection.poll_for_event()?;
+        }
+
+        let now = Instant::now();
+        if next_timeout.map_or(false, |timeout| timeout <= now) {
+            self.run_timers(now);
+        }
+        self.run_idle()?;
+        Ok(!borrow!(self.state)?.quitting)
+    }
+
+    fn run_timers(&self, now: Instant) {
+        if let Ok(state) = self.state.try_borrow() {
+            let values = state.windows.values().cloned().collect::<Vec<_>>();
+            drop(state);
+            for w in values {
+                w.run_timers(now);
             }
+        } else {
+            tracing::error!("In timer loop, application state already borrowed");
         }
     }
 
-    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) {
-        if let Err(e) = self.run_inner() {
-            tracing::error!("{}", e);
+    fn run_idle(&self) -> Result<(), Error> {
+        drain_idle_pipe(self.idle_read)?;
+        if let Ok(state) = self.state.try_borrow() {
+            for w in state.windows.values() {
+                w.run_idle();
+            }
+        } else {
+            tracing::error!("In idle loop, application state already borrowed");
         }
+        Ok(())
+    }
+
+    pub fn run(self, _handler: Option<Box<dyn AppHandler>>) -> Result<RunOutcome, Error> {
+        let state = Rc::clone(&self.state);
+        self.run_inner()?;
+        let quit_requested = borrow!(state)?.quitting;
+        Ok(RunOutcome::new(0, quit_requested))
+    }
+
+    pub fn pump_events(&self, timeout: Option<Duration>) -> bool {
+        self.pump_inner(timeout).unwrap_or_else(|e| {
+            tracing::error!("Error while pumping events: {:#}", e);
+            false
+        })
     }
 
     pub fn quit(&self) {
@@ -821,14 +925,272 @@ impl Application {
         }
     }
 
+    pub fn reply_to_end_session(&self, _allow: bool) {
+        // noop, since the end of the session is never blocked here
+    }
+
+    pub fn set_menu(&self, _menu: Menu) {
+        tracing::warn!("Application::set_menu is currently unimplemented for X11 backend.");
+    }
+
     pub fn clipboard(&self) -> Clipboard {
         self.clipboard.clone()
     }
 
+    pub fn open_uri(&self, uri: &str) -> Result<(), Error> {
+        linux::xdg::open(uri)
+    }
+
+    pub fn reveal_in_file_manager(&self, path: &Path) -> Result<(), Error> {
+        linux::xdg::reveal(path)
+    }
+
+    pub fn note_recent_document(&self, _path: &Path) {
+        tracing::warn!(
+            "Application::note_recent_document is currently unimplemented for X11 backend."
+        );
+    }
+
+    pub fn clear_recent_documents(&self) {
+        tracing::warn!(
+            "Application::clear_recent_documents is currently unimplemented for X11 backend."
+        );
+    }
+
+    pub fn keyboard_layout_name(&self) -> Option<String> {
+        None
+    }
+
+    pub fn layout_direction(&self) -> LayoutDirection {
+        LayoutDirection::from_locale(&linux::env::locale())
+    }
+
+    pub fn prefers_high_contrast(&self) -> bool {
+        false
+    }
+
+    pub fn selection_color(&self) -> Option<Color> {
+        None
+    }
+
+    pub fn text_cursor_color(&self) -> Option<Color> {
+        None
+    }
+
+    pub fn is_active(&self) -> bool {
+        false
+    }
+
+    pub fn activate(&self, _ignore_other_apps: bool) {
+        tracing::warn!("Application::activate is currently unimplemented for X11 backend.");
+    }
+
+    pub fn beep(&self) {
+        // A percent of 0 rings the bell at the volume the user configured.
+        let result = self
+            .connection
+            .bell(0)
+            .map(|_| ())
+            .and_then(|()| self.connection.flush());
+        if let Err(e) = result {
+            tracing::warn!("failed to ring the bell: {}", e);
+        }
+    }
+
+    pub fn play_system_sound(&self, _sound: SystemSound) {
+        self.beep();
+    }
+
+    pub fn system_idle_time(&self) -> Option<Duration> {
+        self.query_idle_time()
+            .map_err(|e| tracing::warn!("failed to query the screensaver extension: {}", e))
+            .ok()
+    }
+
+    fn query_idle_time(&self) -> Result<Duration, Error> {
+        let root = self
+            .connection
+            .setup()
+            .roots
+            .get(self.screen_num)
+            .ok_or_else(|| anyhow!("Invalid screen num: {}", self.screen_num))?
+            .root;
+        let info = self.connection.screensaver_query_info(root)?.reply()?;
+        Ok(Duration::from_millis(info.ms_since_user_input.into()))
+    }
+
+    pub fn mouse_button_count(&self) -> Option<u32> {
+        self.query_mouse_button_count()
+            .map_err(|e| tracing::warn!("failed to get the pointer mapping: {}", e))
+            .ok()
+    }
+
+    pub fn max_cursor_size(&self) -> Size {
+        Size::new(f64::INFINITY, f64::INFINITY)
+    }
+
+    fn query_mouse_button_count(&self) -> Result<u32, Error> {
+        // The core pointer's button mapping has an entry for every button.
+        let reply = self.connection.get_pointer_mapping()?.reply()?;
+        Ok(reply.map.len() as u32)
+    }
+
+    pub fn mouse_buttons(&self) -> MouseButtons {
+        self.query_pointer_mask()
+            .map(mouse_buttons)
+            .map_err(|e| tracing::warn!("failed to query the pointer: {}", e))
+            .unwrap_or_default()
+    }
+
+    pub fn modifiers(&self) -> Modifiers {
+        self.query_pointer_mask()
+            .map(key_mods)
+            .map_err(|e| tracing::warn!("failed to query the pointer: {}", e))
+            .unwrap_or_default()
+    }
+
+    /// Returns the state of the mouse buttons and modifier keys, as in the `state` of events.
+    fn query_pointer_mask(&self) -> Result<u16, Error> {
+        let root = self.connection.setup().roots[self.screen_num].root;
+        let reply = self.connection.query_pointer(root)?.reply()?;
+        Ok(u16::from(reply.mask))
+    }
+
+    pub fn window_list(&self) -> Vec<WindowInfo> {
+        self.query_window_list()
+            .map_err(|e| tracing::warn!("failed to get the window list: {}", e))
+            .unwrap_or_default()
+    }
+
+    fn query_window_list(&self) -> Result<Vec<WindowInfo>, Error> {
+        let root = self.connection.setup().roots[self.screen_num].root;
+        // The window manager lists the client windows from bottom to top.
+        let clients = self
+            .connection
+            .get_property(
+                false,
+                root,
+                self.atoms._NET_CLIENT_LIST_STACKING,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+        let state = self.state.borrow();
+        let list = clients
+            .value32()
+            .ok_or_else(|| anyhow!("the window manager doesn't support _NET_CLIENT_LIST_STACKING"))?
+            .rev()
+            .filter(|window| !state.windows.contains_key(window))
+            // Windows can be destroyed while we query them, so those are skipped.
+            .filter_map(|window| self.query_window_info(root, window).ok())
+            .collect();
+        Ok(list)
+    }
+
+    fn query_window_info(&self, root: u32, window: u32) -> Result<WindowInfo, Error> {
+        let conn = &self.connection;
+        let net_wm_name = conn
+            .get_property(
+                false,
+                window,
+                self.atoms._NET_WM_NAME,
+                self.atoms.UTF8_STRING,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+        let title = if net_wm_name.value.is_empty() {
+            let wm_name = conn
+                .get_property(
+                    false,
+                    window,
+                    AtomEnum::WM_NAME,
+                    AtomEnum::STRING,
+                    0,
+                    u32::MAX,
+                )?
+                .reply()?;
+            String::from_utf8_lossy(&wm_name.value).into_owned()
+        } else {
+            String::from_utf8_lossy(&net_wm_name.value).into_owned()
+        };
+        // WM_CLASS is the instance name followed by the class name, both nul terminated.
+        let wm_class = conn
+            .get_property(
+                false,
+                window,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+        let owner_name = wm_class
+            .value
+            .split(|&b| b == 0)
+            .nth(1)
+            .map(|class| String::from_utf8_lossy(class).into_owned())
+            .unwrap_or_default();
+        let geometry = conn.get_geometry(window)?.reply()?;
+        let origin = conn.translate_coordinates(window, root, 0, 0)?.reply()?;
+        let bounds = Rect::from_origin_size(
+            (origin.dst_x as f64, origin.dst_y as f64),
+            (geometry.width as f64, geometry.height as f64),
+        );
+        Ok(WindowInfo {
+            id: ExternalWindowId::from_raw(window as u64),
+            title,
+            owner_name,
+            bounds,
+        })
+    }
+
+    pub fn has_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn request_screen_capture_access(&self) -> bool {
+        true
+    }
+
+    pub fn check_accessibility_permission(&self, _prompt: bool) -> PermissionState {
+        PermissionState::NotRequired
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "x11"
+    }
+
+    pub fn os_version(&self) -> Option<OsVersion> {
+        crate::backend::shared::kernel_version()
+    }
+
+    pub fn add_global_mouse_monitor(
+        &self,
+        _callback: Box<dyn FnMut(Point)>,
+    ) -> Result<GlobalMouseMonitor, crate::Error> {
+        tracing::warn!(
+            "Application::add_global_mouse_monitor is currently unimplemented for X11 backend."
+        );
+        Err(anyhow!("global mouse monitors are not supported on X11").into())
+    }
+
+    pub fn run_on_main<T: Send + 'static>(
+        _f: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, crate::Error> {
+        tracing::warn!("Application::run_on_main is currently unimplemented for X11 backend.");
+        Err(anyhow!("running on the main thread is not supported on X11").into())
+    }
+
     pub fn get_locale() -> String {
         linux::env::locale()
     }
 
+    pub fn get_locale_tag() -> String {
+        Self::get_locale()
+    }
+
     pub(crate) fn idle_pipe(&self) -> RawFd {
         self.idle_write
     }
@@ -840,6 +1202,17 @@ impl crate::platform::linux::ApplicationExt for crate::Application {
     }
 }
 
+/// Asks for RandR events on `root`, so that the cached monitors are discarded when they change.
+fn select_randr_input(conn: &XCBConnection, root: xproto::Window) -> Result<(), Error> {
+    // The server only sends the events of the version that we ask for.
+    conn.randr_query_version(1, 2)?.reply()?;
+    let mask = randr::NotifyMask::SCREEN_CHANGE
+        | randr::NotifyMask::CRTC_CHANGE
+        | randr::NotifyMask::OUTPUT_CHANGE;
+    conn.randr_select_input(root, mask)?;
+    Ok(())
+}
+
 /// Clears out our idle pipe; `idle_read` should be the reading end of a pipe that was opened with
 /// O_NONBLOCK.
 fn drain_idle_pipe(idle_read: RawFd) -> Result<(), Error> {
@@ -17,6 +17,7 @@
 use std::cell::{Cell, RefCell};
 use std::collections::VecDeque;
 use std::convert::TryFrom;
+use std::path::PathBuf;
 use std::rc::Rc;
 use std::time::{Duration, Instant};
 
@@ -33,7 +34,7 @@ use x11rb::wrapper::ConnectionExt as _;
 use x11rb::xcb_ffi::XCBConnection;
 
 use super::application::AppAtoms;
-use crate::clipboard::{ClipboardFormat, FormatId};
+use crate::clipboard::{uri_list, ClipboardFormat, DataProvider, FormatId};
 use tracing::{debug, error, warn};
 
 // We can pick an arbitrary atom that is used for the transfer. This is our pick.
@@ -102,10 +103,26 @@ impl Clipboard {
         }
     }
 
+    pub fn put_lazy(&mut self, format: FormatId, provider: DataProvider) {
+        // The selection owner keeps the data of its formats, so it is made right away.
+        self.put_formats(&[ClipboardFormat::new(format, provider())]);
+    }
+
     pub fn get_string(&self) -> Option<String> {
         self.0.borrow().get_string()
     }
 
+    pub fn put_file_list(&mut self, paths: &[PathBuf]) {
+        let list = uri_list::from_paths(paths);
+        self.put_formats(&[ClipboardFormat::new(uri_list::URI_LIST, list)]);
+    }
+
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        let list = self.get_format(uri_list::URI_LIST)?;
+        let paths = uri_list::to_paths(std::str::from_utf8(&list).ok()?);
+        (!paths.is_empty()).then(|| paths)
+    }
+
     pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
         self.0.borrow().preferred_format(formats)
     }
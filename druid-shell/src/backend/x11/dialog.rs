@@ -19,7 +19,8 @@ use ashpd::{zbus, WindowIdentifier};
 use futures::executor::block_on;
 use tracing::warn;
 
-use crate::{FileDialogOptions, FileDialogToken, FileInfo};
+use crate::dialog::file_info;
+use crate::{FileDialogOptions, FileDialogToken};
 
 use super::window::IdleHandle;
 
@@ -78,12 +79,7 @@ fn dialog(
                 })
             });
             if multi && open {
-                let infos = paths
-                    .map(|p| FileInfo {
-                        path: p.into(),
-                        format: None,
-                    })
-                    .collect();
+                let infos = paths.map(|p| file_info(p.into(), |_| None)).collect();
                 idle.add_idle_callback(move |handler| handler.open_files(tok, infos));
             } else if !multi {
                 if uris.len() > 2 {
@@ -92,10 +88,7 @@ fn dialog(
                         uris.len()
                     );
                 }
-                let info = paths.next().map(|p| FileInfo {
-                    path: p.into(),
-                    format: None,
-                });
+                let info = paths.next().map(|p| file_info(p.into(), |_| None));
                 if open {
                     idle.add_idle_callback(move |handler| handler.open_file(tok, info));
                 } else {
@@ -52,4 +52,9 @@ impl Menu {
         // TODO(x11/menus): implement Menu::add_separator (currently a no-op)
         tracing::warn!("Menu::add_separator is currently unimplemented for X11 backend.");
     }
+
+    pub fn add_section(&mut self, _title: &str) {
+        // TODO(x11/menus): implement Menu::add_section (currently a no-op)
+        tracing::warn!("Menu::add_section is currently unimplemented for X11 backend.");
+    }
 }
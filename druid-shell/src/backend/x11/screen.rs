@@ -14,23 +14,28 @@
 
 //! X11 Monitors and Screen information.
 
+use anyhow::anyhow;
 use x11rb::connection::Connection;
 use x11rb::errors::ReplyOrIdError;
 use x11rb::protocol::randr::{self, ConnectionExt as _, Crtc};
-use x11rb::protocol::xproto::{Screen, Timestamp};
+use x11rb::protocol::xproto::{self, ConnectionExt as _, ImageOrder, Screen, Timestamp};
 
-use crate::kurbo::Rect;
-use crate::screen::Monitor;
+use super::util;
+use crate::backend::shared::strut;
+use crate::error::Error;
+use crate::kurbo::{Point, Rect, Size};
+use crate::piet::{ImageBuf, ImageFormat};
+use crate::scale::Scale;
+use crate::screen::{is_internal_connector, DisplayMode, Monitor, Orientation, SubpixelOrder};
 
 fn monitor<Pos>(primary: bool, (x, y): (Pos, Pos), (width, height): (u16, u16)) -> Monitor
 where
     Pos: Into<i32>,
 {
-    let rect = Rect::from_points(
+    let rect = Rect::from_origin_size(
         (x.into() as f64, y.into() as f64),
         (width as f64, height as f64),
     );
-    // TODO: Support for work_rect. It's complicated...
     Monitor::new(primary, rect, rect)
 }
 
@@ -57,12 +62,69 @@ pub(crate) fn get_monitors() -> Vec<Monitor> {
     }
 }
 
+pub(crate) fn num_monitors() -> usize {
+    // RandR has no cheaper way to count the monitors.
+    get_monitors().len()
+}
+
+pub(crate) fn get_mouse_position() -> Option<Point> {
+    let result = if let Some(app) = crate::Application::try_global() {
+        let app = app.backend_app;
+        get_mouse_position_impl(app.connection().as_ref(), app.screen_num())
+    } else {
+        match x11rb::connect(None) {
+            Ok((conn, screen_num)) => get_mouse_position_impl(&conn, screen_num),
+            Err(err) => {
+                tracing::error!("Error in Screen::get_mouse_position(): {:?}", err);
+                return None;
+            }
+        }
+    };
+    result
+        .map_err(|err| tracing::error!("Error in Screen::get_mouse_position(): {:?}", err))
+        .ok()
+}
+
+fn get_mouse_position_impl(
+    conn: &impl Connection,
+    screen_num: usize,
+) -> Result<Point, ReplyOrIdError> {
+    let root = conn.setup().roots[screen_num].root;
+    let reply = conn.query_pointer(root)?.reply()?;
+    Ok(Point::new(reply.root_x as f64, reply.root_y as f64))
+}
+
 fn get_monitors_impl(
     conn: &impl Connection,
     screen_num: usize,
 ) -> Result<Vec<Monitor>, ReplyOrIdError> {
     let screen = &conn.setup().roots[screen_num];
+    let monitors = get_monitor_rects(conn, screen)?;
+    if !strut::fallback_enabled() {
+        return Ok(monitors);
+    }
+    let rects: Vec<Rect> = monitors.iter().map(Monitor::virtual_rect).collect();
+    let size = Size::new(
+        screen.width_in_pixels as f64,
+        screen.height_in_pixels as f64,
+    );
+    match strut::work_areas(conn, screen.root, size, &rects) {
+        Ok(work_rects) => Ok(monitors
+            .into_iter()
+            .zip(work_rects)
+            .map(|(monitor, work_rect)| monitor.with_work_rect(work_rect))
+            .collect()),
+        Err(err) => {
+            tracing::warn!("failed to get the work areas from struts: {}", err);
+            Ok(monitors)
+        }
+    }
+}
 
+fn get_monitor_rects(
+    conn: &impl Connection,
+    screen: &Screen,
+) -> Result<Vec<Monitor>, ReplyOrIdError> {
     if conn
         .extension_information(randr::X11_EXTENSION_NAME)?
         .is_none()
@@ -93,13 +155,42 @@ fn get_monitors_randr_monitors(
     conn: &impl Connection,
     screen: &Screen,
 ) -> Result<Vec<Monitor>, ReplyOrIdError> {
-    let result = conn
+    let monitors = conn
         .randr_get_monitors(screen.root, true)?
         .reply()?
-        .monitors
-        .iter()
-        .map(|info| monitor(info.primary, (info.x, info.y), (info.width, info.height)))
-        .collect();
+        .monitors;
+    let mut result = Vec::new();
+    for info in monitors {
+        // Monitors are named after their outputs, like `eDP-1`, unless the user renamed them.
+        let name = conn.get_atom_name(info.name)?.reply()?.name;
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let internal = is_internal_connector(&name);
+        let (order, rotation) = match info.outputs.first() {
+            Some(&output) => {
+                let info = conn
+                    .randr_get_output_info(output, x11rb::CURRENT_TIME)?
+                    .reply()?;
+                // Outputs that are off don't have a CRTC.
+                let rotation = if info.crtc == x11rb::NONE {
+                    Orientation::Landscape
+                } else {
+                    let crtc = conn
+                        .randr_get_crtc_info(info.crtc, x11rb::CURRENT_TIME)?
+                        .reply()?;
+                    orientation(crtc.rotation)
+                };
+                (subpixel_order(info.subpixel_order), rotation)
+            }
+            None => (SubpixelOrder::Unknown, Orientation::Landscape),
+        };
+        result.push(
+            monitor(info.primary, (info.x, info.y), (info.width, info.height))
+                .with_internal(internal)
+                .with_subpixel_order(order)
+                .with_orientation(rotation)
+                .with_device_id(name),
+        );
+    }
     Ok(result)
 }
 
@@ -142,11 +233,19 @@ fn get_monitors_randr_crtcs_timestamp(
         if reply.width != 0 && reply.height != 0 {
             // First CRTC is assumed to be the primary output
             let primary = result.is_empty();
-            result.push(monitor(
-                primary,
-                (reply.x, reply.y),
-                (reply.width, reply.height),
-            ));
+            let mut mon = monitor(primary, (reply.x, reply.y), (reply.width, reply.height))
+                .with_orientation(orientation(reply.rotation));
+            if let Some(&output) = reply.outputs.first() {
+                let info = conn
+                    .randr_get_output_info(output, config_timestamp)?
+                    .reply()?;
+                let name = String::from_utf8_lossy(&info.name).into_owned();
+                mon = mon
+                    .with_internal(is_internal_connector(&name))
+                    .with_subpixel_order(subpixel_order(info.subpixel_order))
+                    .with_device_id(name);
+            }
+            result.push(mon);
         }
     }
     // TODO: I think we need to deduplicate monitors. In clone mode, each "clone" appears as its
@@ -154,3 +253,200 @@ fn get_monitors_randr_crtcs_timestamp(
 
     Ok(result)
 }
+
+pub(crate) fn display_modes(monitor: &Monitor) -> Vec<DisplayMode> {
+    let result = if let Some(app) = crate::Application::try_global() {
+        let app = app.backend_app;
+        display_modes_impl(app.connection().as_ref(), app.screen_num(), monitor)
+    } else {
+        let (conn, screen_num) = match x11rb::connect(None) {
+            Ok(res) => res,
+            Err(err) => {
+                tracing::error!("Error in Monitor::available_modes(): {:?}", err);
+                return Vec::new();
+            }
+        };
+        display_modes_impl(&conn, screen_num, monitor)
+    };
+    match result {
+        Ok(modes) => modes,
+        Err(err) => {
+            tracing::error!("Error in Monitor::available_modes(): {:?}", err);
+            Vec::new()
+        }
+    }
+}
+
+fn display_modes_impl(
+    conn: &impl Connection,
+    screen_num: usize,
+    monitor: &Monitor,
+) -> Result<Vec<DisplayMode>, ReplyOrIdError> {
+    let name = match monitor.device_id() {
+        Some(name) => name.as_bytes(),
+        None => return Ok(Vec::new()),
+    };
+    if conn
+        .extension_information(randr::X11_EXTENSION_NAME)?
+        .is_none()
+    {
+        return Ok(Vec::new());
+    }
+    // Listing the modes needs RandR 1.3
+    let version = conn.randr_query_version(1, 5)?.reply()?;
+    if (version.major_version, version.minor_version) < (1, 3) {
+        return Ok(Vec::new());
+    }
+    let root = conn.setup().roots[screen_num].root;
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+    let mut found = None;
+    for &output in &resources.outputs {
+        let info = conn
+            .randr_get_output_info(output, resources.config_timestamp)?
+            .reply()?;
+        if info.name == name {
+            found = Some(info);
+            break;
+        }
+    }
+    // Monitors the user renamed are found through their first output.
+    if found.is_none() && (version.major_version, version.minor_version) >= (1, 5) {
+        for info in conn.randr_get_monitors(root, true)?.reply()?.monitors {
+            if conn.get_atom_name(info.name)?.reply()?.name != name {
+                continue;
+            }
+            if let Some(&output) = info.outputs.first() {
+                found = Some(
+                    conn.randr_get_output_info(output, resources.config_timestamp)?
+                        .reply()?,
+                );
+            }
+            break;
+        }
+    }
+    let info = match found {
+        Some(info) => info,
+        None => return Ok(Vec::new()),
+    };
+    // Outputs that are off don't have a CRTC.
+    let current = if info.crtc == x11rb::NONE {
+        x11rb::NONE
+    } else {
+        conn.randr_get_crtc_info(info.crtc, resources.config_timestamp)?
+            .reply()?
+            .mode
+    };
+    Ok(output_modes(&info, current, &resources.modes))
+}
+
+/// Returns the modes of an output, marking `current`, the mode of its CRTC.
+fn output_modes(
+    info: &randr::GetOutputInfoReply,
+    current: randr::Mode,
+    mode_infos: &[randr::ModeInfo],
+) -> Vec<DisplayMode> {
+    info.modes
+        .iter()
+        .filter_map(|&id| mode_infos.iter().find(|mode| mode.id == id))
+        .map(|mode| {
+            // X11 doesn't scale, so every mode has the default scale.
+            DisplayMode::new(
+                Size::new(mode.width as f64, mode.height as f64),
+                util::mode_refresh_rate(mode),
+                Scale::default(),
+            )
+            .with_current(mode.id == current)
+        })
+        .collect()
+}
+
+fn orientation(rotation: randr::Rotation) -> Orientation {
+    let rotation = u16::from(rotation);
+    let is_rotated = |by: randr::Rotation| rotation & u16::from(by) != 0;
+    if is_rotated(randr::Rotation::ROTATE90) {
+        Orientation::Portrait
+    } else if is_rotated(randr::Rotation::ROTATE180) {
+        Orientation::LandscapeFlipped
+    } else if is_rotated(randr::Rotation::ROTATE270) {
+        Orientation::PortraitFlipped
+    } else {
+        Orientation::Landscape
+    }
+}
+
+fn subpixel_order(subpixel: randr::SubPixel) -> SubpixelOrder {
+    match subpixel {
+        randr::SubPixel::HORIZONTAL_RGB => SubpixelOrder::Rgb,
+        randr::SubPixel::HORIZONTAL_BGR => SubpixelOrder::Bgr,
+        randr::SubPixel::VERTICAL_RGB => SubpixelOrder::VRgb,
+        randr::SubPixel::VERTICAL_BGR => SubpixelOrder::VBgr,
+        randr::SubPixel::NONE => SubpixelOrder::None,
+        _ => SubpixelOrder::Unknown,
+    }
+}
+
+pub(crate) fn set_mode(_monitor: &Monitor, _mode: &DisplayMode) -> Result<(), Error> {
+    tracing::warn!("Monitor::set_mode is currently unimplemented for X11 backend.");
+    Err(anyhow!("changing the display mode is not supported on X11").into())
+}
+
+pub(crate) fn capture_region(rect: Rect) -> Result<ImageBuf, Error> {
+    let rect = rect.round();
+    let image = if let Some(app) = crate::Application::try_global() {
+        let app = app.backend_app;
+        capture_region_impl(app.connection().as_ref(), app.screen_num(), rect)?
+    } else {
+        let (conn, screen_num) = x11rb::connect(None).map_err(anyhow::Error::from)?;
+        capture_region_impl(&conn, screen_num, rect)?
+    };
+    Ok(image)
+}
+
+fn capture_region_impl(
+    conn: &impl Connection,
+    screen_num: usize,
+    rect: Rect,
+) -> Result<ImageBuf, anyhow::Error> {
+    let setup = conn.setup();
+    let root = setup.roots[screen_num].root;
+    let (width, height) = (rect.width() as u16, rect.height() as u16);
+    let image = conn
+        .get_image(
+            xproto::ImageFormat::Z_PIXMAP,
+            root,
+            rect.x0 as i16,
+            rect.y0 as i16,
+            width,
+            height,
+            !0,
+        )?
+        .reply()?;
+    // These depths store each pixel in 32 bits, with the unused byte at the most significant end.
+    if image.depth != 24 && image.depth != 32 {
+        return Err(anyhow!("unsupported screen depth {}", image.depth));
+    }
+    let lsb_first = setup.image_byte_order == ImageOrder::LSB_FIRST;
+    let pixels: Vec<u8> = image
+        .data
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            if lsb_first {
+                [pixel[2], pixel[1], pixel[0], 255]
+            } else {
+                [pixel[1], pixel[2], pixel[3], 255]
+            }
+        })
+        .collect();
+    Ok(ImageBuf::from_raw(
+        pixels,
+        ImageFormat::RgbaSeparate,
+        width as usize,
+        height as usize,
+    ))
+}
+
+impl crate::platform::linux::ScreenExt for crate::Screen {
+    fn set_work_area_fallback(enabled: bool) {
+        strut::set_fallback_enabled(enabled);
+    }
+}
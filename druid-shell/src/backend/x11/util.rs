@@ -19,7 +19,7 @@ use std::rc::Rc;
 use anyhow::{anyhow, Error};
 use x11rb::connection::RequestConnection;
 use x11rb::errors::ReplyError;
-use x11rb::protocol::randr::{ConnectionExt, ModeFlag};
+use x11rb::protocol::randr::{ConnectionExt, ModeFlag, ModeInfo};
 use x11rb::protocol::render::{self, ConnectionExt as _};
 use x11rb::protocol::xproto::{Screen, Visualid, Visualtype, Window};
 use x11rb::xcb_ffi::XCBConnection;
@@ -38,23 +38,7 @@ pub fn refresh_rate(conn: &Rc<XCBConnection>, window_id: Window) -> Option<f64>
             .first()
             .ok_or_else(|| anyhow!("didn't get any modes"))
             .and_then(|mode_info| {
-                let flags = mode_info.mode_flags;
-                let vtotal = {
-                    let mut val = mode_info.vtotal;
-                    if (flags & u32::from(ModeFlag::DOUBLE_SCAN)) != 0 {
-                        val *= 2;
-                    }
-                    if (flags & u32::from(ModeFlag::INTERLACE)) != 0 {
-                        val /= 2;
-                    }
-                    val
-                };
-
-                if vtotal != 0 && mode_info.htotal != 0 {
-                    Ok((mode_info.dot_clock as f64) / (vtotal as f64 * mode_info.htotal as f64))
-                } else {
-                    Err(anyhow!("got nonsensical mode values"))
-                }
+                mode_refresh_rate(mode_info).ok_or_else(|| anyhow!("got nonsensical mode values"))
             })
     };
 
@@ -67,6 +51,27 @@ pub fn refresh_rate(conn: &Rc<XCBConnection>, window_id: Window) -> Option<f64>
     }
 }
 
+/// Returns the refresh rate of a RandR mode, or `None` if its timings are missing.
+pub fn mode_refresh_rate(mode_info: &ModeInfo) -> Option<f64> {
+    let flags = mode_info.mode_flags;
+    let vtotal = {
+        let mut val = mode_info.vtotal;
+        if (flags & u32::from(ModeFlag::DOUBLE_SCAN)) != 0 {
+            val *= 2;
+        }
+        if (flags & u32::from(ModeFlag::INTERLACE)) != 0 {
+            val /= 2;
+        }
+        val
+    };
+
+    if vtotal != 0 && mode_info.htotal != 0 {
+        Some((mode_info.dot_clock as f64) / (vtotal as f64 * mode_info.htotal as f64))
+    } else {
+        None
+    }
+}
+
 // Apparently you have to get the visualtype this way :|
 fn find_visual_from_screen(screen: &Screen, visual_id: u32) -> Option<Visualtype> {
     for depth in &screen.allowed_depths {
@@ -19,6 +19,7 @@ use std::collections::BinaryHeap;
 use std::convert::{TryFrom, TryInto};
 use std::os::unix::io::RawFd;
 use std::panic::Location;
+use std::path::Path;
 use std::rc::{Rc, Weak};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
@@ -29,7 +30,7 @@ use cairo::{XCBConnection as CairoXCBConnection, XCBDrawable, XCBSurface, XCBVis
 use tracing::{error, info, warn};
 use x11rb::connection::Connection;
 use x11rb::errors::ReplyOrIdError;
-use x11rb::properties::{WmHints, WmHintsState, WmSizeHints};
+use x11rb::properties::{AspectRatio, WmHints, WmHintsState, WmSizeHints};
 use x11rb::protocol::present::{CompleteNotifyEvent, ConnectionExt as _, IdleNotifyEvent};
 use x11rb::protocol::render::{ConnectionExt as _, Pictformat};
 use x11rb::protocol::xfixes::{ConnectionExt as _, Region as XRegion};
@@ -44,14 +45,15 @@ use x11rb::xcb_ffi::XCBConnection;
 #[cfg(feature = "raw-win-handle")]
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle, XcbWindowHandle};
 
+use crate::accessibility::AccessibilityTree;
 use crate::backend::shared::Timer;
-use crate::common_util::IdleCallback;
+use crate::common_util::{CursorStack, IdleCallback};
 use crate::dialog::FileDialogOptions;
 use crate::error::Error as ShellError;
 use crate::keyboard::{KeyState, Modifiers};
 use crate::kurbo::{Insets, Point, Rect, Size, Vec2};
-use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
-use crate::piet::{Piet, PietText, RenderContext};
+use crate::mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
+use crate::piet::{Color, Piet, PietText, RenderContext};
 use crate::region::Region;
 use crate::scale::Scale;
 use crate::text::{simulate_input, Event};
@@ -99,7 +101,12 @@ impl From<Visualtype> for xcb_visualtype_t {
     }
 }
 
-fn size_hints(resizable: bool, size: Size, min_size: Size) -> WmSizeHints {
+fn size_hints(
+    resizable: bool,
+    size: Size,
+    min_size: Size,
+    aspect_ratio: Option<f64>,
+) -> WmSizeHints {
     let mut size_hints = WmSizeHints::new();
     if resizable {
         size_hints.min_size = Some((min_size.width as i32, min_size.height as i32));
@@ -107,6 +114,11 @@ fn size_hints(resizable: bool, size: Size, min_size: Size) -> WmSizeHints {
         size_hints.min_size = Some((size.width as i32, size.height as i32));
         size_hints.max_size = Some((size.width as i32, size.height as i32));
     }
+    if let Some(ratio) = aspect_ratio {
+        // The hints only hold integers, so the ratio is kept to a thousandth.
+        let ratio = AspectRatio::new((ratio * 1000.0).round() as i32, 1000);
+        size_hints.aspect = Some((ratio, ratio));
+    }
     size_hints
 }
 
@@ -171,6 +183,10 @@ impl WindowBuilder {
         self.transparent = transparent;
     }
 
+    pub fn set_background_color(&mut self, _color: Color) {
+        warn!("WindowBuilder::set_background_color is currently unimplemented for X11 backend.");
+    }
+
     pub fn set_position(&mut self, position: Point) {
         self.position = Some(position);
     }
@@ -183,6 +199,18 @@ impl WindowBuilder {
         self.level = level;
     }
 
+    pub fn set_parent(&mut self, _parent: WindowHandle) {
+        warn!("WindowBuilder::set_parent is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_modal(&mut self, _modal: bool) {
+        warn!("WindowBuilder::set_modal is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_type(&mut self, _window_type: window::WindowType) {
+        warn!("WindowBuilder::set_type is currently unimplemented for X11 backend.");
+    }
+
     pub fn set_window_state(&mut self, state: window::WindowState) {
         self.state = Some(state);
     }
@@ -293,7 +321,9 @@ impl WindowBuilder {
         };
 
         let (parent, parent_origin) = match &self.level {
-            WindowLevel::AppWindow => (Weak::new(), Vec2::ZERO),
+            WindowLevel::AppWindow | WindowLevel::Floating | WindowLevel::Overlay => {
+                (Weak::new(), Vec2::ZERO)
+            }
             WindowLevel::Tooltip(parent)
             | WindowLevel::DropDown(parent)
             | WindowLevel::Modal(parent) => {
@@ -432,7 +462,7 @@ impl WindowBuilder {
         .context("set WM_PROTOCOLS")?;
 
         let min_size = self.min_size.to_px(scale);
-        log_x11!(size_hints(self.resizable, size_px, min_size)
+        log_x11!(size_hints(self.resizable, size_px, min_size, None)
             .set_normal_hints(conn.as_ref(), id)
             .context("set wm normal hints"));
 
@@ -450,7 +480,9 @@ impl WindowBuilder {
         // set level
         {
             let window_type = match self.level {
-                WindowLevel::AppWindow => atoms._NET_WM_WINDOW_TYPE_NORMAL,
+                WindowLevel::AppWindow | WindowLevel::Floating | WindowLevel::Overlay => {
+                    atoms._NET_WM_WINDOW_TYPE_NORMAL
+                }
                 WindowLevel::Tooltip(_) => atoms._NET_WM_WINDOW_TYPE_TOOLTIP,
                 WindowLevel::Modal(_) => atoms._NET_WM_WINDOW_TYPE_DIALOG,
                 WindowLevel::DropDown(_) => atoms._NET_WM_WINDOW_TYPE_DROPDOWN_MENU,
@@ -473,6 +505,16 @@ impl WindowBuilder {
                     &ChangeWindowAttributesAux::new().override_redirect(1),
                 ));
             }
+            // The window manager reads the initial state when the window is mapped.
+            if matches!(self.level, WindowLevel::Floating | WindowLevel::Overlay) {
+                log_x11!(conn.change_property32(
+                    xproto::PropMode::REPLACE,
+                    id,
+                    atoms._NET_WM_STATE,
+                    AtomEnum::ATOM,
+                    &[atoms._NET_WM_STATE_ABOVE],
+                ));
+            }
         }
 
         let window = Rc::new(Window {
@@ -484,6 +526,8 @@ impl WindowBuilder {
             area: Cell::new(ScaledArea::from_px(size_px, scale)),
             scale: Cell::new(scale),
             min_size,
+            resizable: Cell::new(self.resizable),
+            aspect_ratio: Cell::new(None),
             invalid: RefCell::new(Region::EMPTY),
             destroyed: Cell::new(false),
             timer_queue: Mutex::new(BinaryHeap::new()),
@@ -493,6 +537,7 @@ impl WindowBuilder {
             buffers,
             active_text_field: Cell::new(None),
             parent,
+            cursor: RefCell::new(CursorStack::default()),
         });
 
         window.set_title(&self.title);
@@ -569,6 +614,9 @@ pub(crate) struct Window {
     scale: Cell<Scale>,
     // min size in px
     min_size: Size,
+    resizable: Cell<bool>,
+    /// The ratio of width to height that the window keeps when it's resized, if any.
+    aspect_ratio: Cell<Option<f64>>,
     /// We've told X11 to destroy this window, so don't do any more X requests with this window id.
     destroyed: Cell<bool>,
     /// The region that was invalidated since the last time we rendered.
@@ -612,6 +660,7 @@ pub(crate) struct Window {
     buffers: RefCell<Buffers>,
     active_text_field: Cell<Option<TextFieldToken>>,
     parent: Weak<Window>,
+    cursor: RefCell<CursorStack>,
 }
 
 /// A collection of pixmaps for rendering to. This gets used in two different ways: if the present
@@ -871,8 +920,24 @@ impl Window {
 
     /// Set whether the window should be resizable
     fn resizable(&self, resizable: bool) {
+        self.resizable.set(resizable);
+        self.update_size_hints();
+    }
+
+    fn set_aspect_ratio(&self, ratio: Option<f64>) {
+        self.aspect_ratio.set(ratio);
+        self.update_size_hints();
+    }
+
+    fn update_size_hints(&self) {
         let conn = self.app.connection().as_ref();
-        log_x11!(size_hints(resizable, self.size().size_px(), self.min_size)
+        let hints = size_hints(
+            self.resizable.get(),
+            self.size().size_px(),
+            self.min_size,
+            self.aspect_ratio.get(),
+        );
+        log_x11!(hints
             .set_normal_hints(conn, self.id)
             .context("set normal hints"));
     }
@@ -906,6 +971,38 @@ impl Window {
             .unwrap_or_default()
     }
 
+    /// Reads the window manager's decorations from `_NET_FRAME_EXTENTS`.
+    ///
+    /// This is zero if the window manager doesn't decorate the window.
+    fn content_insets(&self) -> Insets {
+        fn _content_insets(window: &Window) -> Result<Insets, Error> {
+            let conn = window.app.connection();
+            let atoms = window.app.atoms();
+            let reply = conn
+                .get_property(
+                    false,
+                    window.id,
+                    atoms._NET_FRAME_EXTENTS,
+                    AtomEnum::CARDINAL,
+                    0,
+                    4,
+                )?
+                .reply()?;
+            // The extents are left, right, top, bottom.
+            let extents: Vec<u32> = reply.value32().into_iter().flatten().collect();
+            Ok(match extents[..] {
+                [left, right, top, bottom] => {
+                    Insets::new(left as f64, top as f64, right as f64, bottom as f64)
+                        .to_dp(window.scale.get())
+                }
+                _ => Insets::ZERO,
+            })
+        }
+        let insets = _content_insets(self);
+        log_x11!(&insets);
+        insets.unwrap_or(Insets::ZERO)
+    }
+
     fn set_position(&self, pos: Point) {
         let conn = self.app.connection();
         let scale = self.scale.get();
@@ -922,6 +1019,35 @@ impl Window {
         warn!("Window::set_always_on_top is currently unimplemented for X11 backend.");
     }
 
+    fn set_level(&self, level: WindowLevel) {
+        let above = match level {
+            WindowLevel::AppWindow => false,
+            WindowLevel::Floating | WindowLevel::Overlay => true,
+            WindowLevel::Tooltip(_) | WindowLevel::DropDown(_) | WindowLevel::Modal(_) => {
+                // These are override-redirect, which has to be set before the window is mapped.
+                warn!("Window::set_level can't change to this level after creation on X11.");
+                return;
+            }
+        };
+        let conn = self.app.connection();
+        let atoms = self.app.atoms();
+        let root = conn.setup().roots[self.app.screen_num()].root;
+        // Once mapped, the window manager changes the state when asked, with 1 to add the
+        // state and 0 to remove it. The 1 after the atoms says the request is from an app.
+        let event = xproto::ClientMessageEvent::new(
+            32,
+            self.id,
+            atoms._NET_WM_STATE,
+            [above as u32, atoms._NET_WM_STATE_ABOVE, 0, 1, 0],
+        );
+        log_x11!(conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY,
+            event,
+        ));
+    }
+
     fn set_input_region(&self, _region: Option<Region>) {
         // Looks like con.shape_mask or conn_shape_rectangles may be the
         // correct way to implement this.
@@ -1059,6 +1185,8 @@ impl Window {
             Cursor::NotAllowed => cursors.not_allowed,
             Cursor::ResizeLeftRight => cursors.col_resize,
             Cursor::ResizeUpDown => cursors.row_resize,
+            Cursor::Wait => cursors.wait,
+            Cursor::Progress => cursors.progress,
             Cursor::Custom(custom) => Some(custom.0),
         };
         if cursor.is_none() {
@@ -1111,17 +1239,22 @@ impl Window {
     ) -> Result<(), Error> {
         let button = mouse_button(button_press.detail);
         let scale = self.scale.get();
+        // The xcb state field doesn't include the newly pressed button, but
+        // druid wants it to be included.
+        let buttons = mouse_buttons(button_press.state).with(button);
         let mouse_event = MouseEvent {
             pos: Point::new(button_press.event_x as f64, button_press.event_y as f64).to_dp(scale),
-            // The xcb state field doesn't include the newly pressed button, but
-            // druid wants it to be included.
-            buttons: mouse_buttons(button_press.state).with(button),
+            buttons,
             mods: key_mods(button_press.state),
             // TODO: detect the count
             count: 1,
             focus: false,
             button,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         };
         self.with_handler(|h| h.mouse_down(&mouse_event));
         Ok(())
@@ -1133,17 +1266,22 @@ impl Window {
     ) -> Result<(), Error> {
         let scale = self.scale.get();
         let button = mouse_button(button_release.detail);
+        // The xcb state includes the newly released button, but druid
+        // doesn't want it.
+        let buttons = mouse_buttons(button_release.state).without(button);
         let mouse_event = MouseEvent {
             pos: Point::new(button_release.event_x as f64, button_release.event_y as f64)
                 .to_dp(scale),
-            // The xcb state includes the newly released button, but druid
-            // doesn't want it.
-            buttons: mouse_buttons(button_release.state).without(button),
+            buttons,
             mods: key_mods(button_release.state),
             count: 0,
             focus: false,
             button,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         };
         self.with_handler(|h| h.mouse_up(&mouse_event));
         Ok(())
@@ -1165,14 +1303,19 @@ impl Window {
             7 => (120.0, 0.0),
             _ => return Err(anyhow!("unexpected mouse wheel button: {}", button)),
         };
+        let buttons = mouse_buttons(event.state);
         let mouse_event = MouseEvent {
             pos: Point::new(event.event_x as f64, event.event_y as f64).to_dp(scale),
-            buttons: mouse_buttons(event.state),
+            buttons,
             mods: key_mods(event.state),
             count: 0,
             focus: false,
             button: MouseButton::None,
             wheel_delta: delta.into(),
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         };
 
         self.with_handler(|h| h.wheel(&mouse_event));
@@ -1184,15 +1327,20 @@ impl Window {
         motion_notify: &xproto::MotionNotifyEvent,
     ) -> Result<(), Error> {
         let scale = self.scale.get();
+        let buttons = mouse_buttons(motion_notify.state);
         let mouse_event = MouseEvent {
             pos: Point::new(motion_notify.event_x as f64, motion_notify.event_y as f64)
                 .to_dp(scale),
-            buttons: mouse_buttons(motion_notify.state),
+            buttons,
             mods: key_mods(motion_notify.state),
             count: 0,
             focus: false,
             button: MouseButton::None,
             wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
         };
         self.with_handler(|h| h.mouse_move(&mouse_event));
         Ok(())
@@ -1213,7 +1361,9 @@ impl Window {
         if client_message.type_ == atoms.WM_PROTOCOLS && client_message.format == 32 {
             let protocol = client_message.data.as_data32()[0];
             if protocol == atoms.WM_DELETE_WINDOW {
-                self.with_handler(|h| h.request_close());
+                if self.with_handler(|h| h.should_close()).unwrap_or(false) {
+                    self.close();
+                }
             }
         }
     }
@@ -1515,7 +1665,7 @@ fn mouse_button(button: u8) -> MouseButton {
 
 // Extracts the mouse buttons from, e.g., the `state` field of
 // `xcb::xproto::ButtonPressEvent`
-fn mouse_buttons(mods: u16) -> MouseButtons {
+pub(crate) fn mouse_buttons(mods: u16) -> MouseButtons {
     let mut buttons = MouseButtons::new();
     let button_masks = &[
         (xproto::ButtonMask::M1, MouseButton::Left),
@@ -1534,7 +1684,7 @@ fn mouse_buttons(mods: u16) -> MouseButtons {
 
 // Extracts the keyboard modifiers from, e.g., the `state` field of
 // `xcb::xproto::ButtonPressEvent`
-fn key_mods(mods: u16) -> Modifiers {
+pub(crate) fn key_mods(mods: u16) -> Modifiers {
     let mut ret = Modifiers::default();
     let mut key_masks = [
         (xproto::ModMask::SHIFT, Modifiers::SHIFT),
@@ -1654,7 +1804,7 @@ impl WindowHandle {
         }
     }
 
-    pub fn resizable(&self, resizable: bool) {
+    pub fn set_resizable(&self, resizable: bool) {
         if let Some(w) = self.window.upgrade() {
             w.resizable(resizable);
         } else {
@@ -1662,6 +1812,14 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_aspect_ratio(&self, ratio: Option<f64>) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_aspect_ratio(ratio);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
     pub fn show_titlebar(&self, show_titlebar: bool) {
         if let Some(w) = self.window.upgrade() {
             w.show_titlebar(show_titlebar);
@@ -1686,6 +1844,10 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_always_on_bottom(&self, _on_bottom: bool) {
+        warn!("WindowHandle::set_always_on_bottom is currently unimplemented for X11 backend.");
+    }
+
     pub fn set_input_region(&self, region: Option<Region>) {
         if let Some(w) = self.window.upgrade() {
             w.set_input_region(region);
@@ -1704,8 +1866,12 @@ impl WindowHandle {
     }
 
     pub fn content_insets(&self) -> Insets {
-        warn!("WindowHandle::content_insets unimplemented for X11 backend.");
-        Insets::ZERO
+        if let Some(w) = self.window.upgrade() {
+            w.content_insets()
+        } else {
+            error!("Window {} has already been dropped", self.id);
+            Insets::ZERO
+        }
     }
 
     pub fn set_size(&self, size: Size) {
@@ -1770,6 +1936,17 @@ impl WindowHandle {
         }
     }
 
+    pub fn paint_now(&self) {
+        // While a present is pending this only schedules the paint for when it completes.
+        if let Some(w) = self.window.upgrade() {
+            if let Err(e) = w.redraw_now() {
+                error!("Window::paint_now - failed to redraw: {}", e);
+            }
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
     pub fn set_title(&self, title: &str) {
         if let Some(w) = self.window.upgrade() {
             w.set_title(title);
@@ -1778,6 +1955,10 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_represented_file(&self, _path: Option<&Path>) {}
+
+    pub fn set_document_edited(&self, _edited: bool) {}
+
     pub fn set_menu(&self, menu: Menu) {
         if let Some(w) = self.window.upgrade() {
             w.set_menu(menu);
@@ -1812,6 +1993,14 @@ impl WindowHandle {
         // noop until we get a real text input implementation
     }
 
+    pub fn set_ime_enabled(&self, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
+    pub fn set_text_field_ime_enabled(&self, _token: TextFieldToken, _enabled: bool) {
+        // noop: we don't use an input method yet, so keys are never composed
+    }
+
     pub fn request_timer(&self, deadline: Instant) -> TimerToken {
         if let Some(w) = self.window.upgrade() {
             let timer = Timer::new(deadline, ());
@@ -1824,7 +2013,25 @@ impl WindowHandle {
 
     pub fn set_cursor(&mut self, cursor: &Cursor) {
         if let Some(w) = self.window.upgrade() {
-            w.set_cursor(cursor);
+            if w.cursor.borrow_mut().set(cursor) {
+                w.set_cursor(cursor);
+            }
+        }
+    }
+
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_cursor(&cursor);
+            w.cursor.borrow_mut().push(cursor);
+        }
+    }
+
+    pub fn pop_cursor(&mut self) {
+        if let Some(w) = self.window.upgrade() {
+            let mut stack = w.cursor.borrow_mut();
+            if stack.pop() {
+                w.set_cursor(stack.current().unwrap_or(&Cursor::Arrow));
+            }
         }
     }
 
@@ -1854,6 +2061,44 @@ impl WindowHandle {
         }
     }
 
+    pub fn set_cursor_visible(&self, _visible: bool) {
+        warn!("WindowHandle::set_cursor_visible is currently unimplemented for X11 backend.");
+    }
+
+    pub fn confine_cursor(&self, _confine: bool) {
+        warn!("WindowHandle::confine_cursor is currently unimplemented for X11 backend.");
+    }
+
+    pub fn announce(&self, _message: &str, _politeness: window::Politeness) {
+        warn!("WindowHandle::announce is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_level(&self, level: WindowLevel) {
+        if let Some(w) = self.window.upgrade() {
+            w.set_level(level);
+        } else {
+            error!("Window {} has already been dropped", self.id);
+        }
+    }
+
+    pub fn set_dismiss_on_focus_loss(&self, _dismiss: bool) {
+        warn!(
+            "WindowHandle::set_dismiss_on_focus_loss is currently unimplemented for X11 backend."
+        );
+    }
+
+    pub fn set_opacity(&self, _opacity: f64) {
+        warn!("WindowHandle::set_opacity is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_background_color(&self, _color: Color) {
+        warn!("WindowHandle::set_background_color is currently unimplemented for X11 backend.");
+    }
+
+    pub fn set_accessibility_tree(&self, _tree: AccessibilityTree) {
+        warn!("WindowHandle::set_accessibility_tree is currently unimplemented for X11 backend.");
+    }
+
     pub fn open_file(&mut self, options: FileDialogOptions) -> Option<FileDialogToken> {
         if let Some(w) = self.window.upgrade() {
             if let Some(idle) = self.get_idle_handle() {
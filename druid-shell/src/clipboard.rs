@@ -15,6 +15,9 @@
 //! Interacting with the system pasteboard/clipboard.
 pub use crate::backend::clipboard as backend;
 
+use std::path::PathBuf;
+use std::rc::Rc;
+
 /// A handle to the system clipboard.
 ///
 /// To get access to the global clipboard, call [`Application::clipboard`].
@@ -46,7 +49,9 @@ pub use crate::backend::clipboard as backend;
 /// [`ClipboardFormat`], these `const`s are set per-platform.
 ///
 /// When defining custom formats, you should use the correct identifier for
-/// the current platform.
+/// the current platform. A private format that only your application understands
+/// can use any identifier, such as `com.example.selection`; it is registered as a
+/// clipboard format name on Windows and used as a target on GTK.
 ///
 /// ## Setting custom data
 ///
@@ -122,28 +127,122 @@ pub use crate::backend::clipboard as backend;
 /// [`Universal Type Identifier`]: https://escapetech.eu/manuals/qdrop/uti.html
 /// [MIME types]: https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types
 #[derive(Debug, Clone)]
-pub struct Clipboard(pub(crate) backend::Clipboard);
+pub struct Clipboard(Inner);
+
+#[derive(Debug, Clone)]
+enum Inner {
+    Backend(backend::Clipboard),
+    /// The clipboard of a [`TestState`](crate::testing::TestState).
+    #[cfg(feature = "testing")]
+    Memory(crate::testing::MemoryClipboard),
+}
 
 impl Clipboard {
     /// Put a string onto the system clipboard.
     pub fn put_string(&mut self, s: impl AsRef<str>) {
-        self.0.put_string(s);
+        match &mut self.0 {
+            Inner::Backend(clipboard) => clipboard.put_string(s),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.put_string(s.as_ref()),
+        }
     }
 
     /// Put multi-format data on the system clipboard.
     pub fn put_formats(&mut self, formats: &[ClipboardFormat]) {
-        self.0.put_formats(formats)
+        match &mut self.0 {
+            Inner::Backend(clipboard) => clipboard.put_formats(formats),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.put_formats(formats),
+        }
+    }
+
+    /// Put a string on the system clipboard, along with other representations of it.
+    ///
+    /// `primary` is put as plain text, unchanged, so [`get_string`] returns it exactly.
+    /// The `fallbacks`, such as an [`RTF`](ClipboardFormat::RTF) version, are put together
+    /// with it, like with [`put_formats`], and the application that pastes picks the one it
+    /// handles best. Fallbacks in the plain text format would replace `primary`, so they
+    /// are skipped.
+    ///
+    /// [`get_string`]: Clipboard::get_string
+    /// [`put_formats`]: Clipboard::put_formats
+    pub fn put_string_with_fallbacks(&mut self, primary: &str, fallbacks: &[ClipboardFormat]) {
+        let formats: Vec<ClipboardFormat> = std::iter::once(primary.into())
+            .chain(
+                fallbacks
+                    .iter()
+                    .filter(|format| format.identifier != ClipboardFormat::TEXT)
+                    .cloned(),
+            )
+            .collect();
+        self.put_formats(&formats);
+    }
+
+    /// Put data on the system clipboard that is only made when something pastes it.
+    ///
+    /// `provider` is called when an application asks for `format`, which can be never,
+    /// so large data doesn't have to be made up front. This replaces the current contents
+    /// of the clipboard, and `provider` is dropped when something else is put on it.
+    ///
+    /// When the application quits while it still owns the clipboard, `provider` is called
+    /// one last time so that the data can still be pasted afterwards. This uses
+    /// `pasteboard:provideDataForType:` on macOS, delayed rendering on Windows, and
+    /// `gtk_clipboard_set_with_data` on GTK. On Windows the data is made right away when
+    /// this is called before [`Application::run`].
+    ///
+    /// Other backends call `provider` right away.
+    ///
+    /// [`Application::run`]: crate::Application::run
+    pub fn put_lazy(&mut self, format: FormatId, provider: impl Fn() -> Vec<u8> + 'static) {
+        match &mut self.0 {
+            Inner::Backend(clipboard) => clipboard.put_lazy(format, Rc::new(provider)),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => {
+                clipboard.put_formats(&[ClipboardFormat::new(format, provider())])
+            }
+        }
     }
 
     /// Get a string from the system clipboard, if one is available.
     pub fn get_string(&self) -> Option<String> {
-        self.0.get_string()
+        match &self.0 {
+            Inner::Backend(clipboard) => clipboard.get_string(),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.get_string(),
+        }
+    }
+
+    /// Put a list of files onto the system clipboard, as if they were copied in the
+    /// platform's file manager.
+    ///
+    /// The paths should be absolute. This replaces the current contents of the clipboard.
+    pub fn put_file_list(&mut self, paths: &[PathBuf]) {
+        match &mut self.0 {
+            Inner::Backend(clipboard) => clipboard.put_file_list(paths),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.put_file_list(paths),
+        }
+    }
+
+    /// Get the list of files on the system clipboard, if there is one.
+    ///
+    /// This is how files copied in the platform's file manager are pasted.
+    pub fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        match &self.0 {
+            Inner::Backend(clipboard) => clipboard.get_file_list(),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.get_file_list(),
+        }
     }
 
     /// Given a list of supported clipboard types, returns the supported type which has
     /// highest priority on the system clipboard, or `None` if no types are supported.
     pub fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
-        self.0.preferred_format(formats)
+        match &self.0 {
+            Inner::Backend(clipboard) => clipboard.preferred_format(formats),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.preferred_format(formats),
+        }
     }
 
     /// Return data in a given format, if available.
@@ -154,17 +253,28 @@ impl Clipboard {
     /// [`Clipboard::preferred_format`]: struct.Clipboard.html#method.preferred_format
     /// [`FormatId`]: type.FormatId.html
     pub fn get_format(&self, format: FormatId) -> Option<Vec<u8>> {
-        self.0.get_format(format)
+        match &self.0 {
+            Inner::Backend(clipboard) => clipboard.get_format(format),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.get_format(format),
+        }
     }
 
     /// For debugging: print the resolved identifiers for each type currently
     /// on the clipboard.
     #[doc(hidden)]
     pub fn available_type_names(&self) -> Vec<String> {
-        self.0.available_type_names()
+        match &self.0 {
+            Inner::Backend(clipboard) => clipboard.available_type_names(),
+            #[cfg(feature = "testing")]
+            Inner::Memory(clipboard) => clipboard.available_type_names(),
+        }
     }
 }
 
+/// Makes the data of a format put with [`Clipboard::put_lazy`].
+pub(crate) type DataProvider = Rc<dyn Fn() -> Vec<u8>>;
+
 /// A type identifier for the system clipboard.
 ///
 /// These should be [`UTI` strings] on macOS, and (by convention?) [MIME types] elsewhere.
@@ -208,7 +318,14 @@ impl From<&str> for ClipboardFormat {
 
 impl From<backend::Clipboard> for Clipboard {
     fn from(src: backend::Clipboard) -> Clipboard {
-        Clipboard(src)
+        Clipboard(Inner::Backend(src))
+    }
+}
+
+#[cfg(feature = "testing")]
+impl From<crate::testing::MemoryClipboard> for Clipboard {
+    fn from(src: crate::testing::MemoryClipboard) -> Clipboard {
+        Clipboard(Inner::Memory(src))
     }
 }
 
@@ -218,6 +335,7 @@ cfg_if::cfg_if! {
             pub const PDF: &'static str = "com.adobe.pdf";
             pub const TEXT: &'static str = "public.utf8-plain-text";
             pub const SVG: &'static str = "public.svg-image";
+            pub const RTF: &'static str = "public.rtf";
         }
     } else {
         impl ClipboardFormat {
@@ -231,6 +349,102 @@ cfg_if::cfg_if! {
             }
             pub const PDF: &'static str = "application/pdf";
             pub const SVG: &'static str = "image/svg+xml";
+            cfg_if::cfg_if! {
+                if #[cfg(target_os = "windows")] {
+                    // The name that word processors register the format under.
+                    pub const RTF: &'static str = "Rich Text Format";
+                } else {
+                    pub const RTF: &'static str = "text/rtf";
+                }
+            }
+        }
+    }
+}
+
+/// Lists of files are put on the clipboard as lists of `file://` URIs on Linux.
+#[cfg(any(target_os = "freebsd", target_os = "linux", target_os = "openbsd"))]
+pub(crate) mod uri_list {
+    use std::ffi::OsString;
+    use std::os::unix::ffi::{OsStrExt, OsStringExt};
+    use std::path::{Path, PathBuf};
+
+    use super::FormatId;
+
+    /// The MIME type of a list of URIs.
+    pub(crate) const URI_LIST: FormatId = "text/uri-list";
+
+    /// Encodes `paths` as a `text/uri-list`.
+    pub(crate) fn from_paths(paths: &[PathBuf]) -> String {
+        paths
+            .iter()
+            .map(|path| format!("{}\r\n", file_uri(path)))
+            .collect()
+    }
+
+    /// Decodes the `file://` URIs in a `text/uri-list`, skipping comments and other schemes.
+    pub(crate) fn to_paths(list: &str) -> Vec<PathBuf> {
+        list.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(file_path)
+            .collect()
+    }
+
+    fn file_uri(path: &Path) -> String {
+        let mut uri = String::from("file://");
+        for &byte in path.as_os_str().as_bytes() {
+            if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+                uri.push(byte as char);
+            } else {
+                uri.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        uri
+    }
+
+    fn file_path(uri: &str) -> Option<PathBuf> {
+        let rest = uri.strip_prefix("file://")?;
+        // Skip the host, which is usually empty or `localhost`.
+        let path = &rest[rest.find('/')?..];
+        let mut bytes = Vec::with_capacity(path.len());
+        let mut iter = path.bytes();
+        while let Some(byte) = iter.next() {
+            if byte == b'%' {
+                let hex = [iter.next()?, iter.next()?];
+                let hex = std::str::from_utf8(&hex).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            } else {
+                bytes.push(byte);
+            }
+        }
+        Some(PathBuf::from(OsString::from_vec(bytes)))
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use std::ffi::OsStr;
+
+        #[test]
+        fn round_trip_paths() {
+            let paths = vec![
+                PathBuf::from("/home/me/My Documents/report 100%.pdf"),
+                PathBuf::from("/tmp/日本語/ファイル.txt"),
+                PathBuf::from(OsStr::from_bytes(b"/tmp/not\xffutf8")),
+            ];
+            let list = from_paths(&paths);
+            assert!(list.starts_with("file:///home/me/My%20Documents/report%20100%25.pdf\r\n"));
+            assert_eq!(to_paths(&list), paths);
+        }
+
+        #[test]
+        fn skip_comments_and_other_uris() {
+            let list = "# copied\r\nhttps://example.com/a\r\nfile://localhost/a%20b\r\nfile:///c";
+            assert_eq!(
+                to_paths(list),
+                vec![PathBuf::from("/a b"), PathBuf::from("/c")]
+            );
+            assert!(to_paths("file:///bad%2").is_empty());
         }
     }
 }
@@ -22,6 +22,7 @@ use std::time::Duration;
 use instant::Instant;
 
 use crate::kurbo::Point;
+use crate::mouse::Cursor;
 use crate::WinHandler;
 
 // This is the default timing on windows.
@@ -152,3 +153,127 @@ impl Default for ClickCounter {
         ClickCounter::new(MULTI_CLICK_INTERVAL, MULTI_CLICK_MAX_DISTANCE)
     }
 }
+
+/// The cursor of a window, and the cursors pushed over it with `push_cursor`.
+///
+/// While a cursor is pushed, setting the cursor only changes the one that comes back
+/// once everything is popped.
+#[derive(Debug, Default)]
+pub(crate) struct CursorStack {
+    cursor: Option<Cursor>,
+    pushed: Vec<Cursor>,
+}
+
+#[allow(dead_code)]
+impl CursorStack {
+    /// Sets the cursor, and returns true if it should be shown now.
+    pub fn set(&mut self, cursor: &Cursor) -> bool {
+        self.cursor = Some(cursor.clone());
+        self.pushed.is_empty()
+    }
+
+    /// Pushes a cursor, which should be shown now.
+    pub fn push(&mut self, cursor: Cursor) {
+        self.pushed.push(cursor);
+    }
+
+    /// Pops the last pushed cursor, and returns false if nothing was pushed.
+    ///
+    /// The [`current`](CursorStack::current) cursor should be shown after this.
+    pub fn pop(&mut self) -> bool {
+        self.pushed.pop().is_some()
+    }
+
+    /// Returns the cursor that should be shown, or `None` if no cursor was set yet.
+    pub fn current(&self) -> Option<&Cursor> {
+        self.pushed.last().or(self.cursor.as_ref())
+    }
+}
+
+/// Tracks the frames requested with `request_anim_frame`, and the time between them.
+///
+/// The time is whatever monotonic timestamp the platform gives its frames, which is
+/// an [`Instant`] unless the platform has a better one.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AnimationClock<T = Instant> {
+    requested: bool,
+    last_frame: Option<T>,
+}
+
+impl<T> Default for AnimationClock<T> {
+    fn default() -> Self {
+        AnimationClock {
+            requested: false,
+            last_frame: None,
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl<T: Copy + PartialOrd + std::ops::Sub<Output = Duration>> AnimationClock<T> {
+    /// Notes that the next frame was requested with `request_anim_frame`.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Called before painting the frame for `time`.
+    ///
+    /// Returns the time since the previous animation frame if this frame was requested, which is
+    /// zero for the first frame of an animation. A frame that wasn't requested ends the animation.
+    pub fn frame(&mut self, time: T) -> Option<Duration> {
+        if !std::mem::take(&mut self.requested) {
+            self.last_frame = None;
+            return None;
+        }
+        let interval = match self.last_frame {
+            Some(last) if time > last => time - last,
+            _ => Duration::ZERO,
+        };
+        self.last_frame = Some(time);
+        Some(interval)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn animation_frames_measure_intervals() {
+        let mut clock = AnimationClock::default();
+        assert_eq!(clock.frame(Duration::from_millis(10)), None);
+
+        clock.request();
+        assert_eq!(clock.frame(Duration::from_millis(20)), Some(Duration::ZERO));
+        clock.request();
+        assert_eq!(
+            clock.frame(Duration::from_millis(36)),
+            Some(Duration::from_millis(16))
+        );
+
+        // The animation stopped, so the next one starts from zero again.
+        assert_eq!(clock.frame(Duration::from_millis(52)), None);
+        clock.request();
+        assert_eq!(clock.frame(Duration::from_millis(68)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn pushed_cursors_nest() {
+        let mut stack = CursorStack::default();
+        assert_eq!(stack.current(), None);
+        assert!(stack.set(&Cursor::IBeam));
+
+        stack.push(Cursor::Progress);
+        stack.push(Cursor::Wait);
+        // Setting the cursor, like widgets do on every mouse move, doesn't replace pushed ones.
+        assert!(!stack.set(&Cursor::Pointer));
+        assert_eq!(stack.current(), Some(&Cursor::Wait));
+
+        assert!(stack.pop());
+        assert_eq!(stack.current(), Some(&Cursor::Progress));
+        assert!(stack.pop());
+        assert_eq!(stack.current(), Some(&Cursor::Pointer));
+        assert!(!stack.pop());
+        assert_eq!(stack.current(), Some(&Cursor::Pointer));
+    }
+}
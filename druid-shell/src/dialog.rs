@@ -20,6 +20,7 @@ use std::path::{Path, PathBuf};
 ///
 /// This path might point to a file or a directory.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct FileInfo {
     /// The path to the selected file.
     ///
@@ -34,6 +35,16 @@ pub struct FileInfo {
     /// The `path` above will already contain the appropriate extension chosen in the
     /// `format` property, so it is not necessary to mutate `path` any further.
     pub format: Option<FileSpec>,
+    /// The system's type identifier for the file.
+    ///
+    /// This is a UTI on macOS, and a MIME type on Windows and Linux. For save dialogs
+    /// this reflects the chosen format, or the extension of the chosen path.
+    pub content_type: Option<String>,
+    /// The size of the file in bytes.
+    ///
+    /// This is `None` if the path doesn't point to an existing file,
+    /// which is always the case for directories and usually for save dialogs.
+    pub len: Option<u64>,
 }
 
 /// Type of file dialog.
@@ -176,12 +187,44 @@ pub struct FileSpec {
 }
 
 impl FileInfo {
+    /// Create a new `FileInfo` for `path`, without a format, content type or length.
+    pub fn new(path: PathBuf) -> FileInfo {
+        FileInfo {
+            path,
+            format: None,
+            content_type: None,
+            len: None,
+        }
+    }
+
     /// Returns the underlying path.
     pub fn path(&self) -> &Path {
         &self.path
     }
 }
 
+/// Returns the `FileInfo` of a path picked in a dialog, with the type that `content_type`
+/// finds for it and the length of the file, if it exists.
+#[allow(dead_code)]
+pub(crate) fn file_info(
+    path: PathBuf,
+    content_type: impl FnOnce(&Path) -> Option<String>,
+) -> FileInfo {
+    FileInfo {
+        content_type: content_type(&path),
+        len: file_len(&path),
+        ..FileInfo::new(path)
+    }
+}
+
+/// Returns the length of the file at `path`, if it is an existing file.
+fn file_len(path: &Path) -> Option<u64> {
+    std::fs::metadata(path)
+        .ok()
+        .filter(|meta| meta.is_file())
+        .map(|meta| meta.len())
+}
+
 impl FileDialogOptions {
     /// Create a new set of options.
     pub fn new() -> FileDialogOptions {
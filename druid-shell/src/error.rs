@@ -21,13 +21,25 @@ use crate::backend::error as backend;
 
 /// Shell errors.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// The Application instance has already been created.
     ApplicationAlreadyExists,
+    /// The Application was created on a thread that the platform doesn't allow.
+    ///
+    /// On macOS this has to be the first thread of the process.
+    NotMainThread,
     /// Tried to use the application after it had been dropped.
     ApplicationDropped,
     /// The window has already been destroyed.
     WindowDropped,
+    /// Another instance of the application is already running, and the launch
+    /// arguments were forwarded to it.
+    ///
+    /// See [`Application::new_single_instance`](crate::Application::new_single_instance).
+    AlreadyRunning,
+    /// The user hasn't given the application a permission that the operation requires.
+    PermissionDenied,
     /// Platform specific error.
     Platform(backend::Error),
     /// Other miscellaneous error.
@@ -40,6 +52,10 @@ impl fmt::Display for Error {
             Error::ApplicationAlreadyExists => {
                 write!(f, "An application instance has already been created.")
             }
+            Error::NotMainThread => write!(
+                f,
+                "Main thread assertion failed: the application must be created on the main thread."
+            ),
             Error::ApplicationDropped => {
                 write!(
                     f,
@@ -48,6 +64,13 @@ impl fmt::Display for Error {
             }
             Error::Platform(err) => fmt::Display::fmt(err, f),
             Error::WindowDropped => write!(f, "The window has already been destroyed."),
+            Error::AlreadyRunning => {
+                write!(f, "Another instance of the application is already running.")
+            }
+            Error::PermissionDenied => write!(
+                f,
+                "The user hasn't granted a permission that this operation requires."
+            ),
             Error::Other(s) => write!(f, "{s}"),
         }
     }
@@ -15,6 +15,8 @@
 //! Hotkeys and helpers for parsing keyboard shortcuts.
 
 use std::borrow::Borrow;
+use std::fmt;
+use std::str::FromStr;
 
 use tracing::warn;
 
@@ -54,6 +56,9 @@ use crate::{IntoKey, KbKey, KeyEvent, Modifiers};
 /// assert!(!hotkey.matches(KeyEvent::for_test(RawMods::Ctrl, KbKey::ArrowLeft)));
 /// ```
 ///
+/// Hotkeys can also be parsed from strings such as `"Cmd+Shift+P"`, see
+/// [`HotKey::from_str`].
+///
 /// [`SysMods`]: enum.SysMods.html
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HotKey {
@@ -118,6 +123,179 @@ impl HotKey {
     }
 }
 
+impl FromStr for HotKey {
+    type Err = ParseHotKeyError;
+
+    /// Parses a hotkey from modifier names and a key, separated by `+`.
+    ///
+    /// The modifiers are `Ctrl`, `Alt`, `Shift` and `Meta`, case insensitive, with the
+    /// aliases `Control`, `Option`, `Super` and `Win`. `Cmd` is the Command key on macOS and
+    /// Ctrl elsewhere, like [`SysMods::Cmd`].
+    ///
+    /// The key is a single character, or the name of a [`KbKey`] such as `F4` or `ArrowLeft`.
+    /// `Plus`, `Space`, `Esc`, `Del`, `Left` and similar short names are also accepted.
+    /// Letters are matched in upper case with Shift and in lower case without it, as
+    /// explained at [`HotKey::new`].
+    ///
+    /// # Examples
+    /// ```
+    /// use druid_shell::{HotKey, KbKey, RawMods, SysMods};
+    ///
+    /// let command_palette: HotKey = "Cmd+Shift+P".parse().unwrap();
+    /// assert_eq!(command_palette, HotKey::new(SysMods::CmdShift, "P"));
+    /// let close: HotKey = "alt+F4".parse().unwrap();
+    /// assert_eq!(close, HotKey::new(RawMods::Alt, KbKey::F4));
+    /// assert_eq!(close.to_string(), "Alt+F4");
+    /// assert!("Hyper+P".parse::<HotKey>().is_err());
+    /// ```
+    fn from_str(s: &str) -> Result<HotKey, ParseHotKeyError> {
+        // The key can be `+` itself, as in "Ctrl++".
+        let (mods_str, key_str) = match s.strip_suffix("++") {
+            Some(mods_str) => (Some(mods_str), "+"),
+            None if s.trim() == "+" => (None, "+"),
+            None => match s.rsplit_once('+') {
+                Some((mods_str, key_str)) => (Some(mods_str), key_str),
+                None => (None, s),
+            },
+        };
+        let mut mods = Modifiers::empty();
+        for token in mods_str
+            .into_iter()
+            .flat_map(|mods_str| mods_str.split('+'))
+        {
+            let token = token.trim();
+            let modifier = parse_modifier(token)?;
+            if mods.intersects(modifier) {
+                return Err(ParseHotKeyError::new(token, "duplicate modifier"));
+            }
+            mods |= modifier;
+        }
+        let key = parse_key(key_str.trim(), mods.shift())?;
+        Ok(HotKey {
+            mods: raw_mods(mods),
+            key,
+        })
+    }
+}
+
+/// Writes the hotkey in the form accepted by [`HotKey::from_str`].
+///
+/// The modifiers are written as `Ctrl+Shift+Alt+Meta+`, in that order, and letters in upper
+/// case, so parsing a hotkey and writing it gives the same string for equivalent inputs.
+impl fmt::Display for HotKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mods: Modifiers = self.mods.into();
+        let names = [
+            (Modifiers::CONTROL, "Ctrl"),
+            (Modifiers::SHIFT, "Shift"),
+            (Modifiers::ALT, "Alt"),
+            (Modifiers::META, "Meta"),
+        ];
+        for (modifier, name) in names {
+            if mods.contains(modifier) {
+                write!(f, "{name}+")?;
+            }
+        }
+        match &self.key {
+            KbKey::Character(c) => match c.as_str() {
+                "+" => f.write_str("Plus"),
+                " " => f.write_str("Space"),
+                c => f.write_str(&c.to_uppercase()),
+            },
+            key => write!(f, "{key}"),
+        }
+    }
+}
+
+/// An error from parsing a [`HotKey`] with [`HotKey::from_str`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseHotKeyError {
+    token: String,
+    reason: &'static str,
+}
+
+impl ParseHotKeyError {
+    fn new(token: &str, reason: &'static str) -> Self {
+        ParseHotKeyError {
+            token: token.to_string(),
+            reason,
+        }
+    }
+
+    /// The part of the string that couldn't be parsed, without surrounding whitespace.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+}
+
+impl fmt::Display for ParseHotKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid hotkey token {:?}: {}", self.token, self.reason)
+    }
+}
+
+impl std::error::Error for ParseHotKeyError {}
+
+fn parse_modifier(name: &str) -> Result<Modifiers, ParseHotKeyError> {
+    let modifier = match name.to_ascii_lowercase().as_str() {
+        "" => return Err(ParseHotKeyError::new(name, "missing modifier")),
+        "ctrl" | "control" => Modifiers::CONTROL,
+        "alt" | "option" => Modifiers::ALT,
+        "shift" => Modifiers::SHIFT,
+        "meta" | "super" | "win" => Modifiers::META,
+        "cmd" | "command" => RawMods::from(SysMods::Cmd).into(),
+        _ => return Err(ParseHotKeyError::new(name, "unknown modifier")),
+    };
+    Ok(modifier)
+}
+
+fn parse_key(name: &str, shift: bool) -> Result<KbKey, ParseHotKeyError> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "" => return Err(ParseHotKeyError::new(name, "missing key")),
+        "plus" => KbKey::Character("+".into()),
+        "space" => KbKey::Character(" ".into()),
+        "esc" => KbKey::Escape,
+        "del" => KbKey::Delete,
+        "ins" => KbKey::Insert,
+        "pgup" => KbKey::PageUp,
+        "pgdn" => KbKey::PageDown,
+        "left" => KbKey::ArrowLeft,
+        "right" => KbKey::ArrowRight,
+        "up" => KbKey::ArrowUp,
+        "down" => KbKey::ArrowDown,
+        "return" => KbKey::Enter,
+        _ => match name.parse() {
+            Ok(KbKey::Character(c)) if shift => KbKey::Character(c.to_uppercase()),
+            Ok(KbKey::Character(c)) => KbKey::Character(c.to_lowercase()),
+            Ok(key) => key,
+            Err(_) => return Err(ParseHotKeyError::new(name, "unknown key")),
+        },
+    };
+    Ok(key)
+}
+
+/// The [`RawMods`] with the Alt, Ctrl, Meta and Shift state of `mods`.
+fn raw_mods(mods: Modifiers) -> RawMods {
+    match (mods.alt(), mods.ctrl(), mods.meta(), mods.shift()) {
+        (false, false, false, false) => RawMods::None,
+        (true, false, false, false) => RawMods::Alt,
+        (false, true, false, false) => RawMods::Ctrl,
+        (false, false, true, false) => RawMods::Meta,
+        (false, false, false, true) => RawMods::Shift,
+        (true, true, false, false) => RawMods::AltCtrl,
+        (true, false, true, false) => RawMods::AltMeta,
+        (true, false, false, true) => RawMods::AltShift,
+        (false, true, true, false) => RawMods::CtrlMeta,
+        (false, true, false, true) => RawMods::CtrlShift,
+        (false, false, true, true) => RawMods::MetaShift,
+        (true, true, true, false) => RawMods::AltCtrlMeta,
+        (true, false, true, true) => RawMods::AltMetaShift,
+        (true, true, false, true) => RawMods::AltCtrlShift,
+        (false, true, true, true) => RawMods::CtrlMetaShift,
+        (true, true, true, true) => RawMods::AltCtrlMetaShift,
+    }
+}
+
 /// A platform-agnostic representation of keyboard modifiers, for command handling.
 ///
 /// This does one thing: it allows specifying hotkeys that use the Command key
@@ -245,3 +423,54 @@ impl From<SysMods> for RawMods {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_hotkeys() {
+        let parse = |s: &str| s.parse::<HotKey>().unwrap();
+        assert_eq!(parse("Ctrl+Shift+P"), HotKey::new(RawMods::CtrlShift, "P"));
+        assert_eq!(parse("ctrl + p"), HotKey::new(RawMods::Ctrl, "p"));
+        assert_eq!(parse("Cmd+,"), HotKey::new(SysMods::Cmd, ","));
+        assert_eq!(parse("Option+F4"), HotKey::new(RawMods::Alt, KbKey::F4));
+        assert_eq!(parse("Ctrl++"), HotKey::new(RawMods::Ctrl, "+"));
+        assert_eq!(parse("Ctrl+Plus"), HotKey::new(RawMods::Ctrl, "+"));
+        assert_eq!(parse("Esc"), HotKey::new(None, KbKey::Escape));
+        assert_eq!(
+            parse("Super+Left"),
+            HotKey::new(RawMods::Meta, KbKey::ArrowLeft)
+        );
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in [
+            "Ctrl+Shift+P",
+            "Alt+F4",
+            "Ctrl+Plus",
+            "Shift+Meta+Space",
+            "ArrowUp",
+            "-",
+        ] {
+            assert_eq!(s.parse::<HotKey>().unwrap().to_string(), s);
+        }
+        let hotkey: HotKey = "shift+alt+ctrl+x".parse().unwrap();
+        assert_eq!(hotkey.to_string(), "Ctrl+Shift+Alt+X");
+        assert_eq!(hotkey.to_string().parse::<HotKey>().unwrap(), hotkey);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |s: &str| s.parse::<HotKey>().unwrap_err();
+        assert_eq!(error("Hyper+P").token(), "Hyper");
+        assert_eq!(error("Ctrl+Shift+ctrl+P").token(), "ctrl");
+        assert_eq!(error("Ctrl+Banana").token(), "Banana");
+        assert_eq!(error("Ctrl+").token(), "");
+        assert_eq!(
+            error("Ctrl+Banana").to_string(),
+            "invalid hotkey token \"Banana\": unknown key"
+        );
+    }
+}
@@ -44,6 +44,10 @@ pub struct KeyEvent {
     pub location: Location,
     /// Flags for pressed modifier keys.
     pub mods: Modifiers,
+    /// Which side of the keyboard the pressed modifier keys are on.
+    ///
+    /// This is empty on platforms that don't report it.
+    pub mod_sides: ModifierSides,
     /// True if the key is currently auto-repeated.
     pub repeat: bool,
     /// Events with this flag should be ignored in a text editor
@@ -61,6 +65,13 @@ pub struct KeyEvent {
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Modifiers(keyboard_types::Modifiers);
 
+/// The left and right instances of the modifier keys that are pressed.
+///
+/// [`Modifiers`] only tells whether, for example, a Shift key is pressed.
+/// This tells which one.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct ModifierSides(u8);
+
 /// A convenience trait for creating Key objects.
 ///
 /// This trait is implemented by [`KbKey`] itself and also strings, which are
@@ -85,6 +96,7 @@ impl KeyEvent {
             location: Location::Standard,
             state: KeyState::Down,
             mods,
+            mod_sides: ModifierSides::empty(),
             is_composing: false,
             repeat: false,
         }
@@ -208,6 +220,92 @@ impl Not for Modifiers {
     }
 }
 
+impl ModifierSides {
+    pub const LEFT_SHIFT: ModifierSides = ModifierSides(1 << 0);
+    pub const RIGHT_SHIFT: ModifierSides = ModifierSides(1 << 1);
+    pub const LEFT_CONTROL: ModifierSides = ModifierSides(1 << 2);
+    pub const RIGHT_CONTROL: ModifierSides = ModifierSides(1 << 3);
+    pub const LEFT_ALT: ModifierSides = ModifierSides(1 << 4);
+    pub const RIGHT_ALT: ModifierSides = ModifierSides(1 << 5);
+    pub const LEFT_META: ModifierSides = ModifierSides(1 << 6);
+    pub const RIGHT_META: ModifierSides = ModifierSides(1 << 7);
+
+    /// Returns an empty set of modifier sides.
+    pub fn empty() -> ModifierSides {
+        Default::default()
+    }
+
+    /// Returns `true` if no modifier keys are pressed.
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if all the modifier keys in `other` are pressed.
+    pub fn contains(&self, other: ModifierSides) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Inserts or removes the specified modifier keys depending on the passed value.
+    pub fn set(&mut self, other: ModifierSides, value: bool) {
+        if value {
+            self.0 |= other.0;
+        } else {
+            self.0 &= !other.0;
+        }
+    }
+
+    /// Updates the pressed keys from a key event, for platforms that only report
+    /// which modifiers are pressed.
+    ///
+    /// Sides of modifiers that aren't in the event's `mods` are cleared first, so
+    /// this recovers from missed key releases, such as while the window wasn't focused.
+    #[allow(dead_code)]
+    pub(crate) fn update(&mut self, event: &KeyEvent) {
+        // xkb reports the logo keys as Super rather than Meta.
+        let sides = [
+            (Modifiers::SHIFT, Self::LEFT_SHIFT | Self::RIGHT_SHIFT),
+            (Modifiers::CONTROL, Self::LEFT_CONTROL | Self::RIGHT_CONTROL),
+            (Modifiers::ALT, Self::LEFT_ALT | Self::RIGHT_ALT),
+            (
+                Modifiers::META | Modifiers::SUPER,
+                Self::LEFT_META | Self::RIGHT_META,
+            ),
+        ];
+        for (modifier, both) in sides {
+            if (event.mods & modifier).is_empty() {
+                self.set(both, false);
+            }
+        }
+        let (left, right) = match event.key {
+            KbKey::Shift => (ModifierSides::LEFT_SHIFT, ModifierSides::RIGHT_SHIFT),
+            KbKey::Control => (ModifierSides::LEFT_CONTROL, ModifierSides::RIGHT_CONTROL),
+            KbKey::Alt => (ModifierSides::LEFT_ALT, ModifierSides::RIGHT_ALT),
+            KbKey::Meta | KbKey::Super => (ModifierSides::LEFT_META, ModifierSides::RIGHT_META),
+            _ => return,
+        };
+        let side = match event.location {
+            Location::Left => left,
+            Location::Right => right,
+            _ => return,
+        };
+        self.set(side, event.state == KeyState::Down);
+    }
+}
+
+impl BitOr for ModifierSides {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        ModifierSides(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for ModifierSides {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
 impl IntoKey for KbKey {
     fn into_key(self) -> KbKey {
         self
@@ -219,3 +317,55 @@ impl IntoKey for &str {
         KbKey::Character(self.into())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn key(key: KbKey, location: Location, state: KeyState, mods: Modifiers) -> KeyEvent {
+        let mut event = KeyEvent::for_test(mods, key);
+        event.location = location;
+        event.state = state;
+        event
+    }
+
+    #[test]
+    fn track_modifier_sides() {
+        let mut sides = ModifierSides::empty();
+        // The modifiers of a key event don't include the key itself yet.
+        let left_down = key(
+            KbKey::Shift,
+            Location::Left,
+            KeyState::Down,
+            Modifiers::empty(),
+        );
+        sides.update(&left_down);
+        assert_eq!(sides, ModifierSides::LEFT_SHIFT);
+
+        let right_down = key(
+            KbKey::Shift,
+            Location::Right,
+            KeyState::Down,
+            Modifiers::SHIFT,
+        );
+        sides.update(&right_down);
+        assert_eq!(
+            sides,
+            ModifierSides::LEFT_SHIFT | ModifierSides::RIGHT_SHIFT
+        );
+
+        let left_up = key(KbKey::Shift, Location::Left, KeyState::Up, Modifiers::SHIFT);
+        sides.update(&left_up);
+        assert_eq!(sides, ModifierSides::RIGHT_SHIFT);
+
+        // The right Shift was released while we weren't looking.
+        let a = key(
+            "a".into_key(),
+            Location::Standard,
+            KeyState::Down,
+            Modifiers::CONTROL,
+        );
+        sides.update(&a);
+        assert!(sides.is_empty());
+    }
+}
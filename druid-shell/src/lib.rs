@@ -56,6 +56,7 @@ pub use raw_window_handle;
 #[macro_use]
 mod util;
 
+mod accessibility;
 mod application;
 mod backend;
 mod clipboard;
@@ -69,26 +70,36 @@ mod mouse;
 mod region;
 mod scale;
 mod screen;
+mod touch;
 mod window;
 
 pub mod platform;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod text;
 
-pub use application::{AppHandler, Application};
+pub use accessibility::{AccessibilityNode, AccessibilityNodeId, AccessibilityTree, Role};
+pub use application::{
+    AppHandler, Application, EndSessionResponse, ExternalWindowId, GlobalMouseMonitor,
+    LayoutDirection, OsVersion, PermissionState, PowerSource, RunOutcome, SystemSound, WindowInfo,
+};
 pub use clipboard::{Clipboard, ClipboardFormat, FormatId};
 pub use common_util::Counter;
 pub use dialog::{FileDialogOptions, FileInfo, FileSpec};
 pub use error::Error;
-pub use hotkey::{HotKey, RawMods, SysMods};
-pub use keyboard::{Code, IntoKey, KbKey, KeyEvent, KeyState, Location, Modifiers};
-pub use menu::Menu;
-pub use mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent};
+pub use hotkey::{HotKey, ParseHotKeyError, RawMods, SysMods};
+pub use keyboard::{Code, IntoKey, KbKey, KeyEvent, KeyState, Location, ModifierSides, Modifiers};
+pub use menu::{Menu, StandardCommand};
+pub use mouse::{Cursor, CursorDesc, MouseButton, MouseButtons, MouseEvent, PointerType};
 pub use region::Region;
 pub use scale::{Scalable, Scale, ScaledArea};
-pub use screen::{Monitor, Screen};
+pub use screen::{
+    DisplayMode, Monitor, MonitorArrangement, Orientation, Screen, SnapZone, SubpixelOrder,
+};
+pub use touch::{TouchEvent, TouchId, TouchPhase};
 pub use window::{
-    FileDialogToken, IdleHandle, IdleToken, TextFieldToken, TimerToken, WinHandler, WindowBuilder,
-    WindowHandle, WindowLevel, WindowState,
+    FileDialogToken, IdleHandle, IdleToken, Politeness, TextFieldToken, TimerToken, WinHandler,
+    WindowBuilder, WindowHandle, WindowLevel, WindowState, WindowType,
 };
 
 pub use keyboard_types;
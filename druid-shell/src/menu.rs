@@ -23,8 +23,11 @@ use crate::hotkey::HotKey;
 /// # Configuring menus
 ///
 /// Currently, a menu and its items cannot be changed once created. If you need
-/// to change anything about a menu (for instance, disabling or selecting items)
-/// you need to create a new menu with the desired properties.
+/// to change anything about a menu (for instance, selecting items) you need to
+/// create a new menu with the desired properties. Items can be enabled or disabled
+/// just before the menu is shown, see [`WinHandler::menu_item_enabled`].
+///
+/// [`WinHandler::menu_item_enabled`]: crate::WinHandler::menu_item_enabled
 pub struct Menu(pub(crate) backend::Menu);
 
 impl Menu {
@@ -60,8 +63,8 @@ impl Menu {
     /// If the `selected` argument is `true`, the menu will have a checkmark
     /// or platform appropriate equivalent indicating that it is currently selected.
     /// The `key` argument is an optional [`HotKey`] that will be registered
-    /// with the system.
-    ///
+    /// with the system, and shown next to the item's text the way the platform
+    /// shows shortcuts, such as `⌘S` on macOS and `Ctrl+S` on Windows and GTK.
     ///
     /// [`WinHandler`]: crate::WinHandler
     /// [`command`]: crate::WinHandler::command
@@ -76,8 +79,124 @@ impl Menu {
         self.0.add_item(id, text, key, selected, enabled)
     }
 
+    /// Add an item for a [`StandardCommand`] to this menu.
+    ///
+    /// This is like [`add_item`] with the command's [`id`], but on macOS the edit
+    /// commands send the standard AppKit actions, such as `copy:`, so that they also work
+    /// in native text fields, like the ones in file dialogs.
+    ///
+    /// [`add_item`]: Menu::add_item
+    /// [`id`]: StandardCommand::id
+    pub fn add_standard_item(
+        &mut self,
+        command: StandardCommand,
+        text: &str,
+        key: Option<&HotKey>,
+        enabled: bool,
+    ) {
+        self.0.add_item(command.id(), text, key, None, enabled)
+    }
+
     /// Add a separator to the menu.
     pub fn add_separator(&mut self) {
         self.0.add_separator()
     }
+
+    /// Start a new section of the menu, with a header showing `title`.
+    ///
+    /// The header is a separator followed by a disabled item with the title, or only the
+    /// item at the top of the menu. On macOS 14 and later it is a native section header.
+    pub fn add_section(&mut self, title: &str) {
+        self.0.add_section(title)
+    }
+}
+
+/// A command that platforms have a standard menu item or action for.
+///
+/// Each command has a menu item [`id`] that is the same on every platform, so that it
+/// can be handled in [`WinHandler::command`] or [`AppHandler::command`] without
+/// choosing an id for it. Items for these commands are added with
+/// [`Menu::add_standard_item`].
+///
+/// On macOS, the standard edit actions that AppKit sends to the focused view, such as
+/// `copy:` and `undo:`, are delivered as these commands too, even when they don't come
+/// from a menu item. AppKit sends them along the responder chain, so they go to the
+/// [`WinHandler::command`] of the key window, and only reach [`AppHandler::command`]
+/// when no window is key.
+///
+/// [`id`]: StandardCommand::id
+/// [`WinHandler::command`]: crate::WinHandler::command
+/// [`AppHandler::command`]: crate::AppHandler::command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StandardCommand {
+    /// Show information about the application.
+    About,
+    /// Show the application's preferences, or settings.
+    Preferences,
+    /// Quit the application.
+    Quit,
+    /// Hide the application.
+    Hide,
+    /// Cut the selection to the clipboard.
+    Cut,
+    /// Copy the selection to the clipboard.
+    Copy,
+    /// Paste the contents of the clipboard.
+    Paste,
+    /// Select everything.
+    SelectAll,
+    /// Undo the last change.
+    Undo,
+    /// Redo the last undone change.
+    Redo,
+}
+
+impl StandardCommand {
+    /// Every standard command.
+    pub(crate) const ALL: [StandardCommand; 10] = [
+        StandardCommand::About,
+        StandardCommand::Preferences,
+        StandardCommand::Quit,
+        StandardCommand::Hide,
+        StandardCommand::Cut,
+        StandardCommand::Copy,
+        StandardCommand::Paste,
+        StandardCommand::SelectAll,
+        StandardCommand::Undo,
+        StandardCommand::Redo,
+    ];
+
+    /// The first of the ids reserved for standard commands.
+    ///
+    /// Menu item ids are 16 bits on Windows, so these are the last ids that fit.
+    const FIRST_ID: u32 = 0xFF00;
+
+    /// The menu item id of this command.
+    ///
+    /// Ids from `0xFF00` to `0xFFFF` are reserved for standard commands, and shouldn't be
+    /// used for other menu items.
+    pub fn id(self) -> u32 {
+        StandardCommand::FIRST_ID + self as u32
+    }
+
+    /// Returns the standard command with the menu item id `id`, if there is one.
+    pub fn from_id(id: u32) -> Option<StandardCommand> {
+        StandardCommand::ALL
+            .into_iter()
+            .find(|command| command.id() == id)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn standard_command_ids() {
+        for command in StandardCommand::ALL {
+            assert!(command.id() <= u16::MAX as u32);
+            assert_eq!(StandardCommand::from_id(command.id()), Some(command));
+        }
+        assert_eq!(StandardCommand::from_id(1), None);
+    }
 }
@@ -24,6 +24,7 @@ use crate::Modifiers;
 /// Every mouse event can have a new position. There is no guarantee of
 /// receiving a move event before another mouse event.
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct MouseEvent {
     /// The location of the mouse in [display points] in relation to the current window.
     ///
@@ -54,6 +55,111 @@ pub struct MouseEvent {
     ///
     /// [WheelEvent]: https://w3c.github.io/uievents/#event-type-wheel
     pub wheel_delta: Vec2,
+    /// The kind of device that caused the event.
+    pub pointer_type: PointerType,
+    /// The pressure of a pen or touch, from `0.0` to `1.0`.
+    ///
+    /// Devices that can't measure pressure, such as mice, report `1.0` while a button is held
+    /// down and `0.0` otherwise.
+    pub pressure: f64,
+    /// The angle between the pen and the surface, from `-90.0` to `90.0` degrees,
+    /// with positive values tilting towards the right.
+    ///
+    /// This is `0.0` for devices that don't report tilt.
+    pub tilt_x: f64,
+    /// The angle between the pen and the surface, from `-90.0` to `90.0` degrees,
+    /// with positive values tilting towards the user.
+    ///
+    /// This is `0.0` for devices that don't report tilt.
+    pub tilt_y: f64,
+}
+
+impl MouseEvent {
+    /// Creates an event at `pos` for `button`, while `buttons` are held down.
+    ///
+    /// The event comes from a [`PointerType::Mouse`] with the pressure a mouse reports, and
+    /// has no modifiers, clicks, focus, wheel movement or tilt. The builder-style methods
+    /// set the rest, for example to simulate input in tests.
+    pub fn new(pos: Point, buttons: MouseButtons, button: MouseButton) -> MouseEvent {
+        MouseEvent {
+            pos,
+            buttons,
+            mods: Modifiers::empty(),
+            count: 0,
+            focus: false,
+            button,
+            wheel_delta: Vec2::ZERO,
+            pointer_type: PointerType::Mouse,
+            pressure: buttons.default_pressure(),
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+        }
+    }
+
+    /// Builder-style method to set the keyboard modifiers.
+    pub fn with_mods(mut self, mods: Modifiers) -> Self {
+        self.mods = mods;
+        self
+    }
+
+    /// Builder-style method to set the number of clicks.
+    pub fn with_count(mut self, count: u8) -> Self {
+        self.count = count;
+        self
+    }
+
+    /// Builder-style method to set whether the click focused the window.
+    pub fn with_focus(mut self, focus: bool) -> Self {
+        self.focus = focus;
+        self
+    }
+
+    /// Builder-style method to set the wheel movement.
+    pub fn with_wheel_delta(mut self, wheel_delta: Vec2) -> Self {
+        self.wheel_delta = wheel_delta;
+        self
+    }
+
+    /// Builder-style method to set the kind of device.
+    pub fn with_pointer_type(mut self, pointer_type: PointerType) -> Self {
+        self.pointer_type = pointer_type;
+        self
+    }
+
+    /// Builder-style method to set the pressure, from `0.0` to `1.0`.
+    pub fn with_pressure(mut self, pressure: f64) -> Self {
+        self.pressure = pressure;
+        self
+    }
+
+    /// Builder-style method to set the tilt, in degrees.
+    ///
+    /// See [`tilt_x`](MouseEvent::tilt_x) and [`tilt_y`](MouseEvent::tilt_y).
+    pub fn with_tilt(mut self, tilt_x: f64, tilt_y: f64) -> Self {
+        self.tilt_x = tilt_x;
+        self.tilt_y = tilt_y;
+        self
+    }
+}
+
+/// The kind of device that caused a [`MouseEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum PointerType {
+    /// A mouse, or a device that the platform doesn't tell apart from one.
+    #[default]
+    Mouse,
+    /// The tip of a pen or stylus.
+    Pen,
+    /// The eraser end of a pen, or a pen in eraser mode.
+    Eraser,
+    /// A finger on a touch screen.
+    Touch,
+    /// A touchpad, or trackpad.
+    ///
+    /// Platforms can't always tell touchpads apart from mice, and on macOS and Windows
+    /// this is only reported for wheel events; their pointer events report `Mouse`.
+    Touchpad,
 }
 
 /// An indicator of which mouse button was pressed.
@@ -166,6 +272,16 @@ impl MouseButtons {
         self.0 == 0
     }
 
+    /// The pressure reported for devices that can't measure it.
+    #[inline]
+    pub(crate) fn default_pressure(self) -> f64 {
+        if self.is_empty() {
+            0.0
+        } else {
+            1.0
+        }
+    }
+
     /// Returns `true` if all the `buttons` are in the set.
     #[inline]
     pub fn is_superset(self, buttons: MouseButtons) -> bool {
@@ -247,6 +363,7 @@ impl std::fmt::Debug for MouseButtons {
 //both Windows and macOS. We may want to provide polyfills for various additional cursors.
 /// Mouse cursors.
 #[derive(Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum Cursor {
     /// The default arrow cursor.
     Arrow,
@@ -264,11 +381,41 @@ pub enum Cursor {
     NotAllowed,
     ResizeLeftRight,
     ResizeUpDown,
+    /// The application is busy, and the user can't interact with it until it's done.
+    ///
+    /// macOS has no public busy cursor, so this is the arrow there.
+    Wait,
+    /// The application is busy in the background, but the user can still interact with it.
+    ///
+    /// macOS has no public busy cursor, so this is the arrow there.
+    Progress,
     // The platform cursor should be small. Any image data that it uses should be shared (i.e.
     // behind an `Arc` or using a platform API that does the sharing).
     Custom(backend::window::CustomCursor),
 }
 
+impl Cursor {
+    /// Returns the cursor for a CSS `cursor` keyword, such as `"pointer"` or `"col-resize"`.
+    ///
+    /// Keywords without a cursor of their own get the closest one that is available on
+    /// every platform, so `"grab"` and `"grabbing"` are [`Cursor::Pointer`], and every
+    /// horizontal resize keyword is [`Cursor::ResizeLeftRight`]. Unknown names are
+    /// [`Cursor::Arrow`].
+    pub fn from_name(name: &str) -> Cursor {
+        match name {
+            "text" | "vertical-text" => Cursor::IBeam,
+            "pointer" | "grab" | "grabbing" | "alias" | "copy" => Cursor::Pointer,
+            "crosshair" | "cell" => Cursor::Crosshair,
+            "not-allowed" | "no-drop" => Cursor::NotAllowed,
+            "col-resize" | "ew-resize" | "e-resize" | "w-resize" => Cursor::ResizeLeftRight,
+            "row-resize" | "ns-resize" | "n-resize" | "s-resize" => Cursor::ResizeUpDown,
+            "wait" => Cursor::Wait,
+            "progress" => Cursor::Progress,
+            _ => Cursor::Arrow,
+        }
+    }
+}
+
 /// A platform-independent description of a custom cursor.
 #[derive(Clone)]
 pub struct CursorDesc {
@@ -305,7 +452,37 @@ impl std::fmt::Debug for Cursor {
             Cursor::NotAllowed => write!(f, "Cursor::NotAllowed"),
             Cursor::ResizeLeftRight => write!(f, "Cursor::ResizeLeftRight"),
             Cursor::ResizeUpDown => write!(f, "Cursor::ResizeUpDown"),
+            Cursor::Wait => write!(f, "Cursor::Wait"),
+            Cursor::Progress => write!(f, "Cursor::Progress"),
             Cursor::Custom(_) => write!(f, "Cursor::Custom"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cursor_from_name() {
+        assert_eq!(Cursor::from_name("text"), Cursor::IBeam);
+        assert_eq!(Cursor::from_name("grabbing"), Cursor::Pointer);
+        assert_eq!(Cursor::from_name("col-resize"), Cursor::ResizeLeftRight);
+        assert_eq!(Cursor::from_name("ns-resize"), Cursor::ResizeUpDown);
+        assert_eq!(Cursor::from_name("not-allowed"), Cursor::NotAllowed);
+        assert_eq!(Cursor::from_name("default"), Cursor::Arrow);
+        assert_eq!(Cursor::from_name("no-such-cursor"), Cursor::Arrow);
+    }
+
+    #[test]
+    fn new_events_press_like_a_mouse() {
+        let pressed = MouseButtons::new().with(MouseButton::Left);
+        let down = MouseEvent::new(Point::new(1.0, 2.0), pressed, MouseButton::Left).with_count(1);
+        assert_eq!(down.pointer_type, PointerType::Mouse);
+        assert_eq!(down.pressure, 1.0);
+        assert_eq!(down.count, 1);
+
+        let up = MouseEvent::new(Point::new(1.0, 2.0), MouseButtons::new(), MouseButton::Left);
+        assert_eq!(up.pressure, 0.0);
+    }
+}
@@ -25,6 +25,20 @@ pub trait ApplicationExt {
     fn primary_clipboard(&self) -> Clipboard;
 }
 
+/// Linux specific extensions to [`Screen`]
+///
+/// [`Screen`]: crate::Screen
+pub trait ScreenExt {
+    /// Sets whether monitor work areas are computed from the space that panels reserve.
+    ///
+    /// Some X11 window managers don't report the work area of each monitor, so by default
+    /// it is computed from the `_NET_WM_STRUT_PARTIAL` of panels and docks, or from
+    /// `_NET_WORKAREA`. With GTK this is only done for monitors whose work area GTK reports
+    /// as the whole monitor. Disable it for window managers that report work areas
+    /// correctly. This has no effect on Wayland.
+    fn set_work_area_fallback(enabled: bool);
+}
+
 #[cfg(test)]
 #[allow(unused_imports)]
 mod test {
@@ -35,4 +49,5 @@ mod test {
     // TODO: impl ApplicationExt for wayland
     #[cfg(not(feature = "wayland"))]
     sa::assert_impl_all!(Application: ApplicationExt);
+    sa::assert_impl_all!(crate::Screen: ScreenExt);
 }
@@ -14,6 +14,10 @@
 
 //! macOS specific extensions.
 
+use std::path::PathBuf;
+
+use crate::kurbo::{Point, Rect};
+
 /// macOS specific extensions to [`Application`]
 ///
 /// [`Application`]: crate::Application
@@ -27,14 +31,297 @@ pub trait ApplicationExt {
     /// Sets the global application menu, on platforms where there is one.
     ///
     /// On platforms with no global application menu, this has no effect.
+    ///
+    /// The menu replaces the whole menu bar, including the application menu. While no window
+    /// is open, its items are sent to [`AppHandler::command`].
+    ///
+    /// This is the same as [`Application::set_menu`], which is available on every platform.
+    ///
+    /// [`AppHandler::command`]: crate::AppHandler::command
+    /// [`Application::set_menu`]: crate::Application::set_menu
     fn set_menu(&self, menu: crate::Menu);
+
+    /// Sets the name of the application that is shown in bold in the menu bar.
+    ///
+    /// This renames the application menu, which is the first menu of the menus set
+    /// with [`set_menu`](ApplicationExt::set_menu) and [`WindowHandle::set_menu`], and the
+    /// process. It is useful for a single binary that runs as differently named tools.
+    ///
+    /// When the application runs from a bundle, macOS may keep showing the
+    /// `CFBundleName` of its `Info.plist`, which can't be changed at runtime.
+    ///
+    /// [`WindowHandle::set_menu`]: crate::WindowHandle::set_menu
+    fn set_menu_bar_name(&self, name: &str);
+
+    /// Sets whether AppKit adds its own items to the menus of the application.
+    ///
+    /// These are "Enter Full Screen", the window tab items, "Start Dictation…",
+    /// "Emoji & Symbols" and "Customize Touch Bar…". When disabled, the menu bar has exactly
+    /// the items of the menus that were set. AppKit reads some of these settings once, so
+    /// this should be called before [`Application::run`](crate::Application::run).
+    fn set_automatic_menu_items(&self, enabled: bool);
+
+    /// Resolves a bookmark created with [`FileInfoExt::security_scoped_bookmark`].
+    ///
+    /// On success this returns the bookmarked path, along with a [`ScopedAccess`] guard
+    /// that keeps the path accessible to sandboxed apps for as long as it is alive.
+    fn resolve_bookmark(&self, bookmark: &[u8]) -> Option<(PathBuf, ScopedAccess)>;
+
+    /// Makes this application the provider of the services declared in its `Info.plist`.
+    ///
+    /// Once set, choosing one of those services from another application's Services menu
+    /// calls `handler` with the selection that application sent.
+    /// Only services declared under the `NSServices` key are offered, see
+    /// [`declared_services`](ApplicationExt::declared_services).
+    fn set_services_provider(&self, handler: Box<dyn ServiceHandler>);
+
+    /// Returns the services declared under the `NSServices` key of the `Info.plist`.
+    ///
+    /// This is empty if the application isn't running from a bundle.
+    fn declared_services(&self) -> Vec<ServiceDeclaration>;
+}
+
+/// Handles services invoked from other applications' Services menus.
+///
+/// See [`ApplicationExt::set_services_provider`].
+pub trait ServiceHandler {
+    /// Performs the service that was declared with `request.message`.
+    ///
+    /// Return `Ok(Some(text))` to replace the selection in the requesting application,
+    /// for services that declare return types. An `Err` is a message that is shown to
+    /// the user.
+    fn perform_service(&mut self, request: ServiceRequest) -> Result<Option<String>, String>;
+}
+
+/// The data sent along with the invocation of a service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServiceRequest {
+    /// The `NSMessage` of the service that was invoked.
+    pub message: String,
+    /// The `NSUserData` of the service, if it declared any.
+    pub user_data: Option<String>,
+    /// The selected text, if the requesting application sent any.
+    pub text: Option<String>,
+    /// The pasteboard types of all the data that was sent, such as `public.utf8-plain-text`.
+    pub types: Vec<String>,
+}
+
+/// A service declared in the `Info.plist` of the application.
+///
+/// See [`ApplicationExt::declared_services`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ServiceDeclaration {
+    /// The `NSMessage`, which identifies the service in a [`ServiceRequest`].
+    pub message: String,
+    /// The default title of the service in the Services menu.
+    pub menu_title: Option<String>,
+    /// The pasteboard types the service accepts.
+    pub send_types: Vec<String>,
+    /// The pasteboard types the service can return.
+    pub return_types: Vec<String>,
+}
+
+/// macOS specific extensions to [`FileInfo`]
+///
+/// [`FileInfo`]: crate::FileInfo
+pub trait FileInfoExt {
+    /// Creates a security-scoped bookmark for this path.
+    ///
+    /// Sandboxed apps lose access to user chosen files when they are relaunched.
+    /// Persist the returned data and pass it to [`ApplicationExt::resolve_bookmark`]
+    /// to regain access later.
+    fn security_scoped_bookmark(&self) -> Option<Vec<u8>>;
+}
+
+/// macOS specific extensions to [`WindowHandle`]
+///
+/// [`WindowHandle`]: crate::WindowHandle
+pub trait WindowHandleExt {
+    /// Shows a translucent, blurred `material` behind the window's content, or removes it
+    /// if `material` is `None`.
+    ///
+    /// The window is made non-opaque, so the material only shows through where the
+    /// content is painted with transparent colors.
+    fn set_background_material(&self, material: Option<Material>);
+
+    /// Sets how the window behaves with Spaces, Mission Control and fullscreen windows.
+    ///
+    /// For example, a floating palette that should stay visible everywhere can combine
+    /// [`CollectionBehavior::CAN_JOIN_ALL_SPACES`] with
+    /// [`CollectionBehavior::FULL_SCREEN_AUXILIARY`].
+    fn set_collection_behavior(&self, behavior: CollectionBehavior);
+
+    /// Moves the close, minimize and zoom buttons, for example to line them up with
+    /// a toolbar drawn in a window without a visible titlebar.
+    ///
+    /// The close button is placed `inset.x` points from the left edge of the window, and
+    /// the titlebar is made tall enough to leave `inset.y` points above the buttons.
+    /// The other buttons keep their spacing, and the position is kept when the window
+    /// is resized or leaves fullscreen.
+    fn set_titlebar_button_inset(&self, inset: Point);
+}
+
+/// How a window takes part in Spaces and fullscreen, see
+/// [`WindowHandleExt::set_collection_behavior`].
+///
+/// Behaviors can be combined with `|`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CollectionBehavior(u64);
+
+impl CollectionBehavior {
+    /// The default behavior, where the window stays on the Space it was opened on.
+    pub const DEFAULT: CollectionBehavior = CollectionBehavior(0);
+    /// The window appears on every Space.
+    pub const CAN_JOIN_ALL_SPACES: CollectionBehavior = CollectionBehavior(1 << 0);
+    /// The window floats in Spaces and is hidden by Mission Control.
+    pub const TRANSIENT: CollectionBehavior = CollectionBehavior(1 << 3);
+    /// The window isn't affected by Exposé, so it stays visible and in place.
+    pub const STATIONARY: CollectionBehavior = CollectionBehavior(1 << 4);
+    /// The window can be shown on the same Space as another application's fullscreen window.
+    pub const FULL_SCREEN_AUXILIARY: CollectionBehavior = CollectionBehavior(1 << 8);
+
+    /// Returns `true` if all of the behaviors in `other` are part of this one.
+    pub fn contains(self, other: CollectionBehavior) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The raw `NSWindowCollectionBehavior`, whose flags have the same values.
+    #[allow(dead_code)]
+    pub(crate) fn bits(self) -> u64 {
+        self.0
+    }
+}
+
+impl std::ops::BitOr for CollectionBehavior {
+    type Output = CollectionBehavior;
+
+    fn bitor(self, rhs: CollectionBehavior) -> CollectionBehavior {
+        CollectionBehavior(self.0 | rhs.0)
+    }
+}
+
+impl std::ops::BitOrAssign for CollectionBehavior {
+    fn bitor_assign(&mut self, rhs: CollectionBehavior) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// The appearance of a blurred window background, as used by native macOS windows.
+///
+/// See [`WindowHandleExt::set_background_material`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Material {
+    /// The background of window titlebars.
+    Titlebar,
+    /// The background of menus.
+    Menu,
+    /// The background of popovers.
+    Popover,
+    /// The background of window sidebars.
+    Sidebar,
+    /// The background of sheets.
+    Sheet,
+    /// The background of opaque windows.
+    WindowBackground,
+    /// The background of heads-up display windows.
+    HudWindow,
+    /// The background of tooltips.
+    ToolTip,
+    /// The background of content views, such as scroll views.
+    ContentBackground,
+    /// The background behind the content of a window.
+    UnderWindowBackground,
+}
+
+/// macOS specific extensions to [`Screen`]
+///
+/// [`Screen`]: crate::Screen
+pub trait ScreenExt {
+    /// Returns the height of the menu bar on the primary monitor, in display points.
+    fn menu_bar_height() -> f64;
+
+    /// Returns the area along a monitor's edge that is taken by the Dock, in virtual screen
+    /// coordinates, along with the edge it is on.
+    ///
+    /// The area spans the whole edge, even though the Dock itself is usually shorter.
+    /// When the Dock hides automatically, this is the area it takes up when shown, which
+    /// is estimated from its icon size. Returns `None` if no monitor has room for the Dock.
+    fn dock_frame() -> Option<(Rect, DockEdge)>;
+
+    /// Returns true if the menu bar hides automatically, either because of the system
+    /// setting or because the application asked for it.
+    ///
+    /// A hidden menu bar isn't excluded from [`Monitor::virtual_work_rect`], but it still
+    /// covers the top of the screen when revealed.
+    ///
+    /// [`Monitor::virtual_work_rect`]: crate::Monitor::virtual_work_rect
+    fn menu_bar_autohides() -> bool;
+
+    /// Returns true if the Dock hides automatically.
+    ///
+    /// Like the menu bar, a hidden Dock covers windows when revealed, and
+    /// [`dock_frame`](ScreenExt::dock_frame) estimates how much.
+    fn dock_autohides() -> bool;
+}
+
+/// The edge of the screen that the Dock is on.
+///
+/// See [`ScreenExt::dock_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DockEdge {
+    /// The bottom edge, which is the default.
+    Bottom,
+    /// The left edge.
+    Left,
+    /// The right edge.
+    Right,
+}
+
+/// Access to a security-scoped resource.
+///
+/// Access is granted when this is created, and relinquished when it is dropped.
+pub struct ScopedAccess {
+    stop: Option<Box<dyn FnOnce()>>,
+}
+
+impl ScopedAccess {
+    #[allow(dead_code)]
+    pub(crate) fn new(stop: impl FnOnce() + 'static) -> ScopedAccess {
+        ScopedAccess {
+            stop: Some(Box::new(stop)),
+        }
+    }
+}
+
+impl Drop for ScopedAccess {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::Application;
+    use crate::{Application, FileInfo, Screen, WindowHandle};
 
     use super::*;
     use static_assertions as sa;
     sa::assert_impl_all!(Application: ApplicationExt);
+    sa::assert_impl_all!(FileInfo: FileInfoExt);
+    sa::assert_impl_all!(WindowHandle: WindowHandleExt);
+    sa::assert_impl_all!(Screen: ScreenExt);
+
+    #[test]
+    fn combine_collection_behaviors() {
+        let mut behavior = CollectionBehavior::CAN_JOIN_ALL_SPACES;
+        behavior |= CollectionBehavior::FULL_SCREEN_AUXILIARY;
+        assert_eq!(behavior.bits(), 0x101);
+        assert!(behavior.contains(CollectionBehavior::CAN_JOIN_ALL_SPACES));
+        assert!(!behavior.contains(CollectionBehavior::STATIONARY));
+        assert!(behavior.contains(CollectionBehavior::DEFAULT));
+    }
 }
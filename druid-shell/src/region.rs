@@ -31,10 +31,15 @@ impl Region {
     }
 
     /// Adds a rectangle to this region.
+    ///
+    /// A rectangle that is already covered by one in the region isn't added, and the ones that
+    /// it covers are removed, so that adding the same rectangles repeatedly doesn't grow it.
     pub fn add_rect(&mut self, rect: Rect) {
-        if rect.area() > 0.0 {
-            self.rects.push(rect);
+        if rect.area() <= 0.0 || self.rects.iter().any(|r| covers(*r, rect)) {
+            return;
         }
+        self.rects.retain(|r| !covers(rect, *r));
+        self.rects.push(rect);
     }
 
     /// Replaces this region with a single rectangle.
@@ -68,13 +73,26 @@ impl Region {
     }
 
     /// Returns `true` if this region has a non-empty intersection with the given rectangle.
+    ///
+    /// Rectangles that only share an edge with the region don't intersect it.
     pub fn intersects(&self, rect: Rect) -> bool {
-        self.rects.iter().any(|r| r.intersect(rect).area() > 0.0)
+        match self.rects.as_slice() {
+            [] => false,
+            [r] => r.intersect(rect).area() > 0.0,
+            rects => rects.iter().any(|r| r.intersect(rect).area() > 0.0),
+        }
     }
 
     /// Returns `true` if the given `point` is contained within any rectangle in the region.
+    ///
+    /// Like [`Rect::contains`], this includes the top and left edges of the rectangles, but
+    /// not the bottom and right ones.
     pub fn contains(&self, point: Point) -> bool {
-        self.rects.iter().any(|r| r.contains(point))
+        match self.rects.as_slice() {
+            [] => false,
+            [r] => r.contains(point),
+            rects => rects.iter().any(|r| r.contains(point)),
+        }
     }
 
     /// Returns `true` if this region is empty.
@@ -110,6 +128,11 @@ impl Region {
     }
 }
 
+/// Returns `true` if `inner` is inside `outer`.
+fn covers(outer: Rect, inner: Rect) -> bool {
+    outer.x0 <= inner.x0 && outer.y0 <= inner.y0 && outer.x1 >= inner.x1 && outer.y1 >= inner.y1
+}
+
 impl std::ops::AddAssign<Vec2> for Region {
     fn add_assign(&mut self, rhs: Vec2) {
         for r in &mut self.rects {
@@ -131,3 +154,49 @@ impl From<Rect> for Region {
         Region { rects: vec![rect] }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn covered_rects_coalesce() {
+        let mut region = Region::EMPTY;
+        let small = Rect::new(10.0, 10.0, 20.0, 20.0);
+        for _ in 0..3 {
+            region.add_rect(small);
+        }
+        assert_eq!(region.rects(), &[small]);
+
+        let other = Rect::new(50.0, 0.0, 60.0, 5.0);
+        region.add_rect(other);
+        let big = Rect::new(0.0, 0.0, 30.0, 30.0);
+        region.add_rect(big);
+        assert_eq!(region.rects(), &[other, big]);
+        region.add_rect(small);
+        assert_eq!(region.rects(), &[other, big]);
+        assert_eq!(region.bounding_box(), Rect::new(0.0, 0.0, 60.0, 30.0));
+
+        region.add_rect(Rect::new(5.0, 5.0, 5.0, 40.0));
+        assert_eq!(region.rects().len(), 2);
+    }
+
+    #[test]
+    fn hit_testing() {
+        let rect = Rect::new(0.0, 0.0, 10.0, 10.0);
+        assert!(!Region::EMPTY.intersects(rect));
+        assert!(!Region::EMPTY.contains(Point::ZERO));
+
+        let mut region = Region::from(rect);
+        region.add_rect(Rect::new(20.0, 0.0, 30.0, 10.0));
+        // The gap between the rectangles is inside the bounding box, but not the region.
+        assert!(!region.intersects(Rect::new(12.0, 2.0, 18.0, 8.0)));
+        assert!(!region.contains(Point::new(15.0, 5.0)));
+        assert!(region.intersects(Rect::new(8.0, 2.0, 22.0, 8.0)));
+        assert!(region.contains(Point::new(25.0, 5.0)));
+        // Touching an edge isn't intersecting.
+        assert!(!region.intersects(Rect::new(10.0, 0.0, 20.0, 10.0)));
+        assert!(region.contains(Point::ZERO));
+        assert!(!region.contains(Point::new(10.0, 5.0)));
+    }
+}
@@ -15,9 +15,135 @@
 //! Module to get information about monitors
 
 use crate::backend;
-use crate::kurbo::Rect;
+use crate::error::Error;
+use crate::kurbo::{Affine, Insets, Point, Rect, Size, Vec2};
+use crate::piet::{Color, ImageBuf};
+use crate::scale::Scale;
+use crate::window::WindowHandle;
+use once_cell::sync::Lazy;
+use std::cell::RefCell;
 use std::fmt;
 use std::fmt::Display;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Monitors that replace the real ones, see [`Screen::set_virtual_monitors`].
+static VIRTUAL_MONITORS: Lazy<Mutex<Option<Vec<Monitor>>>> = Lazy::new(Default::default);
+
+/// Incremented when the monitors change, which makes the cached monitors of every thread stale.
+static MONITORS_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The monitors this thread got from the backend, and the generation they are from.
+    static CACHED_MONITORS: RefCell<Option<(u64, Vec<Monitor>)>> = RefCell::new(None);
+}
+
+/// A part of a monitor's work area that a window can be snapped to.
+///
+/// See [`Screen::snap_window`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnapZone {
+    /// The left half.
+    Left,
+    /// The right half.
+    Right,
+    /// The top half.
+    TopHalf,
+    /// The bottom half.
+    BottomHalf,
+    /// All of it.
+    Maximize,
+}
+
+/// The order of the color subpixels of a monitor, for subpixel antialiasing of text.
+///
+/// See [`Monitor::subpixel_order`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SubpixelOrder {
+    /// Horizontal stripes, red on the left.
+    Rgb,
+    /// Horizontal stripes, blue on the left.
+    Bgr,
+    /// Vertical stripes, red on top.
+    VRgb,
+    /// Vertical stripes, blue on top.
+    VBgr,
+    /// Subpixel antialiasing shouldn't be used, because the monitor has no subpixels that can be
+    /// addressed or the platform turned it off.
+    None,
+    /// The platform doesn't report the subpixel order.
+    Unknown,
+}
+
+/// How a monitor is rotated from its natural landscape orientation.
+///
+/// See [`Monitor::orientation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Orientation {
+    /// Not rotated.
+    Landscape,
+    /// Rotated by a quarter turn.
+    Portrait,
+    /// Rotated by half a turn, so it's upside down.
+    LandscapeFlipped,
+    /// Rotated by three quarters of a turn, the other way from [`Portrait`](Orientation::Portrait).
+    PortraitFlipped,
+}
+
+/// A resolution and refresh rate of a monitor.
+///
+/// See [`Monitor::available_modes`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DisplayMode {
+    size: Size,
+    refresh_rate: Option<f64>,
+    scale: Scale,
+    current: bool,
+}
+
+impl DisplayMode {
+    /// Creates a mode with a size in physical pixels, and a refresh rate in hertz if known.
+    pub fn new(size: Size, refresh_rate: Option<f64>, scale: Scale) -> Self {
+        DisplayMode {
+            size,
+            refresh_rate,
+            scale,
+            current: false,
+        }
+    }
+
+    /// Builder-style method to set whether the monitor is currently in this mode.
+    pub fn with_current(mut self, current: bool) -> Self {
+        self.current = current;
+        self
+    }
+
+    /// Returns the resolution of the mode, in physical pixels.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// Returns the refresh rate of the mode in hertz, if the platform reports it.
+    ///
+    /// macOS doesn't report one for some built-in panels.
+    pub fn refresh_rate(&self) -> Option<f64> {
+        self.refresh_rate
+    }
+
+    /// Returns the scale factor that the mode is shown with.
+    ///
+    /// On macOS a mode can scale a smaller resolution up, so this differs between the
+    /// modes. The other platforms scale independently of the mode, so every mode has the
+    /// monitor's current scale.
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// Returns true if the monitor is currently in this mode.
+    pub fn is_current(&self) -> bool {
+        self.current
+    }
+}
 
 /// Monitor struct containing data about a monitor on the system
 ///
@@ -34,33 +160,347 @@ pub struct Monitor {
     // https://docs.microsoft.com/en-us/windows/win32/api/winuser/ns-winuser-monitorinfo
     // Unsure about x11
     work_rect: Rect,
+    scale: Scale,
+    safe_area_insets: Insets,
+    internal: bool,
+    subpixel_order: SubpixelOrder,
+    orientation: Orientation,
+    device_id: Option<String>,
+    /// The platform's current name for the monitor, like the GDI device name on Windows or
+    /// the Core Graphics display ID on macOS, which can change when monitors are plugged in.
+    native_id: Option<String>,
+    /// The modes of a virtual monitor; real monitors ask the platform when needed.
+    modes: Option<Vec<DisplayMode>>,
+    /// Whether the rectangles are in physical pixels already, like on Windows.
+    pixel_coords: bool,
+    /// The transform from the platform's screen coordinates to virtual screen coordinates.
+    backend_transform: Affine,
 }
 
 impl Monitor {
-    #[allow(dead_code)]
-    pub(crate) fn new(primary: bool, rect: Rect, work_rect: Rect) -> Self {
+    /// Creates a monitor with the given rectangle and working rectangle, in virtual screen
+    /// coordinates.
+    ///
+    /// Real monitors come from [`Screen::get_monitors`]; this is for making virtual ones to
+    /// pass to [`Screen::set_virtual_monitors`].
+    pub fn new(primary: bool, rect: Rect, work_rect: Rect) -> Self {
         Monitor {
             primary,
             rect,
             work_rect,
+            scale: Scale::default(),
+            safe_area_insets: Insets::ZERO,
+            internal: false,
+            subpixel_order: SubpixelOrder::Unknown,
+            orientation: Orientation::Landscape,
+            device_id: None,
+            native_id: None,
+            modes: None,
+            pixel_coords: false,
+            backend_transform: Affine::IDENTITY,
         }
     }
+
+    /// Builder-style method to set the scale factor of the monitor.
+    pub fn with_scale(mut self, scale: Scale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Builder-style method to set the identifier of the monitor's device.
+    ///
+    /// See [`device_id`](Monitor::device_id).
+    pub fn with_device_id(mut self, id: impl Into<String>) -> Self {
+        self.device_id = Some(id.into());
+        self
+    }
+
+    /// Builder-style method to set the modes of a virtual monitor, which are then returned
+    /// instead of asking the platform.
+    ///
+    /// See [`available_modes`](Monitor::available_modes).
+    pub fn with_modes(mut self, modes: Vec<DisplayMode>) -> Self {
+        self.modes = Some(modes);
+        self
+    }
+
+    /// Sets the safe area insets of the monitor.
+    #[allow(dead_code)]
+    pub(crate) fn with_safe_area_insets(mut self, insets: Insets) -> Self {
+        self.safe_area_insets = insets;
+        self
+    }
+
+    /// Sets the working rectangle of the monitor, in virtual screen coordinates.
+    #[allow(dead_code)]
+    pub(crate) fn with_work_rect(mut self, work_rect: Rect) -> Self {
+        self.work_rect = work_rect;
+        self
+    }
+
+    /// Sets whether the monitor is built into the device.
+    #[allow(dead_code)]
+    pub(crate) fn with_internal(mut self, internal: bool) -> Self {
+        self.internal = internal;
+        self
+    }
+
+    /// Sets the subpixel order of the monitor.
+    #[allow(dead_code)]
+    pub(crate) fn with_subpixel_order(mut self, order: SubpixelOrder) -> Self {
+        self.subpixel_order = order;
+        self
+    }
+
+    /// Sets how the monitor is rotated.
+    #[allow(dead_code)]
+    pub(crate) fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    /// Sets the transform from the platform's screen coordinates to virtual screen
+    /// coordinates, for platforms whose y axis points up.
+    #[allow(dead_code)]
+    pub(crate) fn with_backend_transform(mut self, transform: Affine) -> Self {
+        self.backend_transform = transform;
+        self
+    }
+
+    /// Sets the platform's current name for the monitor, which the backend uses to find it
+    /// again while it stays connected.
+    #[allow(dead_code)]
+    pub(crate) fn with_native_id(mut self, id: impl Into<String>) -> Self {
+        self.native_id = Some(id.into());
+        self
+    }
+
+    /// Returns the platform's current name for the monitor.
+    #[allow(dead_code)]
+    pub(crate) fn native_id(&self) -> Option<&str> {
+        self.native_id.as_deref()
+    }
+
+    /// Marks the rectangles of the monitor as being in physical pixels rather than display
+    /// points, as virtual screen coordinates are on Windows.
+    #[allow(dead_code)]
+    pub(crate) fn with_pixel_coords(mut self) -> Self {
+        self.pixel_coords = true;
+        self
+    }
+
     /// Returns true if the monitor is the primary monitor.
-    /// The primary monitor has its origin at (0, 0) in virtual screen coordinates.
+    ///
+    /// On macOS and Windows the primary monitor has its origin at (0, 0) in virtual screen
+    /// coordinates. On Linux the origin is the top left of all the monitors instead.
     pub fn is_primary(&self) -> bool {
         self.primary
     }
+
+    /// Returns true if the monitor is built into the device, like a laptop's panel.
+    ///
+    /// This is independent of [`is_primary`](Monitor::is_primary): with an external monitor
+    /// attached, the built-in one is often not primary. On Linux this is guessed from the name
+    /// of the connector, and it is always false on platforms that don't report it.
+    pub fn is_internal(&self) -> bool {
+        self.internal
+    }
+
+    /// Returns the order of the monitor's color subpixels, for subpixel antialiasing of text.
+    ///
+    /// On Windows this follows the ClearType settings, which are the same for every monitor,
+    /// and it is [`SubpixelOrder::None`] when ClearType is off. macOS doesn't use subpixel
+    /// antialiasing, so it is always `None` there. Web reports [`SubpixelOrder::Unknown`].
+    pub fn subpixel_order(&self) -> SubpixelOrder {
+        self.subpixel_order
+    }
+
+    /// Returns an identifier of the monitor's device, which stays the same when its
+    /// resolution, scale or position change.
+    ///
+    /// This can be saved to find the same monitor again later, even after a restart. It is
+    /// the connector name on X11 and Wayland, the monitor's device interface path on Windows,
+    /// and the display's UUID on macOS. It is `None` with GTK on Wayland, which doesn't
+    /// report the connector, and on web.
+    pub fn device_id(&self) -> Option<&str> {
+        self.device_id.as_deref()
+    }
+
+    /// Returns true if `other` is the same device as this monitor, even if its geometry
+    /// changed in between.
+    ///
+    /// Unlike `==`, which compares everything, this only compares the
+    /// [`device_id`](Monitor::device_id)s. Monitors without one are only the same device
+    /// if they are equal.
+    pub fn same_device(&self, other: &Monitor) -> bool {
+        match (&self.device_id, &other.device_id) {
+            (Some(id), Some(other_id)) => id == other_id,
+            _ => self == other,
+        }
+    }
+
+    /// Returns how the monitor is rotated in the display settings.
+    ///
+    /// This doesn't depend on which side of the monitor is longer, so a rotated monitor that
+    /// is almost square is still reported as rotated. GTK doesn't expose the rotation, so it
+    /// is always [`Orientation::Landscape`] there, and on web.
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Returns the resolutions and refresh rates the monitor supports, as listed by the
+    /// display settings.
+    ///
+    /// The current mode is marked by [`DisplayMode::is_current`]. Windows, macOS and X11
+    /// list every mode; GTK and Wayland only report the current one, and web none at all.
+    ///
+    /// The modes are queried on every call, rather than with the monitors, since listing
+    /// them can be slow.
+    pub fn available_modes(&self) -> Vec<DisplayMode> {
+        match &self.modes {
+            Some(modes) => modes.clone(),
+            None => backend::screen::display_modes(self),
+        }
+    }
+
+    /// Returns the mode the monitor is currently in, if the platform reports it.
+    pub fn current_mode(&self) -> Option<DisplayMode> {
+        self.available_modes()
+            .into_iter()
+            .find(DisplayMode::is_current)
+    }
+
+    /// Returns the largest resolution the monitor supports, in physical pixels.
+    pub fn max_resolution(&self) -> Option<Size> {
+        self.available_modes()
+            .iter()
+            .map(DisplayMode::size)
+            .max_by(|a, b| a.area().total_cmp(&b.area()))
+    }
+
+    /// Switches the monitor to `mode`, which has to be one of its
+    /// [`available_modes`](Monitor::available_modes).
+    ///
+    /// The change isn't saved in the display settings, so it is undone when the user logs
+    /// out. Unlike the display settings, this doesn't ask the user to keep the new mode; to
+    /// do that, switch back to the previous [`current_mode`](Monitor::current_mode) if they
+    /// don't confirm it in time.
+    ///
+    /// # Errors
+    ///
+    /// Fails if `mode` isn't one of this monitor's modes, or if the platform rejects it.
+    /// This is only implemented on macOS and Windows.
+    pub fn set_mode(&self, mode: &DisplayMode) -> Result<(), Error> {
+        let mode = mode.with_current(false);
+        let own_modes = self.available_modes();
+        if !own_modes.iter().any(|own| own.with_current(false) == mode) {
+            return Err(anyhow::anyhow!("{:?} is not a mode of the monitor {}", mode, self).into());
+        }
+        backend::screen::set_mode(self, &mode)?;
+        monitors_changed();
+        Ok(())
+    }
+
+    /// Captures what is currently shown on this monitor.
+    ///
+    /// See [`Screen::capture_region`].
+    pub fn capture(&self) -> Result<ImageBuf, Error> {
+        Screen::capture_region(self.rect)
+    }
+
     /// Returns the monitor rectangle in virtual screen coordinates.
     pub fn virtual_rect(&self) -> Rect {
         self.rect
     }
 
+    /// Returns the monitor rectangle in the platform's own screen coordinates, and the
+    /// transform from those coordinates to virtual screen coordinates.
+    ///
+    /// This is for code that uses the platform's APIs directly. On macOS, Cocoa's
+    /// coordinates have their origin at the bottom left of the primary screen and y goes
+    /// up, so the transform flips them. The other platforms use the same coordinates as
+    /// [`virtual_rect`], physical pixels on Windows, and the transform is the identity.
+    ///
+    /// [`virtual_rect`]: Monitor::virtual_rect
+    pub fn frame_in_backend_coords(&self) -> (Rect, Affine) {
+        let frame = self
+            .backend_transform
+            .inverse()
+            .transform_rect_bbox(self.rect);
+        (frame, self.backend_transform)
+    }
+
     /// Returns the monitor working rectangle in virtual screen coordinates.
     /// The working rectangle excludes certain things like the dock and menubar on mac,
     /// and the taskbar on windows.
     pub fn virtual_work_rect(&self) -> Rect {
         self.work_rect
     }
+
+    /// Returns the scale factor of the monitor.
+    ///
+    /// This is the ratio of physical pixels to display points on this monitor.
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// Returns the insets from the edges of the monitor that content is hidden behind,
+    /// in display points.
+    ///
+    /// On MacBooks with a camera housing, the top inset covers the notch and the menu bar
+    /// around it. This is always zero on other monitors and platforms.
+    ///
+    /// The value is read when the monitors are queried, so query them again when a
+    /// window moves to another monitor, see [`WinHandler::monitor_changed`].
+    ///
+    /// [`WinHandler::monitor_changed`]: crate::WinHandler::monitor_changed
+    pub fn safe_area_insets(&self) -> Insets {
+        self.safe_area_insets
+    }
+
+    /// Returns the rectangle that a window snapped to `zone` on this monitor should occupy,
+    /// in virtual screen coordinates.
+    pub fn snap_rect(&self, zone: SnapZone) -> Rect {
+        let work = self.work_rect;
+        let center = work.center();
+        match zone {
+            SnapZone::Left => Rect::new(work.x0, work.y0, center.x, work.y1),
+            SnapZone::Right => Rect::new(center.x, work.y0, work.x1, work.y1),
+            SnapZone::TopHalf => Rect::new(work.x0, work.y0, work.x1, center.y),
+            SnapZone::BottomHalf => Rect::new(work.x0, center.y, work.x1, work.y1),
+            SnapZone::Maximize => work,
+        }
+    }
+
+    /// Converts a point in virtual screen coordinates into physical pixels on this monitor.
+    ///
+    /// The result is relative to the monitor's top-left corner, so a point at the
+    /// monitor's origin maps to `(0, 0)` regardless of where the monitor is placed.
+    ///
+    /// On Windows virtual screen coordinates are physical pixels already, so this only
+    /// moves the origin there.
+    pub fn logical_to_physical(&self, point: Point) -> Point {
+        let offset = point - self.rect.origin();
+        let scale = self.coords_scale();
+        Point::new(offset.x * scale.x(), offset.y * scale.y())
+    }
+
+    /// Converts a point in physical pixels on this monitor into virtual screen coordinates.
+    ///
+    /// This is the inverse of [`logical_to_physical`](Monitor::logical_to_physical).
+    pub fn physical_to_logical(&self, point: Point) -> Point {
+        let (x, y) = self.coords_scale().px_to_dp_xy(point.x, point.y);
+        self.rect.origin() + Vec2::new(x, y)
+    }
+
+    /// Returns the ratio of physical pixels to virtual screen coordinates on this monitor.
+    fn coords_scale(&self) -> Scale {
+        if self.pixel_coords {
+            Scale::default()
+        } else {
+            self.scale
+        }
+    }
 }
 
 impl Display for Monitor {
@@ -79,6 +519,70 @@ impl Display for Monitor {
     }
 }
 
+/// The monitors and where they are relative to each other.
+///
+/// See [`Screen::arrangement`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonitorArrangement {
+    monitors: Vec<Monitor>,
+    bounds: Rect,
+}
+
+impl MonitorArrangement {
+    /// Creates the arrangement of `monitors`.
+    pub fn new(monitors: Vec<Monitor>) -> Self {
+        let bounds = monitors
+            .iter()
+            .map(Monitor::virtual_rect)
+            .reduce(|a, b| a.union(b))
+            .unwrap_or(Rect::ZERO);
+        MonitorArrangement { monitors, bounds }
+    }
+
+    /// Returns the monitors, in the order of [`Screen::get_monitors`].
+    pub fn monitors(&self) -> &[Monitor] {
+        &self.monitors
+    }
+
+    /// Returns the smallest rectangle containing every monitor, in virtual screen coordinates.
+    pub fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    /// Returns the top left corner of [`bounds`](MonitorArrangement::bounds), which is where
+    /// the monitors' rectangles are measured from by [`relative_rects`].
+    ///
+    /// [`relative_rects`]: MonitorArrangement::relative_rects
+    pub fn origin(&self) -> Point {
+        self.bounds.origin()
+    }
+
+    /// Returns the rectangle of every monitor, relative to [`origin`](MonitorArrangement::origin).
+    ///
+    /// These start at (0, 0) on every platform, whichever monitor the platform puts there.
+    pub fn relative_rects(&self) -> Vec<Rect> {
+        let offset = self.origin().to_vec2();
+        self.monitors
+            .iter()
+            .map(|monitor| monitor.virtual_rect() - offset)
+            .collect()
+    }
+
+    /// Returns the rectangle of every monitor, scaled down evenly to fit into `size` and
+    /// relative to its top left corner, for drawing a map of the monitors.
+    pub fn scaled_to_fit(&self, size: Size) -> Vec<Rect> {
+        let bounds = self.bounds.size();
+        if bounds.is_empty() {
+            return vec![Rect::ZERO; self.monitors.len()];
+        }
+        let scale = (size.width / bounds.width).min(size.height / bounds.height);
+        self.relative_rects()
+            .into_iter()
+            .map(|rect| rect.scale_from_origin(scale))
+            .collect()
+    }
+}
+
 /// Information about the screen and monitors
 pub struct Screen {}
 
@@ -86,15 +590,372 @@ impl Screen {
     /// Returns a vector of all the [`monitors`] on the system.
     ///
     /// [`monitors`]: Monitor
+    ///
+    /// These are the virtual monitors instead, if [`Screen::set_virtual_monitors`] was called.
+    ///
+    /// The monitors are queried once and cached until the platform reports that they changed,
+    /// so this is cheap enough to call every frame. Each thread keeps its own copy, so that
+    /// reading it needs no locking. Some changes, like panels reserving space on X11, aren't
+    /// reported; use [`Screen::refresh_monitors`] to query the monitors again.
     pub fn get_monitors() -> Vec<Monitor> {
-        backend::screen::get_monitors()
+        if let Some(monitors) = VIRTUAL_MONITORS.lock().unwrap().clone() {
+            return monitors;
+        }
+        // Loaded before querying, so that a change during the query makes the result stale.
+        let generation = MONITORS_GENERATION.load(Ordering::Acquire);
+        CACHED_MONITORS.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            match &*cache {
+                Some((cached, monitors)) if *cached == generation => monitors.clone(),
+                _ => {
+                    let monitors = backend::screen::get_monitors();
+                    *cache = Some((generation, monitors.clone()));
+                    monitors
+                }
+            }
+        })
+    }
+
+    /// Discards the cached monitors, so that the next [`Screen::get_monitors`] call on any
+    /// thread queries them again.
+    ///
+    /// The cache is already discarded when the platform reports that the monitors changed,
+    /// but that report can come after [`Screen::num_monitors`] sees the change.
+    pub fn refresh_monitors() {
+        monitors_changed();
+    }
+
+    /// Returns the number of monitors on the system.
+    ///
+    /// This is cheaper than [`Screen::get_monitors`], so it can be polled to notice when
+    /// monitors are connected or disconnected.
+    pub fn num_monitors() -> usize {
+        if let Some(monitors) = &*VIRTUAL_MONITORS.lock().unwrap() {
+            return monitors.len();
+        }
+        backend::screen::num_monitors()
+    }
+
+    /// Makes [`Screen::get_monitors`] return `monitors` instead of the system's monitors.
+    ///
+    /// This is for tests that shouldn't depend on the hardware they run on. It only affects
+    /// what is reported; windows are still placed on the real monitors by the platform.
+    pub fn set_virtual_monitors(monitors: Vec<Monitor>) {
+        *VIRTUAL_MONITORS.lock().unwrap() = Some(monitors);
+    }
+
+    /// Makes [`Screen::get_monitors`] return the system's monitors again.
+    pub fn clear_virtual_monitors() {
+        *VIRTUAL_MONITORS.lock().unwrap() = None;
+    }
+
+    /// Returns the position of the mouse pointer, in virtual screen coordinates.
+    ///
+    /// This is `None` on Wayland and web, where applications can't see the pointer outside
+    /// of their windows.
+    pub fn get_mouse_position() -> Option<Point> {
+        #[cfg(feature = "testing")]
+        if let Some(position) = crate::testing::mouse_position() {
+            return position;
+        }
+        backend::screen::get_mouse_position()
     }
 
     /// Returns the bounding rectangle of the total virtual screen space in pixels.
     pub fn get_display_rect() -> Rect {
-        Self::get_monitors()
-            .iter()
-            .map(|x| x.virtual_rect())
-            .fold(Rect::ZERO, |a, b| a.union(b))
+        Self::arrangement().bounds()
+    }
+
+    /// Returns the monitors together with the rectangle that they span, for showing how
+    /// they are arranged.
+    ///
+    /// The monitors' rectangles are in the same virtual screen coordinates as everything else,
+    /// with y growing downwards on every platform. Where (0, 0) is differs between platforms,
+    /// see [`Monitor::is_primary`], so use [`MonitorArrangement::relative_rects`] or
+    /// [`MonitorArrangement::scaled_to_fit`] to draw them.
+    pub fn arrangement() -> MonitorArrangement {
+        MonitorArrangement::new(Self::get_monitors())
+    }
+
+    /// Captures what is currently shown in `rect`, which is in virtual screen coordinates.
+    ///
+    /// The image has one pixel for every physical pixel of the screen, so it is larger than
+    /// `rect` on monitors with a scale above 1 on macOS.
+    ///
+    /// # Errors
+    ///
+    /// On macOS this returns [`Error::PermissionDenied`] without screen recording permission,
+    /// see [`Application::request_screen_capture_access`]. This is not implemented on
+    /// Wayland and web.
+    ///
+    /// [`Application::request_screen_capture_access`]: crate::Application::request_screen_capture_access
+    pub fn capture_region(rect: Rect) -> Result<ImageBuf, Error> {
+        backend::screen::capture_region(rect)
+    }
+
+    /// Returns the color of the screen pixel at `point`, which is in virtual screen coordinates.
+    ///
+    /// This captures the pixel with [`Screen::capture_region`], and fails in the same cases.
+    pub fn pixel_color(point: Point) -> Result<Color, Error> {
+        let image = Self::capture_region(Rect::from_origin_size(point.floor(), (1.0, 1.0)))?;
+        // On monitors with a scale above 1 this is the top left of the captured pixels.
+        let color = image.pixel_colors().next().and_then(|mut row| row.next());
+        color.ok_or_else(|| anyhow::anyhow!("captured an empty image at {}", point).into())
+    }
+
+    /// Returns the monitor that a window with the given frame is on.
+    ///
+    /// This is the monitor containing the center of `rect`, or the monitor closest to it if
+    /// the center is off screen. `rect` is in virtual screen coordinates.
+    pub fn monitor_for_rect(rect: Rect) -> Option<Monitor> {
+        monitor_at(&Self::get_monitors(), rect.center()).cloned()
+    }
+
+    /// Moves and resizes a top-level window to fill `zone` of the monitor it is on.
+    ///
+    /// The monitor is chosen with [`Screen::monitor_for_rect`], so a window spanning two
+    /// monitors snaps to the one holding most of its center.
+    pub fn snap_window(window: &WindowHandle, zone: SnapZone) {
+        let frame = Rect::from_origin_size(window.get_position(), window.get_size());
+        if let Some(monitor) = Self::monitor_for_rect(frame) {
+            let target = monitor.snap_rect(zone);
+            window.set_position(target.origin());
+            window.set_size(target.size());
+        }
+    }
+}
+
+/// Discards the cached monitors, see [`Screen::refresh_monitors`].
+///
+/// Backends call this when the platform reports that the monitors changed.
+pub(crate) fn monitors_changed() {
+    MONITORS_GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// Guesses from the name of a connector, like `eDP-1`, whether its display is built in.
+#[allow(dead_code)]
+pub(crate) fn is_internal_connector(connector: &str) -> bool {
+    ["eDP", "LVDS", "DSI"]
+        .iter()
+        .any(|prefix| connector.starts_with(prefix))
+}
+
+/// Returns the monitor containing `point`, or the closest one if none does.
+fn monitor_at(monitors: &[Monitor], point: Point) -> Option<&Monitor> {
+    let distance = |rect: Rect| {
+        let closest = Point::new(
+            point.x.clamp(rect.x0, rect.x1),
+            point.y.clamp(rect.y0, rect.y1),
+        );
+        point.distance(closest)
+    };
+    monitors
+        .iter()
+        .find(|monitor| monitor.rect.contains(point))
+        .or_else(|| {
+            monitors
+                .iter()
+                .min_by(|a, b| distance(a.rect).total_cmp(&distance(b.rect)))
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn current_and_max_mode() {
+        let rect = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        let scale = Scale::default();
+        let full_hd = DisplayMode::new(Size::new(1920.0, 1080.0), Some(60.0), scale);
+        let modes = vec![
+            DisplayMode::new(Size::new(1280.0, 720.0), Some(60.0), scale),
+            full_hd.with_current(true),
+            DisplayMode::new(Size::new(2560.0, 1440.0), Some(30.0), scale),
+        ];
+        let monitor = Monitor::new(true, rect, rect).with_modes(modes);
+
+        assert_eq!(monitor.current_mode(), Some(full_hd.with_current(true)));
+        assert_eq!(monitor.max_resolution(), Some(Size::new(2560.0, 1440.0)));
+
+        let unknown = Monitor::new(true, rect, rect).with_modes(Vec::new());
+        assert!(unknown.available_modes().is_empty());
+        assert_eq!(unknown.current_mode(), None);
+        assert_eq!(unknown.max_resolution(), None);
+    }
+
+    #[test]
+    fn set_mode_of_another_monitor() {
+        let rect = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        let mode = DisplayMode::new(Size::new(1920.0, 1080.0), None, Scale::default());
+        let other = DisplayMode::new(Size::new(800.0, 600.0), None, Scale::default());
+        let monitor = Monitor::new(true, rect, rect).with_modes(vec![mode.with_current(true)]);
+
+        assert!(monitor.set_mode(&other).is_err());
+    }
+
+    #[test]
+    fn scaled_monitor_left_of_primary() {
+        let rect = Rect::new(-960.0, 0.0, 0.0, 540.0);
+        let monitor = Monitor::new(false, rect, rect).with_scale(Scale::new(2.0, 2.0));
+
+        assert_eq!(monitor.logical_to_physical(rect.origin()), Point::ZERO);
+        let center = Point::new(-480.0, 270.0);
+        let physical = monitor.logical_to_physical(center);
+        assert_eq!(physical, Point::new(960.0, 540.0));
+        assert_eq!(monitor.physical_to_logical(physical), center);
+        assert_eq!(
+            monitor.physical_to_logical(Point::new(1920.0, 1080.0)),
+            Point::new(0.0, 540.0)
+        );
+    }
+
+    #[test]
+    fn pixel_monitor_right_of_primary() {
+        // On Windows the rectangles are in pixels, whatever the scale of the monitor.
+        let rect = Rect::new(1920.0, 0.0, 4480.0, 1440.0);
+        let monitor = Monitor::new(false, rect, rect)
+            .with_scale(Scale::new(1.5, 1.5))
+            .with_pixel_coords();
+
+        assert_eq!(monitor.logical_to_physical(rect.origin()), Point::ZERO);
+        let point = Point::new(2000.0, 100.0);
+        let physical = monitor.logical_to_physical(point);
+        assert_eq!(physical, Point::new(80.0, 100.0));
+        assert_eq!(monitor.physical_to_logical(physical), point);
+    }
+
+    #[test]
+    fn flipped_backend_coords() {
+        let rect = Rect::new(0.0, -200.0, 800.0, 400.0);
+        let flip = Affine::new([1.0, 0.0, 0.0, -1.0, 0.0, 1000.0]);
+        let monitor = Monitor::new(false, rect, rect).with_backend_transform(flip);
+
+        let (frame, transform) = monitor.frame_in_backend_coords();
+        assert_eq!(frame, Rect::new(0.0, 600.0, 800.0, 1200.0));
+        assert_eq!(transform.transform_rect_bbox(frame), rect);
+        assert_eq!(
+            Monitor::new(false, rect, rect).frame_in_backend_coords(),
+            (rect, Affine::IDENTITY)
+        );
+    }
+
+    fn two_monitors() -> Vec<Monitor> {
+        let primary = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        let secondary = Rect::new(1920.0, 0.0, 3200.0, 1024.0);
+        vec![
+            Monitor::new(true, primary, primary.inset((0.0, 0.0, 0.0, -40.0))),
+            Monitor::new(false, secondary, secondary),
+        ]
+    }
+
+    #[test]
+    fn window_straddling_monitors() {
+        let monitors = two_monitors();
+        let mostly_primary = Rect::new(1400.0, 100.0, 2200.0, 700.0);
+        let mostly_secondary = Rect::new(1700.0, 100.0, 2500.0, 700.0);
+        let primary = monitor_at(&monitors, mostly_primary.center()).unwrap();
+        let secondary = monitor_at(&monitors, mostly_secondary.center()).unwrap();
+        assert!(primary.is_primary());
+        assert!(!secondary.is_primary());
+
+        assert_eq!(
+            primary.snap_rect(SnapZone::Right),
+            Rect::new(960.0, 0.0, 1920.0, 1040.0)
+        );
+        assert_eq!(
+            secondary.snap_rect(SnapZone::Left),
+            Rect::new(1920.0, 0.0, 2560.0, 1024.0)
+        );
+        assert_eq!(
+            secondary.snap_rect(SnapZone::BottomHalf),
+            Rect::new(1920.0, 512.0, 3200.0, 1024.0)
+        );
+    }
+
+    #[test]
+    fn internal_connectors() {
+        assert!(is_internal_connector("eDP-1"));
+        assert!(is_internal_connector("LVDS1"));
+        assert!(!is_internal_connector("HDMI-A-1"));
+        assert!(!is_internal_connector("DP-2"));
+        assert!(!is_internal_connector(""));
+    }
+
+    #[test]
+    fn virtual_monitors() {
+        // Tests that use a `TestState` replace the virtual monitors as well.
+        #[cfg(feature = "testing")]
+        let _lock = crate::testing::TEST_STATE_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let monitors = two_monitors();
+        Screen::set_virtual_monitors(monitors.clone());
+        assert_eq!(Screen::get_monitors(), monitors);
+        assert_eq!(
+            Screen::get_display_rect(),
+            Rect::new(0.0, 0.0, 3200.0, 1080.0)
+        );
+        let frame = Rect::new(2000.0, 100.0, 2400.0, 400.0);
+        assert_eq!(Screen::monitor_for_rect(frame), Some(monitors[1].clone()));
+        Screen::clear_virtual_monitors();
+    }
+
+    #[test]
+    fn same_device_after_resolution_change() {
+        let before = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        let after = Rect::new(0.0, 0.0, 2560.0, 1440.0);
+        let monitor = Monitor::new(true, before, before).with_device_id("DP-1");
+        let resized = Monitor::new(true, after, after).with_device_id("DP-1");
+        assert_ne!(monitor, resized);
+        assert!(monitor.same_device(&resized));
+        assert!(!monitor.same_device(&resized.clone().with_device_id("HDMI-1")));
+
+        // Without ids, only equal monitors are the same device.
+        let anonymous = Monitor::new(true, before, before);
+        assert!(anonymous.same_device(&anonymous.clone()));
+        assert!(!anonymous.same_device(&Monitor::new(true, after, after)));
+    }
+
+    #[test]
+    fn arrangement_left_of_primary() {
+        let primary = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+        let left = Rect::new(-1280.0, 200.0, 0.0, 1224.0);
+        let arrangement = MonitorArrangement::new(vec![
+            Monitor::new(true, primary, primary),
+            Monitor::new(false, left, left),
+        ]);
+        assert_eq!(
+            arrangement.bounds(),
+            Rect::new(-1280.0, 0.0, 1920.0, 1224.0)
+        );
+        assert_eq!(arrangement.origin(), Point::new(-1280.0, 0.0));
+        assert_eq!(
+            arrangement.relative_rects(),
+            vec![
+                Rect::new(1280.0, 0.0, 3200.0, 1080.0),
+                Rect::new(0.0, 200.0, 1280.0, 1224.0)
+            ]
+        );
+        // The width limits the scale here.
+        assert_eq!(
+            arrangement.scaled_to_fit(Size::new(320.0, 240.0)),
+            vec![
+                Rect::new(128.0, 0.0, 320.0, 108.0),
+                Rect::new(0.0, 20.0, 128.0, 122.4)
+            ]
+        );
+        assert_eq!(MonitorArrangement::new(Vec::new()).bounds(), Rect::ZERO);
+    }
+
+    #[test]
+    fn window_center_off_screen() {
+        let monitors = two_monitors();
+        // Below the secondary monitor, which is shorter than the primary.
+        let monitor = monitor_at(&monitors, Point::new(2500.0, 1060.0)).unwrap();
+        assert!(!monitor.is_primary());
+        let monitor = monitor_at(&monitors, Point::new(-100.0, 500.0)).unwrap();
+        assert!(monitor.is_primary());
+        assert!(monitor_at(&[], Point::ZERO).is_none());
     }
 }
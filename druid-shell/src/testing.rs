@@ -0,0 +1,269 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! In-memory system state, for testing code that uses [`Screen`] and [`Clipboard`]
+//! without depending on the machine the tests run on.
+//!
+//! This needs the `testing` feature.
+
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::atomic::Ordering;
+use std::sync::{Mutex, MutexGuard};
+
+use once_cell::sync::Lazy;
+
+use crate::clipboard::{Clipboard, ClipboardFormat, FormatId};
+use crate::kurbo::Point;
+use crate::screen::{Monitor, Screen};
+use crate::util;
+
+/// Held by the current [`TestState`], so that only one exists at a time.
+pub(crate) static TEST_STATE_LOCK: Lazy<Mutex<()>> = Lazy::new(Default::default);
+
+/// The mouse position while a [`TestState`] exists.
+static MOUSE_POSITION: Lazy<Mutex<Option<Option<Point>>>> = Lazy::new(Default::default);
+
+/// Scripted system state, which replaces the real one while this exists.
+///
+/// While a `TestState` exists, [`Screen::get_monitors`] returns the monitors set with
+/// [`set_monitors`](TestState::set_monitors) and [`Screen::get_mouse_position`] returns the
+/// position set with [`set_mouse_position`](TestState::set_mouse_position). Dropping it
+/// goes back to the real state.
+///
+/// Only one `TestState` exists at a time: [`TestState::new`] waits until the previous one is
+/// dropped, so tests using it can still run in parallel.
+///
+/// # Examples
+///
+/// ```
+/// use druid_shell::kurbo::{Point, Rect};
+/// use druid_shell::testing::TestState;
+/// use druid_shell::{Monitor, Screen};
+///
+/// let state = TestState::new();
+/// let rect = Rect::new(0.0, 0.0, 1920.0, 1080.0);
+/// state.set_monitors(vec![Monitor::new(true, rect, rect)]);
+/// state.set_mouse_position(Point::new(10.0, 20.0));
+///
+/// assert_eq!(Screen::get_display_rect(), rect);
+/// assert_eq!(Screen::get_mouse_position(), Some(Point::new(10.0, 20.0)));
+///
+/// let mut clipboard = state.clipboard();
+/// clipboard.put_string("hello");
+/// assert_eq!(state.clipboard().get_string().as_deref(), Some("hello"));
+/// ```
+pub struct TestState {
+    clipboard: MemoryClipboard,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl TestState {
+    /// Replaces the system state with one that has no monitors, no mouse position and an
+    /// empty clipboard.
+    pub fn new() -> TestState {
+        // A test that panicked while holding the lock doesn't leave anything broken.
+        let lock = TEST_STATE_LOCK
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        Screen::set_virtual_monitors(Vec::new());
+        *MOUSE_POSITION.lock().unwrap() = Some(None);
+        TestState {
+            clipboard: MemoryClipboard::default(),
+            _lock: lock,
+        }
+    }
+
+    /// Sets the monitors that [`Screen::get_monitors`] returns.
+    pub fn set_monitors(&self, monitors: Vec<Monitor>) {
+        Screen::set_virtual_monitors(monitors);
+    }
+
+    /// Sets the position that [`Screen::get_mouse_position`] returns, in virtual screen
+    /// coordinates.
+    pub fn set_mouse_position(&self, position: Point) {
+        *MOUSE_POSITION.lock().unwrap() = Some(Some(position));
+    }
+
+    /// Returns a clipboard that keeps its contents in memory.
+    ///
+    /// All the clipboards returned by the same `TestState` share their contents.
+    pub fn clipboard(&self) -> Clipboard {
+        self.clipboard.clone().into()
+    }
+}
+
+impl Default for TestState {
+    fn default() -> Self {
+        TestState::new()
+    }
+}
+
+impl Drop for TestState {
+    fn drop(&mut self) {
+        Screen::clear_virtual_monitors();
+        *MOUSE_POSITION.lock().unwrap() = None;
+    }
+}
+
+/// Lets an [`Application`] be created and used on any thread, for test harnesses such as
+/// `cargo test` that run each test on a thread of its own.
+///
+/// Normally the thread that creates the `Application` becomes the main thread, and
+/// using the `Application` from another thread panics. After this is called, no thread
+/// becomes the main thread, so those checks pass everywhere. Call it before creating the
+/// `Application`; it can't be undone.
+///
+/// This only relaxes druid-shell's own checks. The platforms still have their requirements:
+/// the macOS backend can only create the `Application` on the first thread of the process,
+/// since AppKit only works there, and only one `Application` can exist at a time.
+///
+/// [`Application`]: crate::Application
+pub fn allow_any_thread() {
+    util::ANY_THREAD_ALLOWED.store(true, Ordering::Release);
+}
+
+/// Returns the mouse position of the current [`TestState`], if there is one.
+pub(crate) fn mouse_position() -> Option<Option<Point>> {
+    *MOUSE_POSITION.lock().unwrap()
+}
+
+/// The in-memory clipboard of a [`TestState`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MemoryClipboard(Rc<RefCell<Contents>>);
+
+#[derive(Debug, Default)]
+struct Contents {
+    formats: Vec<ClipboardFormat>,
+    files: Option<Vec<PathBuf>>,
+}
+
+impl MemoryClipboard {
+    pub(crate) fn put_string(&mut self, s: &str) {
+        self.put_formats(&[s.into()]);
+    }
+
+    pub(crate) fn put_formats(&mut self, formats: &[ClipboardFormat]) {
+        let mut contents = self.0.borrow_mut();
+        contents.formats = formats.to_vec();
+        contents.files = None;
+    }
+
+    pub(crate) fn get_string(&self) -> Option<String> {
+        let data = self.get_format(ClipboardFormat::TEXT)?;
+        String::from_utf8(data).ok()
+    }
+
+    pub(crate) fn put_file_list(&mut self, paths: &[PathBuf]) {
+        let mut contents = self.0.borrow_mut();
+        contents.formats.clear();
+        contents.files = Some(paths.to_vec());
+    }
+
+    pub(crate) fn get_file_list(&self) -> Option<Vec<PathBuf>> {
+        self.0.borrow().files.clone()
+    }
+
+    pub(crate) fn preferred_format(&self, formats: &[FormatId]) -> Option<FormatId> {
+        // The formats that were put on the clipboard first have the highest priority.
+        let contents = self.0.borrow();
+        contents
+            .formats
+            .iter()
+            .find_map(|format| formats.iter().find(|&&id| id == format.identifier))
+            .copied()
+    }
+
+    pub(crate) fn get_format(&self, format: FormatId) -> Option<Vec<u8>> {
+        let contents = self.0.borrow();
+        contents
+            .formats
+            .iter()
+            .find(|it| it.identifier == format)
+            .map(|it| it.data.clone())
+    }
+
+    pub(crate) fn available_type_names(&self) -> Vec<String> {
+        let contents = self.0.borrow();
+        contents
+            .formats
+            .iter()
+            .map(|format| format.identifier.to_string())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn memory_clipboard_formats() {
+        let state = TestState::new();
+        let mut clipboard = state.clipboard();
+        assert_eq!(clipboard.get_string(), None);
+
+        clipboard.put_formats(&[
+            ClipboardFormat::new(ClipboardFormat::SVG, "<svg/>"),
+            "text".into(),
+        ]);
+        let formats = [ClipboardFormat::TEXT, ClipboardFormat::SVG];
+        assert_eq!(
+            clipboard.preferred_format(&formats),
+            Some(ClipboardFormat::SVG)
+        );
+        assert_eq!(state.clipboard().get_string().as_deref(), Some("text"));
+
+        clipboard.put_file_list(&[PathBuf::from("/tmp/file")]);
+        assert_eq!(clipboard.get_string(), None);
+        assert_eq!(
+            clipboard.get_file_list(),
+            Some(vec![PathBuf::from("/tmp/file")])
+        );
+    }
+
+    #[test]
+    fn string_with_fallbacks() {
+        let state = TestState::new();
+        let mut clipboard = state.clipboard();
+        let text = "naïve 🦀 text";
+        let rtf = r"{\rtf1 na\u239?ve text}";
+
+        clipboard.put_string_with_fallbacks(
+            text,
+            &[
+                ClipboardFormat::new(ClipboardFormat::RTF, rtf),
+                ClipboardFormat::from("naive text"),
+            ],
+        );
+        assert_eq!(clipboard.get_string().as_deref(), Some(text));
+        assert_eq!(
+            clipboard.available_type_names(),
+            [ClipboardFormat::TEXT, ClipboardFormat::RTF]
+        );
+        assert_eq!(
+            clipboard.get_format(ClipboardFormat::RTF),
+            Some(rtf.as_bytes().to_vec())
+        );
+    }
+
+    #[test]
+    fn mouse_position_is_scripted() {
+        let state = TestState::new();
+        assert_eq!(Screen::get_mouse_position(), None);
+        state.set_mouse_position(Point::new(3.0, 4.0));
+        assert_eq!(Screen::get_mouse_position(), Some(Point::new(3.0, 4.0)));
+    }
+}
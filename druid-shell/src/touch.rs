@@ -0,0 +1,68 @@
+// Copyright 2022 The Druid Authors.
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Common types for representing touch events.
+
+use crate::common_util::Counter;
+use crate::kurbo::Point;
+
+/// Identifies a single finger for as long as it touches the surface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Hash)]
+pub struct TouchId(u64);
+
+impl TouchId {
+    /// Create a new id, for platforms that don't identify touches with a number.
+    #[allow(dead_code)]
+    pub(crate) fn next() -> TouchId {
+        static TOUCH_COUNTER: Counter = Counter::new();
+        TouchId(TOUCH_COUNTER.next())
+    }
+
+    /// Create a new id from a raw value.
+    pub const fn from_raw(id: u64) -> TouchId {
+        TouchId(id)
+    }
+
+    /// Get the raw value for an id.
+    pub const fn into_raw(self) -> u64 {
+        self.0
+    }
+}
+
+/// Where a [`TouchEvent`] is in the lifetime of a touch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TouchPhase {
+    /// A finger touched the surface.
+    Began,
+    /// A finger moved on the surface.
+    Moved,
+    /// A finger was lifted from the surface.
+    Ended,
+    /// The system stopped tracking a finger, for example because it started a gesture.
+    Cancelled,
+}
+
+/// Information about a touch event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TouchEvent {
+    /// Identifies the finger, and stays the same from [`TouchPhase::Began`] until
+    /// [`TouchPhase::Ended`] or [`TouchPhase::Cancelled`].
+    pub id: TouchId,
+    /// The location of the touch in [display points] in relation to the current window.
+    ///
+    /// [display points]: crate::Scale
+    pub pos: Point,
+    /// Where the event is in the lifetime of the touch.
+    pub phase: TouchPhase,
+}
@@ -15,11 +15,27 @@
 //! Utility functions for determining the main thread.
 
 use std::mem;
+#[cfg(feature = "testing")]
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::thread;
 
 static MAIN_THREAD_ID: AtomicU64 = AtomicU64::new(0);
 
+/// Whether no thread is made the main thread, see [`crate::testing::allow_any_thread`].
+#[cfg(feature = "testing")]
+pub(crate) static ANY_THREAD_ALLOWED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(feature = "testing")]
+fn any_thread_allowed() -> bool {
+    ANY_THREAD_ALLOWED.load(Ordering::Acquire)
+}
+
+#[cfg(not(feature = "testing"))]
+fn any_thread_allowed() -> bool {
+    false
+}
+
 #[inline]
 fn current_thread_id() -> u64 {
     // TODO: Use .as_u64() instead of mem::transmute
@@ -40,12 +56,23 @@ pub(crate) fn assert_main_thread_or_main_unclaimed() {
     }
 }
 
+/// Returns true if the current thread is the registered main thread or main thread is not
+/// claimed.
+pub(crate) fn is_main_thread_or_main_unclaimed() -> bool {
+    let main_thread_id = MAIN_THREAD_ID.load(Ordering::Acquire);
+    main_thread_id == 0 || main_thread_id == current_thread_id()
+}
+
 /// Register the current thread as the main thread.
 ///
 /// # Panics
 ///
 /// Panics if the main thread has already been claimed by another thread.
 pub(crate) fn claim_main_thread() {
+    // The main thread stays unclaimed, so every thread passes the checks.
+    if any_thread_allowed() {
+        return;
+    }
     let thread_id = current_thread_id();
     let old_thread_id =
         MAIN_THREAD_ID.compare_exchange(0, thread_id, Ordering::AcqRel, Ordering::Acquire);
@@ -65,6 +92,9 @@ pub(crate) fn claim_main_thread() {
 ///
 /// Panics if the main thread status is owned by another thread.
 pub(crate) fn release_main_thread() {
+    if any_thread_allowed() {
+        return;
+    }
     let thread_id = current_thread_id();
     let old_thread_id =
         MAIN_THREAD_ID.compare_exchange(thread_id, 0, Ordering::AcqRel, Ordering::Acquire);
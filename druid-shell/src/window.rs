@@ -15,8 +15,10 @@
 //! Platform independent window types.
 
 use std::any::Any;
+use std::path::Path;
 use std::time::Duration;
 
+use crate::accessibility::AccessibilityTree;
 use crate::application::Application;
 use crate::backend::window as backend;
 use crate::common_util::Counter;
@@ -26,9 +28,12 @@ use crate::keyboard::KeyEvent;
 use crate::kurbo::{Insets, Point, Rect, Size};
 use crate::menu::Menu;
 use crate::mouse::{Cursor, CursorDesc, MouseEvent};
+use crate::piet::Color;
 use crate::region::Region;
 use crate::scale::Scale;
+use crate::screen::Monitor;
 use crate::text::{Event, InputHandler};
+use crate::touch::TouchEvent;
 use piet_common::PietText;
 #[cfg(feature = "raw-win-handle")]
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
@@ -149,15 +154,33 @@ impl FileDialogToken {
 /// Describes the purpose of a window and should be mapped appropriately to match platform
 /// conventions.
 #[derive(Clone, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum WindowLevel {
     /// A top level app window.
     AppWindow,
+    /// A top level window that stays above app windows, like a palette or a popover.
+    Floating,
     /// A window that should stay above app windows - like a tooltip
     Tooltip(WindowHandle),
     /// A user interface element such as a dropdown menu or combo box
     DropDown(WindowHandle),
     /// A modal dialog
     Modal(WindowHandle),
+    /// A top level window that stays above everything else, including menus.
+    Overlay,
+}
+
+/// The kind of window to create.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WindowType {
+    /// A regular window.
+    #[default]
+    Normal,
+    /// A borderless window for menus and tooltips, which never takes keyboard focus.
+    ///
+    /// The window that opens it keeps focus and its active appearance, so for example
+    /// a text field in it can keep editing while a dropdown is shown.
+    Popup,
 }
 
 /// Contains the different states a Window can be in.
@@ -168,12 +191,26 @@ pub enum WindowState {
     Restored,
 }
 
+/// How urgently an accessibility announcement should be spoken.
+///
+/// See [`WindowHandle::announce`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Politeness {
+    /// Wait until the screen reader is done speaking.
+    Polite,
+    /// Interrupt whatever the screen reader is currently saying.
+    Assertive,
+}
+
 /// A handle to a platform window object.
 #[derive(Clone, Default, PartialEq, Eq)]
 pub struct WindowHandle(pub(crate) backend::WindowHandle);
 
 impl WindowHandle {
     /// Make this window visible.
+    ///
+    /// Windows start out hidden, so this can wait until the content is laid out and
+    /// ready to be painted, which avoids showing an empty window first.
     pub fn show(&self) {
         self.0.show()
     }
@@ -183,14 +220,34 @@ impl WindowHandle {
         self.0.close()
     }
 
-    /// Hide the window
+    /// Hide the window.
+    ///
+    /// The window keeps its state, and can be shown again with [`show`](WindowHandle::show).
     pub fn hide(&self) {
         self.0.hide()
     }
 
     /// Set whether the window should be resizable
+    #[deprecated(since = "0.9.0", note = "use set_resizable instead")]
     pub fn resizable(&self, resizable: bool) {
-        self.0.resizable(resizable)
+        self.set_resizable(resizable)
+    }
+
+    /// Set whether the user can resize the window.
+    ///
+    /// This can be changed at any time. Making the window non-resizable keeps its current size,
+    /// but removes the resize handles and disables maximizing it.
+    pub fn set_resizable(&self, resizable: bool) {
+        self.0.set_resizable(resizable)
+    }
+
+    /// Keep the ratio of the window's content width to its height while the user resizes it.
+    ///
+    /// The ratio is kept whichever edge is dragged. `None` removes the constraint.
+    ///
+    /// This is currently unsupported on Wayland and web.
+    pub fn set_aspect_ratio(&self, ratio: Option<f64>) {
+        self.0.set_aspect_ratio(ratio)
     }
 
     /// Sets the state of the window.
@@ -236,6 +293,72 @@ impl WindowHandle {
         self.0.set_always_on_top(always_on_top);
     }
 
+    /// Sets whether the window stays below all other windows, like a desktop widget.
+    ///
+    /// The window stays behind normal windows as they are raised and lowered, and on Windows
+    /// and GTK it doesn't take focus, even when clicked. Turning on
+    /// [`set_always_on_top`](WindowHandle::set_always_on_top) turns this off.
+    ///
+    /// On macOS the window is put at the level of the desktop picture and shown on every
+    /// Space. On GTK it gets the desktop type hint when this is called before the window is
+    /// shown, which window managers only read then, and it is kept below otherwise. X11,
+    /// Wayland and web don't support this.
+    pub fn set_always_on_bottom(&self, on_bottom: bool) {
+        self.0.set_always_on_bottom(on_bottom);
+    }
+
+    /// Sets the level of the window, which moves it above or below other windows right away.
+    ///
+    /// The parent of a [`Tooltip`], [`DropDown`] or [`Modal`] level is only used when
+    /// building the window, so changing to one of those only changes the window's z-order.
+    /// On X11 the window can only switch between [`AppWindow`], [`Floating`] and [`Overlay`].
+    ///
+    /// [`AppWindow`]: WindowLevel::AppWindow
+    /// [`Floating`]: WindowLevel::Floating
+    /// [`Overlay`]: WindowLevel::Overlay
+    /// [`Tooltip`]: WindowLevel::Tooltip
+    /// [`DropDown`]: WindowLevel::DropDown
+    /// [`Modal`]: WindowLevel::Modal
+    pub fn set_level(&self, level: WindowLevel) {
+        self.0.set_level(level)
+    }
+
+    /// Sets whether the window closes when the user clicks outside of it or switches to
+    /// another application, like a menu.
+    ///
+    /// This is meant for [`WindowType::Popup`] windows, which never take focus, so only
+    /// clicks in the application's other windows count as clicking outside. Before the
+    /// window closes, [`WinHandler::dismissed`] is called.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    pub fn set_dismiss_on_focus_loss(&self, dismiss: bool) {
+        self.0.set_dismiss_on_focus_loss(dismiss)
+    }
+
+    /// Sets the opacity of the whole window, from `0.0` (invisible) to `1.0` (opaque).
+    ///
+    /// Values outside that range are clamped. Unlike [`WindowBuilder::set_transparent`], this
+    /// fades everything in the window, including its frame, which is useful for animating
+    /// showing and hiding it. A window with an opacity of `0.0` is still open and shown, so it
+    /// keeps receiving events and is closed along with the others when the application quits.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    pub fn set_opacity(&self, opacity: f64) {
+        self.0.set_opacity(opacity.clamp(0.0, 1.0));
+    }
+
+    /// Sets the color that the window shows where nothing has been painted yet.
+    ///
+    /// This is what fills the new area while the window is being resized, before the
+    /// [`WinHandler`] paints it, so it avoids a white flash in a dark window. It doesn't
+    /// replace painting: the whole window should still be painted.
+    ///
+    /// This has no effect on transparent windows. It is currently only implemented on
+    /// macOS, Windows and GTK.
+    pub fn set_background_color(&self, color: Color) {
+        self.0.set_background_color(color)
+    }
+
     /// Sets where in the window the user can interact with the program.
     ///
     /// This enables irregularly shaped windows. For example, you can make it simply
@@ -277,6 +400,9 @@ impl WindowHandle {
     /// decorations; on GTK neither the insets nor the window size include the title bar or window
     /// decorations.
     ///
+    /// Windows without decorations have zero insets. The insets are looked up on every call, so
+    /// they reflect changes made with [`WindowHandle::show_titlebar`].
+    ///
     /// [display points]: crate::Scale
     pub fn content_insets(&self) -> Insets {
         self.0.content_insets()
@@ -312,6 +438,10 @@ impl WindowHandle {
     /// render another frame. This differs from [`invalidate`] and [`invalidate_rect`] in that it
     /// doesn't invalidate any part of the window.
     ///
+    /// [`WinHandler::anim_frame`] is called first with the time since the previous one. On GTK
+    /// the frame follows the display's frame clock, while on macOS and Windows it isn't
+    /// synchronized with the display yet, and comes as soon as the window can paint.
+    ///
     /// [`invalidate`]: WindowHandle::invalidate
     /// [`invalidate_rect`]: WindowHandle::invalidate_rect
     /// [`paint`]: WinHandler::paint
@@ -325,7 +455,31 @@ impl WindowHandle {
         self.0.invalidate();
     }
 
+    /// Paint the invalid parts of the window right away, instead of on the next turn of
+    /// the event loop.
+    ///
+    /// This calls [`prepare_paint`] and [`paint`] before returning, so that the window's
+    /// contents match the latest state, for example before capturing them.
+    ///
+    /// This must not be called from within a [`WinHandler`] method, because the handler
+    /// is already in use. Call it from outside the handler instead, such as between calls
+    /// to [`Application::pump_events`].
+    ///
+    /// This is not supported on Wayland, where the window is painted as usual. On X11, if the
+    /// X server is still presenting the previous frame, the paint waits until it has, and
+    /// happens on a later turn of the event loop.
+    ///
+    /// [`prepare_paint`]: WinHandler::prepare_paint
+    /// [`paint`]: WinHandler::paint
+    pub fn paint_now(&self) {
+        self.0.paint_now();
+    }
+
     /// Request invalidation of a region of the window.
+    ///
+    /// `rect` is in [display points](crate::Scale). The rects invalidated before the next
+    /// [`paint`](WinHandler::paint) are combined into the region that it gets, so content
+    /// outside of it can be skipped.
     pub fn invalidate_rect(&self, rect: Rect) {
         self.0.invalidate_rect(rect);
     }
@@ -335,6 +489,24 @@ impl WindowHandle {
         self.0.set_title(title)
     }
 
+    /// Set the file that this window shows, or `None` if it doesn't show one.
+    ///
+    /// On macOS this shows the icon of the file next to the title, which can be dragged like
+    /// the file itself, and a menu with the folders containing it when the title is
+    /// command-clicked. This is ignored on the other platforms.
+    pub fn set_represented_file(&self, path: Option<&Path>) {
+        self.0.set_represented_file(path)
+    }
+
+    /// Set whether the document shown in this window has unsaved changes.
+    ///
+    /// On macOS this shows a dot in the close button, and dims the icon of the
+    /// [represented file](WindowHandle::set_represented_file). This is ignored on the
+    /// other platforms.
+    pub fn set_document_edited(&self, edited: bool) {
+        self.0.set_document_edited(edited)
+    }
+
     /// Set the top-level menu for this window.
     pub fn set_menu(&self, menu: Menu) {
         self.0.set_menu(menu.into_inner())
@@ -384,6 +556,33 @@ impl WindowHandle {
         self.0.update_text_field(token, update)
     }
 
+    /// Turn the platform input method on or off for this window.
+    ///
+    /// While it is off, keys are delivered without composition, so an input method
+    /// can't intercept them; this is useful for modes that map keys to actions, such
+    /// as a game or a vi-style editor. Any text being composed is discarded when it
+    /// is turned off. Turning it back on restores normal composition.
+    ///
+    /// The change takes effect once the current handler callback returns, since
+    /// discarding a composition calls back into the handler.
+    ///
+    /// The input method is on by default. Only macOS and Windows currently use an
+    /// input method, so this does nothing on other platforms.
+    pub fn set_ime_enabled(&self, enabled: bool) {
+        self.0.set_ime_enabled(enabled)
+    }
+
+    /// Turn the platform input method on or off for a single text field.
+    ///
+    /// This is like [`set_ime_enabled`], but only applies while `token` is the
+    /// focused text field. The input method is used only if it is on both for
+    /// the window and for the focused field.
+    ///
+    /// [`set_ime_enabled`]: WindowHandle::set_ime_enabled
+    pub fn set_text_field_ime_enabled(&self, token: TextFieldToken, enabled: bool) {
+        self.0.set_text_field_ime_enabled(token, enabled)
+    }
+
     /// Schedule a timer.
     ///
     /// This causes a [`WinHandler::timer`] call at the deadline. The
@@ -399,14 +598,75 @@ impl WindowHandle {
     }
 
     /// Set the cursor icon.
+    ///
+    /// While a cursor is pushed with [`push_cursor`](WindowHandle::push_cursor), this only
+    /// changes the cursor that is shown once it's popped.
     pub fn set_cursor(&mut self, cursor: &Cursor) {
         self.0.set_cursor(cursor)
     }
 
+    /// Show `cursor` over this window until [`pop_cursor`](WindowHandle::pop_cursor) is called,
+    /// for example [`Cursor::Wait`] during a long operation.
+    ///
+    /// The pushed cursor stays when [`set_cursor`](WindowHandle::set_cursor) is called, so it
+    /// isn't replaced on the next mouse move. Pushes nest: each pop goes back to the cursor
+    /// pushed before it, and the last one goes back to the cursor that was set.
+    pub fn push_cursor(&mut self, cursor: Cursor) {
+        self.0.push_cursor(cursor)
+    }
+
+    /// Remove the cursor pushed last with [`push_cursor`](WindowHandle::push_cursor).
+    ///
+    /// This does nothing if no cursor is pushed.
+    pub fn pop_cursor(&mut self) {
+        self.0.pop_cursor()
+    }
+
+    /// Makes a custom cursor from `desc`, which can then be set with
+    /// [`set_cursor`](WindowHandle::set_cursor).
+    ///
+    /// Returns `None` if the platform doesn't support custom cursors, or, on Windows and with
+    /// GTK, if the image is larger than [`Application::max_cursor_size`].
     pub fn make_cursor(&self, desc: &CursorDesc) -> Option<Cursor> {
         self.0.make_cursor(desc)
     }
 
+    /// Hide or show the cursor.
+    ///
+    /// Calls are reference-counted: after hiding the cursor `n` times, it needs to be
+    /// shown `n` times before it becomes visible again. Showing a cursor that isn't
+    /// hidden does nothing.
+    pub fn set_cursor_visible(&self, visible: bool) {
+        self.0.set_cursor_visible(visible)
+    }
+
+    /// Confine the cursor to this window, or release it.
+    ///
+    /// This is intended for things like mouse-look in games. On macOS the cursor is
+    /// moved to the center of the window and detached from the mouse. On Windows it is
+    /// clipped to the window's content area while the window is active. On GTK the
+    /// pointer is grabbed, so its events keep coming to this window.
+    pub fn confine_cursor(&self, confine: bool) {
+        self.0.confine_cursor(confine)
+    }
+
+    /// Ask screen readers to announce `message`, even if it isn't related to the focused element.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK.
+    pub fn announce(&self, message: &str, politeness: Politeness) {
+        self.0.announce(message, politeness)
+    }
+
+    /// Set the accessibility elements of the window, replacing the previous ones.
+    ///
+    /// Screen readers can't see custom drawn content, so this describes it to them. Call it
+    /// again whenever the content or the focus changes.
+    ///
+    /// This is currently only implemented on macOS.
+    pub fn set_accessibility_tree(&self, tree: AccessibilityTree) {
+        self.0.set_accessibility_tree(tree)
+    }
+
     /// Prompt the user to choose a file to open.
     ///
     /// This won't block immediately; the file dialog will be shown whenever control returns to
@@ -427,7 +687,9 @@ impl WindowHandle {
 
     /// Display a pop-up menu at the given position.
     ///
-    /// `pos` is in the coordinate space of the window.
+    /// `pos` is in the coordinate space of the window, in display points. The menu is
+    /// moved if needed to stay on screen, and selecting one of its items calls
+    /// [`WinHandler::command`].
     pub fn show_context_menu(&self, menu: Menu, pos: Point) {
         self.0.show_context_menu(menu.into_inner(), pos)
     }
@@ -519,6 +781,13 @@ impl WindowBuilder {
         self.0.set_transparent(transparent)
     }
 
+    /// Sets the color that the window shows where nothing has been painted yet.
+    ///
+    /// See [`WindowHandle::set_background_color`].
+    pub fn set_background_color(&mut self, color: Color) {
+        self.0.set_background_color(color)
+    }
+
     /// Sets the initial window position in display points.
     /// For windows with a parent, the position is relative to the parent.
     /// For windows without a parent, it is relative to the origin of the virtual screen.
@@ -534,6 +803,39 @@ impl WindowBuilder {
         self.0.set_level(level);
     }
 
+    /// Sets the window that owns this one.
+    ///
+    /// The new window stays above its parent. Combined with [`set_modal`], the parent stops
+    /// receiving input until the new window is closed.
+    ///
+    /// Unlike the parent of a [`WindowLevel`], this doesn't change how [`set_position`]
+    /// is interpreted.
+    ///
+    /// [`set_modal`]: WindowBuilder::set_modal
+    /// [`set_position`]: WindowBuilder::set_position
+    pub fn set_parent(&mut self, parent: WindowHandle) {
+        self.0.set_parent(parent.0);
+    }
+
+    /// Sets whether the window blocks input to its parent while it is open.
+    ///
+    /// This has no effect unless a parent is set with [`set_parent`]. On macOS the window is
+    /// shown as a sheet attached to the parent.
+    ///
+    /// [`set_parent`]: WindowBuilder::set_parent
+    pub fn set_modal(&mut self, modal: bool) {
+        self.0.set_modal(modal);
+    }
+
+    /// Sets the kind of window to create.
+    ///
+    /// A [`WindowType::Popup`] is usually given a parent with [`set_parent`].
+    ///
+    /// [`set_parent`]: WindowBuilder::set_parent
+    pub fn set_type(&mut self, window_type: WindowType) {
+        self.0.set_type(window_type);
+    }
+
     /// Set the window's initial title.
     pub fn set_title(&mut self, title: impl Into<String>) {
         self.0.set_title(title)
@@ -551,6 +853,8 @@ impl WindowBuilder {
 
     /// Attempt to construct the platform window.
     ///
+    /// The window is hidden until [`WindowHandle::show`] is called.
+    ///
     /// If this fails, your application should exit.
     pub fn build(self) -> Result<WindowHandle, Error> {
         self.0.build().map(WindowHandle).map_err(Into::into)
@@ -589,6 +893,17 @@ pub trait WinHandler {
     /// [`WindowHandle::invalidate`].
     fn prepare_paint(&mut self);
 
+    /// Called before [`prepare_paint`](WinHandler::prepare_paint) on frames that were requested
+    /// with [`WindowHandle::request_anim_frame`].
+    ///
+    /// `interval` is the time since the previous animation frame, or zero for the first frame of
+    /// an animation, so that animations can advance by the time that really passed whatever the
+    /// refresh rate of the display is.
+    ///
+    /// This is currently only called on macOS, Windows and GTK.
+    #[allow(unused_variables)]
+    fn anim_frame(&mut self, interval: Duration) {}
+
     /// Request the handler to paint the window contents.  `invalid` is the region in [display
     /// points](crate::Scale) that needs to be repainted; painting outside the invalid region will
     /// have no effect.
@@ -606,6 +921,19 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn command(&mut self, id: u32) {}
 
+    /// Called for each item of a menu of this window just before the menu is shown.
+    ///
+    /// Return `Some(enabled)` to enable or disable the item with the given `id`, or `None` to
+    /// keep its current state. This lets an item like "Paste" reflect the state of the
+    /// application when the menu opens, rather than when the menu was built.
+    ///
+    /// This is called for window and context menus on macOS, Windows and GTK. On Windows
+    /// it is also called before the hotkey of an item is handled.
+    #[allow(unused_variables)]
+    fn menu_item_enabled(&mut self, id: u32) -> Option<bool> {
+        None
+    }
+
     /// Called when a "Save As" dialog is closed.
     ///
     /// `token` is the value returned by [`WindowHandle::save_as`]. `file` contains the information
@@ -671,6 +999,9 @@ pub trait WinHandler {
 
     /// Called on a mouse wheel event.
     ///
+    /// The event's `pos` is where the pointer was when the wheel moved, so it can be used
+    /// as the anchor of a zoom without querying the pointer separately.
+    ///
     /// The polarity is the amount to be added to the scroll position,
     /// in other words the opposite of the direction the content should
     /// move on scrolling. This polarity is consistent with the
@@ -685,6 +1016,35 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn zoom(&mut self, delta: f64) {}
 
+    /// Called when a platform-defined zoom gesture occurs, along with where it happened.
+    ///
+    /// `pos` is where the pointer was during the gesture, in [display points] relative
+    /// to the window, so that the zoom can keep the content under the pointer in place.
+    ///
+    /// The default implementation calls [`zoom`](WinHandler::zoom) without the position.
+    ///
+    /// [display points]: crate::Scale
+    #[allow(unused_variables)]
+    fn zoom_at(&mut self, delta: f64, pos: Point) {
+        self.zoom(delta);
+    }
+
+    /// Called when a finger touches, moves on, or is lifted from a touch surface.
+    ///
+    /// Each finger keeps the same [`TouchId`] from [`TouchPhase::Began`] until
+    /// [`TouchPhase::Ended`] or [`TouchPhase::Cancelled`]. The first finger may also be
+    /// reported as mouse events, so that code which only handles the mouse keeps working.
+    ///
+    /// This is currently only implemented on macOS, Windows and GTK. On macOS the touches
+    /// come from the trackpad, and their positions are scaled to the size of the window.
+    ///
+    /// [`TouchId`]: crate::TouchId
+    /// [`TouchPhase::Began`]: crate::TouchPhase::Began
+    /// [`TouchPhase::Ended`]: crate::TouchPhase::Ended
+    /// [`TouchPhase::Cancelled`]: crate::TouchPhase::Cancelled
+    #[allow(unused_variables)]
+    fn touch(&mut self, event: &TouchEvent) {}
+
     /// Called when the mouse moves.
     #[allow(unused_variables)]
     fn mouse_move(&mut self, event: &MouseEvent) {}
@@ -697,7 +1057,17 @@ pub trait WinHandler {
     #[allow(unused_variables)]
     fn mouse_up(&mut self, event: &MouseEvent) {}
 
+    /// Called when the mouse cursor has entered the application window.
+    ///
+    /// This is always balanced by a later call to [`mouse_leave`].
+    ///
+    /// [`mouse_leave`]: WinHandler::mouse_leave
+    fn mouse_enter(&mut self) {}
+
     /// Called when the mouse cursor has left the application window
+    ///
+    /// This is called exactly once each time the cursor leaves. If mouse buttons are
+    /// held down when the cursor leaves, it is called once they have all been released.
     fn mouse_leave(&mut self) {}
 
     /// Called on timer event.
@@ -709,13 +1079,54 @@ pub trait WinHandler {
     fn timer(&mut self, token: TimerToken) {}
 
     /// Called when this window becomes the focused window.
+    ///
+    /// This is the window that receives keyboard input, which macOS calls the key window.
+    /// It follows `windowDidBecomeKey:` on macOS, `WM_SETFOCUS` on Windows and
+    /// `focus-in-event` on GTK, so it is also the place to redraw anything that looks
+    /// different in inactive windows, such as dimmed window chrome.
     #[allow(unused_variables)]
     fn got_focus(&mut self) {}
 
     /// Called when this window stops being the focused window.
+    ///
+    /// This is the counterpart of [`got_focus`](WinHandler::got_focus), and is also called
+    /// when the whole application loses focus.
     #[allow(unused_variables)]
     fn lost_focus(&mut self) {}
 
+    /// Called when this window moves to another monitor.
+    ///
+    /// Properties of the monitor, such as [`Monitor::safe_area_insets`], may have changed.
+    ///
+    /// This is currently only implemented on macOS.
+    ///
+    /// [`Monitor::safe_area_insets`]: crate::Monitor::safe_area_insets
+    fn monitor_changed(&mut self) {}
+
+    /// Called when the window has moved.
+    ///
+    /// `position` is the new [`WindowHandle::get_position`], and `monitor` is the monitor
+    /// the window is on now, as chosen by [`Screen::monitor_for_rect`].
+    ///
+    /// While the user drags the window, this is only called once the drag ends. This is
+    /// implemented on macOS, Windows and GTK.
+    ///
+    /// [`Screen::monitor_for_rect`]: crate::Screen::monitor_for_rect
+    #[allow(unused_variables)]
+    fn window_moved(&mut self, position: Point, monitor: Option<Monitor>) {}
+
+    /// Called when this window becomes visible to the user, or stops being visible.
+    ///
+    /// Animations can be paused while `visible` is `false`, since nothing that is
+    /// painted will be seen.
+    ///
+    /// On macOS this also reports windows that are entirely covered by other windows.
+    /// On Windows and GTK only minimizing and restoring the window is reported.
+    ///
+    /// This is not implemented on the other platforms.
+    #[allow(unused_variables)]
+    fn occlusion_changed(&mut self, visible: bool) {}
+
     /// Called when the shell requests to close the window, for example because the user clicked
     /// the little "X" in the titlebar.
     ///
@@ -724,6 +1135,32 @@ pub trait WinHandler {
     /// have no effect.
     fn request_close(&mut self) {}
 
+    /// Called before the window closes in response to a close request, for example because
+    /// the user clicked the little "X" in the titlebar.
+    ///
+    /// Return `true` to let the window close, or `false` to keep it open, for example to ask
+    /// the user whether they want to save their changes first.
+    ///
+    /// The default implementation calls [`request_close`] and returns `false`, leaving it up to
+    /// that method to call [`WindowHandle::close`].
+    ///
+    /// This is currently not implemented on web.
+    ///
+    /// [`request_close`]: WinHandler::request_close
+    fn should_close(&mut self) -> bool {
+        self.request_close();
+        false
+    }
+
+    /// Called when the window is closed because the user clicked outside of it or switched
+    /// to another application, after [`WindowHandle::set_dismiss_on_focus_loss`].
+    ///
+    /// The window is closed right after this, without asking [`should_close`].
+    ///
+    /// [`should_close`]: WinHandler::should_close
+    #[allow(unused_variables)]
+    fn dismissed(&mut self) {}
+
     /// Called when the window is being destroyed. Note that this happens
     /// earlier in the sequence than drop (at WM_DESTROY, while the latter is
     /// WM_NCDESTROY).
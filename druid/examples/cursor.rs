@@ -96,7 +96,9 @@ impl AppState {
             Cursor::Crosshair => Cursor::NotAllowed,
             Cursor::NotAllowed => Cursor::ResizeLeftRight,
             Cursor::ResizeLeftRight => Cursor::ResizeUpDown,
-            Cursor::ResizeUpDown => {
+            Cursor::ResizeUpDown => Cursor::Wait,
+            Cursor::Wait => Cursor::Progress,
+            Cursor::Progress => {
                 if let Some(custom) = &self.custom {
                     custom.clone()
                 } else {
@@ -443,7 +443,7 @@ impl WindowConfig {
     /// Apply this window configuration to the passed in WindowHandle
     pub fn apply_to_handle(&self, win_handle: &mut WindowHandle) {
         if let Some(resizable) = self.resizable {
-            win_handle.resizable(resizable);
+            win_handle.set_resizable(resizable);
         }
 
         if let Some(show_titlebar) = self.show_titlebar {
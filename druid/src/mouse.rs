@@ -15,7 +15,7 @@
 //! The mousey bits
 
 use crate::kurbo::{Point, Vec2};
-use crate::{Cursor, Data, Modifiers, MouseButton, MouseButtons};
+use crate::{Cursor, Data, Modifiers, MouseButton, MouseButtons, PointerType};
 
 /// The state of the mouse for a click, mouse-up, move, or wheel event.
 ///
@@ -69,6 +69,17 @@ pub struct MouseEvent {
     ///
     /// [WheelEvent]: https://w3c.github.io/uievents/#event-type-wheel
     pub wheel_delta: Vec2,
+    /// The kind of device that caused the event.
+    pub pointer_type: PointerType,
+    /// The pressure of a pen or touch, from `0.0` to `1.0`.
+    ///
+    /// Devices that can't measure pressure, such as mice, report `1.0` while a button is held
+    /// down and `0.0` otherwise.
+    pub pressure: f64,
+    /// The tilt of a pen towards the right, from `-90.0` to `90.0` degrees.
+    pub tilt_x: f64,
+    /// The tilt of a pen towards the user, from `-90.0` to `90.0` degrees.
+    pub tilt_y: f64,
 }
 
 impl From<druid_shell::MouseEvent> for MouseEvent {
@@ -81,6 +92,11 @@ impl From<druid_shell::MouseEvent> for MouseEvent {
             focus,
             button,
             wheel_delta,
+            pointer_type,
+            pressure,
+            tilt_x,
+            tilt_y,
+            ..
         } = src;
         MouseEvent {
             pos,
@@ -91,6 +107,10 @@ impl From<druid_shell::MouseEvent> for MouseEvent {
             focus,
             button,
             wheel_delta,
+            pointer_type,
+            pressure,
+            tilt_x,
+            tilt_y,
         }
     }
 }
@@ -48,6 +48,10 @@ pub fn move_mouse(p: impl Into<Point>) -> MouseEvent {
         focus: false,
         button: MouseButton::None,
         wheel_delta: Vec2::ZERO,
+        pointer_type: PointerType::Mouse,
+        pressure: 0.0,
+        tilt_x: 0.0,
+        tilt_y: 0.0,
     }
 }
 
@@ -63,6 +67,10 @@ pub fn scroll_mouse(p: impl Into<Point>, delta: impl Into<Vec2>) -> MouseEvent {
         focus: false,
         button: MouseButton::None,
         wheel_delta: delta.into(),
+        pointer_type: PointerType::Mouse,
+        pressure: 0.0,
+        tilt_x: 0.0,
+        tilt_y: 0.0,
     }
 }
 